@@ -0,0 +1,153 @@
+use std::{env, path::PathBuf, process};
+
+pub enum Command {
+    /// Device login in a browser, or a password login with `email`.
+    Login { email: Option<String> },
+    Logout,
+    Ls,
+    /// Files and directories, which are walked recursively.
+    Upload { paths: Vec<PathBuf> },
+    /// Into `output`, a directory or, for a single file, its new path.
+    Download { file_ids: Vec<i32>, output: Option<PathBuf> },
+    Rm { file_ids: Vec<i32> },
+    /// A folder in `parent_id`, or at the root.
+    Mkdir { name: String, parent_id: Option<i32> },
+    /// Renames the file, or with `dir` the folder, `id` and puts it in
+    /// `folder_id`, or at the root.
+    Mv { id: i32, name: String, folder_id: Option<i32>, dir: bool },
+    Quota,
+    /// `dir` both ways with the folder `folder_id`, or the whole personal space.
+    Sync { dir: PathBuf, folder_id: Option<i32> },
+}
+
+pub struct Args {
+    pub command: Command,
+    pub server: Option<String>,
+    pub json: bool,
+}
+
+pub const USAGE: &str = "Usage: filesbox [--server <url>] [--json] <command>
+
+Commands:
+  login [--email <email>]         log in, in a browser unless an email is given
+  logout                          forget the saved session
+  ls                              list the files
+  upload <path>...                upload files, and directories recursively
+  download <id>... [-o <path>]    download files into the current directory or <path>
+  rm <id>...                      delete files
+  mkdir <name> [--folder <id>]    create a folder, at the root or in a folder
+  mv [--dir] <id> <name> [--folder <id>]
+                                  rename a file, or a folder with --dir, and put it at the root
+                                  or in a folder
+  quota                           show the space taken and the quota
+  sync <dir> [--folder <id>]      sync a directory both ways with a folder, or with everything
+
+Environment:
+  FILESBOX_SERVER      the server, instead of --server or the one logged in to
+  FILESBOX_TOKEN       a token to use instead of the saved session
+  FILESBOX_PASSWORD    the password for login --email, instead of asking for it
+  FILESBOX_CONFIG_DIR  where the session is saved";
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn file_id(arg: &str) -> i32 {
+    arg.parse().unwrap_or_else(|_| usage_error(&format!("Not a file id: {}", arg)))
+}
+
+fn folder_id(arg: &str) -> i32 {
+    arg.parse().unwrap_or_else(|_| usage_error(&format!("Not a folder id: {}", arg)))
+}
+
+pub fn parse_args() -> Args {
+    let mut server = None;
+    let mut json = false;
+    let mut email = None;
+    let mut output = None;
+    let mut folder = None;
+    let mut dir = false;
+    let mut operands = Vec::new();
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--server" => server = Some(iter.next().unwrap_or_else(|| usage_error("--server requires a URL"))),
+            "--json" => json = true,
+            "--email" => email = Some(iter.next().unwrap_or_else(|| usage_error("--email requires an address"))),
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(iter.next().unwrap_or_else(|| usage_error("-o requires a path"))))
+            }
+            "--dir" => dir = true,
+            "--folder" => folder = Some(folder_id(&iter.next().unwrap_or_else(|| usage_error("--folder requires an id")))),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') && arg != "-" => usage_error(&format!("Unknown argument: {}", arg)),
+            _ => operands.push(arg),
+        }
+    }
+
+    let mut operands = operands.into_iter();
+    let name = operands.next().unwrap_or_else(|| usage_error("Missing command"));
+    let rest: Vec<String> = operands.collect();
+    let command = match name.as_str() {
+        "login" => Command::Login { email: email.take() },
+        "logout" => Command::Logout,
+        "ls" => Command::Ls,
+        "upload" => Command::Upload { paths: rest.iter().map(PathBuf::from).collect() },
+        "download" => Command::Download { file_ids: rest.iter().map(|arg| file_id(arg)).collect(), output: output.take() },
+        "rm" => Command::Rm { file_ids: rest.iter().map(|arg| file_id(arg)).collect() },
+        "mkdir" if rest.len() > 1 => usage_error("mkdir takes one name"),
+        "mkdir" => Command::Mkdir { name: rest.first().cloned().unwrap_or_default(), parent_id: folder.take() },
+        "mv" if rest.len() != 2 => usage_error("mv requires an id and a name"),
+        "mv" => Command::Mv {
+            id: match dir {
+                true => folder_id(&rest[0]),
+                false => file_id(&rest[0]),
+            },
+            name: rest[1].clone(),
+            folder_id: folder.take(),
+            dir: std::mem::take(&mut dir),
+        },
+        "quota" => Command::Quota,
+        "share" => usage_error("share is not available yet, the server has no shares"),
+        "sync" if rest.len() > 1 => usage_error("sync takes one directory"),
+        "sync" => Command::Sync {
+            dir: rest.first().map(PathBuf::from).unwrap_or_default(),
+            folder_id: folder.take(),
+        },
+        _ => usage_error(&format!("Unknown command: {}", name)),
+    };
+
+    let takes_operands = matches!(
+        &command,
+        Command::Upload { .. }
+            | Command::Download { .. }
+            | Command::Rm { .. }
+            | Command::Mkdir { .. }
+            | Command::Mv { .. }
+            | Command::Sync { .. }
+    );
+    if takes_operands && rest.is_empty() {
+        usage_error(&format!("{} requires at least one argument", name));
+    }
+    if !takes_operands && !rest.is_empty() {
+        usage_error(&format!("{} takes no arguments", name));
+    }
+    if email.is_some() {
+        usage_error("--email only applies to login");
+    }
+    if output.is_some() {
+        usage_error("-o only applies to download");
+    }
+    if folder.is_some() {
+        usage_error("--folder only applies to mkdir, mv and sync");
+    }
+    if dir {
+        usage_error("--dir only applies to mv");
+    }
+    Args { command, server, json }
+}
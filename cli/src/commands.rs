@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use files_box_client::{
+    models::{FileData, UploadedFile},
+    Client, SyncAction,
+};
+use serde::Serialize;
+
+use crate::{
+    output::{format_size, print_json, progress_bar, Failure},
+    session::{logged_in_client, server_url, Session},
+};
+
+/// Reported to the server with device logins.
+const CLIENT_ID: &str = "filesbox";
+
+#[derive(Serialize)]
+struct LoggedIn {
+    server: String,
+    user_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct Uploaded {
+    path: PathBuf,
+    #[serde(flatten)]
+    file: UploadedFile,
+}
+
+#[derive(Serialize)]
+struct Downloaded {
+    id: i32,
+    path: PathBuf,
+    bytes: u64,
+}
+
+pub async fn login(server: Option<&str>, email: Option<&str>, json: bool) -> Result<(), Failure> {
+    let url = server_url(server, Session::load()?.as_ref())
+        .ok_or_else(|| Failure::new("no_server", "which server? run filesbox login --server <url>"))?;
+    let client = Client::new(&url);
+    match email {
+        Some(email) => {
+            let password = match env::var("FILESBOX_PASSWORD") {
+                Ok(password) => password,
+                Err(_) => rpassword::prompt_password("Password: ")?,
+            };
+            client.login(email, &password).await?;
+        }
+        None => {
+            let code = client.request_device_code(CLIENT_ID, None).await?;
+            eprintln!("Open {} and approve the code {}", code.verification_uri_complete, code.user_code);
+            client.device_login(&code, CLIENT_ID).await?;
+        }
+    }
+
+    let token = client.token().expect("the client has just logged in");
+    Session { server: url.clone(), token }.save()?;
+    let logged_in = LoggedIn { server: url, user_id: client.user_id().ok() };
+    match json {
+        true => print_json(&logged_in),
+        false => println!("Logged in to {}", logged_in.server),
+    }
+    Ok(())
+}
+
+pub fn logout(json: bool) -> Result<(), Failure> {
+    let deleted = Session::delete()?;
+    match json {
+        true => print_json(&serde_json::json!({ "logged_out": deleted })),
+        false if deleted => println!("Logged out"),
+        false => println!("Not logged in"),
+    }
+    Ok(())
+}
+
+pub async fn ls(server: Option<&str>, json: bool) -> Result<(), Failure> {
+    let client = logged_in_client(server)?;
+    let files = client.list_files().await?;
+    if json {
+        print_json(&files);
+        return Ok(());
+    }
+    for file in files {
+        println!(
+            "{:>8}  {:>10}  {}  {}",
+            file.id,
+            format_size(file.file_size as u64),
+            file.created_at.format("%Y-%m-%d %H:%M"),
+            file.file_name
+        );
+    }
+    Ok(())
+}
+
+/// The regular files under `path`, in name order.
+fn walk(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Failure> {
+    if !fs::metadata(path)?.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?.map(|entry| entry.map(|entry| entry.path())).collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        walk(&entry, files)?;
+    }
+    Ok(())
+}
+
+pub async fn upload(server: Option<&str>, paths: &[PathBuf], json: bool) -> Result<(), Failure> {
+    let client = logged_in_client(server)?;
+    // Everything is listed first, so that a wrong path fails before any upload.
+    let mut files = Vec::new();
+    for path in paths {
+        walk(path, &mut files)?;
+    }
+
+    let mut uploaded = Vec::new();
+    for path in files {
+        let file = client.upload_file(&path, progress_bar(&path.display().to_string())).await?;
+        if !json {
+            println!("{}  {}", file.id, path.display());
+        }
+        uploaded.push(Uploaded { path, file });
+    }
+    if json {
+        print_json(&uploaded);
+    }
+    Ok(())
+}
+
+/// A name from the server is only used as a file name, never as a path.
+fn local_name(file: &FileData) -> PathBuf {
+    Path::new(&file.file_name)
+        .file_name()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(file.id.to_string()))
+}
+
+pub async fn download(server: Option<&str>, file_ids: &[i32], output: Option<&Path>, json: bool) -> Result<(), Failure> {
+    let client = logged_in_client(server)?;
+    let files: HashMap<i32, FileData> = client
+        .get_files(file_ids)
+        .await?
+        .into_iter()
+        .map(|file| (file.id, file))
+        .collect();
+    if let Some(missing) = file_ids.iter().find(|id| !files.contains_key(id)) {
+        return Err(Failure::new("file_not_found", &format!("file {} not found", missing)));
+    }
+    let into_file = match output {
+        Some(output) => file_ids.len() == 1 && !output.is_dir(),
+        None => false,
+    };
+
+    let mut downloaded = Vec::new();
+    for id in file_ids {
+        let path = match (output, into_file) {
+            (Some(output), true) => output.to_path_buf(),
+            (Some(output), false) => output.join(local_name(&files[id])),
+            (None, _) => local_name(&files[id]),
+        };
+        let bytes = client.download_file_to(*id, &path, progress_bar(&files[id].file_name)).await?;
+        if !json {
+            println!("{}  {}", id, path.display());
+        }
+        downloaded.push(Downloaded { id: *id, path, bytes });
+    }
+    if json {
+        print_json(&downloaded);
+    }
+    Ok(())
+}
+
+pub async fn rm(server: Option<&str>, file_ids: &[i32], json: bool) -> Result<(), Failure> {
+    let client = logged_in_client(server)?;
+    for id in file_ids {
+        client.delete_file(*id).await?;
+        if !json {
+            println!("Deleted {}", id);
+        }
+    }
+    if json {
+        print_json(&file_ids);
+    }
+    Ok(())
+}
+
+pub async fn mkdir(server: Option<&str>, name: &str, parent_id: Option<i32>, json: bool) -> Result<(), Failure> {
+    let client = logged_in_client(server)?;
+    let folder = client.create_folder(name, parent_id).await?;
+    match json {
+        true => print_json(&folder),
+        false => println!("{}  {}/", folder.id, folder.name),
+    }
+    Ok(())
+}
+
+pub async fn mv(
+    server: Option<&str>,
+    id: i32,
+    name: &str,
+    folder_id: Option<i32>,
+    dir: bool,
+    json: bool,
+) -> Result<(), Failure> {
+    let client = logged_in_client(server)?;
+    if dir {
+        let folder = client.move_folder(id, name, folder_id).await?;
+        match json {
+            true => print_json(&folder),
+            false => println!("{}  {}/", folder.id, folder.name),
+        }
+        return Ok(());
+    }
+    let file = client.move_file(id, name, folder_id).await?;
+    match json {
+        true => print_json(&file),
+        false => println!("{}  {}", file.id, file.file_name),
+    }
+    Ok(())
+}
+
+pub async fn quota(server: Option<&str>, json: bool) -> Result<(), Failure> {
+    let client = logged_in_client(server)?;
+    let quota = client.quota().await?;
+    match json {
+        true => print_json(&quota),
+        false => println!(
+            "{} of {} used",
+            format_size(quota.used_bytes.max(0) as u64),
+            format_size(quota.quota_bytes.max(0) as u64)
+        ),
+    }
+    Ok(())
+}
+
+fn describe(action: &SyncAction) -> String {
+    match action {
+        SyncAction::Uploaded { path } => format!("uploaded  {}", path),
+        SyncAction::Downloaded { path } => format!("downloaded  {}", path),
+        SyncAction::FolderCreatedLocally { path } => format!("created  {}/", path),
+        SyncAction::FolderCreatedRemotely { path } => format!("created on the server  {}/", path),
+        SyncAction::DeletedLocally { path } => format!("deleted  {}", path),
+        SyncAction::DeletedRemotely { path } => format!("deleted on the server  {}", path),
+        SyncAction::MovedLocally { from, to } => format!("moved  {} -> {}", from, to),
+        SyncAction::MovedRemotely { from, to } => format!("moved on the server  {} -> {}", from, to),
+        SyncAction::Conflicted { path, copy } => format!("conflict  {}, the local version is in {}", path, copy),
+        SyncAction::Skipped { path, reason } => format!("skipped  {}: {}", path, reason),
+    }
+}
+
+pub async fn sync(server: Option<&str>, dir: &Path, folder_id: Option<i32>, json: bool) -> Result<(), Failure> {
+    let client = logged_in_client(server)?;
+    let report = client.sync(dir, folder_id).await?;
+    if json {
+        print_json(&report);
+        return Ok(());
+    }
+    for action in &report.actions {
+        println!("{}", describe(action));
+    }
+    if report.actions.is_empty() {
+        println!("Up to date");
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! `filesbox`, the command-line client of files-box.
+
+mod args;
+mod commands;
+mod output;
+mod session;
+
+use std::process;
+
+use args::{parse_args, Command};
+use output::Failure;
+
+#[tokio::main]
+async fn main() {
+    let args = parse_args();
+    let server = args.server.as_deref();
+    let json = args.json;
+    let result = match &args.command {
+        Command::Login { email } => commands::login(server, email.as_deref(), json).await,
+        Command::Logout => commands::logout(json),
+        Command::Ls => commands::ls(server, json).await,
+        Command::Upload { paths } => commands::upload(server, paths, json).await,
+        Command::Download { file_ids, output } => commands::download(server, file_ids, output.as_deref(), json).await,
+        Command::Rm { file_ids } => commands::rm(server, file_ids, json).await,
+        Command::Mkdir { name, parent_id } => commands::mkdir(server, name, *parent_id, json).await,
+        Command::Mv { id, name, folder_id, dir } => commands::mv(server, *id, name, *folder_id, *dir, json).await,
+        Command::Quota => commands::quota(server, json).await,
+        Command::Sync { dir, folder_id } => commands::sync(server, dir, *folder_id, json).await,
+    };
+
+    if let Err(failure) = result {
+        report(&failure, json);
+        process::exit(1);
+    }
+}
+
+fn report(failure: &Failure, json: bool) {
+    match json {
+        true => eprintln!("{}", serde_json::to_string(failure).expect("failures are always serializable")),
+        false => eprintln!("filesbox: {}", failure.message),
+    }
+}
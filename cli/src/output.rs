@@ -0,0 +1,106 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use files_box_client::{Error, ErrorKind, Progress, ProgressCallback};
+use serde::Serialize;
+
+/// Why a command failed. With `--json` it is printed to stderr as it is,
+/// `error` being the key of the API error where there is one.
+#[derive(Serialize)]
+pub struct Failure {
+    pub error: String,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(error: &str, message: &str) -> Failure {
+        Failure { error: error.to_string(), message: message.to_string() }
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(e: Error) -> Failure {
+        match &e {
+            Error::Api { kind: ErrorKind::Unauthorized, response } => Failure::new(
+                &response.error,
+                &format!("{}, run filesbox login again", response.message),
+            ),
+            Error::Api { response, .. } => Failure::new(&response.error, &response.message),
+            Error::OAuth(oauth) => Failure::new(&oauth.error, &e.to_string()),
+            Error::NotLoggedIn => Failure::new("not_logged_in", "not logged in, run filesbox login"),
+            Error::Http(_) => Failure::new("connection_failed", &e.to_string()),
+            Error::Io(_) => Failure::new("io_error", &e.to_string()),
+            Error::UnexpectedResponse(_) => Failure::new("unexpected_response", &e.to_string()),
+            Error::State(_) => Failure::new("sync_state_error", &e.to_string()),
+            Error::Sync(_) => Failure::new("sync_failed", &e.to_string()),
+        }
+    }
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Failure {
+        Failure::new("io_error", &e.to_string())
+    }
+}
+
+pub fn print_json(value: &impl Serialize) {
+    println!("{}", serde_json::to_string_pretty(value).expect("results are always serializable"));
+}
+
+/// `1536` as `1.5 KiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
+
+const BAR_WIDTH: u64 = 30;
+
+/// Draws the progress of a transfer on stderr, if it is a terminal, on one
+/// line that ends once everything is transferred.
+pub fn progress_bar(label: &str) -> Option<ProgressCallback> {
+    if !io::stderr().is_terminal() {
+        return None;
+    }
+    let label = label.to_string();
+    // Redrawing for every chunk would flicker, the line changes once per percent.
+    let drawn = AtomicU64::new(u64::MAX);
+    Some(Arc::new(move |progress: Progress| {
+        let line = match progress.total {
+            Some(total) if total > 0 => {
+                let percent = (progress.transferred * 100 / total).min(100);
+                if drawn.swap(percent, Ordering::Relaxed) == percent && progress.transferred < total {
+                    return;
+                }
+                let filled = (percent * BAR_WIDTH / 100) as usize;
+                format!(
+                    "{} [{}{}] {:>3}% {} / {}",
+                    label,
+                    "#".repeat(filled),
+                    "-".repeat(BAR_WIDTH as usize - filled),
+                    percent,
+                    format_size(progress.transferred),
+                    format_size(total)
+                )
+            }
+            _ => format!("{} {}", label, format_size(progress.transferred)),
+        };
+        let done = progress.total.is_some_and(|total| progress.transferred >= total);
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r{}\x1b[K{}", line, if done { "\n" } else { "" });
+        let _ = stderr.flush();
+    }))
+}
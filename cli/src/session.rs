@@ -0,0 +1,96 @@
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use files_box_client::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::output::Failure;
+
+/// What `login` saves: the server and a token, never the password.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub server: String,
+    pub token: String,
+}
+
+/// `FILESBOX_CONFIG_DIR`, or `filesbox` in the user's config directory.
+fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("FILESBOX_CONFIG_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("filesbox"))
+}
+
+fn session_path() -> Result<PathBuf, Failure> {
+    config_dir()
+        .map(|dir| dir.join("session.json"))
+        .ok_or_else(|| Failure::new("no_config_dir", "cannot find the config directory, set FILESBOX_CONFIG_DIR"))
+}
+
+impl Session {
+    pub fn load() -> Result<Option<Session>, Failure> {
+        match fs::read(session_path()?) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map(Some)
+                .map_err(|e| Failure::new("invalid_session", &format!("the saved session is damaged: {}", e))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the session so that only its owner can read it.
+    pub fn save(&self) -> Result<(), Failure> {
+        let path = session_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path)?;
+        file.write_all(&serde_json::to_vec_pretty(self).expect("a session is always serializable"))?;
+        Ok(())
+    }
+
+    /// Forgets the saved session. `false` if there was none.
+    pub fn delete() -> Result<bool, Failure> {
+        match fs::remove_file(session_path()?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// The server to talk to: `--server`, `FILESBOX_SERVER`, then the one of the
+/// saved session.
+pub fn server_url(flag: Option<&str>, saved: Option<&Session>) -> Option<String> {
+    flag.map(str::to_string)
+        .or_else(|| env::var("FILESBOX_SERVER").ok().filter(|url| !url.is_empty()))
+        .or_else(|| saved.map(|session| session.server.clone()))
+        .map(|url| url.trim_end_matches('/').to_string())
+}
+
+/// A client logged in with `FILESBOX_TOKEN`, or the saved session when it is
+/// for the same server.
+pub fn logged_in_client(server: Option<&str>) -> Result<Client, Failure> {
+    let saved = Session::load()?;
+    let url = server_url(server, saved.as_ref())
+        .ok_or_else(|| Failure::new("not_logged_in", "not logged in, run filesbox login --server <url>"))?;
+    let token = env::var("FILESBOX_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .or_else(|| saved.filter(|session| session.server == url).map(|session| session.token))
+        .ok_or_else(|| Failure::new("not_logged_in", &format!("not logged in to {}, run filesbox login", url)))?;
+    let client = Client::new(&url);
+    client.set_token(Some(token));
+    Ok(client)
+}
@@ -0,0 +1,217 @@
+#[path = "../../client/tests/common/mod.rs"]
+mod common;
+
+use std::{path::Path, process::Output, process::Stdio};
+
+use common::{TestServer, PASSWORD};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+};
+
+/// Runs the binary with its own config directory, as the user would.
+fn filesbox(config: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_filesbox"));
+    command
+        .args(args)
+        .env("FILESBOX_CONFIG_DIR", config)
+        .env_remove("FILESBOX_SERVER")
+        .env_remove("FILESBOX_TOKEN")
+        .env_remove("FILESBOX_PASSWORD")
+        .kill_on_drop(true);
+    command
+}
+
+fn stdout_json(output: &Output) -> Value {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).expect("the output is not JSON")
+}
+
+#[tokio::test]
+async fn login_upload_download_and_remove() {
+    let server = TestServer::start().await;
+    server.verified_client("alice@example.com").await;
+    let config = TempDir::new().unwrap();
+    let local = TempDir::new().unwrap();
+    std::fs::create_dir_all(local.path().join("photos/2024")).unwrap();
+    std::fs::write(local.path().join("notes.txt"), b"hello").unwrap();
+    std::fs::write(local.path().join("photos/a.raw"), vec![1u8; 1000]).unwrap();
+    std::fs::write(local.path().join("photos/2024/b.raw"), vec![2u8; 2000]).unwrap();
+
+    let login = filesbox(config.path(), &["--server", &server.base_url, "--json", "login", "--email", "alice@example.com"])
+        .env("FILESBOX_PASSWORD", PASSWORD)
+        .output()
+        .await
+        .unwrap();
+    assert_eq!(stdout_json(&login)["server"], json!(server.base_url));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(config.path().join("session.json")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let notes = local.path().join("notes.txt");
+    let photos = local.path().join("photos");
+    let upload = filesbox(config.path(), &["--json", "upload", notes.to_str().unwrap(), photos.to_str().unwrap()])
+        .output()
+        .await
+        .unwrap();
+    let uploaded = stdout_json(&upload);
+    let names: Vec<&str> = uploaded.as_array().unwrap().iter().map(|file| file["file_name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["notes.txt", "b.raw", "a.raw"]);
+
+    let listed = stdout_json(&filesbox(config.path(), &["--json", "ls"]).output().await.unwrap());
+    assert_eq!(listed.as_array().unwrap().len(), 3);
+    let quota = stdout_json(&filesbox(config.path(), &["--json", "quota"]).output().await.unwrap());
+    assert_eq!(quota["used_bytes"], 3005);
+
+    let ids: Vec<String> = uploaded.as_array().unwrap().iter().map(|file| file["id"].to_string()).collect();
+    let copies = TempDir::new().unwrap();
+    let mut args = vec!["--json", "download", "-o", copies.path().to_str().unwrap()];
+    args.extend(ids.iter().map(String::as_str));
+    let downloaded = stdout_json(&filesbox(config.path(), &args).output().await.unwrap());
+    assert_eq!(downloaded[1]["bytes"], 2000);
+    assert_eq!(std::fs::read(copies.path().join("notes.txt")).unwrap(), b"hello");
+    assert_eq!(std::fs::read(copies.path().join("b.raw")).unwrap(), vec![2u8; 2000]);
+
+    let renamed = copies.path().join("renamed.txt");
+    let single = filesbox(config.path(), &["download", &ids[0], "-o", renamed.to_str().unwrap()]).output().await.unwrap();
+    assert!(single.status.success());
+    assert_eq!(std::fs::read(&renamed).unwrap(), b"hello");
+
+    let mut args = vec!["rm"];
+    args.extend(ids.iter().map(String::as_str));
+    assert!(filesbox(config.path(), &args).output().await.unwrap().status.success());
+    assert_eq!(stdout_json(&filesbox(config.path(), &["--json", "ls"]).output().await.unwrap()), json!([]));
+
+    assert!(filesbox(config.path(), &["logout"]).output().await.unwrap().status.success());
+    let logged_out = filesbox(config.path(), &["--json", "ls"]).output().await.unwrap();
+    assert_eq!(logged_out.status.code(), Some(1));
+    let failure: Value = serde_json::from_slice(&logged_out.stderr).unwrap();
+    assert_eq!(failure["error"], "not_logged_in");
+}
+
+#[tokio::test]
+async fn failures_exit_with_the_error() {
+    let server = TestServer::start().await;
+    server.verified_client("alice@example.com").await;
+    let config = TempDir::new().unwrap();
+
+    let login = filesbox(config.path(), &["--server", &server.base_url, "--json", "login", "--email", "alice@example.com"])
+        .env("FILESBOX_PASSWORD", "Wr0ng!password")
+        .output()
+        .await
+        .unwrap();
+    assert_eq!(login.status.code(), Some(1));
+    let failure: Value = serde_json::from_slice(&login.stderr).unwrap();
+    assert_eq!(failure["error"], "invalid_credentials");
+    assert!(!config.path().join("session.json").exists());
+
+    let token = {
+        let client = server.client();
+        client.login("alice@example.com", PASSWORD).await.unwrap();
+        client.token().unwrap()
+    };
+    let missing = filesbox(config.path(), &["--server", &server.base_url, "download", "42"])
+        .env("FILESBOX_TOKEN", &token)
+        .output()
+        .await
+        .unwrap();
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&missing.stderr).starts_with("filesbox: "));
+
+    let usage = filesbox(config.path(), &["move", "1", "2"]).output().await.unwrap();
+    assert_eq!(usage.status.code(), Some(2));
+    let usage = filesbox(config.path(), &["rm", "first"]).output().await.unwrap();
+    assert_eq!(usage.status.code(), Some(2));
+}
+
+#[tokio::test]
+async fn sync_reports_what_it_did() {
+    let server = TestServer::start().await;
+    let user = server.verified_client("alice@example.com").await;
+    let folder = user.create_folder("Sync", None).await.unwrap();
+    let config = TempDir::new().unwrap();
+    let local = TempDir::new().unwrap();
+    std::fs::write(local.path().join("notes.txt"), b"hello").unwrap();
+    let token = user.token().unwrap();
+    let folder_id = folder.id.to_string();
+    let sync = || {
+        let mut command = filesbox(
+            config.path(),
+            &["--server", &server.base_url, "--json", "sync", local.path().to_str().unwrap(), "--folder", &folder_id],
+        );
+        command.env("FILESBOX_TOKEN", &token);
+        command
+    };
+
+    let report = stdout_json(&sync().output().await.unwrap());
+    assert_eq!(report["actions"], json!([{ "action": "uploaded", "path": "notes.txt" }]));
+    let report = stdout_json(&sync().output().await.unwrap());
+    assert_eq!(report["actions"], json!([]));
+    assert_eq!(user.list_files().await.unwrap()[0].folder_id, Some(folder.id));
+
+    let usage = filesbox(config.path(), &["sync", "a", "b"]).output().await.unwrap();
+    assert_eq!(usage.status.code(), Some(2));
+    let usage = filesbox(config.path(), &["ls", "--folder", "1"]).output().await.unwrap();
+    assert_eq!(usage.status.code(), Some(2));
+}
+
+#[tokio::test]
+async fn folders_are_created_and_entries_moved() {
+    let server = TestServer::start().await;
+    let user = server.verified_client("alice@example.com").await;
+    let file = user.upload("notes.txt", &b"hello"[..], 5, None).await.unwrap();
+    let config = TempDir::new().unwrap();
+    let token = user.token().unwrap();
+    let run = |args: &[&str]| {
+        let mut command = filesbox(config.path(), &[&["--server", &server.base_url, "--json"], args].concat());
+        command.env("FILESBOX_TOKEN", &token);
+        command
+    };
+
+    let docs = stdout_json(&run(&["mkdir", "Docs"]).output().await.unwrap());
+    assert_eq!(docs["parent_id"], Value::Null);
+    let docs_id = docs["id"].to_string();
+    let archive = stdout_json(&run(&["mkdir", "Archive", "--folder", &docs_id]).output().await.unwrap());
+    assert_eq!(archive["parent_id"], docs["id"]);
+
+    let file_id = file.id.to_string();
+    let moved = stdout_json(&run(&["mv", &file_id, "plan.txt", "--folder", &docs_id]).output().await.unwrap());
+    assert_eq!(moved["file_name"], "plan.txt");
+    assert_eq!(moved["folder_id"], docs["id"]);
+    let archive_id = archive["id"].to_string();
+    let moved = stdout_json(&run(&["mv", "--dir", &archive_id, "Old"]).output().await.unwrap());
+    assert_eq!((&moved["name"], &moved["parent_id"]), (&json!("Old"), &Value::Null));
+
+    let missing = run(&["mv", "--dir", &file_id, "x"]).output().await.unwrap();
+    assert_eq!(missing.status.code(), Some(1));
+    for usage in [&["mv", &file_id][..], &["mkdir", "a", "b"], &["ls", "--dir"], &["share", &file_id]] {
+        assert_eq!(run(usage).output().await.unwrap().status.code(), Some(2), "{:?}", usage);
+    }
+}
+
+#[tokio::test]
+async fn device_login_waits_for_the_approval() {
+    let server = TestServer::start().await;
+    let user = server.verified_client("alice@example.com").await;
+    let config = TempDir::new().unwrap();
+
+    let mut login = filesbox(config.path(), &["--server", &server.base_url, "login"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut prompt = String::new();
+    BufReader::new(login.stderr.take().unwrap()).read_line(&mut prompt).await.unwrap();
+    let user_code = prompt.trim_end().rsplit(' ').next().unwrap();
+    user.approve_device(user_code, true).await.unwrap();
+    let output = login.wait_with_output().await.unwrap();
+    assert!(output.status.success());
+
+    let quota = stdout_json(&filesbox(config.path(), &["--json", "quota"]).output().await.unwrap());
+    assert_eq!(quota["used_bytes"], 0);
+}
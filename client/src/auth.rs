@@ -0,0 +1,60 @@
+use files_box_models::auth::{
+    ForgotPassword, LoginUser, MagicLinkLogin, MagicLinkRequest, RegisterUser, Registered, ResetPassword, Token,
+    VerifiedEmail, VerifyEmail,
+};
+
+use crate::{client::Client, error::Error};
+
+impl Client {
+    /// Creates an account and returns its id. The server emails a link to
+    /// verify the address, files can be uploaded once it is followed.
+    pub async fn register(&self, email: &str, password: &str, name: &str) -> Result<i32, Error> {
+        let body = RegisterUser { email: email.to_string(), password: password.to_string(), name: name.to_string() };
+        let registered: Registered = self.public(self.http().post(self.url("/auth/register")).json(&body)).await?;
+        Ok(registered.user_id)
+    }
+
+    /// Starts a session.
+    pub async fn login(&self, email: &str, password: &str) -> Result<(), Error> {
+        let body = LoginUser { email: email.to_string(), password: password.to_string() };
+        let Token { token } = self.public(self.http().post(self.url("/auth/login")).json(&body)).await?;
+        self.set_token(Some(token));
+        Ok(())
+    }
+
+    /// Verifies the email with the token of the emailed link, and returns it.
+    pub async fn verify_email(&self, token: &str) -> Result<String, Error> {
+        let query = VerifyEmail { token: token.to_string() };
+        let verified: VerifiedEmail = self.public(self.http().get(self.url("/auth/verify-email")).query(&query)).await?;
+        Ok(verified.email)
+    }
+
+    pub async fn resend_verification(&self) -> Result<(), Error> {
+        self.authorized(|http| http.post(self.url("/auth/resend-verification"))).await
+    }
+
+    /// Emails a code for [`Client::reset_password`], if the account exists.
+    pub async fn forgot_password(&self, email: &str) -> Result<(), Error> {
+        let body = ForgotPassword { email: email.to_string() };
+        self.public(self.http().post(self.url("/auth/forgot-password")).json(&body)).await
+    }
+
+    pub async fn reset_password(&self, email: &str, code: &str, new_password: &str) -> Result<(), Error> {
+        let body = ResetPassword { email: email.to_string(), code: code.to_string(), new_password: new_password.to_string() };
+        self.public(self.http().post(self.url("/auth/reset-password")).json(&body)).await
+    }
+
+    /// Emails a link to log in without a password, if the account exists.
+    pub async fn request_magic_link(&self, email: &str) -> Result<(), Error> {
+        let body = MagicLinkRequest { email: email.to_string() };
+        self.public(self.http().post(self.url("/auth/magic-link")).json(&body)).await
+    }
+
+    /// Starts a session with the token of a magic link.
+    pub async fn magic_link_login(&self, token: &str) -> Result<(), Error> {
+        let query = MagicLinkLogin { token: token.to_string() };
+        let Token { token } = self.public(self.http().get(self.url("/auth/magic-link/login")).query(&query)).await?;
+        self.set_token(Some(token));
+        Ok(())
+    }
+}
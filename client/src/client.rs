@@ -0,0 +1,145 @@
+use std::{
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use files_box_models::{
+    api::{ApiError, ApiResponse},
+    auth::Token,
+};
+use reqwest::{RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Sessions are renewed when they have less than this left.
+const RENEW_BEFORE_SECS: u64 = 60;
+
+/// The claims of a session token that the client reads. The signature is the
+/// server's business.
+#[derive(Deserialize)]
+struct TokenClaims {
+    sub: i32,
+    exp: u64,
+    /// Set on the access tokens of devices, which can't be renewed.
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+impl TokenClaims {
+    fn of(token: &str) -> Option<TokenClaims> {
+        let payload = token.split('.').nth(1)?;
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()
+    }
+}
+
+/// A connection to one files-box server, shared by every call. Sessions are
+/// renewed with `/auth/refresh` shortly before they expire.
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Mutex<Option<String>>,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Client {
+        Client::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Uses `http` for requests, e.g. one with a proxy or timeouts.
+    pub fn with_http_client(base_url: &str, http: reqwest::Client) -> Client {
+        Client {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: Mutex::new(None),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The token of the session, to save it for later.
+    pub fn token(&self) -> Option<String> {
+        self.token.lock().unwrap().clone()
+    }
+
+    /// Uses a token saved earlier or obtained elsewhere, e.g. by the device
+    /// flow. Sessions are renewed like the ones of a login.
+    pub fn set_token(&self, token: Option<String>) {
+        *self.token.lock().unwrap() = token;
+    }
+
+    /// Forgets the token.
+    pub fn logout(&self) {
+        self.set_token(None);
+    }
+
+    /// The user the session belongs to.
+    pub fn user_id(&self) -> Result<i32, Error> {
+        let token = self.token().ok_or(Error::NotLoggedIn)?;
+        TokenClaims::of(&token)
+            .map(|claims| claims.sub)
+            .ok_or_else(|| Error::UnexpectedResponse("the session token carries no user".to_string()))
+    }
+
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    pub(crate) fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// Exchanges a session for a new one, `None` for the access tokens of
+    /// devices.
+    async fn renew(&self, token: &str) -> Result<Option<String>, Error> {
+        if !matches!(TokenClaims::of(token), Some(TokenClaims { scope: None, .. })) {
+            return Ok(None);
+        }
+        let Token { token } = self.public(self.http.post(self.url("/auth/refresh")).bearer_auth(token)).await?;
+        self.set_token(Some(token.clone()));
+        Ok(Some(token))
+    }
+
+    /// A token for the next request, renewed first when it is about to expire.
+    pub(crate) async fn bearer(&self) -> Result<String, Error> {
+        let token = self.token().ok_or(Error::NotLoggedIn)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        match TokenClaims::of(&token) {
+            Some(claims) if claims.exp <= now + RENEW_BEFORE_SECS => Ok(self.renew(&token).await?.unwrap_or(token)),
+            _ => Ok(token),
+        }
+    }
+
+    /// Sends a request that needs no session and returns its `data`.
+    pub(crate) async fn public<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, Error> {
+        parse(request.send().await?).await
+    }
+
+    /// Sends the request built by `request` with the session and returns its
+    /// `data`.
+    pub(crate) async fn authorized<T: DeserializeOwned>(
+        &self,
+        request: impl Fn(&reqwest::Client) -> RequestBuilder,
+    ) -> Result<T, Error> {
+        let token = self.bearer().await?;
+        parse(request(&self.http).bearer_auth(&token).send().await?).await
+    }
+}
+
+/// Reads the envelope of an API response: `data` for successes, the error
+/// for failures.
+pub(crate) async fn parse<T: DeserializeOwned>(response: Response) -> Result<T, Error> {
+    let body = response.bytes().await?;
+    let unexpected = || Error::UnexpectedResponse(String::from_utf8_lossy(&body).into_owned());
+    let value: Value = serde_json::from_slice(&body).map_err(|_| unexpected())?;
+    if value["code"].as_i64().ok_or_else(unexpected)? >= 400 {
+        let error: ApiError = serde_json::from_value(value).map_err(|_| unexpected())?;
+        return Err(error.into());
+    }
+    let response: ApiResponse<T> = serde_json::from_value(value).map_err(|_| unexpected())?;
+    Ok(response.data)
+}
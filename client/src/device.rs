@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use files_box_models::device::{
+    DeviceApproval, DeviceCodeRequest, DeviceCodeResponse, DeviceLookup, DeviceTokenRequest, DeviceTokenResponse,
+    OAuthError, PendingDeviceAuthorization, DEVICE_CODE_GRANT_TYPE, SLOW_DOWN_SECS,
+};
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+
+use crate::{client::Client, error::Error};
+
+impl Client {
+    /// Starts a device login (RFC 8628). The user approves `user_code` at
+    /// `verification_uri`, from a browser where they are logged in, while
+    /// [`Client::device_login`] waits. `scope` defaults to every files scope.
+    pub async fn request_device_code(&self, client_id: &str, scope: Option<&str>) -> Result<DeviceCodeResponse, Error> {
+        let body = DeviceCodeRequest { client_id: client_id.to_string(), scope: scope.map(str::to_string) };
+        oauth(self.http().post(self.url("/auth/device/code")).form(&body).send().await?).await
+    }
+
+    /// Polls until the device code is approved, then uses the access token,
+    /// which only allows its scope and can't be renewed. Fails with
+    /// [`Error::OAuth`] when the user denies it or the code expires.
+    pub async fn device_login(&self, code: &DeviceCodeResponse, client_id: &str) -> Result<DeviceTokenResponse, Error> {
+        let body = DeviceTokenRequest {
+            grant_type: DEVICE_CODE_GRANT_TYPE.to_string(),
+            device_code: code.device_code.clone(),
+            client_id: client_id.to_string(),
+        };
+        let mut interval = code.interval as u64;
+        loop {
+            let response = self.http().post(self.url("/auth/device/token")).form(&body).send().await?;
+            match oauth::<DeviceTokenResponse>(response).await {
+                Ok(token) => {
+                    self.set_token(Some(token.access_token.clone()));
+                    return Ok(token);
+                }
+                Err(Error::OAuth(e)) if e.error == "authorization_pending" => {}
+                Err(Error::OAuth(e)) if e.error == "slow_down" => interval += SLOW_DOWN_SECS as u64,
+                Err(e) => return Err(e),
+            }
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    }
+
+    /// What a device asks for, before approving its code.
+    pub async fn lookup_device(&self, user_code: &str) -> Result<PendingDeviceAuthorization, Error> {
+        let query = DeviceLookup { user_code: user_code.to_string() };
+        self.authorized(|http| http.get(self.url("/auth/device")).query(&query)).await
+    }
+
+    /// Approves or denies the device login waiting with `user_code`.
+    pub async fn approve_device(&self, user_code: &str, approve: bool) -> Result<(), Error> {
+        let body = DeviceApproval { user_code: user_code.to_string(), approve };
+        self.authorized(|http| http.post(self.url("/auth/device")).json(&body)).await
+    }
+}
+
+/// Reads an OAuth response, which has no envelope and fails with an HTTP
+/// status.
+async fn oauth<T: DeserializeOwned>(response: Response) -> Result<T, Error> {
+    let success = response.status().is_success();
+    let body = response.bytes().await?;
+    let unexpected = || Error::UnexpectedResponse(String::from_utf8_lossy(&body).into_owned());
+    match success {
+        true => serde_json::from_slice(&body).map_err(|_| unexpected()),
+        false => Err(Error::OAuth(serde_json::from_slice::<OAuthError>(&body).map_err(|_| unexpected())?)),
+    }
+}
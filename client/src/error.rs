@@ -0,0 +1,149 @@
+use std::{fmt, io};
+
+use files_box_models::{api::ApiError, device::OAuthError};
+
+/// What went wrong with a failed API call, from the `error` key of the
+/// response, or from its code for keys without a kind of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The token is missing, invalid or expired.
+    Unauthorized,
+    InvalidCredentials,
+    EmailNotVerified,
+    /// Disabled by an admin, or waiting for a forced password reset.
+    AccountLocked,
+    /// The token is valid but may not do this, e.g. a device token without
+    /// the needed scope.
+    Forbidden,
+    NotFound,
+    /// The email is taken, or the user is already a member.
+    Conflict,
+    QuotaExceeded,
+    FileTooLarge,
+    TooManyAttempts,
+    /// Changes after the cursor are past the retention: sync again from `0`.
+    CursorExpired,
+    /// Any other rejected request.
+    Invalid,
+    Server,
+}
+
+impl ErrorKind {
+    pub fn from_response(code: i32, error: &str) -> ErrorKind {
+        match error {
+            "unauthorized" => ErrorKind::Unauthorized,
+            "invalid_credentials" | "invalid_password" => ErrorKind::InvalidCredentials,
+            "email_not_verified" => ErrorKind::EmailNotVerified,
+            "account_disabled" | "password_reset_required" => ErrorKind::AccountLocked,
+            "quota_exceeded" => ErrorKind::QuotaExceeded,
+            "file_too_large" => ErrorKind::FileTooLarge,
+            "too_many_attempts" => ErrorKind::TooManyAttempts,
+            "cursor_expired" => ErrorKind::CursorExpired,
+            _ => match code {
+                401 => ErrorKind::Unauthorized,
+                403 => ErrorKind::Forbidden,
+                404 => ErrorKind::NotFound,
+                409 => ErrorKind::Conflict,
+                429 => ErrorKind::TooManyAttempts,
+                500.. => ErrorKind::Server,
+                _ => ErrorKind::Invalid,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The server refused the request.
+    Api {
+        kind: ErrorKind,
+        /// The body of the response, with the key and the translated message.
+        response: ApiError,
+    },
+    /// The device login failed, e.g. the user denied it or the code expired.
+    OAuth(OAuthError),
+    /// The call needs a session and the client has none.
+    NotLoggedIn,
+    /// The server couldn't be reached, or the connection broke.
+    Http(reqwest::Error),
+    /// Reading or writing a local file failed.
+    Io(io::Error),
+    /// The server answered with something that isn't the API's JSON.
+    UnexpectedResponse(String),
+    /// The state database of a synced directory couldn't be read or written.
+    State(sqlx::Error),
+    /// The directory can't be synced as asked, e.g. it is synced with
+    /// another folder, or the folder is gone.
+    Sync(String),
+}
+
+impl Error {
+    /// `None` unless the server refused the request.
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            Error::Api { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+
+    /// Seconds to wait before trying again, for [`ErrorKind::TooManyAttempts`].
+    pub fn retry_after(&self) -> Option<i64> {
+        match self {
+            Error::Api { response, .. } => response.data.as_ref()?["retry_after"].as_i64(),
+            _ => None,
+        }
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(response: ApiError) -> Error {
+        Error::Api { kind: ErrorKind::from_response(response.code, &response.error), response }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Error {
+        Error::Http(e)
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(e: sqlx::Error) -> Error {
+        Error::State(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Api { response, .. } => write!(f, "{} ({}): {}", response.error, response.code, response.message),
+            Error::OAuth(e) => match &e.error_description {
+                Some(description) => write!(f, "{}: {}", e.error, description),
+                None => write!(f, "{}", e.error),
+            },
+            Error::NotLoggedIn => write!(f, "not logged in"),
+            Error::Http(e) => write!(f, "request failed: {}", e),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::UnexpectedResponse(body) => write!(f, "unexpected response: {}", body),
+            Error::State(e) => write!(f, "sync state: {}", e),
+            Error::Sync(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::State(e) => Some(e),
+            _ => None,
+        }
+    }
+}
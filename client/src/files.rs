@@ -0,0 +1,221 @@
+use std::path::Path;
+
+use files_box_models::{
+    files::{ChangePage, FileData, FileList, GetFiles, MoveFile, StorageQuota, UploadedFile},
+    org::{TeamDrive, TeamDriveList},
+};
+use futures_util::StreamExt;
+use reqwest::{
+    header::CONTENT_DISPOSITION,
+    multipart::{Form, Part},
+    Body,
+};
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+};
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    client::{parse, Client},
+    error::Error,
+    progress::{track, ProgressCallback},
+};
+
+impl Client {
+    /// Every personal file of the user.
+    pub async fn list_files(&self) -> Result<Vec<FileData>, Error> {
+        self.get_files(&[-1]).await
+    }
+
+    /// The personal files with these ids. Fails with
+    /// [`ErrorKind::Invalid`](crate::ErrorKind::Invalid) when none is found.
+    pub async fn get_files(&self, file_ids: &[i32]) -> Result<Vec<FileData>, Error> {
+        let body = GetFiles { file_ids: file_ids.to_vec() };
+        let list: FileList = self.authorized(|http| http.post(self.url("/files/get")).json(&body)).await?;
+        Ok(list.files)
+    }
+
+    /// Uploads a local file under its name.
+    pub async fn upload_file(&self, path: impl AsRef<Path>, progress: Option<ProgressCallback>) -> Result<UploadedFile, Error> {
+        let (file_name, file, size) = open(path.as_ref()).await?;
+        self.upload(&file_name, file, size, progress).await
+    }
+
+    /// Uploads `size` bytes read from `contents` as `file_name`.
+    pub async fn upload(
+        &self,
+        file_name: &str,
+        contents: impl AsyncRead + Send + 'static,
+        size: u64,
+        progress: Option<ProgressCallback>,
+    ) -> Result<UploadedFile, Error> {
+        self.upload_to("/files/upload", file_name, contents, size, progress).await
+    }
+
+    /// Uploads `size` bytes read from `contents` as `file_name` into a
+    /// folder, or at the root when `folder_id` is `None`.
+    pub async fn upload_into(
+        &self,
+        folder_id: Option<i32>,
+        file_name: &str,
+        contents: impl AsyncRead + Send + 'static,
+        size: u64,
+        progress: Option<ProgressCallback>,
+    ) -> Result<UploadedFile, Error> {
+        let path = match folder_id {
+            Some(folder_id) => format!("/files/upload?folder_id={}", folder_id),
+            None => "/files/upload".to_string(),
+        };
+        self.upload_to(&path, file_name, contents, size, progress).await
+    }
+
+    /// Replaces the contents of a personal file with `size` bytes read from
+    /// `contents`. With `expected_hash`, fails with
+    /// [`ErrorKind::Conflict`](crate::ErrorKind::Conflict) if the contents on
+    /// the server are no longer the ones with this hash.
+    pub async fn replace_file(
+        &self,
+        file_id: i32,
+        expected_hash: Option<&str>,
+        contents: impl AsyncRead + Send + 'static,
+        size: u64,
+        progress: Option<ProgressCallback>,
+    ) -> Result<UploadedFile, Error> {
+        let path = match expected_hash {
+            Some(expected_hash) => format!("/files/replace?file_id={}&expected_hash={}", file_id, expected_hash),
+            None => format!("/files/replace?file_id={}", file_id),
+        };
+        self.upload_to(&path, "contents", contents, size, progress).await
+    }
+
+    /// Renames a personal file and puts it in a folder, or at the root when
+    /// `folder_id` is `None`.
+    pub async fn move_file(&self, file_id: i32, file_name: &str, folder_id: Option<i32>) -> Result<FileData, Error> {
+        let body = MoveFile { file_id, file_name: file_name.to_string(), folder_id };
+        self.authorized(|http| http.post(self.url("/files/move")).json(&body)).await
+    }
+
+    /// Streams the contents of a personal file into `writer` and returns
+    /// their size.
+    pub async fn download_file(
+        &self,
+        file_id: i32,
+        writer: &mut (impl AsyncWrite + Unpin),
+        progress: Option<ProgressCallback>,
+    ) -> Result<u64, Error> {
+        let token = self.bearer().await?;
+        let response = self
+            .http()
+            .get(self.url("/files/download"))
+            .query(&[("file_id", file_id)])
+            .bearer_auth(token)
+            .send()
+            .await?;
+        // Failures are JSON too, only files come as attachments.
+        if !response.headers().contains_key(CONTENT_DISPOSITION) {
+            parse::<()>(response).await?;
+            return Err(Error::UnexpectedResponse("the download has no attachment".to_string()));
+        }
+
+        let total = response.content_length();
+        let mut body = track(response.bytes_stream(), total, progress);
+        let mut written = 0;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            writer.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        writer.flush().await?;
+        Ok(written)
+    }
+
+    /// Downloads a personal file into `path`, which is created or replaced.
+    pub async fn download_file_to(
+        &self,
+        file_id: i32,
+        path: impl AsRef<Path>,
+        progress: Option<ProgressCallback>,
+    ) -> Result<u64, Error> {
+        let mut file = File::create(path).await?;
+        self.download_file(file_id, &mut file, progress).await
+    }
+
+    pub async fn delete_file(&self, file_id: i32) -> Result<(), Error> {
+        self.authorized(|http| http.delete(self.url("/files/delete")).query(&[("file_id", file_id)])).await
+    }
+
+    /// Up to `limit` changes to the personal files and folders after
+    /// `cursor`, `0` to start from the first one. Continue from the
+    /// `cursor` of the page while it `has_more`. Fails with
+    /// [`ErrorKind::CursorExpired`](crate::ErrorKind::CursorExpired) once
+    /// changes after the cursor are past the retention of the server.
+    pub async fn changes(&self, cursor: i64, limit: i64) -> Result<ChangePage, Error> {
+        self.authorized(|http| http.get(self.url("/files/changes")).query(&[("cursor", cursor), ("limit", limit)])).await
+    }
+
+    /// The space taken by the personal files and their quota.
+    pub async fn quota(&self) -> Result<StorageQuota, Error> {
+        self.authorized(|http| http.get(self.url("/files/quota"))).await
+    }
+
+    /// The team drives of an organization the user is a member of.
+    pub async fn list_drives(&self, organization_id: i32) -> Result<Vec<TeamDrive>, Error> {
+        let list: TeamDriveList =
+            self.authorized(|http| http.get(self.url(&format!("/orgs/{}/drives", organization_id)))).await?;
+        Ok(list.drives)
+    }
+
+    pub async fn list_drive_files(&self, organization_id: i32, drive_id: i32) -> Result<Vec<FileData>, Error> {
+        let path = format!("/orgs/{}/drives/{}/files", organization_id, drive_id);
+        let list: FileList = self.authorized(|http| http.get(self.url(&path))).await?;
+        Ok(list.files)
+    }
+
+    /// Uploads a local file to a team drive under its name.
+    pub async fn upload_drive_file(
+        &self,
+        organization_id: i32,
+        drive_id: i32,
+        path: impl AsRef<Path>,
+        progress: Option<ProgressCallback>,
+    ) -> Result<UploadedFile, Error> {
+        let (file_name, file, size) = open(path.as_ref()).await?;
+        let path = format!("/orgs/{}/drives/{}/files", organization_id, drive_id);
+        self.upload_to(&path, &file_name, file, size, progress).await
+    }
+
+    pub async fn delete_drive_file(&self, organization_id: i32, drive_id: i32, file_id: i32) -> Result<(), Error> {
+        let path = format!("/orgs/{}/drives/{}/files/{}", organization_id, drive_id, file_id);
+        self.authorized(|http| http.delete(self.url(&path))).await
+    }
+
+    /// Streams an upload. The body can't be sent twice, so the request is
+    /// built by hand rather than by `authorized`.
+    async fn upload_to(
+        &self,
+        path: &str,
+        file_name: &str,
+        contents: impl AsyncRead + Send + 'static,
+        size: u64,
+        progress: Option<ProgressCallback>,
+    ) -> Result<UploadedFile, Error> {
+        let body = Body::wrap_stream(track(ReaderStream::new(contents), Some(size), progress));
+        let part = Part::stream_with_length(body, size)
+            .file_name(file_name.to_string())
+            .mime_str("application/octet-stream")?;
+        let token = self.bearer().await?;
+        let request = self.http().post(self.url(path)).bearer_auth(token).multipart(Form::new().part("file", part));
+        parse(request.send().await?).await
+    }
+}
+
+async fn open(path: &Path) -> Result<(String, File, u64), Error> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, "the path has no file name")))?;
+    let file = File::open(path).await?;
+    let size = file.metadata().await?.len();
+    Ok((file_name, file, size))
+}
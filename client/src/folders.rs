@@ -0,0 +1,29 @@
+use files_box_models::files::{CreateFolder, Folder, FolderList, MoveFolder};
+
+use crate::{client::Client, error::Error};
+
+impl Client {
+    /// Every folder of the personal space, nested through their `parent_id`.
+    pub async fn list_folders(&self) -> Result<Vec<Folder>, Error> {
+        let list: FolderList = self.authorized(|http| http.get(self.url("/folders"))).await?;
+        Ok(list.folders)
+    }
+
+    /// Creates a folder in `parent_id`, or at the root when it is `None`.
+    pub async fn create_folder(&self, name: &str, parent_id: Option<i32>) -> Result<Folder, Error> {
+        let body = CreateFolder { name: name.to_string(), parent_id };
+        self.authorized(|http| http.post(self.url("/folders")).json(&body)).await
+    }
+
+    /// Renames a folder and puts it in `parent_id`, or at the root when it is `None`.
+    pub async fn move_folder(&self, folder_id: i32, name: &str, parent_id: Option<i32>) -> Result<Folder, Error> {
+        let body = MoveFolder { name: name.to_string(), parent_id };
+        self.authorized(|http| http.patch(self.url(&format!("/folders/{}", folder_id))).json(&body)).await
+    }
+
+    /// Deletes an empty folder. Fails with
+    /// [`ErrorKind::Conflict`](crate::ErrorKind::Conflict) if it has files or folders.
+    pub async fn delete_folder(&self, folder_id: i32) -> Result<(), Error> {
+        self.authorized(|http| http.delete(self.url(&format!("/folders/{}", folder_id)))).await
+    }
+}
@@ -0,0 +1,42 @@
+//! Client of the files-box API.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), files_box_client::Error> {
+//! let client = files_box_client::Client::new("https://files.example.com");
+//! client.login("alice@example.com", "correct horse battery staple").await?;
+//! let uploaded = client.upload_file("report.pdf", None).await?;
+//! client.download_file_to(uploaded.id, "copy.pdf", None).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests and responses are the models of the `files-box-models` crate,
+//! which the server shares, re-exported in [`models`]. [`Client::sync`]
+//! mirrors a local directory with a folder.
+
+mod auth;
+mod client;
+mod device;
+mod error;
+mod files;
+mod folders;
+mod progress;
+mod sync;
+mod user;
+
+pub use client::Client;
+pub use error::{Error, ErrorKind};
+pub use progress::{Progress, ProgressCallback};
+pub use sync::{SyncAction, SyncReport, STATE_DIR};
+
+/// The request and response bodies of the API.
+pub mod models {
+    pub use files_box_models::{
+        api::{ApiError, ApiMessage, ApiResponse},
+        auth::{Registered, Token, VerifiedEmail},
+        device::{DeviceCodeResponse, DeviceTokenResponse, OAuthError, PendingDeviceAuthorization},
+        files::{Change, ChangeKind, ChangePage, FileData, FileList, Folder, StorageQuota, UploadedFile},
+        org::TeamDrive,
+        user::{UpdateUser, User},
+    };
+}
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use futures_util::{Stream, StreamExt};
+
+/// How much of an upload or download is done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub transferred: u64,
+    /// `None` when the server doesn't tell the size of a download.
+    pub total: Option<u64>,
+}
+
+/// Called after every chunk of an upload or download.
+pub type ProgressCallback = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// Reports the bytes of `stream` to `progress` as they go through.
+pub(crate) fn track<S, B, E>(stream: S, total: Option<u64>, progress: Option<ProgressCallback>) -> impl Stream<Item = Result<B, E>>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+{
+    let mut transferred = 0;
+    stream.inspect(move |chunk| {
+        if let (Ok(chunk), Some(progress)) = (chunk, &progress) {
+            transferred += chunk.as_ref().len() as u64;
+            progress(Progress { transferred, total });
+        }
+    })
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, Metadata},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use files_box_models::files::normalize_entry_name;
+use ring::digest::{Context, SHA256};
+
+use super::{state::Synced, STATE_DIR};
+
+/// A file or directory of the synced directory. Directories have no hash.
+#[derive(Clone, Debug)]
+pub(crate) struct LocalEntry {
+    pub is_dir: bool,
+    pub hash: Option<String>,
+    pub size: i64,
+    pub modified_ns: i64,
+}
+
+/// Names the server would store as they are, the others aren't synced.
+pub(crate) fn is_syncable(name: &str) -> bool {
+    normalize_entry_name(name).as_deref() == Some(name)
+}
+
+pub(crate) fn modified_ns(metadata: &Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_nanos() as i64)
+}
+
+/// SHA-256 of the file in hex, as the server computes it.
+pub(crate) fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut context = Context::new(&SHA256);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        context.update(&buffer[..read]);
+    }
+    Ok(context.finish().as_ref().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// The files and directories under `root` by their `/` separated path,
+/// and the paths left out. Files whose size and modification time are the
+/// synced ones keep the synced hash instead of being read again.
+pub(crate) fn scan(
+    root: &Path,
+    synced: &BTreeMap<String, Synced>,
+) -> io::Result<(BTreeMap<String, LocalEntry>, Vec<String>)> {
+    let mut entries = BTreeMap::new();
+    let mut skipped = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let path = match name.to_str() {
+                Some(name) if prefix.is_empty() => name.to_string(),
+                Some(name) => format!("{}/{}", prefix, name),
+                None => format!("{}/{}", prefix, name.to_string_lossy()),
+            };
+            if path == STATE_DIR {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if !name.to_str().is_some_and(is_syncable) || file_type.is_symlink() {
+                skipped.push(path);
+                continue;
+            }
+
+            let metadata = entry.metadata()?;
+            let size = metadata.len() as i64;
+            let modified_ns = modified_ns(&metadata);
+            if file_type.is_dir() {
+                pending.push((entry.path(), path.clone()));
+                entries.insert(path, LocalEntry { is_dir: true, hash: None, size: 0, modified_ns });
+                continue;
+            }
+            let hash = match synced.get(&path) {
+                Some(base) if !base.is_dir && base.size == size && base.modified_ns == modified_ns => base.hash.clone(),
+                _ => Some(hash_file(&entry.path())?),
+            };
+            entries.insert(path, LocalEntry { is_dir: false, hash, size, modified_ns });
+        }
+    }
+    Ok((entries, skipped))
+}
+
+/// A free path next to `path` for the local side of a conflict, e.g.
+/// `notes (conflicted copy 2024-05-01 093000).txt`.
+pub(crate) fn conflicted_copy(root: &Path, path: &str, timestamp: &str) -> String {
+    let (parent, name) = match path.rsplit_once('/') {
+        Some((parent, name)) => (format!("{}/", parent), name),
+        None => (String::new(), path),
+    };
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+    let mut copy = format!("{}{} (conflicted copy {}){}", parent, stem, timestamp, extension);
+    let mut number = 2;
+    while root.join(&copy).exists() {
+        copy = format!("{}{} (conflicted copy {} {}){}", parent, stem, timestamp, number, extension);
+        number += 1;
+    }
+    copy
+}
+
+/// Where `path` is on disk.
+pub(crate) fn local_path(root: &Path, path: &str) -> PathBuf {
+    path.split('/').fold(root.to_path_buf(), |local, name| local.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflicted_copies_keep_the_extension() {
+        let root = Path::new("/nonexistent");
+        assert_eq!(
+            conflicted_copy(root, "docs/notes.txt", "2024-05-01 093000"),
+            "docs/notes (conflicted copy 2024-05-01 093000).txt"
+        );
+        assert_eq!(conflicted_copy(root, ".env", "2024-05-01 093000"), ".env (conflicted copy 2024-05-01 093000)");
+        assert_eq!(conflicted_copy(root, "README", "2024-05-01 093000"), "README (conflicted copy 2024-05-01 093000)");
+    }
+
+    #[test]
+    fn names_the_server_would_change_are_not_synced() {
+        assert!(is_syncable("notes.txt"));
+        assert!(!is_syncable(" notes.txt"));
+        assert!(!is_syncable(".."));
+    }
+}
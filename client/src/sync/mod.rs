@@ -0,0 +1,634 @@
+//! Two-way sync of a local directory with a folder of the personal space.
+//!
+//! The server's change feed keeps a copy of the remote entries in the state
+//! database of the directory, next to the entries as they were when both
+//! sides last agreed. A run compares the three: what changed on one side
+//! only is applied to the other, and files changed differently on both
+//! sides keep both versions, the local one as a "conflicted copy".
+
+mod local;
+mod state;
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use tokio::fs;
+
+use crate::{client::Client, error::Error, ErrorKind};
+use local::{conflicted_copy, hash_file, is_syncable, local_path, modified_ns, scan, LocalEntry};
+use state::{State, Synced};
+
+/// The directory of the state database inside a synced directory. It is
+/// never synced.
+pub const STATE_DIR: &str = ".filesbox";
+
+/// Changes asked for at a time.
+const PAGE_SIZE: i64 = 1000;
+
+/// What a sync run did, by `/` separated path in the synced directory.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum SyncAction {
+    Uploaded { path: String },
+    Downloaded { path: String },
+    FolderCreatedLocally { path: String },
+    FolderCreatedRemotely { path: String },
+    DeletedLocally { path: String },
+    DeletedRemotely { path: String },
+    /// Renamed on disk after it moved on the server.
+    MovedLocally { from: String, to: String },
+    /// Moved on the server after it was renamed on disk.
+    MovedRemotely { from: String, to: String },
+    /// Both sides changed the file: the server's version is now at `path`,
+    /// the local one at `copy`, which is uploaded too.
+    Conflicted { path: String, copy: String },
+    /// Left alone, e.g. a name the server can't store or two entries with the
+    /// same name.
+    Skipped { path: String, reason: String },
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SyncReport {
+    pub actions: Vec<SyncAction>,
+    /// The change of the feed the remote side is known up to.
+    pub cursor: i64,
+}
+
+/// A file or folder on the server, by its path under the synced folder.
+struct RemoteEntry {
+    id: i32,
+    is_dir: bool,
+    hash: Option<String>,
+}
+
+impl Client {
+    /// Syncs `dir` both ways with the folder `folder_id`, or with the whole
+    /// personal space when it is `None`. The state is kept in
+    /// [`STATE_DIR`] inside `dir`, which can only be synced with one folder
+    /// of one account.
+    pub async fn sync(&self, dir: impl AsRef<Path>, folder_id: Option<i32>) -> Result<SyncReport, Error> {
+        let root = dir.as_ref().to_path_buf();
+        fs::create_dir_all(root.join(STATE_DIR)).await?;
+        let state = State::open(&root.join(STATE_DIR).join("state.db")).await?;
+        let mut sync = SyncRun {
+            client: self,
+            root,
+            folder_id,
+            state,
+            remote_dirs: HashMap::new(),
+            actions: Vec::new(),
+        };
+        let result = sync.run().await;
+        let SyncRun { state, actions, .. } = sync;
+        let cursor = state.cursor().await;
+        state.close().await;
+        result?;
+        Ok(SyncReport { actions, cursor: cursor? })
+    }
+}
+
+struct SyncRun<'a> {
+    client: &'a Client,
+    root: PathBuf,
+    folder_id: Option<i32>,
+    state: State,
+    /// Remote folders by path, `""` being the synced folder itself.
+    remote_dirs: HashMap<String, Option<i32>>,
+    actions: Vec<SyncAction>,
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+fn name(path: &str) -> &str {
+    path.rsplit_once('/').map_or(path, |(_, name)| name)
+}
+
+fn join(parent: &str, name: &str) -> String {
+    match parent {
+        "" => name.to_string(),
+        _ => format!("{}/{}", parent, name),
+    }
+}
+
+fn is_under(path: &str, dir: &str) -> bool {
+    path == dir || path.strip_prefix(dir).is_some_and(|rest| rest.starts_with('/'))
+}
+
+impl SyncRun<'_> {
+    async fn run(&mut self) -> Result<(), Error> {
+        self.check_owner().await?;
+        self.pull().await?;
+        let mut remote = self.remote_tree().await?;
+        let mut synced = self.state.synced().await?;
+        let (root, base) = (self.root.clone(), synced.clone());
+        let (mut local, skipped) = tokio::task::spawn_blocking(move || scan(&root, &base))
+            .await
+            .map_err(io::Error::other)??;
+        for path in skipped {
+            self.skip(&path, "the name can't be stored on the server");
+        }
+        self.remote_dirs = remote
+            .iter()
+            .filter(|(_, entry)| entry.is_dir)
+            .map(|(path, entry)| (path.clone(), Some(entry.id)))
+            .collect();
+        self.remote_dirs.insert(String::new(), self.folder_id);
+
+        // A file on one side and a folder on the other can't be synced.
+        let clashes: Vec<String> = local
+            .iter()
+            .filter(|(path, entry)| remote.get(*path).is_some_and(|remote| remote.is_dir != entry.is_dir))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &clashes {
+            self.skip(path, "a file and a folder have this name");
+        }
+        let clashing = |path: &str| clashes.iter().any(|clash| is_under(path, clash));
+
+        let mut paths: BTreeSet<String> = local.keys().chain(remote.keys()).chain(synced.keys()).cloned().collect();
+        paths.retain(|path| !clashing(path));
+
+        let (local_gone, remote_gone) = self.sync_folders(&paths, &local, &remote, &synced).await?;
+        self.follow_remote_moves(&remote, &mut local, &mut synced).await?;
+        self.follow_local_moves(&mut remote, &local, &mut synced).await?;
+        let paths: BTreeSet<String> = local.keys().chain(remote.keys()).chain(synced.keys()).cloned().collect();
+        for path in paths.iter().filter(|path| !clashing(path)) {
+            let local = local.get(path).filter(|entry| !entry.is_dir);
+            let remote = remote.get(path).filter(|entry| !entry.is_dir);
+            let base = synced.get(path).filter(|entry| !entry.is_dir);
+            self.sync_file(path, local, remote, base).await?;
+        }
+        self.delete_folders(local_gone, remote_gone).await
+    }
+
+    /// A directory is only ever synced with one folder of one account.
+    async fn check_owner(&self) -> Result<(), Error> {
+        let owner = [
+            ("server", self.client.base_url().to_string()),
+            ("user_id", self.client.user_id()?.to_string()),
+            ("folder_id", self.folder_id.map(|id| id.to_string()).unwrap_or_default()),
+        ];
+        for (key, value) in &owner {
+            match self.state.setting(key).await? {
+                Some(saved) if saved != *value => {
+                    return Err(Error::Sync(format!(
+                        "{} is synced with another {}",
+                        self.root.display(),
+                        key.replace('_', " ")
+                    )));
+                }
+                Some(_) => {}
+                None => self.state.set_setting(key, value).await?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Brings the remote entries up to date with the change feed, from the
+    /// start again when the server no longer has the changes after the cursor.
+    async fn pull(&self) -> Result<(), Error> {
+        let mut cursor = self.state.cursor().await?;
+        loop {
+            let page = match self.client.changes(cursor, PAGE_SIZE).await {
+                Err(e) if e.kind() == Some(ErrorKind::CursorExpired) && cursor > 0 => {
+                    self.state.reset_remote().await?;
+                    cursor = 0;
+                    continue;
+                }
+                page => page?,
+            };
+            self.state.apply(&page.changes, page.cursor).await?;
+            cursor = page.cursor;
+            if !page.has_more {
+                return Ok(());
+            }
+        }
+    }
+
+    /// The remote entries under the synced folder by path. Entries that
+    /// couldn't be created on disk are skipped.
+    async fn remote_tree(&mut self) -> Result<BTreeMap<String, RemoteEntry>, Error> {
+        let folders = self.state.remote_folders().await?;
+        let files = self.state.remote_files().await?;
+        if let Some(folder_id) = self.folder_id {
+            if !folders.iter().any(|folder| folder.id == folder_id) {
+                return Err(Error::Sync(format!("the folder {} doesn't exist on the server", folder_id)));
+            }
+        }
+
+        // Folders are placed parents first, so that the path of a folder is
+        // known once its parent is.
+        let mut folder_paths: HashMap<Option<i32>, String> = HashMap::from([(self.folder_id, String::new())]);
+        let mut tree = BTreeMap::new();
+        let mut placed = true;
+        let mut waiting: Vec<_> = folders.iter().filter(|folder| Some(folder.id) != self.folder_id).collect();
+        while placed {
+            placed = false;
+            let mut still_waiting = Vec::new();
+            for folder in waiting {
+                let Some(parent) = folder_paths.get(&folder.parent_id) else {
+                    still_waiting.push(folder);
+                    continue;
+                };
+                placed = true;
+                let path = join(parent, &folder.name);
+                if let Some(reason) = self.unplaceable(&path, &tree) {
+                    self.skip(&path, reason);
+                    continue;
+                }
+                folder_paths.insert(Some(folder.id), path.clone());
+                tree.insert(path, RemoteEntry { id: folder.id, is_dir: true, hash: None });
+            }
+            waiting = still_waiting;
+        }
+        for file in files {
+            let Some(parent) = folder_paths.get(&file.folder_id) else {
+                continue;
+            };
+            let path = join(parent, &file.name);
+            if let Some(reason) = self.unplaceable(&path, &tree) {
+                self.skip(&path, reason);
+                continue;
+            }
+            tree.insert(path, RemoteEntry { id: file.id, is_dir: false, hash: file.hash });
+        }
+        Ok(tree)
+    }
+
+    fn unplaceable(&self, path: &str, tree: &BTreeMap<String, RemoteEntry>) -> Option<&'static str> {
+        if !is_syncable(name(path)) || path == STATE_DIR {
+            Some("the name can't be used on disk")
+        } else if tree.contains_key(path) {
+            Some("another entry on the server has this name")
+        } else {
+            None
+        }
+    }
+
+    /// Creates the folders that are new on one side on the other, parents
+    /// first. Folders gone from one side are returned, to be deleted on the
+    /// other once their files are synced: first the ones gone locally, then
+    /// the ones gone remotely.
+    async fn sync_folders(
+        &mut self,
+        paths: &BTreeSet<String>,
+        local: &BTreeMap<String, LocalEntry>,
+        remote: &BTreeMap<String, RemoteEntry>,
+        synced: &BTreeMap<String, Synced>,
+    ) -> Result<(Vec<String>, Vec<String>), Error> {
+        let mut local_gone = Vec::new();
+        let mut remote_gone = Vec::new();
+        for path in paths {
+            let in_local = local.get(path).is_some_and(|entry| entry.is_dir);
+            let in_remote = remote.get(path).filter(|entry| entry.is_dir);
+            let base = synced.get(path).filter(|entry| entry.is_dir);
+            match (in_local, in_remote, base) {
+                (true, Some(remote), base) => {
+                    if base.is_none_or(|base| base.remote_id != remote.id) {
+                        self.record_dir(path, remote.id).await?;
+                    }
+                }
+                (true, None, None) => {
+                    self.remote_folder(path).await?;
+                }
+                (false, Some(remote), None) => {
+                    fs::create_dir_all(local_path(&self.root, path)).await?;
+                    self.record_dir(path, remote.id).await?;
+                    self.actions.push(SyncAction::FolderCreatedLocally { path: path.clone() });
+                }
+                (true, None, Some(_)) => remote_gone.push(path.clone()),
+                (false, Some(_), Some(_)) => local_gone.push(path.clone()),
+                (false, None, Some(_)) => self.state.forget(path).await?,
+                (false, None, None) => {}
+            }
+        }
+        Ok((local_gone, remote_gone))
+    }
+
+    /// The id of the remote folder at `path`, created with its parents if
+    /// they are missing.
+    async fn remote_folder(&mut self, path: &str) -> Result<Option<i32>, Error> {
+        let mut parent_id = self.folder_id;
+        let mut current = String::new();
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            current = join(&current, name);
+            parent_id = match self.remote_dirs.get(&current) {
+                Some(id) => *id,
+                None => {
+                    let folder = self.client.create_folder(name, parent_id).await?;
+                    self.remote_dirs.insert(current.clone(), Some(folder.id));
+                    self.record_dir(&current, folder.id).await?;
+                    self.actions.push(SyncAction::FolderCreatedRemotely { path: current.clone() });
+                    Some(folder.id)
+                }
+            };
+        }
+        Ok(parent_id)
+    }
+
+    async fn record_dir(&self, path: &str, remote_id: i32) -> Result<(), Error> {
+        let entry = Synced { path: path.to_string(), is_dir: true, remote_id, hash: None, size: 0, modified_ns: 0 };
+        self.state.record(&entry).await
+    }
+
+    /// Renames the local files that moved on the server, rather than
+    /// downloading them again, as long as they didn't change locally.
+    async fn follow_remote_moves(
+        &mut self,
+        remote: &BTreeMap<String, RemoteEntry>,
+        local: &mut BTreeMap<String, LocalEntry>,
+        synced: &mut BTreeMap<String, Synced>,
+    ) -> Result<(), Error> {
+        let synced_paths: HashMap<i32, String> = synced
+            .values()
+            .filter(|base| !base.is_dir)
+            .map(|base| (base.remote_id, base.path.clone()))
+            .collect();
+        for (to, entry) in remote.iter().filter(|(_, entry)| !entry.is_dir) {
+            let Some(from) = synced_paths.get(&entry.id) else {
+                continue;
+            };
+            let unchanged = local.get(from).is_some_and(|file| !file.is_dir && file.hash == synced[from].hash);
+            if from == to || !unchanged || local.contains_key(to) || synced.contains_key(to) {
+                continue;
+            }
+            let target = local_path(&self.root, to);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::rename(local_path(&self.root, from), &target).await?;
+
+            let file = local.remove(from).expect("the file was found above");
+            let mut base = synced.remove(from).expect("the file was found above");
+            self.state.forget(from).await?;
+            base.path = to.clone();
+            self.state.record(&base).await?;
+            synced.insert(to.clone(), base);
+            local.insert(to.clone(), file);
+            self.actions.push(SyncAction::MovedLocally { from: from.clone(), to: to.clone() });
+        }
+        Ok(())
+    }
+
+    /// Moves the remote files that were renamed on disk, found by their
+    /// hash, rather than uploading them again.
+    async fn follow_local_moves(
+        &mut self,
+        remote: &mut BTreeMap<String, RemoteEntry>,
+        local: &BTreeMap<String, LocalEntry>,
+        synced: &mut BTreeMap<String, Synced>,
+    ) -> Result<(), Error> {
+        // Files gone from disk that are still the same on the server.
+        let mut gone: HashMap<String, Vec<String>> = HashMap::new();
+        for base in synced.values().filter(|base| !base.is_dir && !local.contains_key(&base.path)) {
+            let unchanged = remote.get(&base.path).is_some_and(|entry| entry.id == base.remote_id && entry.hash == base.hash);
+            if let (true, Some(hash)) = (unchanged, &base.hash) {
+                gone.entry(hash.clone()).or_default().push(base.path.clone());
+            }
+        }
+
+        for (to, file) in local.iter().filter(|(_, file)| !file.is_dir) {
+            if remote.contains_key(to) || synced.contains_key(to) {
+                continue;
+            }
+            let Some(from) = file.hash.as_ref().and_then(|hash| gone.get_mut(hash)).and_then(|paths| paths.pop()) else {
+                continue;
+            };
+            let entry = remote.remove(&from).expect("only remote files are gone");
+            let folder_id = self.remote_folder(parent(to)).await?;
+            self.client.move_file(entry.id, name(to), folder_id).await?;
+
+            self.state.forget(&from).await?;
+            let base = Synced {
+                path: to.clone(),
+                is_dir: false,
+                remote_id: entry.id,
+                hash: file.hash.clone(),
+                size: file.size,
+                modified_ns: file.modified_ns,
+            };
+            self.state.record(&base).await?;
+            synced.remove(&from);
+            synced.insert(to.clone(), base);
+            remote.insert(to.clone(), entry);
+            self.actions.push(SyncAction::MovedRemotely { from, to: to.clone() });
+        }
+        Ok(())
+    }
+
+    /// Syncs one file given how it is on disk, on the server, and how both
+    /// sides last agreed on it.
+    async fn sync_file(
+        &mut self,
+        path: &str,
+        local: Option<&LocalEntry>,
+        remote: Option<&RemoteEntry>,
+        base: Option<&Synced>,
+    ) -> Result<(), Error> {
+        let local_changed = match (local, base) {
+            (None, None) => false,
+            (Some(local), Some(base)) => local.hash != base.hash,
+            _ => true,
+        };
+        // Files uploaded before the server kept hashes have none, and never
+        // change without getting one.
+        let remote_changed = match (remote, base) {
+            (None, None) => false,
+            (Some(remote), Some(base)) => {
+                remote.id != base.remote_id || remote.hash.as_ref().is_some_and(|hash| Some(hash) != base.hash.as_ref())
+            }
+            _ => true,
+        };
+
+        match (local, remote) {
+            _ if !local_changed && !remote_changed => Ok(()),
+            (Some(local), Some(remote)) if !remote_changed => self.replace(path, local, remote).await,
+            (Some(local), Some(remote)) if local_changed => self.resolve(path, local, remote).await,
+            (Some(_), Some(remote)) => self.download(path, remote).await,
+            // Changes win over deletions.
+            (Some(local), None) if local_changed => {
+                self.upload(path, local).await?;
+                self.actions.push(SyncAction::Uploaded { path: path.to_string() });
+                Ok(())
+            }
+            (Some(_), None) => {
+                fs::remove_file(local_path(&self.root, path)).await?;
+                self.state.forget(path).await?;
+                self.actions.push(SyncAction::DeletedLocally { path: path.to_string() });
+                Ok(())
+            }
+            (None, Some(remote)) if remote_changed => self.download(path, remote).await,
+            (None, Some(remote)) => {
+                match self.client.delete_file(remote.id).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == Some(ErrorKind::NotFound) => {}
+                    Err(e) => return Err(e),
+                }
+                self.state.forget(path).await?;
+                self.actions.push(SyncAction::DeletedRemotely { path: path.to_string() });
+                Ok(())
+            }
+            (None, None) => self.state.forget(path).await,
+        }
+    }
+
+    async fn upload(&mut self, path: &str, local: &LocalEntry) -> Result<(), Error> {
+        let folder_id = self.remote_folder(parent(path)).await?;
+        let file = fs::File::open(local_path(&self.root, path)).await?;
+        let uploaded = self.client.upload_into(folder_id, name(path), file, local.size as u64, None).await?;
+        self.record_file(path, uploaded.id, Some(uploaded.content_hash), local).await
+    }
+
+    /// Uploads new contents of a file only changed locally. If the server got
+    /// other contents meanwhile, the next run sees a conflict.
+    async fn replace(&mut self, path: &str, local: &LocalEntry, remote: &RemoteEntry) -> Result<(), Error> {
+        let file = fs::File::open(local_path(&self.root, path)).await?;
+        let replaced = self
+            .client
+            .replace_file(remote.id, remote.hash.as_deref(), file, local.size as u64, None)
+            .await;
+        let replaced = match replaced {
+            Ok(replaced) => replaced,
+            Err(e) if e.kind() == Some(ErrorKind::Conflict) => {
+                self.skip(path, "the file changed on the server meanwhile");
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        self.record_file(path, replaced.id, Some(replaced.content_hash), local).await?;
+        self.actions.push(SyncAction::Uploaded { path: path.to_string() });
+        Ok(())
+    }
+
+    /// Puts the server's version of a file in place. It is downloaded next to
+    /// the state database first, so that an interrupted download never
+    /// leaves a partial file behind.
+    async fn download(&mut self, path: &str, remote: &RemoteEntry) -> Result<(), Error> {
+        let temporary = self.fetch(remote).await?;
+        self.put_in_place(path, &temporary, remote.id).await?;
+        self.actions.push(SyncAction::Downloaded { path: path.to_string() });
+        Ok(())
+    }
+
+    async fn fetch(&self, remote: &RemoteEntry) -> Result<PathBuf, Error> {
+        let temporary = self.root.join(STATE_DIR).join(format!("download-{}", remote.id));
+        if let Err(e) = self.client.download_file_to(remote.id, &temporary, None).await {
+            let _ = fs::remove_file(&temporary).await;
+            return Err(e);
+        }
+        Ok(temporary)
+    }
+
+    async fn put_in_place(&self, path: &str, temporary: &Path, remote_id: i32) -> Result<(), Error> {
+        let target = local_path(&self.root, path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(temporary, &target).await?;
+        let local = describe(&target).await?;
+        self.record_file(path, remote_id, local.hash.clone(), &local).await
+    }
+
+    /// Both sides changed the file. Unless they ended up the same, the local
+    /// version becomes a conflicted copy next to the server's.
+    async fn resolve(&mut self, path: &str, local: &LocalEntry, remote: &RemoteEntry) -> Result<(), Error> {
+        if remote.hash.is_some() && remote.hash == local.hash {
+            return self.record_file(path, remote.id, local.hash.clone(), local).await;
+        }
+        let temporary = self.fetch(remote).await?;
+        let fetched = temporary.clone();
+        let hash = tokio::task::spawn_blocking(move || hash_file(&fetched)).await.map_err(io::Error::other)??;
+        if Some(&hash) == local.hash.as_ref() {
+            fs::remove_file(&temporary).await?;
+            return self.record_file(path, remote.id, Some(hash), local).await;
+        }
+
+        let timestamp = chrono::Local::now().format("%Y-%m-%d %H%M%S").to_string();
+        let copy = conflicted_copy(&self.root, path, &timestamp);
+        fs::rename(local_path(&self.root, path), local_path(&self.root, &copy)).await?;
+        self.put_in_place(path, &temporary, remote.id).await?;
+        let copied = describe(&local_path(&self.root, &copy)).await?;
+        self.upload(&copy, &copied).await?;
+        self.actions.push(SyncAction::Conflicted { path: path.to_string(), copy });
+        Ok(())
+    }
+
+    async fn record_file(&self, path: &str, remote_id: i32, hash: Option<String>, local: &LocalEntry) -> Result<(), Error> {
+        let entry = Synced {
+            path: path.to_string(),
+            is_dir: false,
+            remote_id,
+            hash,
+            size: local.size,
+            modified_ns: local.modified_ns,
+        };
+        self.state.record(&entry).await
+    }
+
+    /// Deletes the folders gone from one side on the other, deepest first,
+    /// once their files are synced. A folder that got new files on the
+    /// other side stays, and is created again where it was deleted.
+    async fn delete_folders(&mut self, mut local_gone: Vec<String>, mut remote_gone: Vec<String>) -> Result<(), Error> {
+        local_gone.sort_by(|a, b| b.cmp(a));
+        for path in local_gone {
+            if fs::try_exists(local_path(&self.root, &path)).await? {
+                continue;
+            }
+            let Some(Some(id)) = self.remote_dirs.get(&path).copied() else {
+                continue;
+            };
+            match self.client.delete_folder(id).await {
+                Ok(()) => {
+                    self.remote_dirs.remove(&path);
+                    self.state.forget(&path).await?;
+                    self.actions.push(SyncAction::DeletedRemotely { path });
+                }
+                Err(e) if e.kind() == Some(ErrorKind::Conflict) => {
+                    self.skip(&path, "the folder isn't empty on the server");
+                }
+                Err(e) if e.kind() == Some(ErrorKind::NotFound) => self.state.forget(&path).await?,
+                Err(e) => return Err(e),
+            }
+        }
+
+        remote_gone.sort_by(|a, b| b.cmp(a));
+        for path in remote_gone {
+            if self.remote_dirs.contains_key(&path) {
+                continue;
+            }
+            let local = local_path(&self.root, &path);
+            let mut entries = fs::read_dir(&local).await?;
+            if entries.next_entry().await?.is_some() {
+                self.remote_folder(&path).await?;
+                continue;
+            }
+            fs::remove_dir(&local).await?;
+            self.state.forget(&path).await?;
+            self.actions.push(SyncAction::DeletedLocally { path });
+        }
+        Ok(())
+    }
+
+    fn skip(&mut self, path: &str, reason: &str) {
+        self.actions.push(SyncAction::Skipped { path: path.to_string(), reason: reason.to_string() });
+    }
+}
+
+/// A file as it is on disk now.
+async fn describe(path: &Path) -> Result<LocalEntry, Error> {
+    let metadata = fs::metadata(path).await?;
+    let hashed = path.to_path_buf();
+    let hash = tokio::task::spawn_blocking(move || hash_file(&hashed)).await.map_err(io::Error::other)??;
+    Ok(LocalEntry {
+        is_dir: false,
+        hash: Some(hash),
+        size: metadata.len() as i64,
+        modified_ns: modified_ns(&metadata),
+    })
+}
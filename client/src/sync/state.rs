@@ -0,0 +1,199 @@
+use std::{collections::BTreeMap, path::Path};
+
+use files_box_models::files::{Change, ChangeKind};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
+    Sqlite, Transaction,
+};
+
+use crate::error::Error;
+
+/// The remote entries as the change feed left them, and the entries as they
+/// were when both sides last agreed, which tells which side changed since.
+const SCHEMA: [&str; 4] = [
+    "CREATE TABLE IF NOT EXISTS settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS remote_folders (id INTEGER PRIMARY KEY, name TEXT NOT NULL, parent_id INTEGER)",
+    "CREATE TABLE IF NOT EXISTS remote_files (
+        id INTEGER PRIMARY KEY, name TEXT NOT NULL, folder_id INTEGER, size INTEGER NOT NULL, hash TEXT
+    )",
+    "CREATE TABLE IF NOT EXISTS synced (
+        path TEXT PRIMARY KEY, is_dir INTEGER NOT NULL, remote_id INTEGER NOT NULL, hash TEXT,
+        size INTEGER NOT NULL, modified_ns INTEGER NOT NULL
+    )",
+];
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct RemoteFolder {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct RemoteFile {
+    pub id: i32,
+    pub name: String,
+    pub folder_id: Option<i32>,
+    pub hash: Option<String>,
+}
+
+/// An entry both sides had the last time it was synced. `size` and
+/// `modified_ns` are the local ones, so that unchanged files aren't hashed again.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub(crate) struct Synced {
+    pub path: String,
+    pub is_dir: bool,
+    pub remote_id: i32,
+    pub hash: Option<String>,
+    pub size: i64,
+    pub modified_ns: i64,
+}
+
+/// The SQLite database of a synced directory.
+pub(crate) struct State {
+    pool: SqlitePool,
+}
+
+impl State {
+    pub async fn open(path: &Path) -> Result<State, Error> {
+        let options = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+        let pool = SqlitePoolOptions::new().max_connections(1).connect_with(options).await?;
+        for statement in SCHEMA {
+            sqlx::query(statement).execute(&pool).await?;
+        }
+        Ok(State { pool })
+    }
+
+    pub async fn close(self) {
+        self.pool.close().await;
+    }
+
+    pub async fn setting(&self, key: &str) -> Result<Option<String>, Error> {
+        Ok(sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    pub async fn set_setting(&self, key: &str, value: &str) -> Result<(), Error> {
+        set_setting(&self.pool, key, value).await
+    }
+
+    pub async fn cursor(&self) -> Result<i64, Error> {
+        Ok(self.setting("cursor").await?.and_then(|cursor| cursor.parse().ok()).unwrap_or(0))
+    }
+
+    /// Applies a page of the change feed to the remote entries and moves the
+    /// cursor past it, all at once.
+    pub async fn apply(&self, changes: &[Change], cursor: i64) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        for change in changes {
+            apply_change(&mut tx, change).await?;
+        }
+        set_setting(&mut *tx, "cursor", &cursor.to_string()).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Forgets the remote entries, to read the change feed again from the
+    /// start. What was synced stays, it tells the changes of both sides apart.
+    pub async fn reset_remote(&self) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM remote_files").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM remote_folders").execute(&mut *tx).await?;
+        set_setting(&mut *tx, "cursor", "0").await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn remote_folders(&self) -> Result<Vec<RemoteFolder>, Error> {
+        Ok(sqlx::query_as("SELECT id, name, parent_id FROM remote_folders ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    pub async fn remote_files(&self) -> Result<Vec<RemoteFile>, Error> {
+        Ok(sqlx::query_as("SELECT id, name, folder_id, hash FROM remote_files ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    pub async fn synced(&self) -> Result<BTreeMap<String, Synced>, Error> {
+        let entries: Vec<Synced> = sqlx::query_as("SELECT path, is_dir, remote_id, hash, size, modified_ns FROM synced")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(entries.into_iter().map(|entry| (entry.path.clone(), entry)).collect())
+    }
+
+    pub async fn record(&self, entry: &Synced) -> Result<(), Error> {
+        sqlx::query(
+            "INSERT INTO synced (path, is_dir, remote_id, hash, size, modified_ns) VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT (path) DO UPDATE SET is_dir = excluded.is_dir, remote_id = excluded.remote_id,
+             hash = excluded.hash, size = excluded.size, modified_ns = excluded.modified_ns",
+        )
+        .bind(&entry.path)
+        .bind(entry.is_dir)
+        .bind(entry.remote_id)
+        .bind(&entry.hash)
+        .bind(entry.size)
+        .bind(entry.modified_ns)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn forget(&self, path: &str) -> Result<(), Error> {
+        sqlx::query("DELETE FROM synced WHERE path = ?").bind(path).execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+async fn set_setting<'e>(executor: impl sqlx::Executor<'e, Database = Sqlite>, key: &str, value: &str) -> Result<(), Error> {
+    sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT (key) DO UPDATE SET value = excluded.value")
+        .bind(key)
+        .bind(value)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Changes carry the entry as it is after them, so anything but a deletion
+/// is stored as it comes.
+async fn apply_change(tx: &mut Transaction<'_, Sqlite>, change: &Change) -> Result<(), Error> {
+    let deleted = change.kind == ChangeKind::Deleted;
+    match (change.file_id, change.folder_id) {
+        (Some(id), _) if deleted => {
+            sqlx::query("DELETE FROM remote_files WHERE id = ?").bind(id).execute(&mut **tx).await?;
+        }
+        (Some(id), _) => {
+            sqlx::query(
+                "INSERT INTO remote_files (id, name, folder_id, size, hash) VALUES (?, ?, ?, ?, ?)
+                 ON CONFLICT (id) DO UPDATE SET name = excluded.name, folder_id = excluded.folder_id,
+                 size = excluded.size, hash = excluded.hash",
+            )
+            .bind(id)
+            .bind(&change.name)
+            .bind(change.parent_id)
+            .bind(change.file_size.unwrap_or_default())
+            .bind(&change.content_hash)
+            .execute(&mut **tx)
+            .await?;
+        }
+        (None, Some(id)) if deleted => {
+            sqlx::query("DELETE FROM remote_folders WHERE id = ?").bind(id).execute(&mut **tx).await?;
+        }
+        (None, Some(id)) => {
+            sqlx::query(
+                "INSERT INTO remote_folders (id, name, parent_id) VALUES (?, ?, ?)
+                 ON CONFLICT (id) DO UPDATE SET name = excluded.name, parent_id = excluded.parent_id",
+            )
+            .bind(id)
+            .bind(&change.name)
+            .bind(change.parent_id)
+            .execute(&mut **tx)
+            .await?;
+        }
+        (None, None) => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use files_box_models::user::{ChangeEmail, ChangePassword, DeleteUser, UpdateUser, User};
+
+use crate::{client::Client, error::Error};
+
+impl Client {
+    /// The profile of the logged in user.
+    pub async fn me(&self) -> Result<User, Error> {
+        let id = self.user_id()?;
+        self.authorized(|http| http.get(self.url(&format!("/user/{}", id)))).await
+    }
+
+    pub async fn update_me(&self, update: &UpdateUser) -> Result<User, Error> {
+        self.authorized(|http| http.patch(self.url("/user/me")).json(update)).await
+    }
+
+    pub async fn change_password(&self, current_password: &str, new_password: &str) -> Result<(), Error> {
+        let body = ChangePassword {
+            current_password: Some(current_password.to_string()),
+            new_password: new_password.to_string(),
+        };
+        self.authorized(|http| http.post(self.url("/user/me/password")).json(&body)).await
+    }
+
+    /// Emails a confirmation link to the new address, which replaces the
+    /// current one once followed.
+    pub async fn change_email(&self, email: &str, password: &str) -> Result<(), Error> {
+        let body = ChangeEmail { email: email.to_string(), password: Some(password.to_string()) };
+        self.authorized(|http| http.post(self.url("/user/me/email")).json(&body)).await
+    }
+
+    /// Deletes the account with every file, and ends the session.
+    pub async fn delete_me(&self, password: &str) -> Result<(), Error> {
+        let body = DeleteUser { password: Some(password.to_string()) };
+        self.authorized::<()>(|http| http.delete(self.url("/user/me")).json(&body)).await?;
+        self.logout();
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{TestServer, PASSWORD};
+use files_box_client::{models::UpdateUser, Error, ErrorKind, Progress, ProgressCallback};
+
+fn recorder() -> (ProgressCallback, Arc<Mutex<Vec<Progress>>>) {
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let recorded = updates.clone();
+    (Arc::new(move |progress| recorded.lock().unwrap().push(progress)), updates)
+}
+
+#[tokio::test]
+async fn files_round_trip_with_progress() {
+    let server = TestServer::start().await;
+    let client = server.verified_client("alice@example.com").await;
+    let local = tempfile::tempdir().unwrap();
+    let contents = vec![7u8; 200_000];
+    std::fs::write(local.path().join("photo.raw"), &contents).unwrap();
+
+    let (progress, uploads) = recorder();
+    let uploaded = client.upload_file(local.path().join("photo.raw"), Some(progress)).await.unwrap();
+    assert_eq!(uploaded.file_name, "photo.raw");
+    assert_eq!(uploaded.file_size, 200_000);
+    assert_eq!(
+        uploads.lock().unwrap().last(),
+        Some(&Progress { transferred: 200_000, total: Some(200_000) })
+    );
+
+    let files = client.list_files().await.unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].id, uploaded.id);
+
+    let (progress, downloads) = recorder();
+    let copy = local.path().join("copy.raw");
+    assert_eq!(client.download_file_to(uploaded.id, &copy, Some(progress)).await.unwrap(), 200_000);
+    assert_eq!(std::fs::read(&copy).unwrap(), contents);
+    assert_eq!(
+        downloads.lock().unwrap().last(),
+        Some(&Progress { transferred: 200_000, total: Some(200_000) })
+    );
+
+    client.delete_file(uploaded.id).await.unwrap();
+    assert!(client.list_files().await.unwrap().is_empty());
+    let missing = client.download_file(uploaded.id, &mut Vec::new(), None).await.unwrap_err();
+    assert_eq!(missing.kind(), Some(ErrorKind::NotFound));
+}
+
+#[tokio::test]
+async fn failures_carry_the_kind_of_the_server_error() {
+    let server = TestServer::start().await;
+    let client = server.client();
+
+    assert!(matches!(client.list_files().await, Err(Error::NotLoggedIn)));
+
+    client.register("alice@example.com", PASSWORD, "Alice").await.unwrap();
+    let wrong = client.login("alice@example.com", "Wr0ng!password").await.unwrap_err();
+    assert_eq!(wrong.kind(), Some(ErrorKind::InvalidCredentials));
+    let Error::Api { response, .. } = &wrong else {
+        panic!("not an API error: {:?}", wrong);
+    };
+    assert_eq!(response.code, 401);
+    assert_eq!(response.error, "invalid_credentials");
+
+    client.login("alice@example.com", PASSWORD).await.unwrap();
+    let unverified = client.upload("notes.txt", &b"hello"[..], 5, None).await.unwrap_err();
+    assert_eq!(unverified.kind(), Some(ErrorKind::EmailNotVerified));
+
+    let taken = server.client().register("alice@example.com", PASSWORD, "Alice").await.unwrap_err();
+    assert_eq!(taken.kind(), Some(ErrorKind::Invalid));
+}
+
+#[tokio::test]
+async fn profile_and_password() {
+    let server = TestServer::start().await;
+    let client = server.verified_client("alice@example.com").await;
+
+    let me = client.me().await.unwrap();
+    assert_eq!(me.email, "alice@example.com");
+    let update = UpdateUser { name: Some("Alice".to_string()), locale: None };
+    assert_eq!(client.update_me(&update).await.unwrap().name, "Alice");
+
+    let new_password = "Nw5$kTq9@xLm";
+    client.change_password(PASSWORD, new_password).await.unwrap();
+    let fresh = server.client();
+    assert!(fresh.login("alice@example.com", PASSWORD).await.is_err());
+    fresh.login("alice@example.com", new_password).await.unwrap();
+}
+
+#[tokio::test]
+async fn sessions_about_to_expire_are_renewed() {
+    let server = TestServer::start_with(|config| config.tokens.session_secs = 30).await;
+    let client = server.verified_client("alice@example.com").await;
+    let first = client.token().unwrap();
+
+    // Tokens carry their expiry in seconds, a new one differs a second later.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    client.me().await.unwrap();
+    let renewed = client.token().unwrap();
+    assert_ne!(first, renewed);
+
+    // Access tokens of devices are not.
+    let id = client.user_id().unwrap();
+    let device = server.state.auth.generate_access_token(id, "files:read", 30);
+    client.set_token(Some(device.clone()));
+    client.list_files().await.unwrap();
+    assert_eq!(client.token(), Some(device));
+}
+
+#[tokio::test]
+async fn devices_log_in_once_approved() {
+    let server = TestServer::start().await;
+    let user = server.verified_client("alice@example.com").await;
+    user.upload("notes.txt", &b"hello"[..], 5, None).await.unwrap();
+
+    let device = server.client();
+    let code = device.request_device_code("filesbox", Some("files:read")).await.unwrap();
+    assert_eq!(user.lookup_device(&code.user_code).await.unwrap().scope, "files:read");
+    user.approve_device(&code.user_code, true).await.unwrap();
+
+    let token = device.device_login(&code, "filesbox").await.unwrap();
+    assert_eq!(token.scope, "files:read");
+    assert_eq!(device.token(), Some(token.access_token));
+    assert_eq!(device.list_files().await.unwrap().len(), 1);
+    assert_eq!(device.quota().await.unwrap().used_bytes, 5);
+    let denied = device.delete_file(1).await.unwrap_err();
+    assert_eq!(denied.kind(), Some(ErrorKind::Forbidden));
+
+    let code = device.request_device_code("filesbox", None).await.unwrap();
+    user.approve_device(&code.user_code, false).await.unwrap();
+    let Err(Error::OAuth(e)) = device.device_login(&code, "filesbox").await else {
+        panic!("a denied device logged in");
+    };
+    assert_eq!(e.error, "access_denied");
+}
@@ -0,0 +1,98 @@
+//! Serves the whole API on a local port, over the in-memory repositories, a
+//! temporary storage directory and the in-memory mail transport.
+
+#![allow(dead_code)]
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, MutexGuard},
+};
+
+use files_box_client::Client;
+use server::{
+    config::outbox::deliver_due_emails,
+    models::{
+        app::AppState,
+        auth::Auth,
+        mail::{MailTransport, Mailer},
+        oidc::OidcProviders,
+        repository::{MemoryRepository, MemoryState},
+        settings::{Config, MailTransportKind},
+    },
+    routes::app_router::app_router,
+};
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+
+pub const PASSWORD: &str = "Zq8!vLp3#rT";
+
+pub struct TestServer {
+    pub base_url: String,
+    pub state: AppState,
+    pub memory: Arc<MemoryRepository>,
+    pub mailer: Mailer,
+    pub storage: TempDir,
+}
+
+impl TestServer {
+    pub async fn start() -> TestServer {
+        TestServer::start_with(|_| {}).await
+    }
+
+    /// Starts the server with `configure` applied to the test configuration.
+    pub async fn start_with(configure: impl FnOnce(&mut Config)) -> TestServer {
+        let storage = TempDir::new().expect("cannot create the storage directory");
+        let mut config = Config::default();
+        config.auth.secret_key = Some("test-secret".to_string());
+        config.storage.root = storage.path().to_path_buf();
+        config.mail.transport = Some(MailTransportKind::Memory);
+        // Hashing with the default parameters takes seconds in debug builds.
+        config.password.argon2_memory_kib = 8;
+        config.password.argon2_iterations = 1;
+        configure(&mut config);
+
+        let auth = Auth::load(&config).expect("cannot load the token keys");
+        let oidc = OidcProviders::load(&config);
+        let mailer = Mailer::load(&config.mail).expect("cannot set up the mail transport");
+        let memory = Arc::new(MemoryRepository::default());
+        let state = AppState::with_repository(memory.clone(), config, auth, oidc);
+        let app = app_router(&state);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+        });
+        TestServer { base_url: format!("http://{}", address), state, memory, mailer, storage }
+    }
+
+    /// The data of the server, to set up what the API can't.
+    pub fn data(&self) -> MutexGuard<'_, MemoryState> {
+        self.memory.state.lock().unwrap()
+    }
+
+    pub fn client(&self) -> Client {
+        Client::new(&self.base_url)
+    }
+
+    /// The word after `marker` in the latest email sent to `to`, once the
+    /// outbox is delivered.
+    pub async fn emailed(&self, to: &str, marker: &str) -> String {
+        while deliver_due_emails(&*self.state.outbox, &self.mailer).await > 0 {}
+        let MailTransport::Memory(sent) = &self.mailer.transport else {
+            panic!("the mail transport is not the in-memory one");
+        };
+        let sent = sent.lock().unwrap();
+        let email = sent.iter().rev().find(|email| email.to == to).expect("no email sent");
+        let start = email.text_body.find(marker).expect("marker not found") + marker.len();
+        email.text_body[start..].chars().take_while(|c| c.is_ascii_alphanumeric()).collect()
+    }
+
+    /// A client logged in as a new account with a verified email.
+    pub async fn verified_client(&self, email: &str) -> Client {
+        let client = self.client();
+        client.register(email, PASSWORD, "Test").await.unwrap();
+        client.verify_email(&self.emailed(email, "token=").await).await.unwrap();
+        client.login(email, PASSWORD).await.unwrap();
+        client
+    }
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use std::{fs, path::Path};
+
+use chrono::Duration;
+use common::{TestServer, PASSWORD};
+use files_box_client::{Error, SyncAction, SyncReport};
+use server::config::changes::compact_changes;
+
+fn read(dir: &Path, path: &str) -> String {
+    fs::read_to_string(dir.join(path)).unwrap()
+}
+
+fn write(dir: &Path, path: &str, contents: &str) {
+    let path = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+/// The synced files under `dir`, without the state.
+fn tree(dir: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(current).unwrap() {
+            let path = entry.unwrap().path();
+            let relative = path.strip_prefix(dir).unwrap().to_string_lossy().into_owned();
+            if relative == ".filesbox" {
+                continue;
+            }
+            match path.is_dir() {
+                true => pending.push(path),
+                false => files.push(relative),
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn acted(report: &SyncReport, action: SyncAction) -> bool {
+    report.actions.contains(&action)
+}
+
+fn path(path: &str) -> String {
+    path.to_string()
+}
+
+#[tokio::test]
+async fn two_directories_stay_in_sync() {
+    let server = TestServer::start().await;
+    let laptop = server.verified_client("alice@example.com").await;
+    let desktop = server.client();
+    desktop.login("alice@example.com", PASSWORD).await.unwrap();
+    let folder = laptop.create_folder("Sync", None).await.unwrap();
+    let (a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let (a, b) = (a.path(), b.path());
+
+    write(a, "notes.txt", "hello");
+    write(a, "docs/plan.md", "v1");
+    let report = laptop.sync(a, Some(folder.id)).await.unwrap();
+    assert!(acted(&report, SyncAction::FolderCreatedRemotely { path: path("docs") }));
+    assert!(acted(&report, SyncAction::Uploaded { path: path("docs/plan.md") }));
+    let report = desktop.sync(b, Some(folder.id)).await.unwrap();
+    assert!(acted(&report, SyncAction::Downloaded { path: path("notes.txt") }));
+    assert_eq!(tree(b), ["docs/plan.md", "notes.txt"]);
+    assert_eq!(read(b, "docs/plan.md"), "v1");
+    // Files outside the folder aren't synced.
+    laptop.upload("outside.txt", &b"x"[..], 1, None).await.unwrap();
+    assert!(laptop.sync(a, Some(folder.id)).await.unwrap().actions.is_empty());
+
+    // A modification on one side, a rename on the other.
+    write(b, "docs/plan.md", "version 2");
+    fs::rename(a.join("notes.txt"), a.join("readme.txt")).unwrap();
+    desktop.sync(b, Some(folder.id)).await.unwrap();
+    let report = laptop.sync(a, Some(folder.id)).await.unwrap();
+    assert!(acted(&report, SyncAction::MovedRemotely { from: path("notes.txt"), to: path("readme.txt") }));
+    assert!(acted(&report, SyncAction::Downloaded { path: path("docs/plan.md") }));
+    let report = desktop.sync(b, Some(folder.id)).await.unwrap();
+    assert!(acted(&report, SyncAction::MovedLocally { from: path("notes.txt"), to: path("readme.txt") }));
+    assert_eq!(tree(a), tree(b));
+    assert_eq!(read(a, "docs/plan.md"), "version 2");
+
+    // Both sides change the same file.
+    write(a, "readme.txt", "from the laptop");
+    write(b, "readme.txt", "from the desktop!");
+    laptop.sync(a, Some(folder.id)).await.unwrap();
+    let report = desktop.sync(b, Some(folder.id)).await.unwrap();
+    let copy = report
+        .actions
+        .iter()
+        .find_map(|action| match action {
+            SyncAction::Conflicted { path, copy } if path == "readme.txt" => Some(copy.clone()),
+            _ => None,
+        })
+        .expect("no conflict");
+    assert!(copy.starts_with("readme (conflicted copy "), "{}", copy);
+    assert_eq!(read(b, "readme.txt"), "from the laptop");
+    assert_eq!(read(b, &copy), "from the desktop!");
+    laptop.sync(a, Some(folder.id)).await.unwrap();
+    assert_eq!(read(a, &copy), "from the desktop!");
+
+    // Deleting a directory deletes its folder on the server and elsewhere.
+    fs::remove_dir_all(a.join("docs")).unwrap();
+    let report = laptop.sync(a, Some(folder.id)).await.unwrap();
+    assert!(acted(&report, SyncAction::DeletedRemotely { path: path("docs") }));
+    let report = desktop.sync(b, Some(folder.id)).await.unwrap();
+    assert!(acted(&report, SyncAction::DeletedLocally { path: path("docs") }));
+    assert!(!b.join("docs").exists());
+    assert_eq!(tree(a), tree(b));
+    assert!(desktop.sync(b, Some(folder.id)).await.unwrap().actions.is_empty());
+    assert!(laptop.sync(a, Some(folder.id)).await.unwrap().actions.is_empty());
+}
+
+#[tokio::test]
+async fn a_directory_stays_with_its_folder() {
+    let server = TestServer::start().await;
+    let client = server.verified_client("alice@example.com").await;
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "a.txt", "a");
+    write(dir.path(), " padded.txt", "b");
+
+    let report = client.sync(dir.path(), None).await.unwrap();
+    assert!(report.actions.iter().any(|action| matches!(action, SyncAction::Skipped { path, .. } if path == " padded.txt")));
+    let files = client.list_files().await.unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].file_name, "a.txt");
+
+    let folder = client.create_folder("Other", None).await.unwrap();
+    let other = client.sync(dir.path(), Some(folder.id)).await.unwrap_err();
+    assert!(matches!(other, Error::Sync(_)), "{}", other);
+}
+
+#[tokio::test]
+async fn a_directory_syncs_again_once_its_cursor_expires() {
+    let server = TestServer::start().await;
+    let laptop = server.verified_client("alice@example.com").await;
+    let desktop = server.client();
+    desktop.login("alice@example.com", PASSWORD).await.unwrap();
+    let (a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let (a, b) = (a.path(), b.path());
+    write(a, "keep.txt", "keep");
+    write(a, "old.txt", "old");
+    laptop.sync(a, None).await.unwrap();
+    desktop.sync(b, None).await.unwrap();
+
+    fs::remove_file(b.join("old.txt")).unwrap();
+    write(b, "new.txt", "new");
+    desktop.sync(b, None).await.unwrap();
+    // The laptop was away past the retention.
+    for change in server.data().changes.iter_mut() {
+        change.change.created_at -= Duration::days(40);
+    }
+    assert!(compact_changes(&*server.state.changes, 30).await.unwrap() > 0);
+
+    let report = laptop.sync(a, None).await.unwrap();
+    assert_eq!(
+        report.actions,
+        [SyncAction::Downloaded { path: path("new.txt") }, SyncAction::DeletedLocally { path: path("old.txt") }]
+    );
+    assert_eq!(tree(a), ["keep.txt", "new.txt"]);
+    assert!(laptop.sync(a, None).await.unwrap().actions.is_empty());
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// `Response` of a success that carries `T`, as the OpenAPI document shows it
+/// and clients read it.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ApiResponse<T> {
+    /// The outcome, which the responses of the document are listed by. The
+    /// HTTP status itself is 200.
+    pub code: i32,
+    /// Translated into the language of the request.
+    pub message: Option<String>,
+    pub data: T,
+}
+
+/// `Response` of a success without data.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ApiMessage {
+    pub code: i32,
+    pub message: Option<String>,
+    #[schema(value_type = Option<Object>, example = json!(null))]
+    pub data: Option<Value>,
+}
+
+/// `Response` of a failure.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ApiError {
+    /// The outcome, as for successes.
+    pub code: i32,
+    /// Key of the message, which doesn't depend on the language.
+    #[schema(example = "unauthorized")]
+    pub error: String,
+    pub message: String,
+    /// Details of some failures, e.g. `retry_after` in seconds for 429.
+    #[schema(value_type = Option<Object>)]
+    pub data: Option<Value>,
+}
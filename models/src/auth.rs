@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RegisterUser {
+    pub email: String,
+    pub password: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
+pub struct VerifyEmail {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LoginUser {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ResetPassword {
+    pub email: String,
+    pub code: String,
+    pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, IntoParams)]
+pub struct MagicLinkLogin {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+/// A session token, returned by every way of logging in.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Token {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Registered {
+    pub user_id: i32,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct VerifiedEmail {
+    pub email: String,
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+// Added to the interval whenever a device polls too fast (RFC 8628, section 3.5).
+pub const SLOW_DOWN_SECS: i32 = 5;
+pub const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DeviceCodeRequest {
+    pub client_id: String,
+    /// Space separated, `files:read files:write` when omitted.
+    pub scope: Option<String>,
+}
+
+/// Device authorization response (RFC 8628, section 3.2).
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    pub interval: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DeviceTokenRequest {
+    pub grant_type: String,
+    pub device_code: String,
+    pub client_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DeviceTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub scope: String,
+}
+
+/// OAuth error response (RFC 6749, section 5.2).
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OAuthError {
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
+pub struct DeviceLookup {
+    pub user_code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DeviceApproval {
+    pub user_code: String,
+    pub approve: bool,
+}
+
+/// What the user approves or denies on the verification page.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct PendingDeviceAuthorization {
+    pub user_code: String,
+    pub client_id: String,
+    pub scope: String,
+    pub expires_at: DateTime<Utc>,
+}
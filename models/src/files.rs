@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub const MAX_ENTRY_NAME_LENGTH: usize = 255;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetFiles {
+    /// `[-1]` lists every personal file.
+    pub file_ids: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FileData {
+    pub id: i32,
+    pub file_name: String,
+    pub file_path: String,
+    pub file_size: i32,
+    pub file_content_type: String,
+    pub file_type: String,
+    /// Owner of a personal file, uploader of a team drive file.
+    pub user_id: Option<i32>,
+    pub team_drive_id: Option<i32>,
+    /// Folder of a personal file, `None` at the root.
+    pub folder_id: Option<i32>,
+    /// SHA-256 of the contents in hex, `None` for files uploaded before it was kept.
+    pub content_hash: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Folder {
+    pub id: i32,
+    pub name: String,
+    pub user_id: i32,
+    /// `None` at the root of the personal space.
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FolderList {
+    pub folders: Vec<Folder>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CreateFolder {
+    pub name: String,
+    pub parent_id: Option<i32>,
+}
+
+/// Renames a folder and puts it in `parent_id`, the root when it is `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MoveFolder {
+    pub name: String,
+    pub parent_id: Option<i32>,
+}
+
+/// Renames a personal file and puts it in `folder_id`, the root when it is `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MoveFile {
+    pub file_id: i32,
+    pub file_name: String,
+    pub folder_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UploadedFile {
+    pub id: i32,
+    pub file_name: String,
+    pub file_size: i32,
+    pub content_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct FileList {
+    pub files: Vec<FileData>,
+}
+
+/// Space taken by the personal files and the quota that applies to them.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct StorageQuota {
+    pub used_bytes: i64,
+    pub quota_bytes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    /// New contents.
+    Modified,
+    /// Renamed, or put in another folder.
+    Moved,
+    Deleted,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Moved => "moved",
+            ChangeKind::Deleted => "deleted",
+        }
+    }
+
+    /// Reads a kind as stored in the database.
+    pub fn parse(kind: &str) -> ChangeKind {
+        match kind {
+            "created" => ChangeKind::Created,
+            "modified" => ChangeKind::Modified,
+            "moved" => ChangeKind::Moved,
+            _ => ChangeKind::Deleted,
+        }
+    }
+}
+
+/// A change to a personal file or folder, or to a file of a team drive, with
+/// the entry as it is after it.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Change {
+    /// The cursor to continue from once this change is applied.
+    pub id: i64,
+    pub kind: ChangeKind,
+    /// Set for files of team drives, which only come with the events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team_drive_id: Option<i32>,
+    /// Set for files.
+    pub file_id: Option<i32>,
+    /// Set for folders.
+    pub folder_id: Option<i32>,
+    pub name: String,
+    /// The folder of a file, or the parent of a folder, `None` at the root.
+    pub parent_id: Option<i32>,
+    pub file_size: Option<i32>,
+    pub content_hash: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ChangePage {
+    pub changes: Vec<Change>,
+    /// The id of the last change returned, or the cursor asked for if there
+    /// are none. Changes after it come with the next call.
+    pub cursor: i64,
+    /// Whether more changes are waiting after `cursor`.
+    pub has_more: bool,
+}
+
+/// Trims the name of a folder or of a moved file, or returns `None` if it
+/// can't be one: clients that sync create entries under these names on disk.
+pub fn normalize_entry_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_ENTRY_NAME_LENGTH
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', '\0']);
+    valid.then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_that_cannot_be_paths_are_rejected() {
+        assert_eq!(normalize_entry_name("  Photos "), Some("Photos".to_string()));
+        for name in ["", "  ", ".", "..", "a/b", "a\\b"] {
+            assert_eq!(normalize_entry_name(name), None, "{:?}", name);
+        }
+        assert_eq!(normalize_entry_name(&"x".repeat(256)), None);
+    }
+}
@@ -0,0 +1,9 @@
+//! The request and response bodies of the files-box API, shared by the server
+//! and its clients so that both read and write the same JSON.
+
+pub mod api;
+pub mod auth;
+pub mod device;
+pub mod files;
+pub mod org;
+pub mod user;
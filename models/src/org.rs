@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A shared drive whose files belong to the organization.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct TeamDrive {
+    pub id: i32,
+    pub organization_id: i32,
+    pub name: String,
+    /// Quota set by an admin, `None` for the default.
+    pub quota_bytes: Option<i64>,
+    pub effective_quota_bytes: i64,
+    pub used_bytes: i64,
+    pub file_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct TeamDriveList {
+    pub drives: Vec<TeamDrive>,
+}
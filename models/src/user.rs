@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct User {
+    pub id: i32,
+    pub email: String,
+    pub password: Option<String>,
+    pub name: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub is_admin: bool,
+    pub disabled_at: Option<DateTime<Utc>>,
+    /// Set by an admin. The account can't be used until the password is reset.
+    pub password_reset_required: bool,
+    /// Storage limit in bytes, `None` for the default quota.
+    pub quota_bytes: Option<i64>,
+    /// Language of messages and emails, `None` to follow `Accept-Language`.
+    pub locale: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ChangeEmail {
+    pub email: String,
+    /// Required when the account has a password.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UpdateUser {
+    pub name: Option<String>,
+    /// `en` or `ru`, an empty string to follow `Accept-Language` again.
+    pub locale: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ChangePassword {
+    /// Required when the account has a password. Accounts without one set it
+    /// within `tokens.reauthentication_secs` of a login.
+    #[serde(default)]
+    pub current_password: Option<String>,
+    pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DeleteUser {
+    /// Required when the account has a password.
+    #[serde(default)]
+    pub password: Option<String>,
+}
@@ -0,0 +1,54 @@
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS organizations (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS organization_members (
+    organization_id INT NOT NULL,
+    user_id INT NOT NULL,
+    -- owner, admin or member
+    role VARCHAR(16) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (organization_id, user_id),
+    FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS organization_members_user_id_idx ON organization_members (user_id);
+
+CREATE TABLE IF NOT EXISTS organization_invitations (
+    id SERIAL PRIMARY KEY,
+    organization_id INT NOT NULL,
+    email VARCHAR(255) NOT NULL,
+    role VARCHAR(16) NOT NULL,
+    token VARCHAR(255) NOT NULL UNIQUE,
+    invited_by INT,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE,
+    FOREIGN KEY (invited_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS team_drives (
+    id SERIAL PRIMARY KEY,
+    organization_id INT NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    -- NULL means the default quota from TEAM_DRIVE_QUOTA_BYTES.
+    quota_bytes BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE
+);
+
+-- Files in a team drive belong to the drive. Their user_id is only the
+-- uploader and is cleared when that account is deleted.
+ALTER TABLE files ADD COLUMN IF NOT EXISTS team_drive_id INT REFERENCES team_drives(id) ON DELETE CASCADE;
+ALTER TABLE files ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE files DROP CONSTRAINT IF EXISTS files_owner_check;
+ALTER TABLE files ADD CONSTRAINT files_owner_check CHECK (user_id IS NOT NULL OR team_drive_id IS NOT NULL);
+
+CREATE INDEX IF NOT EXISTS files_team_drive_id_idx ON files (team_drive_id);
+
+COMMIT;
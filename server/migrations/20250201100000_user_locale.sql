@@ -0,0 +1,7 @@
+BEGIN;
+
+-- Language chosen by the user for API messages and emails. NULL follows the
+-- Accept-Language header of each request.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locale TEXT;
+
+COMMIT;
@@ -0,0 +1,7 @@
+BEGIN;
+
+-- Id of the request that queued the email, so that the logs of its delivery
+-- can be matched with the ones of the request.
+ALTER TABLE email_outbox ADD COLUMN IF NOT EXISTS request_id TEXT;
+
+COMMIT;
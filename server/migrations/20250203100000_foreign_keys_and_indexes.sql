@@ -0,0 +1,19 @@
+-- Codes and folders go with their user.
+ALTER TABLE codes DROP CONSTRAINT IF EXISTS codes_user_id_fkey;
+ALTER TABLE codes ADD CONSTRAINT codes_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE folders DROP CONSTRAINT IF EXISTS folders_user_id_fkey;
+ALTER TABLE folders ADD CONSTRAINT folders_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+-- Team drive files stay without their uploader. Personal files have to be
+-- deleted first, with their content on disk: files_owner_check stops a user
+-- who still has some from being deleted.
+ALTER TABLE files DROP CONSTRAINT IF EXISTS files_user_id_fkey;
+ALTER TABLE files ADD CONSTRAINT files_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS files_user_id_idx ON files (user_id);
+CREATE INDEX IF NOT EXISTS codes_user_id_idx ON codes (user_id);
+CREATE INDEX IF NOT EXISTS folders_user_id_idx ON folders (user_id);
+-- Emails are looked up case-insensitively, through users_email_lower_key.
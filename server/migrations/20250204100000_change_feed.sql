@@ -0,0 +1,113 @@
+-- Folders nest, personal files live in a folder or at the root of the space.
+ALTER TABLE folders ADD COLUMN IF NOT EXISTS parent_id INT REFERENCES folders(id);
+ALTER TABLE files ADD COLUMN IF NOT EXISTS folder_id INT REFERENCES folders(id);
+-- SHA-256 of the contents in hex, NULL for files uploaded before it was kept.
+ALTER TABLE files ADD COLUMN IF NOT EXISTS content_hash VARCHAR(64);
+
+CREATE INDEX IF NOT EXISTS folders_parent_id_idx ON folders (parent_id);
+CREATE INDEX IF NOT EXISTS files_folder_id_idx ON files (folder_id);
+
+-- Every change to the personal files and folders of a user, with the entry as
+-- it is after the change. The id is the cursor clients sync from.
+CREATE TABLE IF NOT EXISTS changes (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    -- created, modified, moved or deleted
+    kind VARCHAR(16) NOT NULL,
+    -- One of the two is set. There is no foreign key: deleted entries keep
+    -- their changes.
+    file_id INT,
+    folder_id INT,
+    name VARCHAR(255) NOT NULL,
+    -- The folder of a file, or the parent of a folder, NULL at the root.
+    parent_id INT,
+    file_size INT,
+    content_hash VARCHAR(64),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS changes_user_id_idx ON changes (user_id, id);
+
+-- Changes of a user get their id in commit order: the lock is taken before
+-- the id and held until the transaction ends, so a client that has seen a
+-- change never misses one committed later with a smaller id. Entries removed
+-- along with their user aren't recorded.
+CREATE OR REPLACE FUNCTION record_change(
+    change_user_id INT, change_kind TEXT, change_file_id INT, change_folder_id INT, change_name TEXT,
+    change_parent_id INT, change_file_size INT, change_content_hash TEXT
+) RETURNS VOID AS $$
+BEGIN
+    IF change_user_id IS NULL OR NOT EXISTS (SELECT 1 FROM users WHERE id = change_user_id) THEN
+        RETURN;
+    END IF;
+    PERFORM pg_advisory_xact_lock(hashtext('changes'), change_user_id);
+    INSERT INTO changes (user_id, kind, file_id, folder_id, name, parent_id, file_size, content_hash)
+    VALUES (change_user_id, change_kind, change_file_id, change_folder_id, change_name, change_parent_id,
+            change_file_size, change_content_hash);
+END;
+$$ LANGUAGE plpgsql;
+
+-- Team drive files have no feed.
+CREATE OR REPLACE FUNCTION files_record_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        IF OLD.team_drive_id IS NULL THEN
+            PERFORM record_change(OLD.user_id, 'deleted', OLD.id, NULL, OLD.file_name, OLD.folder_id, OLD.file_size,
+                                  OLD.content_hash);
+        END IF;
+        RETURN NULL;
+    END IF;
+    IF NEW.team_drive_id IS NOT NULL THEN
+        RETURN NULL;
+    END IF;
+    IF TG_OP = 'INSERT' THEN
+        PERFORM record_change(NEW.user_id, 'created', NEW.id, NULL, NEW.file_name, NEW.folder_id, NEW.file_size,
+                              NEW.content_hash);
+        RETURN NULL;
+    END IF;
+    IF NEW.file_path IS DISTINCT FROM OLD.file_path OR NEW.content_hash IS DISTINCT FROM OLD.content_hash THEN
+        PERFORM record_change(NEW.user_id, 'modified', NEW.id, NULL, NEW.file_name, NEW.folder_id, NEW.file_size,
+                              NEW.content_hash);
+    END IF;
+    IF NEW.file_name IS DISTINCT FROM OLD.file_name OR NEW.folder_id IS DISTINCT FROM OLD.folder_id THEN
+        PERFORM record_change(NEW.user_id, 'moved', NEW.id, NULL, NEW.file_name, NEW.folder_id, NEW.file_size,
+                              NEW.content_hash);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS files_record_change ON files;
+CREATE TRIGGER files_record_change
+    AFTER INSERT OR UPDATE OR DELETE ON files
+    FOR EACH ROW EXECUTE FUNCTION files_record_change();
+
+CREATE OR REPLACE FUNCTION folders_record_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_change(OLD.user_id, 'deleted', NULL, OLD.id, OLD.name, OLD.parent_id, NULL, NULL);
+    ELSIF TG_OP = 'INSERT' THEN
+        PERFORM record_change(NEW.user_id, 'created', NULL, NEW.id, NEW.name, NEW.parent_id, NULL, NULL);
+    ELSIF NEW.name IS DISTINCT FROM OLD.name OR NEW.parent_id IS DISTINCT FROM OLD.parent_id THEN
+        PERFORM record_change(NEW.user_id, 'moved', NULL, NEW.id, NEW.name, NEW.parent_id, NULL, NULL);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS folders_record_change ON folders;
+CREATE TRIGGER folders_record_change
+    AFTER INSERT OR UPDATE OR DELETE ON folders
+    FOR EACH ROW EXECUTE FUNCTION folders_record_change();
+
+-- Entries that existed before the feed start it as created.
+INSERT INTO changes (user_id, kind, folder_id, name, parent_id)
+SELECT user_id, 'created', id, name, parent_id FROM folders
+WHERE NOT EXISTS (SELECT 1 FROM changes)
+ORDER BY id;
+INSERT INTO changes (user_id, kind, file_id, name, parent_id, file_size, content_hash)
+SELECT user_id, 'created', id, file_name, folder_id, file_size, content_hash FROM files
+WHERE team_drive_id IS NULL AND user_id IS NOT NULL
+  AND NOT EXISTS (SELECT 1 FROM changes WHERE file_id IS NOT NULL)
+ORDER BY id;
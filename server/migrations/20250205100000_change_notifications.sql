@@ -0,0 +1,72 @@
+-- Team drive files join the feed, for the members of their organization:
+-- their changes have a team_drive_id and no user_id. There is no foreign key,
+-- deleting a drive records the deletion of its files.
+ALTER TABLE changes ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE changes ADD COLUMN IF NOT EXISTS team_drive_id INT;
+ALTER TABLE changes DROP CONSTRAINT IF EXISTS changes_owner_check;
+ALTER TABLE changes ADD CONSTRAINT changes_owner_check CHECK (user_id IS NOT NULL OR team_drive_id IS NOT NULL);
+
+CREATE INDEX IF NOT EXISTS changes_team_drive_id_idx ON changes (team_drive_id, id);
+
+DROP FUNCTION IF EXISTS record_change(INT, TEXT, INT, INT, TEXT, INT, INT, TEXT);
+
+-- The events of a user mix their own changes with those of their drives, so
+-- ids are now handed out in commit order across all users: the lock is taken
+-- before the id and held until the transaction ends. Every change is also
+-- sent on the 'changes' channel once committed, for the servers listening.
+CREATE OR REPLACE FUNCTION record_change(
+    change_user_id INT, change_team_drive_id INT, change_kind TEXT, change_file_id INT, change_folder_id INT,
+    change_name TEXT, change_parent_id INT, change_file_size INT, change_content_hash TEXT
+) RETURNS VOID AS $$
+DECLARE
+    recorded changes;
+BEGIN
+    IF change_team_drive_id IS NULL
+        AND (change_user_id IS NULL OR NOT EXISTS (SELECT 1 FROM users WHERE id = change_user_id)) THEN
+        RETURN;
+    END IF;
+    PERFORM pg_advisory_xact_lock(hashtext('changes'));
+    INSERT INTO changes (user_id, team_drive_id, kind, file_id, folder_id, name, parent_id, file_size, content_hash)
+    VALUES (CASE WHEN change_team_drive_id IS NULL THEN change_user_id END, change_team_drive_id, change_kind,
+            change_file_id, change_folder_id, change_name, change_parent_id, change_file_size, change_content_hash)
+    RETURNING * INTO recorded;
+    PERFORM pg_notify('changes', row_to_json(recorded)::text);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION files_record_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_change(OLD.user_id, OLD.team_drive_id, 'deleted', OLD.id, NULL, OLD.file_name, OLD.folder_id,
+                              OLD.file_size, OLD.content_hash);
+        RETURN NULL;
+    END IF;
+    IF TG_OP = 'INSERT' THEN
+        PERFORM record_change(NEW.user_id, NEW.team_drive_id, 'created', NEW.id, NULL, NEW.file_name, NEW.folder_id,
+                              NEW.file_size, NEW.content_hash);
+        RETURN NULL;
+    END IF;
+    IF NEW.file_path IS DISTINCT FROM OLD.file_path OR NEW.content_hash IS DISTINCT FROM OLD.content_hash THEN
+        PERFORM record_change(NEW.user_id, NEW.team_drive_id, 'modified', NEW.id, NULL, NEW.file_name, NEW.folder_id,
+                              NEW.file_size, NEW.content_hash);
+    END IF;
+    IF NEW.file_name IS DISTINCT FROM OLD.file_name OR NEW.folder_id IS DISTINCT FROM OLD.folder_id THEN
+        PERFORM record_change(NEW.user_id, NEW.team_drive_id, 'moved', NEW.id, NULL, NEW.file_name, NEW.folder_id,
+                              NEW.file_size, NEW.content_hash);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION folders_record_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM record_change(OLD.user_id, NULL, 'deleted', NULL, OLD.id, OLD.name, OLD.parent_id, NULL, NULL);
+    ELSIF TG_OP = 'INSERT' THEN
+        PERFORM record_change(NEW.user_id, NULL, 'created', NULL, NEW.id, NEW.name, NEW.parent_id, NULL, NULL);
+    ELSIF NEW.name IS DISTINCT FROM OLD.name OR NEW.parent_id IS DISTINCT FROM OLD.parent_id THEN
+        PERFORM record_change(NEW.user_id, NULL, 'moved', NULL, NEW.id, NEW.name, NEW.parent_id, NULL, NULL);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,6 @@
+-- Accounts from before email verification never got a link to follow, so
+-- they count as verified, as they did before. Every later registration got
+-- one, which stays until its address is verified.
+UPDATE users SET email_verified_at = NOW()
+WHERE email_verified_at IS NULL
+  AND NOT EXISTS (SELECT 1 FROM email_verifications WHERE email_verifications.user_id = users.id);
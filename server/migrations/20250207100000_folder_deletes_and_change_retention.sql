@@ -0,0 +1,22 @@
+-- Only empty folders are deleted. Whatever is put in one while it is deleted
+-- moves to the root, rather than failing the deletion or going with it.
+ALTER TABLE folders DROP CONSTRAINT IF EXISTS folders_parent_id_fkey;
+ALTER TABLE folders ADD CONSTRAINT folders_parent_id_fkey
+    FOREIGN KEY (parent_id) REFERENCES folders(id) ON DELETE SET NULL;
+ALTER TABLE files DROP CONSTRAINT IF EXISTS files_folder_id_fkey;
+ALTER TABLE files ADD CONSTRAINT files_folder_id_fkey
+    FOREIGN KEY (folder_id) REFERENCES folders(id) ON DELETE SET NULL;
+
+-- Changes past the retention are removed when a later change of their entry
+-- supersedes them, and deletions altogether: a feed read from the start still
+-- has every entry as it is. A cursor below the last removed change may have
+-- missed some, its client syncs again from the start.
+CREATE TABLE IF NOT EXISTS change_retention (
+    only_row BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (only_row),
+    compacted_through BIGINT NOT NULL
+);
+INSERT INTO change_retention (compacted_through) VALUES (0) ON CONFLICT DO NOTHING;
+
+CREATE INDEX IF NOT EXISTS changes_file_id_idx ON changes (file_id, id) WHERE file_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS changes_folder_id_idx ON changes (folder_id, id) WHERE folder_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS changes_created_at_idx ON changes (created_at);
@@ -0,0 +1,36 @@
+-- Changes of a user, or of a team drive, still get their ids in commit order,
+-- the lock of their scope being taken before the id. Writes to other scopes
+-- no longer wait for each other.
+--
+-- Event streams mix scopes, whose ids commit in any order. They follow the
+-- transaction that recorded each change instead: once no transaction older
+-- than txid is running, no change with a smaller txid can still appear.
+ALTER TABLE changes ADD COLUMN IF NOT EXISTS txid BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE changes ALTER COLUMN txid SET DEFAULT pg_current_xact_id()::text::bigint;
+
+CREATE INDEX IF NOT EXISTS changes_user_id_txid_idx ON changes (user_id, txid, id);
+CREATE INDEX IF NOT EXISTS changes_team_drive_id_txid_idx ON changes (team_drive_id, txid, id);
+
+CREATE OR REPLACE FUNCTION record_change(
+    change_user_id INT, change_team_drive_id INT, change_kind TEXT, change_file_id INT, change_folder_id INT,
+    change_name TEXT, change_parent_id INT, change_file_size INT, change_content_hash TEXT
+) RETURNS VOID AS $$
+DECLARE
+    recorded changes;
+BEGIN
+    IF change_team_drive_id IS NULL
+        AND (change_user_id IS NULL OR NOT EXISTS (SELECT 1 FROM users WHERE id = change_user_id)) THEN
+        RETURN;
+    END IF;
+    IF change_team_drive_id IS NULL THEN
+        PERFORM pg_advisory_xact_lock(hashtext('changes'), change_user_id);
+    ELSE
+        PERFORM pg_advisory_xact_lock(hashtext('team_drive_changes'), change_team_drive_id);
+    END IF;
+    INSERT INTO changes (user_id, team_drive_id, kind, file_id, folder_id, name, parent_id, file_size, content_hash)
+    VALUES (CASE WHEN change_team_drive_id IS NULL THEN change_user_id END, change_team_drive_id, change_kind,
+            change_file_id, change_folder_id, change_name, change_parent_id, change_file_size, change_content_hash)
+    RETURNING * INTO recorded;
+    PERFORM pg_notify('changes', row_to_json(recorded)::text);
+END;
+$$ LANGUAGE plpgsql;
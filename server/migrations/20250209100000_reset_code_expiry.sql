@@ -0,0 +1,3 @@
+-- Password reset codes expire. Codes sent before this migration expire with it.
+ALTER TABLE codes ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE codes ALTER COLUMN expires_at DROP DEFAULT;
@@ -1,32 +1,79 @@
-use axum::http::HeaderMap;
+use axum::{
+    http::HeaderMap,
+    response::{IntoResponse, Response as HttpResponse},
+    Json,
+};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 
-use crate::models::auth::{Auth, AuthVerifyResponse};
+use crate::{
+    models::{
+        api::{ApiCode, Response},
+        auth::{Auth, AuthVerifyResponse},
+    },
+    repositories::user_repository::UserRepository,
+};
 
-pub async fn auth_header(headers: &HeaderMap) -> AuthVerifyResponse {
-    let auth = match headers.get("Authorization") {
-        Some(auth_header) => match auth_header.to_str() {
-            Ok(auth_str) => auth_str,
-            Err(_) => return AuthVerifyResponse { authorized: false, user_id: None }
-        },
-        None => return AuthVerifyResponse { authorized: false, user_id: None }
-    };
+impl Serialize for Response {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let error = self.message.as_ref().filter(|_| self.code >= 400).map(|message| message.key);
+        let mut response = serializer.serialize_struct("Response", 4)?;
+        response.serialize_field("code", &self.code)?;
+        if let Some(error) = error {
+            response.serialize_field("error", error)?;
+        } else {
+            response.skip_field("error")?;
+        }
+        response.serialize_field("message", &self.message)?;
+        response.serialize_field("data", &self.data)?;
+        response.end()
+    }
+}
 
-    if auth.is_empty() {
-        return AuthVerifyResponse { authorized: false, user_id: None };
+impl IntoResponse for Response {
+    fn into_response(self) -> HttpResponse {
+        let code = ApiCode(self.code);
+        let mut response = Json(self).into_response();
+        response.extensions_mut().insert(code);
+        response
     }
+}
+
+/// The token of the `Authorization: Bearer` header, if there is one.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let header = headers.get("Authorization")?.to_str().ok()?;
+    header.split(" ").last().filter(|token| !token.is_empty())
+}
 
-    let token = match auth.split(" ").last() {
-        Some(t) => t,
-        None => return AuthVerifyResponse { authorized: false, user_id: None }
+/// Checks the bearer token, and that its account is neither disabled nor
+/// waiting for a forced password reset.
+pub async fn auth_header(auth: &Auth, users: &dyn UserRepository, headers: &HeaderMap) -> AuthVerifyResponse {
+    let token = match bearer_token(headers) {
+        Some(token) => token,
+        None => return AuthVerifyResponse { authorized: false, user_id: None, scope: None }
     };
 
-    if token.is_empty() {
-        return AuthVerifyResponse { authorized: false, user_id: None };
+    let claims = match auth.verify_jwt(token) {
+        Ok(claims) => claims,
+        Err(_) => return AuthVerifyResponse { authorized: false, user_id: None, scope: None }
+    };
+
+    match users.is_user_active(claims.sub).await {
+        Ok(true) => AuthVerifyResponse { authorized: true, user_id: Some(claims.sub), scope: claims.scope },
+        _ => AuthVerifyResponse { authorized: false, user_id: None, scope: None }
+    }
+}
+
+impl AuthVerifyResponse {
+    /// Full sessions may do anything, access tokens only what their scope lists.
+    pub fn allows(&self, scope: &str) -> bool {
+        match &self.scope {
+            Some(granted) => granted.split(' ').any(|granted| granted == scope),
+            None => true,
+        }
     }
 
-    let auth = Auth::new();
-    match auth.verify_jwt(token) {
-        Ok(user_id) => AuthVerifyResponse { authorized: true, user_id: Some(user_id) },
-        Err(_) => AuthVerifyResponse { authorized: false, user_id: None }
+    /// Whether the token is a full session, which account management requires.
+    pub fn is_session(&self) -> bool {
+        self.scope.is_none()
     }
 }
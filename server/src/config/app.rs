@@ -0,0 +1,57 @@
+use std::sync::{atomic::AtomicBool, Arc};
+
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+
+use crate::{
+    config::events::NOTICE_CAPACITY,
+    models::{
+        app::AppState,
+        auth::Auth,
+        oidc::OidcProviders,
+        repository::{MemoryRepository, PgRepository, Repository},
+        settings::Config,
+    },
+};
+
+impl AppState {
+    /// State of a server that keeps its data in Postgres.
+    pub fn new(pool: PgPool, config: Config, auth: Auth, oidc: OidcProviders) -> AppState {
+        AppState::with_repository(Arc::new(PgRepository { pool }), config, auth, oidc)
+    }
+
+    /// State of a server that keeps its data in memory, for tests.
+    pub fn in_memory(config: Config, auth: Auth, oidc: OidcProviders) -> AppState {
+        AppState::with_repository(Arc::new(MemoryRepository::default()), config, auth, oidc)
+    }
+
+    /// State whose repositories are all `repository`.
+    pub fn with_repository<R: Repository + 'static>(
+        repository: Arc<R>,
+        config: Config,
+        auth: Auth,
+        oidc: OidcProviders,
+    ) -> AppState {
+        AppState {
+            users: repository.clone(),
+            codes: repository.clone(),
+            files: repository.clone(),
+            folders: repository.clone(),
+            changes: repository.clone(),
+            throttles: repository.clone(),
+            identities: repository.clone(),
+            devices: repository.clone(),
+            admin: repository.clone(),
+            audit: repository.clone(),
+            orgs: repository.clone(),
+            drives: repository.clone(),
+            outbox: repository.clone(),
+            health: repository,
+            notices: broadcast::channel(NOTICE_CAPACITY).0,
+            config: Arc::new(config),
+            auth: Arc::new(auth),
+            oidc: Arc::new(oidc),
+            shutting_down: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::net::SocketAddr;
+
+use axum::http::{header, HeaderMap};
+use serde_json::Value;
+
+use crate::{
+    models::audit::{AuditEvent, AuditEventRecord},
+    repositories::audit_repository::AuditRepository,
+};
+
+impl AuditEvent {
+    /// An event caused by the request with the given client address and headers.
+    pub fn new(action: &str, actor_id: Option<i32>, addr: &SocketAddr, headers: &HeaderMap) -> AuditEvent {
+        AuditEvent {
+            actor_id,
+            action: action.to_string(),
+            target_user_id: None,
+            details: Value::Object(Default::default()),
+            ip: Some(addr.ip().to_string()),
+            user_agent: headers
+                .get(header::USER_AGENT)
+                .and_then(|user_agent| user_agent.to_str().ok())
+                .map(|user_agent| user_agent.chars().take(512).collect()),
+        }
+    }
+
+    pub fn target(mut self, user_id: i32) -> AuditEvent {
+        self.target_user_id = Some(user_id);
+        self
+    }
+
+    pub fn details(mut self, details: Value) -> AuditEvent {
+        self.details = details;
+        self
+    }
+
+    /// Callers go on when this fails, so the failure is logged here.
+    pub async fn record(&self, audit: &dyn AuditRepository) -> Result<(), axum::Error> {
+        let result = audit.record_event(self).await;
+        if let Err(e) = &result {
+            tracing::error!(action = %self.action, error = %e, "audit event not recorded");
+        }
+        result
+    }
+}
+
+/// Quotes a CSV field when needed, and defuses values that spreadsheets
+/// would run as formulas, including those behind a leading tab or carriage
+/// return.
+fn csv_field(value: &str) -> String {
+    let value = match value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        true => format!("'{}", value),
+        false => value.to_string(),
+    };
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value,
+    }
+}
+
+impl AuditEventRecord {
+    pub const CSV_HEADER: &'static str = "id,created_at,actor_id,action,target_user_id,ip,user_agent,details\n";
+
+    pub fn to_csv_row(&self) -> String {
+        let fields = [
+            self.id.to_string(),
+            self.created_at.to_rfc3339(),
+            self.actor_id.map(|id| id.to_string()).unwrap_or_default(),
+            self.action.clone(),
+            self.target_user_id.map(|id| id.to_string()).unwrap_or_default(),
+            self.ip.clone().unwrap_or_default(),
+            self.user_agent.clone().unwrap_or_default(),
+            self.details.to_string(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        format!("{}\n", fields.join(","))
+    }
+
+    pub fn to_json_line(&self) -> String {
+        format!("{}\n", serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formulas_are_defused_and_fields_quoted() {
+        assert_eq!(csv_field("Mozilla/5.0"), "Mozilla/5.0");
+        for formula in ["=1+1", "+1", "-1", "@SUM(A1)", "\t=1+1"] {
+            assert_eq!(csv_field(formula), format!("'{}", formula), "{:?}", formula);
+        }
+        assert_eq!(csv_field("\r=1+1"), "\"'\r=1+1\"");
+        assert_eq!(csv_field(r#"a,"b""#), r#""a,""b""""#);
+    }
+}
@@ -1,40 +1,267 @@
-use std::env;
+use std::fs;
 
-use bcrypt::{hash, verify};
-use jsonwebtoken::{encode, Header, EncodingKey, decode, DecodingKey, Validation, TokenData};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    encode, decode, decode_header, Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters,
+        OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+};
 use chrono::{Utc, Duration};
-use rand::Rng;
-use crate::models::auth::{Auth, Claims};
+use rand::{distributions::Alphanumeric, Rng};
+use ring::{
+    digest::{digest, SHA256},
+    signature::{Ed25519KeyPair, KeyPair, RsaKeyPair, RsaPublicKeyComponents},
+};
+use crate::models::{
+    auth::{Auth, Claims, SigningKey, VerificationKey},
+    settings::Config,
+};
+
+/// An asymmetric key read from a PEM file, with its public JWK.
+struct KeyFile {
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    jwk: Jwk,
+}
+
+/// Reads an Ed25519 (PKCS#8) or RSA (PKCS#8 or PKCS#1) private key. The key id
+/// is the RFC 7638 thumbprint, so it stays the same wherever the key is loaded.
+fn read_key_file(path: &str) -> Result<KeyFile, String> {
+    let contents = fs::read(path).map_err(|e| format!("Cannot read key file {}: {}", path, e))?;
+    let pem = pem::parse(&contents).map_err(|e| format!("Key file {} is not PEM: {}", path, e))?;
+
+    let (algorithm, encoding_key, parameters, thumbprint_input) =
+        if let Ok(key_pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(pem.contents()) {
+            let x = URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref());
+            let encoding_key = EncodingKey::from_ed_pem(&contents).map_err(|e| format!("Invalid key file {}: {}", path, e))?;
+            let thumbprint_input = format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, x);
+            let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x,
+            });
+            (Algorithm::EdDSA, encoding_key, parameters, thumbprint_input)
+        } else if let Ok(key_pair) = RsaKeyPair::from_pkcs8(pem.contents()).or_else(|_| RsaKeyPair::from_der(pem.contents())) {
+            let components = RsaPublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+            let n = URL_SAFE_NO_PAD.encode(&components.n);
+            let e = URL_SAFE_NO_PAD.encode(&components.e);
+            let encoding_key = EncodingKey::from_rsa_pem(&contents).map_err(|e| format!("Invalid key file {}: {}", path, e))?;
+            let thumbprint_input = format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n);
+            let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n,
+                e,
+            });
+            (Algorithm::RS256, encoding_key, parameters, thumbprint_input)
+        } else {
+            return Err(format!("Key file {} is neither an Ed25519 nor an RSA private key", path));
+        };
+
+    let kid = URL_SAFE_NO_PAD.encode(digest(&SHA256, thumbprint_input.as_bytes()));
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(match algorithm {
+                Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+                _ => KeyAlgorithm::RS256,
+            }),
+            key_id: Some(kid),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+
+    Ok(KeyFile { algorithm, encoding_key, jwk })
+}
+
+fn verification_key(key_file: &KeyFile) -> Result<VerificationKey, String> {
+    let key = DecodingKey::from_jwk(&key_file.jwk).map_err(|e| format!("Invalid public key: {}", e))?;
+    Ok(VerificationKey {
+        kid: key_file.jwk.common.key_id.clone(),
+        algorithm: key_file.algorithm,
+        key,
+        jwk: Some(key_file.jwk.clone()),
+    })
+}
 
 impl Auth {
-    pub fn new() -> Auth {
-        let secret_key = env::var("SECRET_KEY").unwrap();
-        Auth { secret_key }
+    /// Loads the token keys once at startup.
+    ///
+    /// - `auth.signing_key_file`: Ed25519 or RSA private key signing new tokens.
+    /// - `auth.verification_key_files`: keys whose tokens are still accepted,
+    ///   e.g. the previous signing key during a rotation.
+    /// - `auth.secret_key`: HS256 secret. Signs tokens when no key file is set,
+    ///   otherwise only keeps already issued HS256 tokens valid.
+    pub fn load(config: &Config) -> Result<Auth, String> {
+        let secret_key = config.auth.secret_key.clone();
+        let mut verification_keys = Vec::new();
+
+        let signing_key = match &config.auth.signing_key_file {
+            Some(path) => {
+                let key_file = read_key_file(path)?;
+                verification_keys.push(verification_key(&key_file)?);
+                SigningKey {
+                    kid: key_file.jwk.common.key_id.clone(),
+                    algorithm: key_file.algorithm,
+                    key: key_file.encoding_key,
+                }
+            }
+            None => {
+                let secret_key = secret_key
+                    .clone()
+                    .ok_or("Either auth.signing_key_file or auth.secret_key must be set")?;
+                SigningKey {
+                    kid: None,
+                    algorithm: Algorithm::HS256,
+                    key: EncodingKey::from_secret(secret_key.as_bytes()),
+                }
+            }
+        };
+
+        for path in &config.auth.verification_key_files {
+            let key = verification_key(&read_key_file(path)?)?;
+            if !verification_keys.iter().any(|known| known.kid == key.kid) {
+                verification_keys.push(key);
+            }
+        }
+
+        if let Some(secret_key) = secret_key {
+            verification_keys.push(VerificationKey {
+                kid: None,
+                algorithm: Algorithm::HS256,
+                key: DecodingKey::from_secret(secret_key.as_bytes()),
+                jwk: None,
+            });
+        }
+
+        let auth = Auth {
+            signing_key,
+            verification_keys,
+            session_ttl_secs: config.tokens.session_secs,
+            argon2_params: config.password.argon2_params().map_err(|e| format!("Invalid Argon2 parameters: {}", e))?,
+            min_password_length: config.password.min_length,
+        };
+        // Fail at startup rather than on the first login if the key can't sign.
+        let probe = Claims { sub: 0, exp: 0, purpose: None, jti: None, scope: None, auth_time: None };
+        encode(&Header::new(auth.signing_key.algorithm), &probe, &auth.signing_key.key)
+            .map_err(|e| format!("The signing key cannot sign tokens: {}", e))?;
+
+        Ok(auth)
     }
 
     pub fn generate_code() -> String {
-        let code = rand::thread_rng().gen_range(1000..9999);
-        code.to_string()
+        let code = rand::thread_rng().gen_range(0..100_000_000);
+        format!("{:08}", code)
+    }
+
+    pub fn generate_token() -> String {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(48)
+            .map(char::from)
+            .collect()
     }
 
+    /// Trims and lowercases an email address, or returns `None` if it
+    /// doesn't look like one.
+    pub fn normalize_email(email: &str) -> Option<String> {
+        let email = email.trim().to_lowercase();
+        let (local, domain) = email.split_once('@')?;
+        let valid = !local.is_empty()
+            && domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && !email.chars().any(char::is_whitespace);
+        valid.then_some(email)
+    }
+
+    fn sign(&self, claims: &Claims) -> String {
+        let mut header = Header::new(self.signing_key.algorithm);
+        header.kid = self.signing_key.kid.clone();
+        encode(&header, claims, &self.signing_key.key).unwrap()
+    }
+
+    /// Verifies a token with the key named by its `kid` header. Tokens
+    /// without a `kid` can only be checked against the shared secret.
+    fn decode_claims(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        let header = decode_header(token)?;
+        let key = self
+            .verification_keys
+            .iter()
+            .find(|key| key.kid == header.kid && key.algorithm == header.alg)
+            .ok_or(jsonwebtoken::errors::ErrorKind::InvalidSignature)?;
+        let token_data: TokenData<Claims> = decode::<Claims>(token, &key.key, &Validation::new(key.algorithm))?;
+        Ok(token_data.claims)
+    }
+
+    /// Signs a session for a user who has just logged in.
     pub fn generate_jwt(&self, user_id: i32) -> String {
-        let claims = Claims {
+        self.generate_jwt_authenticated_at(user_id, Some(Utc::now().timestamp()))
+    }
+
+    /// Signs a session for a user who proved who they are at `auth_time`,
+    /// which an identity provider may report to be earlier than the login, or
+    /// not at all.
+    pub fn generate_jwt_authenticated_at(&self, user_id: i32, auth_time: Option<i64>) -> String {
+        self.sign(&Claims {
             sub: user_id,
-            exp: (Utc::now() + Duration::hours(24)).timestamp() as usize,
-        };
-        encode(&Header::default(), &claims, &EncodingKey::from_secret(self.secret_key.as_bytes())).unwrap()
-    }  
+            exp: (Utc::now() + Duration::seconds(self.session_ttl_secs)).timestamp() as usize,
+            purpose: None,
+            jti: None,
+            scope: None,
+            auth_time,
+        })
+    }
+
+    /// Signs an access token that only allows what `scope` lists, as issued
+    /// to devices.
+    pub fn generate_access_token(&self, user_id: i32, scope: &str, ttl_secs: i64) -> String {
+        self.sign(&Claims {
+            sub: user_id,
+            exp: (Utc::now() + Duration::seconds(ttl_secs)).timestamp() as usize,
+            purpose: None,
+            jti: None,
+            scope: Some(scope.to_string()),
+            auth_time: None,
+        })
+    }
+
+    /// Verifies a session or access token.
+    pub fn verify_jwt(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        let claims = self.decode_claims(token)?;
+        if claims.purpose.is_some() {
+            return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+        }
+        Ok(claims)
+    }
 
-    pub fn verify_jwt(&self, token: &str) -> Result<i32, jsonwebtoken::errors::Error> {
-        let token_data: TokenData<Claims> = decode::<Claims>(token, &DecodingKey::from_secret(self.secret_key.as_bytes()), &Validation::default())?;
-        Ok(token_data.claims.sub)
+    /// Signs a short-lived token that is only accepted for `purpose`. The
+    /// `jti` lets the caller make the token single-use.
+    pub fn generate_purpose_token(&self, user_id: i32, purpose: &str, jti: &str, ttl_secs: i64) -> String {
+        self.sign(&Claims {
+            sub: user_id,
+            exp: (Utc::now() + Duration::seconds(ttl_secs)).timestamp() as usize,
+            purpose: Some(purpose.to_string()),
+            jti: Some(jti.to_string()),
+            scope: None,
+            auth_time: None,
+        })
     }
 
-    pub fn hash_password(&self, password: &str) -> String {
-        hash(password, 10).unwrap()
+    pub fn verify_purpose_token(&self, token: &str, purpose: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        let claims = self.decode_claims(token)?;
+        if claims.purpose.as_deref() != Some(purpose) || claims.jti.is_none() {
+            return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+        }
+        Ok(claims)
     }
 
-    pub fn verify_password(&self, password: &str, hashed_password: &str) -> bool {
-        verify(password, hashed_password).unwrap()
+    /// Public keys accepted for verification, for `/.well-known/jwks.json`.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.verification_keys.iter().filter_map(|key| key.jwk.clone()).collect(),
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,42 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::Error;
+use tracing::{error, info};
+
+use crate::repositories::change_repository::ChangeRepository;
+
+const COMPACTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Changes removed at a time, so that no transaction runs for long.
+const BATCH_SIZE: i64 = 10_000;
+
+/// Removes the changes past the retention every hour, until the server stops.
+pub async fn run_change_compaction(changes: Arc<dyn ChangeRepository>, retention_days: i64) {
+    loop {
+        if let Err(e) = compact_changes(&*changes, retention_days).await {
+            error!(error = %e, "cannot compact the changes");
+        }
+        tokio::time::sleep(COMPACTION_INTERVAL).await;
+    }
+}
+
+/// Removes every change past the retention and returns how many there were.
+pub async fn compact_changes(changes: &dyn ChangeRepository, retention_days: i64) -> Result<u64, Error> {
+    let mut total = 0;
+    loop {
+        let removed = changes.compact_changes(retention_days, BATCH_SIZE).await?;
+        total += removed;
+        if removed < BATCH_SIZE as u64 {
+            break;
+        }
+    }
+    if total > 0 {
+        info!(removed = total, "changes compacted");
+    }
+    Ok(total)
+}
+
+/// Whether changes after `cursor` were removed, so that its client has to
+/// sync again from the start. `0`, the start, never expires.
+pub async fn is_cursor_expired(changes: &dyn ChangeRepository, cursor: i64) -> Result<bool, Error> {
+    Ok(cursor > 0 && cursor < changes.compacted_through().await?)
+}
@@ -0,0 +1,47 @@
+use rand::Rng;
+
+pub use files_box_models::device::{DEVICE_CODE_GRANT_TYPE, SLOW_DOWN_SECS};
+
+pub const POLL_INTERVAL_SECS: i32 = 5;
+
+/// Scopes a device can ask for. Account management always needs a full session.
+pub const SCOPES: [&str; 2] = ["files:read", "files:write"];
+
+// Consonants only, so that codes can't spell words and are easy to type.
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+/// An eight letter code such as `WDJB-MJHT`.
+pub fn generate_user_code() -> String {
+    let mut rng = rand::thread_rng();
+    let letters: String = (0..8)
+        .map(|_| USER_CODE_ALPHABET[rng.gen_range(0..USER_CODE_ALPHABET.len())] as char)
+        .collect();
+    format!("{}-{}", &letters[..4], &letters[4..])
+}
+
+/// Accepts the code in any case, with or without the dash.
+pub fn normalize_user_code(user_code: &str) -> String {
+    let letters: String = user_code
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    match letters.len() {
+        8 => format!("{}-{}", &letters[..4], &letters[4..]),
+        _ => letters,
+    }
+}
+
+/// Validates a requested scope and puts it in canonical order.
+pub fn parse_scope(scope: Option<&str>) -> Result<String, String> {
+    let requested: Vec<&str> = scope.unwrap_or_default().split_whitespace().collect();
+    if let Some(unknown) = requested.iter().find(|scope| !SCOPES.contains(scope)) {
+        return Err(format!("Unknown scope {}", unknown));
+    }
+    let scopes: Vec<&str> = SCOPES
+        .into_iter()
+        .filter(|scope| requested.is_empty() || requested.contains(scope))
+        .collect();
+    Ok(scopes.join(" "))
+}
+
@@ -0,0 +1,20 @@
+use axum::Error;
+
+use crate::{
+    config::outbox::enqueue_email,
+    models::{app::AppState, auth::Auth, mail::EmailTemplate},
+};
+
+/// Sends a link confirming that `email` belongs to the user. Once followed,
+/// the address becomes the user's verified email.
+pub async fn send_verification_email(app_state: &AppState, user_id: i32, email: &str) -> Result<(), Error> {
+    let config = &app_state.config;
+    let token = Auth::generate_token();
+    app_state
+        .codes
+        .create_email_verification(user_id, email, &token, config.tokens.email_verification_secs)
+        .await?;
+
+    let link = format!("{}/auth/verify-email?token={}", config.server.app_url, token);
+    enqueue_email(&*app_state.outbox, email, EmailTemplate::VerifyEmail { link }).await
+}
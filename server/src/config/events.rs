@@ -0,0 +1,283 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    convert::Infallible,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::response::sse::Event;
+use futures_util::{stream, Stream};
+use sqlx::{postgres::PgListener, PgPool};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::Instant,
+};
+use tracing::{error, warn};
+
+use crate::{
+    config::metrics::metrics,
+    models::{
+        app::AppState,
+        events::{ChangeNotification, Notice, StreamPosition, StreamedChange},
+        files::Change,
+    },
+};
+
+/// The Postgres channel `record_change` notifies.
+pub const CHANNEL: &str = "changes";
+/// Notices kept for streams that fall behind. Those that fall further read
+/// what they missed from the database.
+pub const NOTICE_CAPACITY: usize = 1024;
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+/// Changes read from the database at a time when a stream catches up.
+const PAGE_SIZE: i64 = 500;
+/// How often a stream reads again a change it was told about while an older
+/// transaction still runs.
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Listens for the changes every instance commits and hands them to the event
+/// streams of this one, until the server stops.
+pub async fn run_change_listener(pool: PgPool, notices: broadcast::Sender<Notice>) {
+    loop {
+        match listen(&pool, &notices).await {
+            Ok(()) => warn!("change listener disconnected, reconnecting"),
+            Err(e) => error!(error = %e, "change listener failed, reconnecting"),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Forwards notifications until the connection is lost.
+async fn listen(pool: &PgPool, notices: &broadcast::Sender<Notice>) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(CHANNEL).await?;
+    // Whatever was committed while nobody listened is in the database.
+    let _ = notices.send(Notice::Missed);
+    while let Some(notification) = listener.try_recv().await? {
+        let notice = match serde_json::from_str::<ChangeNotification>(notification.payload()) {
+            Ok(change) => Notice::Change(Arc::new(change)),
+            Err(e) => {
+                warn!(error = %e, "unreadable change notification");
+                Notice::Missed
+            }
+        };
+        // Fails only when no stream is open.
+        let _ = notices.send(notice);
+    }
+    Ok(())
+}
+
+/// Counts the stream in the metrics while it is open.
+struct OpenStream;
+
+impl OpenStream {
+    fn new() -> OpenStream {
+        metrics().event_streams.inc();
+        OpenStream
+    }
+}
+
+impl Drop for OpenStream {
+    fn drop(&mut self) {
+        metrics().event_streams.dec();
+    }
+}
+
+/// The stream read past a transaction that held its changes back for longer
+/// than `changes.stream_wait_secs`.
+struct Overtaken {
+    /// The oldest transaction still running then.
+    txid: i64,
+    /// Changes of that transaction or later ones sent since, whose notices may
+    /// still come.
+    sent: HashSet<i64>,
+}
+
+/// Changes are sent in stream order, which isn't the order they commit in:
+/// the stream only reads them from the database, once every older transaction
+/// has ended. Notices tell it when to.
+///
+/// Any transaction of the database holds the stream back, even one that
+/// records no change, so the wait is bounded: past it the notified changes
+/// are read anyway, and those the older transactions commit later are sent
+/// out of order when notified. A stream that lags behind the notices, or is
+/// resumed after them, misses those.
+struct EventStream {
+    app_state: AppState,
+    user_id: i32,
+    /// The last change sent.
+    position: StreamPosition,
+    notices: broadcast::Receiver<Notice>,
+    pending: VecDeque<StreamedChange>,
+    /// Changes are to be read, e.g. after a notice.
+    behind: bool,
+    /// The furthest change notified, until it has been read.
+    notified: Option<StreamPosition>,
+    /// When to read again while a notified change can't be read yet.
+    retry_at: Option<Instant>,
+    /// Since when an older transaction holds the notified change back.
+    held_since: Option<Instant>,
+    overtaken: Option<Overtaken>,
+    ends_at: Instant,
+    _open: OpenStream,
+}
+
+impl EventStream {
+    /// The next change the user may see, or `None` once the stream ends.
+    async fn next(&mut self) -> Option<Change> {
+        loop {
+            if let Some(streamed) = self.pending.pop_front() {
+                // Late changes are behind the position already.
+                self.position = self.position.max(streamed.position);
+                if let Some(overtaken) = self.overtaken.as_mut().filter(|o| streamed.position.txid >= o.txid) {
+                    overtaken.sent.insert(streamed.change.id);
+                }
+                return Some(streamed.change);
+            }
+            if self.behind {
+                self.read().await?;
+                continue;
+            }
+
+            let notice = tokio::select! {
+                notice = self.notices.recv() => notice,
+                _ = sleep_until(self.retry_at) => {
+                    self.behind = true;
+                    continue;
+                }
+                _ = tokio::time::sleep_until(self.ends_at) => return None,
+            };
+            match notice {
+                Ok(Notice::Change(notification)) if notification.position() > self.position => {
+                    if self.can_see(&notification).await? {
+                        self.notified = self.notified.max(Some(notification.position()));
+                        self.behind = true;
+                    }
+                }
+                Ok(Notice::Change(notification)) if self.is_late(&notification) => {
+                    if self.can_see(&notification).await? {
+                        let position = notification.position();
+                        self.pending.push_back(StreamedChange { position, change: notification.change.clone() });
+                    }
+                }
+                Ok(Notice::Change(_)) => {}
+                Ok(Notice::Missed) | Err(RecvError::Lagged(_)) => self.behind = true,
+                Ok(Notice::ShuttingDown) | Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Reads a page of changes, and whether the notified ones were all read.
+    async fn read(&mut self) -> Option<()> {
+        let changes = &self.app_state.changes;
+        // Read before the page, which has every change below it the user may see.
+        let horizon = match self.notified {
+            Some(_) => Some(self.log(changes.current_event_position().await, "cannot read the stream position")?),
+            None => None,
+        };
+        let wait = Duration::from_secs(self.app_state.config.changes.stream_wait_secs);
+        let through = self.notified.filter(|_| self.held_since.is_some_and(|since| since.elapsed() >= wait));
+        let page = changes.find_events(self.user_id, self.position, through, PAGE_SIZE).await;
+        let page = self.log(page, "cannot read the events")?;
+        let full = page.len() as i64 == PAGE_SIZE;
+        self.pending.extend(page);
+        if full {
+            return Some(());
+        }
+        self.behind = false;
+        self.retry_at = None;
+        if let (Some(notified), Some(horizon)) = (self.notified, horizon) {
+            if notified.txid < horizon.txid {
+                self.notified = None;
+                self.held_since = None;
+            } else if through.is_some() {
+                let (user_id, txid) = (self.user_id, horizon.txid);
+                warn!(user_id, txid, "a long transaction held the events back, sending them");
+                let overtaken = self.overtaken.get_or_insert(Overtaken { txid: horizon.txid, sent: HashSet::new() });
+                overtaken.txid = overtaken.txid.min(horizon.txid);
+                self.notified = None;
+                self.held_since = None;
+            } else {
+                // An older transaction still runs: the change can't be read yet.
+                self.held_since.get_or_insert_with(Instant::now);
+                self.retry_at = Some(Instant::now() + RETRY_DELAY);
+            }
+        }
+        Some(())
+    }
+
+    /// Whether a change behind the position is one of a transaction the stream
+    /// read past that isn't sent yet. It counts as sent from then on.
+    fn is_late(&mut self, notification: &ChangeNotification) -> bool {
+        match &mut self.overtaken {
+            Some(overtaken) if notification.txid >= overtaken.txid => overtaken.sent.insert(notification.change.id),
+            _ => false,
+        }
+    }
+
+    fn log<T>(&self, result: Result<T, axum::Error>, message: &str) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                error!(user_id = self.user_id, error = %e, "{}", message);
+                None
+            }
+        }
+    }
+
+    /// Membership is checked for every change of a drive, so that members who
+    /// leave stop seeing them at once.
+    async fn can_see(&self, notification: &ChangeNotification) -> Option<bool> {
+        let Some(team_drive_id) = notification.change.team_drive_id else {
+            return Some(notification.user_id == Some(self.user_id));
+        };
+        let member = self.app_state.changes.is_drive_member(self.user_id, team_drive_id).await;
+        self.log(member, "cannot check the drive membership")
+    }
+}
+
+/// Waits until `at`, or forever without it.
+async fn sleep_until(at: Option<Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn event(change: &Change) -> Event {
+    Event::default()
+        .id(change.id.to_string())
+        .event(change.kind.as_str())
+        .json_data(change)
+        .expect("changes are always serializable")
+}
+
+/// The events of the user after `position`: first those in the database, then
+/// those the listener tells about, until `ends_at`. `notices` is subscribed
+/// before `position` is read, so that nothing committed in between is missed.
+pub fn event_stream(
+    app_state: AppState,
+    notices: broadcast::Receiver<Notice>,
+    user_id: i32,
+    position: StreamPosition,
+    ends_at: Instant,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let events = EventStream {
+        app_state,
+        user_id,
+        position,
+        notices,
+        pending: VecDeque::new(),
+        behind: true,
+        notified: None,
+        retry_at: None,
+        held_since: None,
+        overtaken: None,
+        ends_at,
+        _open: OpenStream::new(),
+    };
+    stream::unfold(events, |mut events| async move {
+        let change = events.next().await?;
+        Some((Ok(event(&change)), events))
+    })
+}
@@ -1,173 +1,359 @@
-use axum::extract::multipart::Multipart;
+use std::{path::Path, time::Instant};
+
+use axum::{body::Bytes, extract::multipart::{Multipart, MultipartError}, http::{HeaderValue, StatusCode}};
+use rand::{distributions::Alphanumeric, Rng};
+use ring::digest::{digest, SHA256};
 use serde_json::json;
-use sqlx::PgPool;
 use tokio::fs;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 
-use crate::models::files::{FileAction, FileResponse, FileData};
+use crate::{
+    config::metrics::metrics,
+    models::{files::{FileAction, FileContents, FileData, FileList, FileResponse, NewFile, UploadedFile}, i18n::Message},
+    repositories::file_repository::FileRepository,
+};
+
+fn failure(message: Message) -> FileResponse {
+    FileResponse {
+        data: None,
+        error_message: Some(message),
+        is_error: true,
+    }
+}
+
+/// A body over `limits.max_upload_bytes` surfaces here while it is read.
+fn multipart_error(e: MultipartError) -> FileResponse {
+    match e.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => failure("file_too_large".into()),
+        _ => failure(Message::server_error(e)),
+    }
+}
+
+/// The file of a multipart body, read in full.
+struct Upload {
+    file_name: String,
+    file_content_type: String,
+    body_bytes: Bytes,
+}
+
+async fn read_upload(multipart: &mut Multipart) -> Result<Upload, FileResponse> {
+    let Some(field) = multipart.next_field().await.map_err(multipart_error)? else {
+        return Err(failure("no_file".into()));
+    };
+    let file_name = field.file_name().unwrap_or("unknown").to_string();
+    let file_content_type = field
+        .content_type()
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let body_bytes = field.bytes().await.map_err(multipart_error)?;
+    Ok(Upload { file_name, file_content_type, body_bytes })
+}
+
+/// SHA-256 of the contents in hex, as clients that sync compute it.
+pub fn content_hash(contents: &[u8]) -> String {
+    digest(&SHA256, contents).as_ref().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+async fn quota_check(files: &dyn FileRepository, user_id: i32, team_drive_id: Option<i32>, added_bytes: i64, quota_bytes: i64) -> Result<(), FileResponse> {
+    match files.used_bytes(user_id, team_drive_id).await {
+        Ok(used_bytes) if used_bytes + added_bytes > quota_bytes => Err(failure("quota_exceeded".into())),
+        Ok(_) => Ok(()),
+        Err(e) => Err(failure(Message::server_error(e))),
+    }
+}
 
+/// Writes the contents under a new name in the storage root and returns its path.
+async fn store(storage_root: &Path, contents: &[u8]) -> Result<String, FileResponse> {
+    let file_path = storage_root.join(generate_stored_name()).to_string_lossy().into_owned();
+    if let Err(e) = write_file(&file_path, contents).await {
+        metrics().storage_errors.with_label_values(&["write"]).inc();
+        return Err(failure(Message::server_error(e)));
+    }
+    Ok(file_path)
+}
+
+/// Removes a stored file nothing refers to anymore. A failure only leaves it
+/// behind on disk, so it is counted but not reported to the client.
+async fn discard(file_path: &str) {
+    if let Err(e) = fs::remove_file(file_path).await {
+        metrics().storage_errors.with_label_values(&["delete"]).inc();
+        tracing::warn!(path = %file_path, error = %e, "cannot remove stored file");
+    }
+}
+
+/// Name of a file on disk. The uploaded name is only kept in the database, so
+/// that files with the same name don't overwrite each other and names such as
+/// `../x` can't leave the storage root.
+fn generate_stored_name() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+async fn write_file(path: &str, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = File::create(path).await?;
+    file.write_all(contents).await
+}
 
 impl FileAction {
+    /// Stores the uploaded file in the user's personal space, in `folder_id`
+    /// if it is set, or in the team drive when `team_drive_id` is set.
+    /// `quota_bytes` is the limit of that space.
     pub async fn upload_file(
-        pool: &PgPool,
+        files: &dyn FileRepository,
+        storage_root: &Path,
         mut multipart: Multipart,
         user_id: i32,
+        team_drive_id: Option<i32>,
+        folder_id: Option<i32>,
+        quota_bytes: i64,
     ) -> FileResponse {
-        if let Some(field) = multipart.next_field().await.unwrap() {
-            let file_name = field.file_name().unwrap_or("unknown").to_string();
-            let file_content_type = field
-                .content_type()
-                .unwrap_or("application/octet-stream")
-                .to_string();
-            let body_bytes = field.bytes().await.unwrap();
-            let file_size = body_bytes.len() as i32;
-
-            let file_path = format!("uploads/{}", file_name);
-            let file_type = file_name.split('.').last().unwrap_or("unknown").to_string();
-            let mut file = File::create(&file_path).await.unwrap();
-            file.write_all(&body_bytes).await.unwrap();
-
-            let id_file = sqlx::query!(
-                "INSERT INTO files (file_name, file_path, file_size, file_content_type, file_type, user_id) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
-                file_name,
-                file_path,
+        let started = Instant::now();
+        let space = if team_drive_id.is_some() { "team_drive" } else { "personal" };
+        let upload = match read_upload(&mut multipart).await {
+            Ok(upload) => upload,
+            Err(response) => return response,
+        };
+        // Sizes are stored as INT, so larger files are refused like oversized bodies.
+        let Ok(file_size) = i32::try_from(upload.body_bytes.len()) else {
+            return failure("file_too_large".into());
+        };
+        if let Err(response) = quota_check(files, user_id, team_drive_id, file_size as i64, quota_bytes).await {
+            return response;
+        }
+
+        let file_path = match store(storage_root, &upload.body_bytes).await {
+            Ok(file_path) => file_path,
+            Err(response) => return response,
+        };
+        let file_name = upload.file_name;
+        let file_type = file_name.split('.').next_back().unwrap_or("unknown").to_string();
+        let content_hash = content_hash(&upload.body_bytes);
+        let id_file = files
+            .create_file(NewFile {
+                file_name: file_name.clone(),
+                file_path: file_path.clone(),
                 file_size,
-                file_content_type,
+                file_content_type: upload.file_content_type,
                 file_type,
-                user_id
-            )
-            .fetch_one(pool)
+                user_id,
+                team_drive_id,
+                folder_id,
+                content_hash: content_hash.clone(),
+            })
             .await;
-            
-            match id_file {
-                Ok(id_file) => {
-                    return FileResponse {
-                        data: Some(json!({"id": id_file.id})),
-                        error_message: "".to_string(),
-                        is_error: false,
-                    };
+
+        match id_file {
+            Ok(id_file) => {
+                tracing::info!(file_id = id_file.id, file_size, team_drive_id, "file uploaded");
+                metrics().upload_bytes.with_label_values(&[space]).inc_by(file_size as u64);
+                metrics()
+                    .upload_duration
+                    .with_label_values(&[space])
+                    .observe(started.elapsed().as_secs_f64());
+                FileResponse {
+                    data: Some(json!(UploadedFile { id: id_file.id, file_name, file_size, content_hash })),
+                    error_message: None,
+                    is_error: false,
                 }
-                Err(_) => {
-                    return FileResponse {
-                        data: None,
-                        error_message: "File not found".to_string(),
-                        is_error: true,
-                    };
+            }
+            Err(e) => {
+                tracing::error!(path = %file_path, error = %e, "cannot save uploaded file");
+                failure("file_not_found".into())
+            }
+        }
+    }
+
+    /// Replaces the contents of a personal file, keeping its id, name and
+    /// folder. With `expected_hash`, fails with `file_changed` unless the
+    /// contents are still the ones the client saw, so that a client that sync
+    /// never overwrites changes it doesn't know of.
+    pub async fn replace_file(
+        files: &dyn FileRepository,
+        storage_root: &Path,
+        mut multipart: Multipart,
+        user_id: i32,
+        file: FileData,
+        expected_hash: Option<&str>,
+        quota_bytes: i64,
+    ) -> FileResponse {
+        let started = Instant::now();
+        if expected_hash.is_some_and(|expected_hash| file.content_hash.as_deref() != Some(expected_hash)) {
+            return failure("file_changed".into());
+        }
+        let upload = match read_upload(&mut multipart).await {
+            Ok(upload) => upload,
+            Err(response) => return response,
+        };
+        // Sizes are stored as INT, so larger files are refused like oversized bodies.
+        let Ok(file_size) = i32::try_from(upload.body_bytes.len()) else {
+            return failure("file_too_large".into());
+        };
+        let added_bytes = file_size as i64 - file.file_size as i64;
+        if let Err(response) = quota_check(files, user_id, None, added_bytes, quota_bytes).await {
+            return response;
+        }
+
+        let file_path = match store(storage_root, &upload.body_bytes).await {
+            Ok(file_path) => file_path,
+            Err(response) => return response,
+        };
+        let content_hash = content_hash(&upload.body_bytes);
+        let contents = FileContents {
+            file_path: file_path.clone(),
+            file_size,
+            file_content_type: upload.file_content_type,
+            content_hash: content_hash.clone(),
+        };
+        // Only the contents read above are replaced: if another upload won the
+        // race, this one is dropped.
+        match files.replace_contents(file.id, &file.file_path, contents).await {
+            Ok(Some(replaced)) => {
+                discard(&file.file_path).await;
+                tracing::info!(file_id = replaced.id, file_size, "file replaced");
+                metrics().upload_bytes.with_label_values(&["personal"]).inc_by(file_size as u64);
+                metrics()
+                    .upload_duration
+                    .with_label_values(&["personal"])
+                    .observe(started.elapsed().as_secs_f64());
+                FileResponse {
+                    data: Some(json!(UploadedFile { id: replaced.id, file_name: replaced.file_name, file_size, content_hash })),
+                    error_message: None,
+                    is_error: false,
                 }
             }
-        } else {
-            return FileResponse {
-                data: None,
-                error_message: "No file uploaded".to_string(),
-                is_error: true,
-            };
+            Ok(None) => {
+                discard(&file_path).await;
+                failure("file_changed".into())
+            }
+            Err(e) => {
+                discard(&file_path).await;
+                failure(Message::server_error(e))
+            }
         }
     }
 
-    pub async fn get_files(pool: &PgPool, user_id: i32, file_ids: &[i32]) -> FileResponse {
-        if file_ids.len() != 0 && file_ids[0] == -1 {
-            let files = sqlx::query!("SELECT * FROM files WHERE user_id = $1", user_id)
-                .fetch_all(pool)
-                .await;
-            if files.is_err() {
+    pub async fn get_files(files: &dyn FileRepository, user_id: i32, file_ids: &[i32]) -> FileResponse {
+        if !file_ids.is_empty() && file_ids[0] == -1 {
+            let files_data = files.find_personal_files(user_id).await;
+            if files_data.is_err() {
                 return FileResponse {
                     data: None,
-                    error_message: "Files not found".to_string(),
+                    error_message: Some("files_not_found".into()),
                     is_error: true,
                 };
             }
-            let files_data: Vec<FileData> = files.unwrap().into_iter().map(|file| FileData {
-                id: file.id,
-                file_name: file.file_name,
-                file_path: file.file_path,
-                file_size: file.file_size,
-                file_content_type: file.file_content_type,
-                file_type: file.file_type,
-                user_id: file.user_id,
-            }).collect();
             return FileResponse {
-                data: Some(json!({"files": files_data})),
-                error_message: "".to_string(),
+                data: Some(json!(FileList { files: files_data.unwrap() })),
+                error_message: None,
                 is_error: false,
             };
         }
-        match sqlx::query!("SELECT * FROM files WHERE id = ANY($1)", file_ids)
-            .fetch_all(pool)
-            .await
-        {
-            Ok(files) => {
-                if files.is_empty() {
+        match files.find_files_by_ids(user_id, file_ids).await {
+            Ok(files_data) => {
+                if files_data.is_empty() {
                     return FileResponse {
-                        data: Some(json!({"files": []})),
-                        error_message: "Files not found".to_string(),
+                        data: Some(json!(FileList { files: vec![] })),
+                        error_message: Some("files_not_found".into()),
                         is_error: true,
                     };
                 }
-                let files_data: Vec<FileData> = files.into_iter().map(|file| FileData {
-                    id: file.id,
-                    file_name: file.file_name,
-                    file_path: file.file_path,
-                    file_size: file.file_size,
-                    file_content_type: file.file_content_type,
-                    file_type: file.file_type,
-                    user_id: file.user_id,
-                }).collect();
-                return FileResponse {
-                    data: Some(json!({"files": files_data})),
-                    error_message: "".to_string(),
+                FileResponse {
+                    data: Some(json!(FileList { files: files_data })),
+                    error_message: None,
                     is_error: false,
                 }
             }
             Err(e) => FileResponse {
                 data: None,
-                error_message: format!("Error fetching files: {}", e),
+                error_message: Some(Message::server_error(e)),
                 is_error: true,
             },
         }
     }
 
-    pub async fn delete_file(pool: &PgPool, file_id: i32) -> FileResponse {
-        let check_file = sqlx::query!("SELECT id, file_path FROM files WHERE id = $1", file_id)
-            .fetch_optional(pool)
-            .await;
+    pub async fn delete_file(files: &dyn FileRepository, file_id: i32) -> FileResponse {
+        let check_file = files.find_file_by_id(file_id).await;
 
         match check_file {
             Ok(Some(file)) => {
-                let result_delete = sqlx::query!("DELETE FROM files WHERE id = $1", file.id)
-                    .execute(pool)
-                    .await;
-                if result_delete.is_err() {
+                let result_delete = files.delete_file(file.id).await;
+                if let Err(e) = result_delete {
                     return FileResponse {
                         data: None,
-                        error_message: "Error deleting file from database".to_string(),
+                        error_message: Some(Message::server_error(e)),
                         is_error: true,
                     };
                 }
-                let file_delete = fs::remove_file(file.file_path).await;
-                if file_delete.is_err() {
+                if let Err(e) = fs::remove_file(file.file_path).await {
+                    metrics().storage_errors.with_label_values(&["delete"]).inc();
                     return FileResponse {
                         data: None,
-                        error_message: format!("Error deleting file: {}", file_delete.unwrap_err()),
+                        error_message: Some(Message::server_error(e)),
                         is_error: true,
                     };
                 }
                 FileResponse {
-                    data: Some(json!({"message": "File deleted successfully"})),
-                    error_message: "".to_string(),
+                    data: None,
+                    error_message: None,
                     is_error: false,
                 }
             }
             Ok(None) => FileResponse {
                 data: None,
-                error_message: "File not found".to_string(),
+                error_message: Some("file_not_found".into()),
                 is_error: true,
             },
-            Err(_) => FileResponse {
+            Err(e) => FileResponse {
                 data: None,
-                error_message: "Error checking file existence".to_string(),
+                error_message: Some(Message::server_error(e)),
                 is_error: true,
             },
         }
     }
+
+    /// Makes browsers save the file under its uploaded name, which is encoded
+    /// as RFC 6266 requires, since it may contain any character.
+    pub fn content_disposition(file_name: &str) -> HeaderValue {
+        let mut encoded = String::with_capacity(file_name.len());
+        for byte in file_name.bytes() {
+            if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        HeaderValue::from_str(&format!("attachment; filename*=UTF-8''{}", encoded))
+            .unwrap_or(HeaderValue::from_static("attachment"))
+    }
+
+    /// Removes stored files from disk, skipping the ones that are already gone.
+    pub async fn remove_stored_files(file_paths: &[String]) -> FileResponse {
+        let mut failed = Vec::new();
+        for file_path in file_paths {
+            if let Err(e) = fs::remove_file(file_path).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    metrics().storage_errors.with_label_values(&["delete"]).inc();
+                    failed.push(file_path.clone());
+                }
+            }
+        }
+
+        if failed.is_empty() {
+            FileResponse {
+                data: Some(json!({"deleted": file_paths.len()})),
+                error_message: None,
+                is_error: false,
+            }
+        } else {
+            FileResponse {
+                data: Some(json!({"failed": failed})),
+                error_message: Some("files_not_removed".into()),
+                is_error: true,
+            }
+        }
+    }
 }
@@ -0,0 +1,36 @@
+use crate::models::files::Folder;
+
+pub use files_box_models::files::normalize_entry_name;
+
+/// Whether `folder_id` is `ancestor_id` or one of the folders inside it, among
+/// the folders of one user.
+pub fn is_within(folders: &[Folder], folder_id: i32, ancestor_id: i32) -> bool {
+    let mut current = Some(folder_id);
+    // The walk is bounded, in case the data already has a cycle.
+    for _ in 0..=folders.len() {
+        match current {
+            Some(id) if id == ancestor_id => return true,
+            Some(id) => current = folders.iter().find(|folder| folder.id == id).and_then(|folder| folder.parent_id),
+            None => return false,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(id: i32, parent_id: Option<i32>) -> Folder {
+        Folder { id, name: format!("f{}", id), user_id: 1, parent_id }
+    }
+
+    #[test]
+    fn folders_are_within_their_ancestors() {
+        let folders = [folder(1, None), folder(2, Some(1)), folder(3, Some(2)), folder(4, None)];
+        assert!(is_within(&folders, 3, 1));
+        assert!(is_within(&folders, 1, 1));
+        assert!(!is_within(&folders, 1, 3));
+        assert!(!is_within(&folders, 4, 1));
+    }
+}
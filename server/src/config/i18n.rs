@@ -0,0 +1,155 @@
+use std::{collections::HashMap, fmt, sync::OnceLock};
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{ACCEPT_LANGUAGE, CONTENT_LANGUAGE, VARY},
+        HeaderMap, HeaderValue,
+    },
+    middleware::Next,
+    response::Response as HttpResponse,
+};
+use serde::{Serialize, Serializer};
+use tracing::Span;
+
+use crate::{
+    config::{api::bearer_token, mail::render_template},
+    models::{
+        app::AppState,
+        i18n::{Locale, Message},
+    },
+};
+
+tokio::task_local! {
+    static LOCALE: Locale;
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Ru];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Ru => "ru",
+        }
+    }
+
+    /// Accepts language tags such as `ru` or `ru-RU`.
+    pub fn parse(tag: &str) -> Option<Locale> {
+        let language = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        Locale::ALL.into_iter().find(|locale| locale.as_str() == language)
+    }
+
+    /// The supported language the client prefers most, by the `q` weights of
+    /// its `Accept-Language` header.
+    pub fn from_accept_language(headers: &HeaderMap) -> Option<Locale> {
+        let header = headers.get(ACCEPT_LANGUAGE)?.to_str().ok()?;
+        let mut languages = header
+            .split(',')
+            .filter_map(|language| {
+                let mut parts = language.split(';');
+                let locale = Locale::parse(parts.next()?)?;
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (quality > 0.0).then_some((locale, quality))
+            })
+            .collect::<Vec<_>>();
+        // Stable, so the header order decides between equal weights.
+        languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+        languages.first().map(|(locale, _)| *locale)
+    }
+
+    /// Looks the key up in the catalog of the language, then in the English
+    /// one. Unknown keys are returned as they are.
+    pub fn text(&self, key: &str) -> String {
+        catalog(*self)
+            .get(key)
+            .or_else(|| catalog(Locale::En).get(key))
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    pub fn translate(&self, message: &Message) -> String {
+        let args = message.args.iter().map(|(name, value)| (*name, value.as_str())).collect::<Vec<_>>();
+        render_template(&self.text(message.key), &args, false)
+    }
+}
+
+fn catalog(locale: Locale) -> &'static HashMap<String, String> {
+    static EN: OnceLock<HashMap<String, String>> = OnceLock::new();
+    static RU: OnceLock<HashMap<String, String>> = OnceLock::new();
+    let (catalog, source) = match locale {
+        Locale::En => (&EN, include_str!("locales/en.json")),
+        Locale::Ru => (&RU, include_str!("locales/ru.json")),
+    };
+    catalog.get_or_init(|| serde_json::from_str(source).expect("invalid message catalog"))
+}
+
+impl Message {
+    pub fn new(key: &'static str) -> Message {
+        Message { key, args: Vec::new() }
+    }
+
+    /// A failure the client can't do anything about. The error is logged with
+    /// the request and also returned in the message.
+    pub fn server_error(error: impl fmt::Display) -> Message {
+        tracing::error!(error = %error, "server error");
+        Message::new("server_error").arg("error", error)
+    }
+
+    /// Sets the value of a `{{name}}` placeholder.
+    pub fn arg(mut self, name: &'static str, value: impl ToString) -> Message {
+        self.args.push((name, value.to_string()));
+        self
+    }
+}
+
+/// Serialized in the language of the request.
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&current_locale().translate(self))
+    }
+}
+
+/// The English text, for logs.
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Locale::En.translate(self))
+    }
+}
+
+impl From<&'static str> for Message {
+    fn from(key: &'static str) -> Message {
+        Message::new(key)
+    }
+}
+
+/// Language of the request being handled, English outside of a request.
+pub fn current_locale() -> Locale {
+    LOCALE.try_with(|locale| *locale).unwrap_or_default()
+}
+
+/// Chooses the language of the request: the one saved in the profile of the
+/// signed in user, else the `Accept-Language` header, else English.
+///
+/// As it checks the bearer token anyway, it also adds the user to the span of
+/// the request.
+pub async fn localize(State(app_state): State<AppState>, request: Request, next: Next) -> HttpResponse {
+    let mut locale = None;
+    if let Some(claims) = bearer_token(request.headers()).and_then(|token| app_state.auth.verify_jwt(token).ok()) {
+        Span::current().record("user_id", claims.sub);
+        if let Ok(Some(tag)) = app_state.users.find_user_locale(claims.sub).await {
+            locale = Locale::parse(&tag);
+        }
+    }
+    let locale = locale
+        .or_else(|| Locale::from_accept_language(request.headers()))
+        .unwrap_or_default();
+
+    let mut response = LOCALE.scope(locale, next.run(request)).await;
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_LANGUAGE, HeaderValue::from_static(locale.as_str()));
+    headers.append(VARY, HeaderValue::from_static("accept-language"));
+    response
+}
@@ -0,0 +1,100 @@
+use std::{
+    io::{stdout, IsTerminal},
+    time::Instant,
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response as HttpResponse,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use tracing::{field, info, info_span, Instrument};
+use tracing_subscriber::EnvFilter;
+
+use crate::models::settings::{LogConfig, LogFormat};
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Sends the logs to stdout, filtered and formatted as configured. Called
+/// once, after the configuration is loaded.
+pub fn init_logging(config: &LogConfig) {
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(&config.filter))
+        .with_ansi(stdout().is_terminal());
+    match config.format {
+        LogFormat::Pretty => builder.init(),
+        LogFormat::Json => builder.json().flatten_event(true).with_current_span(true).with_span_list(false).init(),
+    }
+}
+
+/// Id of the request being handled, which is also stored with the work it
+/// leaves for later, e.g. queued emails.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|request_id| request_id.clone()).ok()
+}
+
+/// Ids sent by a proxy or a client are kept when they are short and printable.
+fn is_valid_request_id(request_id: &str) -> bool {
+    !request_id.is_empty()
+        && request_id.len() <= MAX_REQUEST_ID_LEN
+        && request_id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+fn generate_request_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(20)
+        .map(char::from)
+        .collect()
+}
+
+/// Runs the request in a span with its id, method and route, which every log
+/// line written while handling it carries. The id is taken from the
+/// `X-Request-Id` header, or generated, and returned in the same header.
+/// `user_id` is filled in once the bearer token is checked.
+pub async fn trace_request(request: Request, next: Next) -> HttpResponse {
+    let request_id = request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid_request_id(value))
+        .map(str::to_string)
+        .unwrap_or_else(generate_request_id);
+    // The route template, so that ids in the path don't make every line unique.
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| request.uri().path().to_string(), |path| path.as_str().to_string());
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        route = %route,
+        user_id = field::Empty,
+    );
+
+    let started = Instant::now();
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), next.run(request))
+        .instrument(span.clone())
+        .await;
+    span.in_scope(|| {
+        info!(
+            status = response.status().as_u16(),
+            latency_ms = started.elapsed().as_millis() as u64,
+            "request finished"
+        )
+    });
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(X_REQUEST_ID, value);
+    }
+    response
+}
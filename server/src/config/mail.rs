@@ -0,0 +1,221 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use rand::Rng;
+
+use crate::models::{
+    i18n::Locale,
+    mail::{Email, EmailTemplate, MailError, MailTransport, Mailer},
+    settings::{MailConfig, MailTransportKind, SmtpSecurity},
+};
+
+const LAYOUT: &str = include_str!("email_templates/layout.html");
+
+impl EmailTemplate {
+    /// Name of the template files, also stored with queued emails.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::VerifyEmail { .. } => "verify_email",
+            EmailTemplate::PasswordReset { .. } => "password_reset",
+            EmailTemplate::PasswordResetRequired { .. } => "password_reset_required",
+            EmailTemplate::PasswordChanged => "password_changed",
+            EmailTemplate::EmailChangeRequested { .. } => "email_change_requested",
+            EmailTemplate::MagicLink { .. } => "magic_link",
+            EmailTemplate::AccountLocked { .. } => "account_locked",
+            EmailTemplate::OrgInvitation { .. } => "org_invitation",
+        }
+    }
+
+    fn bodies(&self, locale: Locale) -> (&'static str, &'static str) {
+        macro_rules! bodies {
+            ($name:literal) => {
+                match locale {
+                    Locale::En => (
+                        include_str!(concat!("email_templates/en/", $name, ".txt")),
+                        include_str!(concat!("email_templates/en/", $name, ".html")),
+                    ),
+                    Locale::Ru => (
+                        include_str!(concat!("email_templates/ru/", $name, ".txt")),
+                        include_str!(concat!("email_templates/ru/", $name, ".html")),
+                    ),
+                }
+            };
+        }
+
+        match self {
+            EmailTemplate::VerifyEmail { .. } => bodies!("verify_email"),
+            EmailTemplate::PasswordReset { .. } => bodies!("password_reset"),
+            EmailTemplate::PasswordResetRequired { .. } => bodies!("password_reset_required"),
+            EmailTemplate::PasswordChanged => bodies!("password_changed"),
+            EmailTemplate::EmailChangeRequested { .. } => bodies!("email_change_requested"),
+            EmailTemplate::MagicLink { .. } => bodies!("magic_link"),
+            EmailTemplate::AccountLocked { .. } => bodies!("account_locked"),
+            EmailTemplate::OrgInvitation { .. } => bodies!("org_invitation"),
+        }
+    }
+
+    /// Values of the placeholders. Roles and throttled actions are keys of the
+    /// catalog, translated here.
+    fn variables(&self, locale: Locale) -> Vec<(&'static str, String)> {
+        match self {
+            EmailTemplate::VerifyEmail { link } | EmailTemplate::MagicLink { link } => vec![("link", link.clone())],
+            EmailTemplate::PasswordReset { code } | EmailTemplate::PasswordResetRequired { code } => {
+                vec![("code", code.clone())]
+            }
+            EmailTemplate::PasswordChanged => vec![],
+            EmailTemplate::EmailChangeRequested { email } => vec![("email", email.clone())],
+            EmailTemplate::AccountLocked { action } => vec![("action", locale.text(&format!("throttle.{}", action)))],
+            EmailTemplate::OrgInvitation { inviter, organization, role, link } => vec![
+                ("inviter", inviter.clone()),
+                ("organization", organization.clone()),
+                ("role", locale.text(&format!("role.{}", role))),
+                ("link", link.clone()),
+            ],
+        }
+    }
+
+    pub fn render(&self, to: &str, locale: Locale) -> Email {
+        let variables = self.variables(locale);
+        let variables = variables.iter().map(|(name, value)| (*name, value.as_str())).collect::<Vec<_>>();
+        let (text, html) = self.bodies(locale);
+        let subject = locale.text(&format!("email.{}.subject", self.name()));
+        let content = render_template(html, &variables, true);
+        Email {
+            to: to.to_string(),
+            subject: render_template(&subject, &variables, false),
+            text_body: render_template(text, &variables, false),
+            html_body: render_template(LAYOUT, &[("lang", locale.as_str()), ("content", &content)], false),
+        }
+    }
+}
+
+/// Replaces every `{{name}}` in a single pass, so values that look like
+/// placeholders are left alone. Unknown placeholders are kept as they are.
+pub fn render_template(template: &str, variables: &[(&str, &str)], escape: bool) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rendered.push_str(&rest[start..]);
+            return rendered;
+        };
+        let name = after[..end].trim();
+        match variables.iter().find(|(variable, _)| *variable == name) {
+            Some((_, value)) if escape => rendered.push_str(&escape_html(value)),
+            Some((_, value)) => rendered.push_str(value),
+            None => rendered.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+impl Mailer {
+    /// Sets up the transport once at startup. Without a choice in
+    /// `mail.transport`, SMTP is used when `mail.smtp_host` is set and a
+    /// Maildir in `mail.dir` otherwise.
+    pub fn load(config: &MailConfig) -> Result<Mailer, String> {
+        let from = config
+            .from
+            .parse::<Mailbox>()
+            .map_err(|e| format!("mail.from is not a valid address: {}", e))?;
+
+        let transport = match config.transport() {
+            MailTransportKind::Smtp => {
+                let host = config.smtp_host.as_deref().ok_or("mail.smtp_host must be set for the smtp mail transport")?;
+                let builder = match config.smtp_security {
+                    SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+                    SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+                    SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)),
+                }
+                .map_err(|e| format!("Invalid mail.smtp_host {}: {}", host, e))?;
+                let mut builder = match config.smtp_port {
+                    Some(port) => builder.port(port),
+                    None => builder,
+                };
+                if let Some(username) = &config.smtp_username {
+                    let password = config.smtp_password.clone().unwrap_or_default();
+                    builder = builder.credentials(Credentials::new(username.clone(), password));
+                }
+                MailTransport::Smtp(builder.build())
+            }
+            MailTransportKind::Maildir => {
+                let dir = config.dir.clone();
+                for sub_dir in ["tmp", "new", "cur"] {
+                    fs::create_dir_all(dir.join(sub_dir))
+                        .map_err(|e| format!("Cannot create mail directory {}: {}", dir.display(), e))?;
+                }
+                MailTransport::Maildir(dir)
+            }
+            MailTransportKind::Memory => MailTransport::Memory(Arc::new(Mutex::new(Vec::new()))),
+        };
+
+        Ok(Mailer { from, transport })
+    }
+
+    pub async fn send(&self, email: &Email) -> Result<(), MailError> {
+        let permanent = |message: String| MailError { message, permanent: true };
+        let to = email
+            .to
+            .parse::<Mailbox>()
+            .map_err(|e| permanent(format!("Invalid recipient {}: {}", email.to, e)))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&email.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                email.text_body.clone(),
+                email.html_body.clone(),
+            ))
+            .map_err(|e| permanent(format!("Cannot build email: {}", e)))?;
+
+        match &self.transport {
+            MailTransport::Smtp(transport) => transport.send(message).await.map(|_| ()).map_err(|e| MailError {
+                permanent: e.is_permanent(),
+                message: format!("SMTP error: {}", e),
+            }),
+            MailTransport::Maildir(dir) => write_maildir(dir, &message.formatted()).await.map_err(|e| MailError {
+                message: format!("Cannot write to {}: {}", dir.display(), e),
+                permanent: false,
+            }),
+            MailTransport::Memory(sent) => {
+                sent.lock().unwrap().push(email.clone());
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Delivers like an MTA: written to `tmp`, then moved into `new`, so that
+/// readers never see half-written files.
+async fn write_maildir(dir: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let name = format!(
+        "{}.{}.files-box.eml",
+        Utc::now().timestamp_micros(),
+        rand::thread_rng().gen::<u32>()
+    );
+    let tmp_path = dir.join("tmp").join(&name);
+    tokio::fs::write(&tmp_path, contents).await?;
+    tokio::fs::rename(&tmp_path, dir.join("new").join(&name)).await
+}
@@ -0,0 +1,170 @@
+use std::{sync::OnceLock, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response as HttpResponse},
+};
+use prometheus::{
+    exponential_buckets, Gauge, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::{
+    config::api::bearer_token,
+    models::{api::ApiCode, app::AppState, metrics::Metrics},
+    repositories::{health_repository::HealthRepository, outbox_repository::OutboxRepository},
+};
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(registry: &Registry, metric: T) -> T {
+    registry.register(Box::new(metric.clone())).expect("metric registered twice");
+    metric
+}
+
+impl Metrics {
+    fn new() -> Metrics {
+        let registry = Registry::new();
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            register(&registry, IntCounterVec::new(Opts::new(name, help), labels).unwrap())
+        };
+        let histogram = |name: &str, help: &str, labels: &[&str], buckets: Vec<f64>| {
+            register(&registry, HistogramVec::new(HistogramOpts::new(name, help).buckets(buckets), labels).unwrap())
+        };
+
+        Metrics {
+            http_requests: counter("http_requests_total", "HTTP requests handled", &["method", "route", "status"]),
+            http_request_duration: histogram(
+                "http_request_duration_seconds",
+                "Time to handle HTTP requests",
+                &["method", "route", "status"],
+                prometheus::DEFAULT_BUCKETS.to_vec(),
+            ),
+            upload_bytes: counter("file_upload_bytes_total", "Bytes of uploaded files", &["space"]),
+            // 10 ms to about 10 minutes.
+            upload_duration: histogram(
+                "file_upload_duration_seconds",
+                "Time to receive and store an uploaded file",
+                &["space"],
+                exponential_buckets(0.01, 4.0, 9).unwrap(),
+            ),
+            download_bytes: counter("file_download_bytes_total", "Bytes of downloaded files", &["space"]),
+            storage_errors: counter("storage_errors_total", "Failed operations on stored files", &["operation"]),
+            emails: counter("email_deliveries_total", "Attempts to send queued emails", &["result"]),
+            db_connections: register(
+                &registry,
+                IntGaugeVec::new(Opts::new("db_pool_connections", "Connections of the database pool"), &["state"])
+                    .unwrap(),
+            ),
+            db_max_connections: register(
+                &registry,
+                IntGauge::new("db_pool_max_connections", "Largest size of the database pool").unwrap(),
+            ),
+            outbox_pending: register(
+                &registry,
+                IntGauge::new("email_outbox_pending", "Queued emails not sent yet").unwrap(),
+            ),
+            outbox_failed: register(
+                &registry,
+                IntGauge::new("email_outbox_failed", "Queued emails that were given up on").unwrap(),
+            ),
+            outbox_lag_seconds: register(
+                &registry,
+                Gauge::new("email_outbox_lag_seconds", "How long the oldest due email has been waiting").unwrap(),
+            ),
+            event_streams: register(
+                &registry,
+                IntGauge::new("event_streams_open", "Open streams of change events").unwrap(),
+            ),
+            registry,
+        }
+    }
+
+    /// The metrics in the Prometheus text format. Gauges of the pool and the
+    /// outbox are read now.
+    pub async fn render(
+        &self,
+        health: &dyn HealthRepository,
+        outbox: &dyn OutboxRepository,
+        max_connections: u32,
+    ) -> String {
+        if let Some(connections) = health.pool_connections() {
+            self.db_connections.with_label_values(&["idle"]).set(connections.idle);
+            self.db_connections
+                .with_label_values(&["in_use"])
+                .set(connections.size - connections.idle);
+        }
+        self.db_max_connections.set(max_connections as i64);
+        match outbox.outbox_stats().await {
+            Ok(stats) => {
+                self.outbox_pending.set(stats.pending);
+                self.outbox_failed.set(stats.failed);
+                self.outbox_lag_seconds.set(stats.lag_seconds);
+            }
+            Err(e) => tracing::warn!(error = %e, "cannot read outbox metrics"),
+        }
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .unwrap_or_default()
+    }
+}
+
+/// Metrics of the process, shared by the handlers and the workers.
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// Counts the request and its duration by route and status, which is the
+/// `code` of API responses rather than their HTTP status.
+pub async fn track_metrics(request: Request, next: Next) -> HttpResponse {
+    let method = request.method().to_string();
+    // Unmatched paths share one label, so that scanners can't add series.
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let status = match response.extensions().get::<ApiCode>() {
+        Some(ApiCode(code)) => code.to_string(),
+        None => response.status().as_u16().to_string(),
+    };
+
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    metrics().http_requests.with_label_values(&labels).inc();
+    metrics()
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+    response
+}
+
+fn is_authorized(app_state: &AppState, headers: &HeaderMap) -> bool {
+    match &app_state.config.metrics.token {
+        Some(token) => bearer_token(headers) == Some(token.as_str()),
+        None => true,
+    }
+}
+
+/// Метрики Prometheus
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    security((), ("metrics_token" = [])),
+    responses(
+        (status = 200, description = "Метрики в текстовом формате Prometheus", content_type = "text/plain; version=0.0.4", body = String),
+        (status = 401, description = "Неверный METRICS_TOKEN")
+    ),
+    tag = "health"
+)]
+pub async fn metrics_handler(State(app_state): State<AppState>, headers: HeaderMap) -> HttpResponse {
+    if !is_authorized(&app_state, &headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let body = metrics()
+        .render(&*app_state.health, &*app_state.outbox, app_state.config.database.max_connections)
+        .await;
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
@@ -1,4 +1,21 @@
 pub mod auth;
-pub mod send_email;
+pub mod mail;
+pub mod outbox;
 pub mod files_actions;
-pub mod api;
\ No newline at end of file
+pub mod api;
+pub mod throttle;
+pub mod email_verification;
+pub mod password;
+pub mod oidc;
+pub mod device;
+pub mod audit;
+pub mod org;
+pub mod i18n;
+pub mod settings;
+pub mod logging;
+pub mod metrics;
+pub mod openapi;
+pub mod app;
+pub mod folders;
+pub mod events;
+pub mod changes;
\ No newline at end of file
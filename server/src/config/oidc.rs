@@ -0,0 +1,190 @@
+use std::{sync::RwLock, time::Duration};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use reqwest::{Client, Url};
+use ring::digest::{digest, SHA256};
+
+use crate::models::{
+    oidc::{IdTokenClaims, OidcLogin, OidcMetadata, OidcProvider, OidcProviders, OidcTokenResponse},
+    settings::Config,
+};
+
+/// PKCE `S256` challenge for a code verifier.
+pub fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, code_verifier.as_bytes()))
+}
+
+impl OidcProviders {
+    /// Sets up the providers of the `oidc` section. Their discovery documents
+    /// are fetched on first use.
+    pub fn load(config: &Config) -> OidcProviders {
+        let providers = config
+            .oidc
+            .iter()
+            .map(|(name, provider)| OidcProvider {
+                name: name.clone(),
+                issuer: provider.issuer.clone(),
+                client_id: provider.client_id.clone(),
+                client_secret: provider.client_secret.clone(),
+                scopes: provider.scopes.clone().unwrap_or_else(|| "openid email profile".to_string()),
+                redirect_uri: provider
+                    .redirect_uri
+                    .clone()
+                    .unwrap_or_else(|| format!("{}/auth/oidc/{}/callback", config.server.app_url, name)),
+                post_login_redirect: provider.post_login_redirect.clone(),
+                metadata: RwLock::new(None),
+                jwks: RwLock::new(None),
+            })
+            .collect();
+
+        OidcProviders { providers }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OidcProvider> {
+        self.providers.iter().find(|provider| provider.name == name)
+    }
+}
+
+fn http_client() -> Client {
+    Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default()
+}
+
+impl OidcProvider {
+    /// Fetches the discovery document once and keeps it for the lifetime of
+    /// the server.
+    async fn metadata(&self) -> Result<OidcMetadata, String> {
+        if let Some(metadata) = self.metadata.read().unwrap().clone() {
+            return Ok(metadata);
+        }
+
+        let url = format!("{}/.well-known/openid-configuration", self.issuer);
+        let metadata: OidcMetadata = http_client()
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Discovery request to {} failed: {}", url, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid discovery document at {}: {}", url, e))?;
+
+        if metadata.issuer.trim_end_matches('/') != self.issuer {
+            return Err(format!("Discovery document at {} is for issuer {}", url, metadata.issuer));
+        }
+
+        *self.metadata.write().unwrap() = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Returns the provider's signing keys, downloading them again when
+    /// `refresh` is set, e.g. because a token names an unknown key.
+    async fn jwks(&self, refresh: bool) -> Result<JwkSet, String> {
+        if !refresh {
+            if let Some(jwks) = self.jwks.read().unwrap().clone() {
+                return Ok(jwks);
+            }
+        }
+
+        let metadata = self.metadata().await?;
+        let jwks: JwkSet = http_client()
+            .get(&metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("JWKS request to {} failed: {}", metadata.jwks_uri, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid JWKS at {}: {}", metadata.jwks_uri, e))?;
+
+        *self.jwks.write().unwrap() = Some(jwks.clone());
+        Ok(jwks)
+    }
+
+    /// With `reauthenticate`, `max_age=0` makes the provider ask the user to
+    /// log in again even if it remembers them.
+    pub async fn authorization_url(&self, login: &OidcLogin, reauthenticate: bool) -> Result<String, String> {
+        let metadata = self.metadata().await?;
+        let mut url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.client_id),
+                ("redirect_uri", &self.redirect_uri),
+                ("scope", &self.scopes),
+                ("state", &login.state),
+                ("nonce", &login.nonce),
+                ("code_challenge", &code_challenge(&login.code_verifier)),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| format!("Invalid authorization endpoint: {}", e))?;
+        if reauthenticate {
+            url.query_pairs_mut().append_pair("max_age", "0");
+        }
+        Ok(url.to_string())
+    }
+
+    /// Redeems an authorization code and returns the ID token.
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<String, String> {
+        let metadata = self.metadata().await?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.redirect_uri),
+            ("client_id", &self.client_id),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(client_secret) = &self.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+
+        let response: OidcTokenResponse = http_client()
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Token request failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid token response: {}", e))?;
+        Ok(response.id_token)
+    }
+
+    /// Checks the signature, issuer, audience, expiry and nonce of an ID token.
+    pub async fn verify_id_token(&self, id_token: &str, nonce: &str) -> Result<IdTokenClaims, String> {
+        let header = decode_header(id_token).map_err(|e| format!("Invalid ID token: {}", e))?;
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err("ID tokens signed with a shared secret are not accepted".to_string());
+        }
+
+        let mut jwks = self.jwks(false).await?;
+        if header.kid.as_ref().is_some_and(|kid| jwks.find(kid).is_none()) {
+            jwks = self.jwks(true).await?;
+        }
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        }
+        .ok_or("ID token is signed with an unknown key")?;
+        let key = DecodingKey::from_jwk(jwk).map_err(|e| format!("Invalid provider key: {}", e))?;
+
+        let metadata = self.metadata().await?;
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| format!("Invalid ID token: {}", e))?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err("ID token nonce does not match".to_string());
+        }
+        Ok(claims)
+    }
+}
@@ -0,0 +1,133 @@
+use utoipa::{
+    openapi::security::{Http, HttpAuthScheme, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use crate::{config, models, services};
+
+/// The OpenAPI document of the API, served at `/api-docs/openapi.json`.
+///
+/// Every route has to be listed in `paths`, `tests/openapi.rs` compares them
+/// with the routers.
+#[derive(OpenApi)]
+#[openapi(
+    info(description = "Ответы API приходят с HTTP статусом 200, исход запроса передаётся в поле `code` тела \
+                        (ApiResponse, ApiMessage или ApiError), ответы операций описаны по нему. \
+                        Исключения: вход устройства (OAuth), скачивание файлов, выгрузка аудита, \
+                        перенаправления OIDC, /healthz, /readyz и /metrics."),
+    modifiers(&SecurityAddon),
+    paths(
+        services::auth_service::login,
+        services::auth_service::refresh_session,
+        services::auth_service::register,
+        services::auth_service::forgot_password,
+        services::auth_service::reset_password,
+        services::auth_service::confirm_email,
+        services::auth_service::resend_verification,
+        services::auth_service::request_magic_link,
+        services::auth_service::magic_link_login,
+        services::auth_service::jwks,
+        services::device_service::device_code,
+        services::device_service::device_token,
+        services::device_service::device_lookup,
+        services::device_service::device_approve,
+        services::oidc_service::oidc_providers,
+        services::oidc_service::oidc_login,
+        services::oidc_service::oidc_callback,
+        services::user_service::get_user,
+        services::user_service::update_me,
+        services::user_service::change_password,
+        services::user_service::delete_me,
+        services::user_service::change_email,
+        services::user_service::get_activity,
+        services::admin_service::list_users,
+        services::admin_service::get_user,
+        services::admin_service::disable_user,
+        services::admin_service::enable_user,
+        services::admin_service::force_password_reset,
+        services::admin_service::set_quota,
+        services::admin_service::set_admin,
+        services::admin_service::set_drive_quota,
+        services::admin_service::get_user_files,
+        services::admin_service::get_stats,
+        services::admin_service::list_audit_events,
+        services::admin_service::export_audit_events,
+        services::org_service::create_org,
+        services::org_service::list_orgs,
+        services::org_service::get_org,
+        services::org_service::update_org,
+        services::org_service::delete_org,
+        services::org_service::invite_member,
+        services::org_service::list_invitations,
+        services::org_service::revoke_invitation,
+        services::org_service::lookup_invitation,
+        services::org_service::accept_org_invitation,
+        services::org_service::set_role,
+        services::org_service::remove_org_member,
+        services::team_drive_service::create_drive,
+        services::team_drive_service::list_drives,
+        services::team_drive_service::update_drive,
+        services::team_drive_service::delete_drive,
+        services::team_drive_service::upload_drive_file,
+        services::team_drive_service::list_drive_files,
+        services::team_drive_service::download_drive_file,
+        services::team_drive_service::delete_drive_file,
+        services::files_service::upload_file,
+        services::files_service::get_files,
+        services::files_service::download_file,
+        services::files_service::delete_file,
+        services::files_service::get_quota,
+        services::files_service::replace_file,
+        services::files_service::move_file,
+        services::files_service::get_changes,
+        services::files_service::get_events,
+        services::folders_service::list_folders,
+        services::folders_service::create_folder,
+        services::folders_service::update_folder,
+        services::folders_service::delete_folder,
+        services::health_service::healthz,
+        services::health_service::readyz,
+        config::metrics::metrics_handler
+    ),
+    components(
+        schemas(models::user::User, models::user::UpdateUser, models::user::ChangePassword, models::user::DeleteUser, models::user::ChangeEmail, models::auth::RegisterUser, models::auth::LoginUser, models::auth::ResetPassword, models::auth::MagicLinkRequest, models::oidc::OidcProviderInfo, models::device::DeviceCodeRequest, models::device::DeviceCodeResponse, models::device::DeviceTokenRequest, models::device::DeviceTokenResponse, models::device::DeviceApproval, models::device::OAuthError, models::admin::AdminUser, models::admin::SetQuota, models::admin::SetAdmin, models::admin::SystemStats, models::admin::DailyUploads, models::audit::AuditEventRecord, models::org::OrgRole, models::org::Organization, models::org::Membership, models::org::OrgMember, models::org::OrgInvitation, models::org::TeamDrive, models::org::CreateOrganization, models::org::UpdateOrganization, models::org::CreateInvitation, models::org::AcceptInvitation, models::org::SetMemberRole, models::org::CreateTeamDrive, models::org::UpdateTeamDrive, models::health::Liveness, models::health::Readiness, models::health::ReadinessChecks)
+    ),
+    tags(
+        (name = "auth", description = "Аутентификация"),
+        (name = "files", description = "Операции с файлами"),
+        (name = "folders", description = "Папки личного пространства"),
+        (name = "user", description = "Операции с пользователями"),
+        (name = "orgs", description = "Организации и командные диски"),
+        (name = "admin", description = "Администрирование"),
+        (name = "health", description = "Состояние сервера")
+    )
+)]
+pub struct ApiDoc;
+
+/// Declares how operations with `security(...)` authenticate.
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                Http::builder()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .description(Some("Токен сессии из /auth/login или токен устройства"))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "metrics_token",
+            SecurityScheme::Http(
+                Http::builder()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("METRICS_TOKEN, если он задан"))
+                    .build(),
+            ),
+        );
+    }
+}
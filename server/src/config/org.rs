@@ -0,0 +1,35 @@
+use crate::models::{org::OrgRole, settings::Config};
+
+pub const MAX_NAME_LENGTH: usize = 255;
+
+impl OrgRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrgRole::Member => "member",
+            OrgRole::Admin => "admin",
+            OrgRole::Owner => "owner",
+        }
+    }
+
+    /// Reads a role as stored in the database. Unknown roles get no privileges.
+    pub fn parse(role: &str) -> OrgRole {
+        match role {
+            "owner" => OrgRole::Owner,
+            "admin" => OrgRole::Admin,
+            _ => OrgRole::Member,
+        }
+    }
+}
+
+/// Link sent in invitation emails. A front-end page can take it over through
+/// `server.invitation_url`.
+pub fn invitation_link(config: &Config, token: &str) -> String {
+    format!("{}?token={}", config.server.invitation_url(), token)
+}
+
+/// Trims an organization or drive name, or returns `None` if it is empty or
+/// too long.
+pub fn normalize_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    (!name.is_empty() && name.chars().count() <= MAX_NAME_LENGTH).then(|| name.to_string())
+}
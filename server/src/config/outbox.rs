@@ -0,0 +1,114 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::Error;
+use tracing::{error, field, info, info_span, warn, Instrument};
+
+use crate::{
+    config::{i18n::current_locale, logging::current_request_id, metrics::metrics},
+    models::{
+        i18n::Locale,
+        mail::{Email, EmailTemplate, Mailer, OutboxEmail},
+    },
+    repositories::outbox_repository::OutboxRepository,
+};
+
+const BATCH_SIZE: i64 = 20;
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Long enough for a slow SMTP server, short enough to retry after a crash.
+const LEASE_SECS: i64 = 5 * 60;
+const MAX_ATTEMPTS: i32 = 8;
+const BASE_RETRY_SECS: i64 = 30;
+const MAX_RETRY_SECS: i64 = 6 * 60 * 60;
+
+/// Renders the template and queues the email. It is sent by the outbox worker.
+///
+/// Written in the language the recipient chose, or else in the one of the
+/// current request.
+pub async fn enqueue_email(outbox: &dyn OutboxRepository, to: &str, template: EmailTemplate) -> Result<(), Error> {
+    let result = queue(outbox, to, &template).await;
+    match &result {
+        Ok(id) => info!(email_id = id, template = template.name(), "email queued"),
+        Err(e) => error!(template = template.name(), error = %e, "email not queued"),
+    }
+    result.map(|_| ())
+}
+
+async fn queue(outbox: &dyn OutboxRepository, to: &str, template: &EmailTemplate) -> Result<i64, Error> {
+    let locale = outbox
+        .find_recipient_locale(to)
+        .await?
+        .and_then(|tag| Locale::parse(&tag))
+        .unwrap_or_else(current_locale);
+    outbox
+        .insert_email(template.name(), &template.render(to, locale), current_request_id().as_deref())
+        .await
+}
+
+/// Sends queued emails until the server stops.
+pub async fn run_outbox_worker(outbox: Arc<dyn OutboxRepository>, mailer: Arc<Mailer>) {
+    loop {
+        let batch_was_full = deliver_due_emails(&*outbox, &mailer).await == BATCH_SIZE as usize;
+        if !batch_was_full {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Tries to send one batch of due emails and returns how many were claimed.
+pub async fn deliver_due_emails(outbox: &dyn OutboxRepository, mailer: &Mailer) -> usize {
+    let emails = match outbox.claim_due_emails(BATCH_SIZE, LEASE_SECS).await {
+        Ok(emails) => emails,
+        Err(e) => {
+            error!(error = %e, "cannot claim emails");
+            Vec::new()
+        }
+    };
+    let claimed = emails.len();
+    for email in emails {
+        // Logged with the id of the request that queued the email.
+        let span = info_span!(
+            "email",
+            email_id = email.id,
+            template = %email.template,
+            request_id = email.request_id.as_deref().map(field::display),
+        );
+        deliver(outbox, mailer, email).instrument(span).await;
+    }
+    claimed
+}
+
+async fn deliver(outbox: &dyn OutboxRepository, mailer: &Mailer, outbox_email: OutboxEmail) {
+    let email = Email {
+        to: outbox_email.recipient,
+        subject: outbox_email.subject,
+        text_body: outbox_email.text_body,
+        html_body: outbox_email.html_body,
+    };
+    let result = match mailer.send(&email).await {
+        Ok(()) => {
+            info!("email sent");
+            metrics().emails.with_label_values(&["sent"]).inc();
+            outbox.mark_email_sent(outbox_email.id).await
+        }
+        Err(e) => {
+            let attempts = outbox_email.attempts + 1;
+            let retry_in_secs = (!e.permanent && attempts < MAX_ATTEMPTS).then(|| retry_delay_secs(attempts));
+            let result = if retry_in_secs.is_some() { "retry" } else { "failed" };
+            metrics().emails.with_label_values(&[result]).inc();
+            match retry_in_secs {
+                Some(retry_in_secs) => warn!(attempts, retry_in_secs, error = %e.message, "email not sent, will retry"),
+                None => error!(attempts, error = %e.message, "giving up on email"),
+            }
+            outbox.mark_email_failed(outbox_email.id, &e.message, retry_in_secs).await
+        }
+    };
+    if let Err(e) = result {
+        error!(error = %e, "cannot update email");
+    }
+}
+
+/// 30 seconds after the first failure, doubling up to 6 hours.
+fn retry_delay_secs(attempts: i32) -> i64 {
+    let factor = 1i64 << (attempts - 1).clamp(0, 20);
+    (BASE_RETRY_SECS * factor).min(MAX_RETRY_SECS)
+}
@@ -0,0 +1,123 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, OnceLock},
+};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+
+use crate::models::{auth::Auth, i18n::Message};
+
+const MAX_PASSWORD_LENGTH: usize = 256;
+
+// Shipped with the binary so the policy works without any extra files.
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+fn common_passwords() -> &'static HashSet<&'static str> {
+    static PASSWORDS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    PASSWORDS.get_or_init(|| {
+        COMMON_PASSWORDS
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect()
+    })
+}
+
+fn is_bcrypt(hashed_password: &str) -> bool {
+    hashed_password.starts_with("$2")
+}
+
+impl Auth {
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.argon2_params.clone())
+    }
+
+    /// Runs `task` on the blocking pool: hashing is slow on purpose and would
+    /// otherwise hold up a worker of the runtime for every login.
+    async fn run_blocking<T: Send + 'static>(self: &Arc<Self>, task: impl FnOnce(&Auth) -> T + Send + 'static) -> T {
+        let auth = self.clone();
+        tokio::task::spawn_blocking(move || task(&auth)).await.expect("the password task panicked")
+    }
+
+    fn hash(&self, password: &str) -> String {
+        let salt = SaltString::generate(&mut OsRng);
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .expect("Argon2 parameters are validated on load")
+            .to_string()
+    }
+
+    fn verify(&self, password: &str, hashed_password: &str) -> bool {
+        if is_bcrypt(hashed_password) {
+            return bcrypt::verify(password, hashed_password).unwrap_or(false);
+        }
+        match PasswordHash::new(hashed_password) {
+            Ok(parsed) => self.argon2().verify_password(password.as_bytes(), &parsed).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    pub async fn hash_password(self: &Arc<Self>, password: &str) -> String {
+        let password = password.to_string();
+        self.run_blocking(move |auth| auth.hash(&password)).await
+    }
+
+    /// Checks a password against an Argon2 or a legacy bcrypt hash. Malformed
+    /// hashes never match.
+    pub async fn verify_password(self: &Arc<Self>, password: &str, hashed_password: &str) -> bool {
+        let (password, hashed_password) = (password.to_string(), hashed_password.to_string());
+        self.run_blocking(move |auth| auth.verify(&password, &hashed_password)).await
+    }
+
+    /// Whether a hash was made with another algorithm or other parameters than
+    /// the current ones and should be replaced after a successful login.
+    pub fn password_needs_rehash(&self, hashed_password: &str) -> bool {
+        let parsed = match PasswordHash::new(hashed_password) {
+            Ok(parsed) => parsed,
+            Err(_) => return true,
+        };
+        if parsed.algorithm != Algorithm::Argon2id.ident() || parsed.version != Some(Version::V0x13.into()) {
+            return true;
+        }
+        match Params::try_from(&parsed) {
+            Ok(params) => {
+                let current = &self.argon2_params;
+                params.m_cost() != current.m_cost()
+                    || params.t_cost() != current.t_cost()
+                    || params.p_cost() != current.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+
+    /// Spends the same time as `verify_password` so that unknown accounts can't
+    /// be told apart from wrong passwords by the response time.
+    pub async fn verify_dummy_password(self: &Arc<Self>, password: &str) {
+        static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+        let password = password.to_string();
+        self.run_blocking(move |auth| {
+            let hashed_password = DUMMY_HASH.get_or_init(|| auth.hash(&Auth::generate_token()));
+            let _ = auth.verify(&password, hashed_password);
+        })
+        .await
+    }
+
+    /// Enforces the password policy: a minimum length (`password.min_length`,
+    /// 8 by default) and no passwords from the list of common ones.
+    pub fn check_password_policy(&self, password: &str) -> Result<(), Message> {
+        let length = password.chars().count();
+        if length < self.min_password_length {
+            return Err(Message::new("password_too_short").arg("min", self.min_password_length));
+        }
+        if length > MAX_PASSWORD_LENGTH {
+            return Err(Message::new("password_too_long").arg("max", MAX_PASSWORD_LENGTH));
+        }
+        if common_passwords().contains(password.to_lowercase().as_str()) {
+            return Err("password_too_common".into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use axum::Error;
+
+use crate::{
+    config::outbox::enqueue_email,
+    models::{
+        app::AppState,
+        auth::{Throttle, ThrottleAction},
+        mail::EmailTemplate,
+    },
+};
+
+const ACCOUNT_SCOPE: &str = "account";
+const IP_SCOPE: &str = "ip";
+
+// Failures older than this no longer count towards a lockout. Much longer than
+// the longest lockout, so that waiting one out doesn't start the count afresh.
+const FAILURE_WINDOW_SECS: i64 = 24 * 60 * 60;
+const BASE_LOCKOUT_SECS: i64 = 60;
+const MAX_LOCKOUT_SECS: i64 = 60 * 60;
+
+impl ThrottleAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThrottleAction::Login => "login",
+            ThrottleAction::ForgotPassword => "forgot_password",
+            ThrottleAction::ResetPassword => "reset_password",
+            ThrottleAction::VerifyEmail => "verify_email",
+            ThrottleAction::MagicLink => "magic_link",
+            ThrottleAction::DeviceApproval => "device_approval",
+        }
+    }
+
+    /// Allowed failures per account and per IP before the lockout kicks in.
+    fn limits(&self) -> (i32, i32) {
+        match self {
+            ThrottleAction::Login => (5, 20),
+            ThrottleAction::ForgotPassword => (3, 10),
+            ThrottleAction::ResetPassword => (5, 20),
+            ThrottleAction::VerifyEmail => (3, 10),
+            ThrottleAction::MagicLink => (3, 10),
+            ThrottleAction::DeviceApproval => (5, 20),
+        }
+    }
+}
+
+impl Throttle {
+    pub fn new(action: ThrottleAction, email: &str, ip: &str) -> Throttle {
+        Throttle {
+            action,
+            email: email.trim().to_lowercase(),
+            ip: ip.to_string(),
+        }
+    }
+
+    /// Returns the number of seconds until the next attempt is allowed, if the
+    /// account or the IP address is currently locked.
+    pub async fn retry_after(&self, app_state: &AppState) -> Result<Option<i64>, Error> {
+        let throttles = &app_state.throttles;
+        let action = self.action.as_str();
+        let account = throttles.find_lock(action, ACCOUNT_SCOPE, &self.email).await?;
+        let ip = throttles.find_lock(action, IP_SCOPE, &self.ip).await?;
+        Ok(account.max(ip))
+    }
+
+    /// Records a failed attempt and locks the account or IP address once its
+    /// limit is exceeded. The lockout doubles with every further failure.
+    pub async fn fail(&self, app_state: &AppState) -> Result<(), Error> {
+        let throttles = &app_state.throttles;
+        let action = self.action.as_str();
+        let (account_limit, ip_limit) = self.action.limits();
+
+        let account_attempts = throttles
+            .register_failure(action, ACCOUNT_SCOPE, &self.email, FAILURE_WINDOW_SECS)
+            .await?;
+        if account_attempts >= account_limit {
+            let lock_secs = lockout_secs(account_attempts - account_limit);
+            throttles.lock(action, ACCOUNT_SCOPE, &self.email, lock_secs).await?;
+            // Requests that send emails are a plain rate limit, not a sign of an attack.
+            let sends_email = matches!(
+                self.action,
+                ThrottleAction::ForgotPassword | ThrottleAction::VerifyEmail | ThrottleAction::MagicLink
+            );
+            if account_attempts == account_limit && !sends_email {
+                self.notify_locked(app_state).await;
+            }
+        }
+
+        let ip_attempts = throttles.register_failure(action, IP_SCOPE, &self.ip, FAILURE_WINDOW_SECS).await?;
+        if ip_attempts >= ip_limit {
+            throttles.lock(action, IP_SCOPE, &self.ip, lockout_secs(ip_attempts - ip_limit)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the failures of the account after a successful attempt.
+    pub async fn succeed(&self, app_state: &AppState) -> Result<(), Error> {
+        app_state
+            .throttles
+            .clear_failures(self.action.as_str(), ACCOUNT_SCOPE, &self.email)
+            .await
+    }
+
+    async fn notify_locked(&self, app_state: &AppState) {
+        if let Ok(Some(user)) = app_state.users.find_user_by_email(self.email.clone()).await {
+            let action = self.action.as_str().to_string();
+            let _ = enqueue_email(&*app_state.outbox, &user.email, EmailTemplate::AccountLocked { action }).await;
+        }
+    }
+}
+
+fn lockout_secs(extra_failures: i32) -> i64 {
+    let factor = 1i64 << extra_failures.clamp(0, 16);
+    (BASE_LOCKOUT_SECS * factor).min(MAX_LOCKOUT_SECS)
+}
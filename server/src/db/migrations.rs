@@ -0,0 +1,108 @@
+use sqlx::{
+    migrate::{Migrate, MigrateError, Migration, Migrator},
+    PgPool,
+};
+
+const UNDEFINED_TABLE: &str = "42P01";
+/// The schema the project started from. It drops its tables before creating
+/// them.
+const BASELINE_VERSION: i64 = 20250112152618;
+/// Makes emails unique regardless of case.
+const EMAIL_VERIFICATION_VERSION: i64 = 20250121090000;
+
+/// Migrations of the `migrations` directory, built into the binary. They are
+/// forward-only: a released migration is never edited, changes get a new one.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Versions recorded in `_sqlx_migrations`, none before the first run.
+async fn applied_versions(pool: &PgPool) -> Result<Vec<i64>, sqlx::Error> {
+    match sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+        .fetch_all(pool)
+        .await
+    {
+        Ok(applied) => Ok(applied),
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some(UNDEFINED_TABLE) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether the database has the baseline tables without any migration
+/// recorded, having been set up before migrations were tracked.
+async fn is_untracked(pool: &PgPool, applied: &[i64]) -> Result<bool, sqlx::Error> {
+    if !applied.is_empty() {
+        return Ok(false);
+    }
+    sqlx::query_scalar("SELECT to_regclass('users') IS NOT NULL").fetch_one(pool).await
+}
+
+/// Records the baseline of an untracked database as applied instead of
+/// running it, which would drop the existing tables.
+async fn adopt_baseline(pool: &PgPool) -> Result<(), MigrateError> {
+    let baseline = MIGRATOR
+        .iter()
+        .find(|migration| migration.version == BASELINE_VERSION)
+        .expect("the baseline migration is embedded");
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+         VALUES ($1, $2, TRUE, $3, 0) ON CONFLICT (version) DO NOTHING",
+    )
+    .bind(baseline.version)
+    .bind(&*baseline.description)
+    .bind(&*baseline.checksum)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Emails shared, in any case, by several users, which the unique index of
+/// the email verification migration can't be created with. Those accounts are
+/// merged by hand, the readme tells how.
+async fn case_duplicate_emails(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT LOWER(email) FROM users GROUP BY 1 HAVING COUNT(*) > 1 ORDER BY 1")
+        .fetch_all(pool)
+        .await
+}
+
+/// Migrations that haven't been applied yet, in the order they will be.
+pub async fn pending_migrations(pool: &PgPool) -> Result<Vec<&'static Migration>, sqlx::Error> {
+    let mut applied = applied_versions(pool).await?;
+    if is_untracked(pool, &applied).await? {
+        applied.push(BASELINE_VERSION);
+    }
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration() && !applied.contains(&migration.version))
+        .collect())
+}
+
+/// Whether every migration has been applied.
+pub async fn is_schema_current(pool: &PgPool) -> Result<bool, sqlx::Error> {
+    Ok(pending_migrations(pool).await?.is_empty())
+}
+
+/// Applies the pending migrations, each in its own transaction, and returns
+/// them. Concurrent servers wait for each other on an advisory lock, and a
+/// migration that was edited after being applied stops the run, as do users
+/// whose emails differ only in case.
+pub async fn run_migrations(pool: &PgPool) -> Result<Vec<&'static Migration>, MigrateError> {
+    let pending = pending_migrations(pool).await?;
+    let is_pending = |version| pending.iter().any(|migration| migration.version == version);
+    // Without the baseline there are no users yet.
+    if is_pending(EMAIL_VERIFICATION_VERSION) && !is_pending(BASELINE_VERSION) {
+        let duplicates = case_duplicate_emails(pool).await?;
+        if !duplicates.is_empty() {
+            let message = format!("several users have the emails {}, merge them first", duplicates.join(", "));
+            return Err(MigrateError::ExecuteMigration(
+                sqlx::Error::InvalidArgument(message),
+                EMAIL_VERIFICATION_VERSION,
+            ));
+        }
+    }
+    if is_untracked(pool, &applied_versions(pool).await?).await? {
+        adopt_baseline(pool).await?;
+    }
+    MIGRATOR.run(pool).await?;
+    Ok(pending)
+}
@@ -1,8 +1,32 @@
-use sqlx::postgres::PgPool;
+use std::time::Duration;
 
-pub async fn create_pool() -> PgPool {
-    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not set");
-    PgPool::connect(&db_url)
-        .await
-        .expect("Failed to connect to database")
+use sqlx::postgres::{PgPool, PgPoolOptions};
+
+use crate::models::settings::DatabaseConfig;
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Connects to the database, retrying with backoff so that the server can
+/// start before the database is up.
+pub async fn create_pool(config: &DatabaseConfig) -> Result<PgPool, sqlx::Error> {
+    let mut delay = Duration::from_secs(1);
+    let mut attempt = 1;
+    loop {
+        let pool = PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+            .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+            .connect(&config.url)
+            .await;
+        match pool {
+            Ok(pool) => return Ok(pool),
+            Err(e) if attempt < config.connect_attempts => {
+                tracing::warn!(attempt, error = %e, "cannot connect to the database, retrying in {:?}", delay);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
 }
\ No newline at end of file
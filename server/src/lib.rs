@@ -0,0 +1,6 @@
+pub mod models;
+pub mod services;
+pub mod config;
+pub mod db;
+pub mod routes;
+pub mod repositories;
@@ -1,55 +1,212 @@
-mod models;
-mod services;
-mod config;
-mod db;
-mod routes;
-mod repositories;
-
-use crate::{
-    routes::auth_router::auth_router,
-    routes::files_router::files_router,
-    routes::user_router::user_router,
+use server::{
+    db::{self, migrations::{pending_migrations, run_migrations}},
+    config::{
+        changes::run_change_compaction, events::run_change_listener, logging::init_logging, outbox::run_outbox_worker,
+    },
+    models::{app::AppState, auth::Auth, events::Notice, mail::Mailer, oidc::OidcProviders, settings::Config},
+    routes::{app_router::app_router, metrics_router::metrics_router},
 };
-use axum::Router;
-use tokio::net::TcpListener;
-use utoipa::OpenApi;
-use utoipa_swagger_ui::SwaggerUi;
-
-
-#[derive(OpenApi)]
-#[openapi(
-    paths(
-        services::auth_service::login,
-        services::auth_service::register,
-        services::auth_service::forgot_password,
-        services::auth_service::reset_password
-    ),
-    components(
-        schemas(models::user::User, models::auth::RegisterUser, models::auth::LoginUser, models::auth::ResetPassword)
-    ),
-    tags(
-        (name = "auth", description = "Аутентификация"),
-        (name = "files", description = "Операции с файлами"),
-        (name = "user", description = "Операции с пользователями")
-    )
-)]
-struct ApiDoc;
+use sqlx::PgPool;
+use std::{
+    env,
+    net::SocketAddr,
+    path::PathBuf,
+    process,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use tokio::{net::TcpListener, signal, sync::Notify};
+
+enum Command {
+    Serve,
+    /// Applies the pending migrations, or only lists them with `--dry-run`.
+    Migrate { dry_run: bool },
+}
+
+struct Args {
+    command: Command,
+    config: Option<PathBuf>,
+    print_config: bool,
+}
+
+const USAGE: &str = "Usage: server [--config <path>] [--print-config]\n       server migrate [--dry-run] [--config <path>]";
+
+fn parse_args() -> Args {
+    let mut args = Args { command: Command::Serve, config: None, print_config: false };
+    let mut iter = env::args().skip(1).peekable();
+    if iter.peek().map(String::as_str) == Some("migrate") {
+        iter.next();
+        args.command = Command::Migrate { dry_run: false };
+    }
+    while let Some(arg) = iter.next() {
+        match (arg.as_str(), &mut args.command) {
+            ("--config", _) => match iter.next() {
+                Some(path) => args.config = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("--config requires a path");
+                    process::exit(2);
+                }
+            },
+            ("--print-config", _) => args.print_config = true,
+            ("--dry-run", Command::Migrate { dry_run }) => *dry_run = true,
+            _ => {
+                eprintln!("Unknown argument: {}", arg);
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        }
+    }
+    args
+}
+
+async fn connect(config: &Config) -> PgPool {
+    db::pool::create_pool(&config.database).await.unwrap_or_else(|e| {
+        tracing::error!(error = %e, "cannot connect to the database");
+        process::exit(1);
+    })
+}
+
+async fn apply_migrations(pool: &PgPool) {
+    match run_migrations(pool).await {
+        Ok(applied) => {
+            for migration in applied {
+                tracing::info!(version = migration.version, "applied migration {}", migration.description);
+            }
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "cannot apply the migrations");
+            process::exit(1);
+        }
+    }
+}
+
+async fn migrate(config: &Config, dry_run: bool) {
+    let pool = connect(config).await;
+    if !dry_run {
+        apply_migrations(&pool).await;
+        return;
+    }
+    match pending_migrations(&pool).await {
+        Ok(pending) if pending.is_empty() => println!("The database is up to date."),
+        Ok(pending) => {
+            println!("Pending migrations:");
+            for migration in pending {
+                println!("  {} {}", migration.version, migration.description);
+            }
+        }
+        Err(e) => {
+            eprintln!("Cannot read the applied migrations: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Resolves on SIGINT (Ctrl+C) or SIGTERM, which orchestrators send first.
+async fn shutdown_signal() {
+    let interrupt = async {
+        signal::ctrl_c().await.expect("Failed to listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+}
 
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
-    let pool = db::pool::create_pool().await;
-
-    let app = Router::new()
-        .nest("/auth", auth_router(&pool))
-        .nest("/files", files_router(&pool))
-        .nest("/user", user_router(&pool))
-        .merge(SwaggerUi::new("/swagger-ui")
-            .url("/api-docs/openapi.json", ApiDoc::openapi()));
-
-    let listener = TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    println!("Сервер запущен на http://0.0.0.0:3000");
-    println!("Swagger UI доступен на http://0.0.0.0:3000/swagger-ui/");
-    
-    axum::serve(listener, app).await.unwrap();
+    let args = parse_args();
+    let config = Config::load(args.config).unwrap_or_else(|errors| {
+        eprintln!("Invalid configuration:");
+        for error in errors {
+            eprintln!("  - {}", error);
+        }
+        process::exit(1);
+    });
+    if args.print_config {
+        print!("{}", toml::to_string_pretty(&config.redacted()).expect("Failed to serialize the configuration"));
+        return;
+    }
+    init_logging(&config.log);
+    if let Command::Migrate { dry_run } = args.command {
+        migrate(&config, dry_run).await;
+        return;
+    }
+
+    tokio::fs::create_dir_all(&config.storage.root)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to create {}: {}", config.storage.root.display(), e));
+    let pool = connect(&config).await;
+    if config.database.auto_migrate {
+        apply_migrations(&pool).await;
+    }
+    let auth = Auth::load(&config).unwrap_or_else(|e| panic!("Failed to load token keys: {}", e));
+    let oidc = OidcProviders::load(&config);
+    let mailer = Mailer::load(&config.mail).unwrap_or_else(|e| panic!("Failed to set up the mail transport: {}", e));
+    let bind = config.server.bind;
+    let shutdown_timeout = Duration::from_secs(config.server.shutdown_timeout_secs);
+    let state = AppState::new(pool.clone(), config, auth, oidc);
+    tokio::spawn(run_outbox_worker(state.outbox.clone(), Arc::new(mailer)));
+    tokio::spawn(run_change_listener(pool.clone(), state.notices.clone()));
+    tokio::spawn(run_change_compaction(state.changes.clone(), state.config.changes.retention_days));
+
+    let app = app_router(&state);
+    if let (true, Some(metrics_bind)) = (state.config.metrics.enabled, state.config.metrics.bind) {
+        let listener = TcpListener::bind(metrics_bind)
+            .await
+            .unwrap_or_else(|e| panic!("Failed to bind {}: {}", metrics_bind, e));
+        tracing::info!("metrics available at http://{}/metrics", listener.local_addr().unwrap());
+        let metrics_app = metrics_router(&state);
+        tokio::spawn(async move { axum::serve(listener, metrics_app).await });
+    }
+
+    let listener = TcpListener::bind(bind)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to bind {}: {}", bind, e));
+    let address = listener.local_addr().unwrap();
+    tracing::info!("listening on http://{}", address);
+    tracing::info!("Swagger UI available at http://{}/swagger-ui/", address);
+
+    // Stops accepting connections on the signal, then waits for in-flight
+    // requests, e.g. uploads, for up to the shutdown timeout.
+    let shutdown_started = Arc::new(Notify::new());
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown({
+            let shutting_down = state.shutting_down.clone();
+            let notices = state.notices.clone();
+            let shutdown_started = shutdown_started.clone();
+            async move {
+                shutdown_signal().await;
+                tracing::info!("shutting down, waiting up to {:?} for in-flight requests", shutdown_timeout);
+                shutting_down.store(true, Ordering::Relaxed);
+                // Event streams would hold the shutdown until the timeout.
+                let _ = notices.send(Notice::ShuttingDown);
+                shutdown_started.notify_one();
+            }
+        });
+    tokio::select! {
+        result = server => {
+            if let Err(e) = result {
+                tracing::error!(error = %e, "server error");
+            }
+            tracing::info!("stopped");
+        }
+        _ = async {
+            shutdown_started.notified().await;
+            tokio::time::sleep(shutdown_timeout).await;
+        } => {
+            tracing::warn!("shutdown timeout reached, dropping in-flight requests");
+        }
+    }
+    pool.close().await;
 }
\ No newline at end of file
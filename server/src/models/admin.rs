@@ -0,0 +1,63 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// A user as seen by admins, with their storage usage.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AdminUser {
+    pub id: i32,
+    pub email: String,
+    pub name: String,
+    pub is_admin: bool,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub password_reset_required: bool,
+    /// Quota set for this user, `None` for the default.
+    pub quota_bytes: Option<i64>,
+    pub effective_quota_bytes: i64,
+    pub used_bytes: i64,
+    pub file_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, IntoParams)]
+pub struct UserSearch {
+    /// Part of the email or name.
+    pub search: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SetQuota {
+    /// `null` restores the default quota.
+    pub quota_bytes: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SetAdmin {
+    pub is_admin: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SystemStats {
+    pub users: i64,
+    pub disabled_users: i64,
+    pub files: i64,
+    pub bytes: i64,
+    /// The last 30 days, days without uploads are left out.
+    pub uploads_per_day: Vec<DailyUploads>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DailyUploads {
+    pub day: NaiveDate,
+    pub files: i64,
+    pub bytes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UserPage {
+    pub users: Vec<AdminUser>,
+    pub limit: i64,
+    pub offset: i64,
+}
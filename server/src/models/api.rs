@@ -1,11 +1,18 @@
-use serde::Serialize;
 use serde_json::Value;
 
+use crate::models::i18n::Message;
 
+pub use files_box_models::api::{ApiError, ApiMessage, ApiResponse};
 
-#[derive(Serialize)]
+/// Body of every API response. Serialized with the message translated into
+/// the language of the request, and its key as `error` for failures.
 pub struct Response {
     pub code: i32,
-    pub message: Option<String>,
+    pub message: Option<Message>,
     pub data: Option<Value>
-}
\ No newline at end of file
+}
+
+/// The `code` of a `Response`, which the metrics count instead of the HTTP
+/// status. Kept in the extensions of the HTTP response.
+#[derive(Clone, Copy, Debug)]
+pub struct ApiCode(pub i32);
@@ -1,6 +1,41 @@
-use sqlx::postgres::PgPool;
+use std::sync::{atomic::AtomicBool, Arc};
 
-#[derive(Clone, Debug)]
+use tokio::sync::broadcast;
+
+use crate::{
+    models::{auth::Auth, events::Notice, oidc::OidcProviders, settings::Config},
+    repositories::{
+        admin_repository::AdminRepository, audit_repository::AuditRepository, auth_repository::AuthRepository,
+        change_repository::ChangeRepository, device_repository::DeviceRepository, file_repository::FileRepository,
+        folder_repository::FolderRepository, health_repository::HealthRepository, oidc_repository::IdentityRepository,
+        org_repository::OrgRepository, outbox_repository::OutboxRepository, team_drive_repository::TeamDriveRepository,
+        throttle_repository::ThrottleRepository, user_repository::UserRepository,
+    },
+};
+
+#[derive(Clone)]
 pub struct AppState {
-    pub pool: PgPool,
+    pub users: Arc<dyn UserRepository>,
+    /// Password reset codes, email verifications and magic links.
+    pub codes: Arc<dyn AuthRepository>,
+    pub files: Arc<dyn FileRepository>,
+    pub folders: Arc<dyn FolderRepository>,
+    pub changes: Arc<dyn ChangeRepository>,
+    pub throttles: Arc<dyn ThrottleRepository>,
+    /// OpenID Connect logins and linked identities.
+    pub identities: Arc<dyn IdentityRepository>,
+    pub devices: Arc<dyn DeviceRepository>,
+    pub admin: Arc<dyn AdminRepository>,
+    pub audit: Arc<dyn AuditRepository>,
+    pub orgs: Arc<dyn OrgRepository>,
+    pub drives: Arc<dyn TeamDriveRepository>,
+    pub outbox: Arc<dyn OutboxRepository>,
+    pub health: Arc<dyn HealthRepository>,
+    /// Changes for the event streams of this instance, from `run_change_listener`.
+    pub notices: broadcast::Sender<Notice>,
+    pub config: Arc<Config>,
+    pub auth: Arc<Auth>,
+    pub oidc: Arc<OidcProviders>,
+    /// Set once a shutdown is requested, so that `/readyz` turns traffic away.
+    pub shutting_down: Arc<AtomicBool>,
 }
\ No newline at end of file
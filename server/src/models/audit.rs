@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+
+pub struct AuditEvent {
+    pub actor_id: Option<i32>,
+    /// Dotted name such as `admin.user_disabled`.
+    pub action: String,
+    pub target_user_id: Option<i32>,
+    pub details: Value,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AuditEventRecord {
+    pub id: i64,
+    pub actor_id: Option<i32>,
+    pub action: String,
+    pub target_user_id: Option<i32>,
+    pub details: Value,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Filters of the admin audit log. Every filter is optional.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, IntoParams)]
+pub struct AuditQuery {
+    pub actor_id: Option<i32>,
+    pub target_user_id: Option<i32>,
+    /// An action such as `auth.login_failed`, or a prefix such as `auth`.
+    pub action: Option<String>,
+    pub ip: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Only events older than this id, for paging.
+    pub before_id: Option<i64>,
+    pub limit: Option<i64>,
+    /// Export only: `jsonl` (default) or `csv`.
+    pub format: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, IntoParams)]
+pub struct ActivityQuery {
+    /// Only events older than this id, for paging.
+    pub before_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// A page of audit events, newest first.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AuditEventPage {
+    pub events: Vec<AuditEventRecord>,
+    /// `before_id` of the next page, `None` on the last one.
+    pub next_before_id: Option<i64>,
+}
+
+impl AuditEventPage {
+    pub fn new(events: Vec<AuditEventRecord>, limit: i64) -> AuditEventPage {
+        let next_before_id = (events.len() as i64 == limit).then(|| events.last().map(|event| event.id)).flatten();
+        AuditEventPage { events, next_before_id }
+    }
+}
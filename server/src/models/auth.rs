@@ -1,16 +1,34 @@
+use argon2::Params;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{jwk::Jwk, Algorithm, DecodingKey, EncodingKey};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize, ToSchema)]
+pub use files_box_models::auth::{
+    ForgotPassword, LoginUser, MagicLinkLogin, MagicLinkRequest, RegisterUser, Registered, ResetPassword, Token,
+    VerifiedEmail, VerifyEmail,
+};
+
 pub struct Auth {
-    pub secret_key: String,
+    pub signing_key: SigningKey,
+    pub verification_keys: Vec<VerificationKey>,
+    pub session_ttl_secs: i64,
+    pub argon2_params: Params,
+    pub min_password_length: usize,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
-pub struct RegisterUser {
-    pub email: String,
-    pub password: String,
-    pub name: String,
+pub struct SigningKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    pub key: EncodingKey,
+}
+
+pub struct VerificationKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    pub key: DecodingKey,
+    /// Public form of the key, `None` for shared secrets that must not be published.
+    pub jwk: Option<Jwk>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -18,36 +36,55 @@ pub struct Code {
     pub id: i32,
     pub code: String,
     pub user_id: i32,
+    pub expires_at: DateTime<Utc>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
-pub struct LoginUser {
-    pub email: String,
-    pub password: String,
-}
-
-#[derive(Serialize, Deserialize, ToSchema)]
-pub struct ResetPassword {
-    pub email: String,
-    pub code: String,
-    pub new_password: String,
-}
-
-#[derive(Serialize, Deserialize, ToSchema)]
-pub struct ForgotPassword {
+pub struct EmailVerification {
+    pub id: i32,
+    pub user_id: i32,
     pub email: String,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
 }
 
 #[derive(Serialize,Deserialize, ToSchema)]
 pub struct Claims {
     pub sub: i32,
     pub exp: usize,
+    /// Set on single-purpose tokens such as magic links, which can't be used as sessions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Set on access tokens issued to devices, which may only do what the scope lists.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Set on sessions: when the user logged in, in seconds since the epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct AuthVerifyResponse {
     pub authorized: bool,
-    pub user_id: Option<i32>
+    pub user_id: Option<i32>,
+    /// `None` for full sessions.
+    pub scope: Option<String>,
 }
 
+#[derive(Clone, Copy, Debug)]
+pub enum ThrottleAction {
+    Login,
+    ForgotPassword,
+    ResetPassword,
+    VerifyEmail,
+    MagicLink,
+    DeviceApproval,
+}
 
+pub struct Throttle {
+    pub action: ThrottleAction,
+    pub email: String,
+    pub ip: String,
+}
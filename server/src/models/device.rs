@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+
+pub use files_box_models::device::{
+    DeviceApproval, DeviceCodeRequest, DeviceCodeResponse, DeviceLookup, DeviceTokenRequest, DeviceTokenResponse,
+    OAuthError, PendingDeviceAuthorization,
+};
+
+#[derive(Clone)]
+pub struct DeviceAuthorization {
+    pub device_code: String,
+    pub user_code: String,
+    pub client_id: String,
+    pub scope: String,
+    pub status: String,
+    pub user_id: Option<i32>,
+    pub interval_secs: i32,
+    pub last_polled_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
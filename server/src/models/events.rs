@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::models::files::Change;
+
+/// A change as the `changes` channel sends it once it is committed.
+#[derive(Deserialize, Debug)]
+pub struct ChangeNotification {
+    /// The owner of a personal entry, `None` for files of team drives.
+    pub user_id: Option<i32>,
+    /// The transaction that recorded the change.
+    pub txid: i64,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+impl ChangeNotification {
+    pub fn position(&self) -> StreamPosition {
+        StreamPosition { txid: self.txid, id: self.change.id }
+    }
+}
+
+/// Where a change is in the event streams: changes are sent in the order of
+/// the transactions that recorded them, then of their ids.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StreamPosition {
+    pub txid: i64,
+    pub id: i64,
+}
+
+/// A change read for an event stream.
+#[derive(Clone, Debug)]
+pub struct StreamedChange {
+    pub position: StreamPosition,
+    pub change: Change,
+}
+
+/// What the change listener of an instance tells its event streams.
+#[derive(Clone, Debug)]
+pub enum Notice {
+    Change(Arc<ChangeNotification>),
+    /// Notifications may have been lost, e.g. while the listener reconnected:
+    /// the streams read what they missed from the database.
+    Missed,
+    /// The server is stopping, the streams end so that it doesn't wait for them.
+    ShuttingDown,
+}
@@ -1,38 +1,80 @@
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::models::i18n::Message;
+
+pub use files_box_models::files::{
+    Change, ChangeKind, ChangePage, CreateFolder, FileData, FileList, Folder, FolderList, GetFiles, MoveFile,
+    MoveFolder, StorageQuota, UploadedFile,
+};
 
 pub struct FileAction {
 }
 
-#[derive(Serialize,ToSchema)]
 pub struct FileResponse {
     pub data: Option<serde_json::Value>,
-    pub error_message: String,
+    pub error_message: Option<Message>,
     pub is_error: bool,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct GetFiles {
-    pub file_ids: Vec<i32>,
+#[derive(Serialize, Deserialize, ToSchema, IntoParams)]
+pub struct FileQuery {
+    pub file_id: i32,
 }
 
+#[derive(Serialize, Deserialize, ToSchema, IntoParams)]
+pub struct UploadQuery {
+    /// A folder of the user, the root of the personal space when omitted.
+    pub folder_id: Option<i32>,
+}
 
-#[derive(Serialize)]
-pub struct FileData {
-    pub id: i32,
+#[derive(Serialize, Deserialize, ToSchema, IntoParams)]
+pub struct ReplaceQuery {
+    pub file_id: i32,
+    /// `content_hash` the client last saw. The contents are only replaced if
+    /// they haven't changed since.
+    pub expected_hash: Option<String>,
+}
+
+/// A file to record once it is stored on disk.
+pub struct NewFile {
     pub file_name: String,
     pub file_path: String,
     pub file_size: i32,
     pub file_content_type: String,
     pub file_type: String,
     pub user_id: i32,
+    pub team_drive_id: Option<i32>,
+    pub folder_id: Option<i32>,
+    pub content_hash: String,
 }
 
-#[derive(Serialize, Deserialize, ToSchema)]
-pub struct FileUploadRequest {
-    pub file_name: String,
+/// New contents of a file, already stored on disk.
+pub struct FileContents {
     pub file_path: String,
     pub file_size: i32,
     pub file_content_type: String,
-    pub file_type: String,
-}
\ No newline at end of file
+    pub content_hash: String,
+}
+
+/// The multipart form of uploads.
+#[derive(ToSchema)]
+pub struct FileUpload {
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, IntoParams)]
+pub struct ChangeQuery {
+    /// Changes after this one, `0` for every change from the start.
+    pub cursor: Option<i64>,
+    /// At most 1000, 500 when omitted.
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, IntoParams)]
+pub struct EventQuery {
+    /// Events after this change. `Last-Event-ID` wins over it, and without
+    /// either only the events from now on are sent.
+    pub cursor: Option<i64>,
+}
@@ -0,0 +1,22 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct Liveness {
+    pub status: String,
+}
+
+/// `ok`, or what is wrong, for every dependency of the server.
+#[derive(Serialize, ToSchema)]
+pub struct ReadinessChecks {
+    pub database: String,
+    pub storage: String,
+    pub migrations: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Readiness {
+    /// `ready`, `not_ready`, or `shutting_down` once the server stops taking requests.
+    pub status: String,
+    pub checks: ReadinessChecks,
+}
\ No newline at end of file
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Languages with a message catalog.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Ru,
+}
+
+/// A message of the catalog, with the values of its placeholders.
+///
+/// The key doesn't depend on the language, clients get it as the error code.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub key: &'static str,
+    pub args: Vec<(&'static str, String)>,
+}
@@ -0,0 +1,61 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use lettre::{message::Mailbox, AsyncSmtpTransport, Tokio1Executor};
+
+/// A rendered email, ready to be sent.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+/// An email claimed from the outbox by the worker.
+#[derive(Clone)]
+pub struct OutboxEmail {
+    pub id: i64,
+    pub template: String,
+    pub request_id: Option<String>,
+    pub recipient: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+    pub attempts: i32,
+}
+
+/// Every email the server sends, with the variables of its template.
+pub enum EmailTemplate {
+    VerifyEmail { link: String },
+    PasswordReset { code: String },
+    PasswordResetRequired { code: String },
+    PasswordChanged,
+    EmailChangeRequested { email: String },
+    MagicLink { link: String },
+    /// `action` is the name of a throttled action, such as `login`.
+    AccountLocked { action: String },
+    /// `role` is the name of an organization role, such as `member`.
+    OrgInvitation { inviter: String, organization: String, role: String, link: String },
+}
+
+pub struct Mailer {
+    pub from: Mailbox,
+    pub transport: MailTransport,
+}
+
+pub enum MailTransport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    /// Writes every email into a Maildir, for development without a mail server.
+    Maildir(PathBuf),
+    /// Keeps sent emails in memory, for tests.
+    Memory(Arc<Mutex<Vec<Email>>>),
+}
+
+/// Why an email couldn't be sent. Permanent failures are not retried.
+pub struct MailError {
+    pub message: String,
+    pub permanent: bool,
+}
@@ -0,0 +1,39 @@
+use prometheus::{Gauge, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Registry};
+
+/// Prometheus metrics of the server, served by `GET /metrics`.
+pub struct Metrics {
+    pub registry: Registry,
+    /// By method, route and status. The status is the `code` of the JSON
+    /// body when there is one, as failed API calls are sent with HTTP 200.
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    /// By space, `personal` or `team_drive`.
+    pub upload_bytes: IntCounterVec,
+    pub upload_duration: HistogramVec,
+    pub download_bytes: IntCounterVec,
+    /// By operation, `write` or `delete`.
+    pub storage_errors: IntCounterVec,
+    /// Delivery attempts by result, `sent`, `retry` or `failed`.
+    pub emails: IntCounterVec,
+    /// Connections of the pool by state, `idle` or `in_use`.
+    pub db_connections: IntGaugeVec,
+    pub db_max_connections: IntGauge,
+    pub outbox_pending: IntGauge,
+    pub outbox_failed: IntGauge,
+    /// How long the oldest due email has been waiting for the worker.
+    pub outbox_lag_seconds: Gauge,
+    pub event_streams: IntGauge,
+}
+
+/// Work of the email outbox, read when the metrics are scraped.
+pub struct OutboxStats {
+    pub pending: i64,
+    pub failed: i64,
+    pub lag_seconds: f64,
+}
+
+/// Connections of the database pool, read when the metrics are scraped.
+pub struct PoolConnections {
+    pub size: i64,
+    pub idle: i64,
+}
@@ -2,4 +2,16 @@ pub mod user;
 pub mod auth;
 pub mod api;
 pub mod app;
-pub mod files;
\ No newline at end of file
+pub mod files;
+pub mod oidc;
+pub mod device;
+pub mod admin;
+pub mod audit;
+pub mod org;
+pub mod mail;
+pub mod i18n;
+pub mod settings;
+pub mod metrics;
+pub mod health;
+pub mod repository;
+pub mod events;
\ No newline at end of file
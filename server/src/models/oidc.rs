@@ -0,0 +1,83 @@
+use std::sync::RwLock;
+
+use jsonwebtoken::jwk::JwkSet;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub struct OidcProviders {
+    pub providers: Vec<OidcProvider>,
+}
+
+pub struct OidcProvider {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scopes: String,
+    pub redirect_uri: String,
+    /// Where the browser is sent after a successful login, with the token in
+    /// the URL fragment. Without it the callback answers with JSON.
+    pub post_login_redirect: Option<String>,
+    pub metadata: RwLock<Option<OidcMetadata>>,
+    pub jwks: RwLock<Option<JwkSet>>,
+}
+
+/// The parts of the discovery document that the login flow needs.
+#[derive(Deserialize, Clone, Debug)]
+pub struct OidcMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OidcTokenResponse {
+    pub id_token: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+    /// When the user last logged in at the provider, in seconds since the epoch.
+    pub auth_time: Option<i64>,
+}
+
+#[derive(Clone)]
+pub struct OidcLogin {
+    pub state: String,
+    pub provider: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
+pub struct OidcLoginQuery {
+    /// Asks the provider for a fresh login, whose session then allows
+    /// account changes without a password.
+    pub reauthenticate: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
+pub struct OidcCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OidcProviderInfo {
+    pub name: String,
+    pub login_url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OidcProviderList {
+    pub providers: Vec<OidcProviderInfo>,
+}
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub use files_box_models::org::{TeamDrive, TeamDriveList};
+
+/// Role of a member in an organization, ordered from the least to the most
+/// privileged.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Organization {
+    pub id: i32,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An organization as seen by one of its members.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Membership {
+    pub organization_id: i32,
+    pub name: String,
+    pub role: OrgRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OrgMember {
+    pub user_id: i32,
+    pub email: String,
+    pub name: String,
+    pub role: OrgRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+/// A pending invitation. The token is only sent by email.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OrgInvitation {
+    pub id: i32,
+    pub organization_id: i32,
+    pub email: String,
+    pub role: OrgRole,
+    pub invited_by: Option<i32>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CreateOrganization {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UpdateOrganization {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CreateInvitation {
+    pub email: String,
+    /// `member` when omitted.
+    pub role: Option<OrgRole>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, IntoParams)]
+pub struct InvitationLookup {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AcceptInvitation {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SetMemberRole {
+    pub role: OrgRole,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CreateTeamDrive {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UpdateTeamDrive {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MembershipList {
+    pub organizations: Vec<Membership>,
+}
+
+/// An organization with the role of the user asking and every member.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OrganizationDetails {
+    pub organization: Organization,
+    pub role: OrgRole,
+    pub members: Vec<OrgMember>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MemberList {
+    pub members: Vec<OrgMember>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct InvitationList {
+    pub invitations: Vec<OrgInvitation>,
+}
+
+/// What the invited person is shown before accepting.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct InvitationPreview {
+    /// Name of the organization.
+    pub organization: String,
+    pub email: String,
+    pub role: OrgRole,
+    pub expires_at: DateTime<Utc>,
+}
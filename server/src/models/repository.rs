@@ -0,0 +1,164 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    models::{
+        audit::AuditEventRecord,
+        auth::{Code, EmailVerification},
+        device::DeviceAuthorization,
+        files::{Change, FileData, Folder},
+        mail::OutboxEmail,
+        oidc::OidcLogin,
+        org::{OrgInvitation, OrgRole, Organization},
+        user::User,
+    },
+    repositories::{
+        admin_repository::AdminRepository, audit_repository::AuditRepository, auth_repository::AuthRepository,
+        change_repository::ChangeRepository, device_repository::DeviceRepository, file_repository::FileRepository,
+        folder_repository::FolderRepository, health_repository::HealthRepository, oidc_repository::IdentityRepository,
+        org_repository::OrgRepository, outbox_repository::OutboxRepository, team_drive_repository::TeamDriveRepository,
+        throttle_repository::ThrottleRepository, user_repository::UserRepository,
+    },
+};
+
+/// Every repository the server uses, implemented by one store.
+pub trait Repository:
+    UserRepository
+    + AuthRepository
+    + FileRepository
+    + FolderRepository
+    + ChangeRepository
+    + ThrottleRepository
+    + IdentityRepository
+    + DeviceRepository
+    + AdminRepository
+    + AuditRepository
+    + OrgRepository
+    + TeamDriveRepository
+    + OutboxRepository
+    + HealthRepository
+{
+}
+
+impl<T> Repository for T where
+    T: UserRepository
+        + AuthRepository
+        + FileRepository
+        + FolderRepository
+        + ChangeRepository
+        + ThrottleRepository
+        + IdentityRepository
+        + DeviceRepository
+        + AdminRepository
+        + AuditRepository
+        + OrgRepository
+        + TeamDriveRepository
+        + OutboxRepository
+        + HealthRepository
+{
+}
+
+/// Repositories backed by Postgres, used by the server.
+#[derive(Clone)]
+pub struct PgRepository {
+    pub pool: PgPool,
+}
+
+/// Repositories that keep everything in memory, so that the HTTP layer can be
+/// tested without a database.
+#[derive(Default)]
+pub struct MemoryRepository {
+    pub state: Mutex<MemoryState>,
+}
+
+#[derive(Default)]
+pub struct MemoryState {
+    /// Stored with their password hash.
+    pub users: Vec<User>,
+    pub codes: Vec<Code>,
+    pub email_verifications: Vec<EmailVerification>,
+    pub magic_links: Vec<MagicLink>,
+    pub files: Vec<FileData>,
+    pub folders: Vec<Folder>,
+    pub changes: Vec<MemoryChange>,
+    pub throttles: Vec<Throttle>,
+    pub oidc_logins: Vec<PendingOidcLogin>,
+    pub identities: Vec<Identity>,
+    pub device_authorizations: Vec<DeviceAuthorization>,
+    pub audit_events: Vec<AuditEventRecord>,
+    pub organizations: Vec<Organization>,
+    pub members: Vec<Member>,
+    pub invitations: Vec<Invitation>,
+    pub team_drives: Vec<StoredTeamDrive>,
+    pub emails: Vec<QueuedEmail>,
+    /// Last id handed out, shared by every table.
+    pub last_id: i32,
+    /// Changes have their own ids, which compaction doesn't hand out again.
+    pub last_change_id: i64,
+    pub compacted_through: i64,
+}
+
+pub struct MagicLink {
+    pub jti: String,
+    pub user_id: i32,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+/// A change of the feed with the user it belongs to, `None` for the changes
+/// of team drives.
+pub struct MemoryChange {
+    pub user_id: Option<i32>,
+    pub change: Change,
+}
+
+pub struct Throttle {
+    pub action: String,
+    pub scope: String,
+    pub key: String,
+    pub failed_attempts: i32,
+    pub last_failed_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+pub struct PendingOidcLogin {
+    pub login: OidcLogin,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct Identity {
+    pub user_id: i32,
+    pub provider: String,
+    pub subject: String,
+}
+
+pub struct Member {
+    pub organization_id: i32,
+    pub user_id: i32,
+    pub role: OrgRole,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct Invitation {
+    pub invitation: OrgInvitation,
+    pub token: String,
+}
+
+/// A team drive without its usage, which is counted from the files.
+pub struct StoredTeamDrive {
+    pub id: i32,
+    pub organization_id: i32,
+    pub name: String,
+    pub quota_bytes: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct QueuedEmail {
+    pub email: OutboxEmail,
+    pub next_attempt_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub failed_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
@@ -0,0 +1,195 @@
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Settings of the server, read from a TOML file and the environment when it
+/// starts, then validated once.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub storage: StorageConfig,
+    pub limits: LimitsConfig,
+    pub auth: AuthConfig,
+    pub tokens: TokenConfig,
+    pub password: PasswordConfig,
+    pub mail: MailConfig,
+    pub cors: CorsConfig,
+    pub log: LogConfig,
+    pub metrics: MetricsConfig,
+    pub changes: ChangesConfig,
+    /// Single sign-on providers by name.
+    pub oidc: BTreeMap<String, OidcProviderConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: SocketAddr,
+    /// Public base URL of the server, used for links sent by email.
+    pub app_url: String,
+    /// Front-end pages that take over the links of emails and devices.
+    pub magic_link_url: Option<String>,
+    pub invitation_url: Option<String>,
+    pub device_verification_url: Option<String>,
+    /// How long in-flight requests, e.g. uploads, may take to finish once a
+    /// shutdown is requested.
+    pub shutdown_timeout_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    /// Tries to connect at startup, waiting longer after each failure.
+    pub connect_attempts: u32,
+    /// Applies pending migrations at startup. Otherwise `server migrate` does.
+    pub auto_migrate: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Directory the uploaded files are stored in.
+    pub root: PathBuf,
+    /// Quota of users and team drives without their own.
+    pub quota_bytes: i64,
+    pub team_drive_quota_bytes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Largest request body, which bounds the size of an upload.
+    pub max_upload_bytes: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// HS256 secret. Signs tokens when no key file is set.
+    pub secret_key: Option<String>,
+    pub signing_key_file: Option<String>,
+    /// Keys whose tokens are still accepted, e.g. during a rotation.
+    pub verification_key_files: Vec<String>,
+}
+
+/// Lifetimes, in seconds.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TokenConfig {
+    pub session_secs: i64,
+    pub access_token_secs: i64,
+    pub magic_link_secs: i64,
+    /// Password reset codes, sent by email.
+    pub reset_code_secs: i64,
+    pub email_verification_secs: i64,
+    pub invitation_secs: i64,
+    pub device_code_secs: i64,
+    pub oidc_login_secs: i64,
+    /// How long after a login accounts without a password can change themselves.
+    pub reauthentication_secs: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct PasswordConfig {
+    pub min_length: usize,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransportKind {
+    Smtp,
+    Maildir,
+    Memory,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    Starttls,
+    Tls,
+    None,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MailConfig {
+    /// `smtp` when a host is set and `maildir` otherwise, unless chosen here.
+    pub transport: Option<MailTransportKind>,
+    pub from: String,
+    /// Maildir the emails are written to by the `maildir` transport.
+    pub dir: PathBuf,
+    pub smtp_host: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_security: SmtpSecurity,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins browsers may call the API from, `*` for any. Empty disables CORS.
+    pub allowed_origins: Vec<String>,
+    pub max_age_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines, for development.
+    Pretty,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Levels by target, in the `tracing` filter syntax, e.g. `info,sqlx=warn`.
+    pub filter: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    /// Serves `/metrics` on this address only, e.g. an admin port that
+    /// isn't exposed, instead of on `server.bind`.
+    pub bind: Option<SocketAddr>,
+    /// Bearer token required to scrape the metrics.
+    pub token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChangesConfig {
+    /// Days changes that are superseded, or deletions, stay in the feed.
+    /// Clients with an older cursor have to sync again from the start.
+    pub retention_days: i64,
+    /// Longest an event stream holds back a change while an older transaction
+    /// runs, e.g. a long report or a session left idle in a transaction.
+    pub stream_wait_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct OidcProviderConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scopes: Option<String>,
+    pub redirect_uri: Option<String>,
+    /// Where the browser is sent after a successful login.
+    pub post_login_redirect: Option<String>,
+}
@@ -1,10 +1 @@
-use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
-
-#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
-pub struct User {
-    pub id: i32,
-    pub email: String,
-    pub password: Option<String>,
-    pub name: String,
-}
+pub use files_box_models::user::{ChangeEmail, ChangePassword, DeleteUser, UpdateUser, User};
@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use axum::Error;
+
+use crate::{
+    models::{
+        admin::{AdminUser, DailyUploads, SystemStats},
+        audit::AuditEvent,
+        files::FileData,
+        repository::PgRepository,
+    },
+    repositories::audit_repository::commit_audited,
+};
+
+/// Users and files as the admin panel sees them.
+#[async_trait]
+pub trait AdminRepository: Send + Sync {
+    /// Lists users whose email or name contains `search`, with their storage usage.
+    async fn search_users(
+        &self,
+        search: Option<&str>,
+        limit: i64,
+        offset: i64,
+        default_quota_bytes: i64,
+    ) -> Result<Vec<AdminUser>, Error>;
+
+    async fn find_admin_user(&self, user_id: i32, default_quota_bytes: i64) -> Result<Option<AdminUser>, Error>;
+
+    /// Returns `false` if the user doesn't exist. Like the other changes of
+    /// an admin, it is recorded as `event` in the same transaction.
+    async fn set_user_disabled(&self, user_id: i32, disabled: bool, event: &AuditEvent) -> Result<bool, Error>;
+
+    /// Blocks the account until its password is reset.
+    async fn require_password_reset(&self, user_id: i32, event: &AuditEvent) -> Result<bool, Error>;
+
+    async fn set_user_quota(&self, user_id: i32, quota_bytes: Option<i64>, event: &AuditEvent) -> Result<bool, Error>;
+
+    async fn set_user_admin(&self, user_id: i32, is_admin: bool, event: &AuditEvent) -> Result<bool, Error>;
+
+    async fn find_user_files(&self, user_id: i32) -> Result<Vec<FileData>, Error>;
+
+    async fn system_stats(&self) -> Result<SystemStats, Error>;
+}
+
+#[async_trait]
+impl AdminRepository for PgRepository {
+    async fn search_users(
+        &self,
+        search: Option<&str>,
+        limit: i64,
+        offset: i64,
+        default_quota_bytes: i64,
+    ) -> Result<Vec<AdminUser>, Error> {
+        let pattern = search.map(|search| {
+            let escaped = search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{}%", escaped)
+        });
+        let users = sqlx::query!(
+            r#"SELECT u.id, u.email, u.name, u.is_admin, u.email_verified_at, u.disabled_at,
+                      u.password_reset_required, u.quota_bytes,
+                      COALESCE(SUM(f.file_size), 0)::BIGINT AS "used_bytes!",
+                      COUNT(f.id) AS "file_count!"
+               FROM users u
+               LEFT JOIN files f ON f.user_id = u.id AND f.team_drive_id IS NULL
+               WHERE $1::TEXT IS NULL OR u.email ILIKE $1 OR u.name ILIKE $1
+               GROUP BY u.id
+               ORDER BY u.id
+               LIMIT $2 OFFSET $3"#,
+            pattern,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match users {
+            Ok(users) => Ok(users
+                .into_iter()
+                .map(|user| AdminUser {
+                    id: user.id,
+                    email: user.email,
+                    name: user.name,
+                    is_admin: user.is_admin,
+                    email_verified_at: user.email_verified_at,
+                    disabled_at: user.disabled_at,
+                    password_reset_required: user.password_reset_required,
+                    quota_bytes: user.quota_bytes,
+                    effective_quota_bytes: user.quota_bytes.unwrap_or(default_quota_bytes),
+                    used_bytes: user.used_bytes,
+                    file_count: user.file_count,
+                })
+                .collect()),
+            Err(e) => Err(Error::new(format!("Error finding users: {}", e))),
+        }
+    }
+
+    async fn find_admin_user(&self, user_id: i32, default_quota_bytes: i64) -> Result<Option<AdminUser>, Error> {
+        let user = sqlx::query!(
+            r#"SELECT u.id, u.email, u.name, u.is_admin, u.email_verified_at, u.disabled_at,
+                      u.password_reset_required, u.quota_bytes,
+                      COALESCE(SUM(f.file_size), 0)::BIGINT AS "used_bytes!",
+                      COUNT(f.id) AS "file_count!"
+               FROM users u
+               LEFT JOIN files f ON f.user_id = u.id AND f.team_drive_id IS NULL
+               WHERE u.id = $1
+               GROUP BY u.id"#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match user {
+            Ok(user) => Ok(user.map(|user| AdminUser {
+                id: user.id,
+                email: user.email,
+                name: user.name,
+                is_admin: user.is_admin,
+                email_verified_at: user.email_verified_at,
+                disabled_at: user.disabled_at,
+                password_reset_required: user.password_reset_required,
+                quota_bytes: user.quota_bytes,
+                effective_quota_bytes: user.quota_bytes.unwrap_or(default_quota_bytes),
+                used_bytes: user.used_bytes,
+                file_count: user.file_count,
+            })),
+            Err(e) => Err(Error::new(format!("Error finding user: {}", e))),
+        }
+    }
+
+    async fn set_user_disabled(&self, user_id: i32, disabled: bool, event: &AuditEvent) -> Result<bool, Error> {
+        let error = |e: sqlx::Error| Error::new(format!("Error updating user: {}", e));
+        let mut tx = self.pool.begin().await.map_err(error)?;
+        let user = sqlx::query!(
+            "UPDATE users SET disabled_at = CASE WHEN $1 THEN COALESCE(disabled_at, NOW()) END WHERE id = $2",
+            disabled,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
+
+        commit_audited(tx, user.rows_affected(), event).await
+    }
+
+    async fn require_password_reset(&self, user_id: i32, event: &AuditEvent) -> Result<bool, Error> {
+        let error = |e: sqlx::Error| Error::new(format!("Error updating user: {}", e));
+        let mut tx = self.pool.begin().await.map_err(error)?;
+        let user = sqlx::query!("UPDATE users SET password_reset_required = TRUE WHERE id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(error)?;
+
+        commit_audited(tx, user.rows_affected(), event).await
+    }
+
+    async fn set_user_quota(&self, user_id: i32, quota_bytes: Option<i64>, event: &AuditEvent) -> Result<bool, Error> {
+        let error = |e: sqlx::Error| Error::new(format!("Error updating user: {}", e));
+        let mut tx = self.pool.begin().await.map_err(error)?;
+        let user = sqlx::query!("UPDATE users SET quota_bytes = $1 WHERE id = $2", quota_bytes, user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(error)?;
+
+        commit_audited(tx, user.rows_affected(), event).await
+    }
+
+    async fn set_user_admin(&self, user_id: i32, is_admin: bool, event: &AuditEvent) -> Result<bool, Error> {
+        let error = |e: sqlx::Error| Error::new(format!("Error updating user: {}", e));
+        let mut tx = self.pool.begin().await.map_err(error)?;
+        let user = sqlx::query!("UPDATE users SET is_admin = $1 WHERE id = $2", is_admin, user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(error)?;
+
+        commit_audited(tx, user.rows_affected(), event).await
+    }
+
+    async fn find_user_files(&self, user_id: i32) -> Result<Vec<FileData>, Error> {
+        let files = sqlx::query!("SELECT * FROM files WHERE user_id = $1 ORDER BY id", user_id)
+            .fetch_all(&self.pool)
+            .await;
+
+        match files {
+            Ok(files) => Ok(files
+                .into_iter()
+                .map(|file| FileData {
+                    id: file.id,
+                    file_name: file.file_name,
+                    file_path: file.file_path,
+                    file_size: file.file_size,
+                    file_content_type: file.file_content_type,
+                    file_type: file.file_type,
+                    user_id: file.user_id,
+                    team_drive_id: file.team_drive_id,
+                    folder_id: file.folder_id,
+                    content_hash: file.content_hash,
+                    created_at: file.created_at,
+                })
+                .collect()),
+            Err(e) => Err(Error::new(format!("Error finding files: {}", e))),
+        }
+    }
+
+    async fn system_stats(&self) -> Result<SystemStats, Error> {
+        let totals = sqlx::query!(
+            r#"SELECT
+                   (SELECT COUNT(*) FROM users) AS "users!",
+                   (SELECT COUNT(*) FROM users WHERE disabled_at IS NOT NULL) AS "disabled_users!",
+                   (SELECT COUNT(*) FROM files) AS "files!",
+                   (SELECT COALESCE(SUM(file_size), 0)::BIGINT FROM files) AS "bytes!""#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| Error::new(format!("Error counting users and files: {}", e)))?;
+
+        let uploads_per_day = sqlx::query!(
+            r#"SELECT (created_at AT TIME ZONE 'UTC')::DATE AS "day!",
+                      COUNT(*) AS "files!",
+                      COALESCE(SUM(file_size), 0)::BIGINT AS "bytes!"
+               FROM files
+               WHERE created_at > NOW() - INTERVAL '30 days'
+               GROUP BY 1
+               ORDER BY 1"#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::new(format!("Error counting uploads: {}", e)))?;
+
+        Ok(SystemStats {
+            users: totals.users,
+            disabled_users: totals.disabled_users,
+            files: totals.files,
+            bytes: totals.bytes,
+            uploads_per_day: uploads_per_day
+                .into_iter()
+                .map(|day| DailyUploads {
+                    day: day.day,
+                    files: day.files,
+                    bytes: day.bytes,
+                })
+                .collect(),
+        })
+    }
+}
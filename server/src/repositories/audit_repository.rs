@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use axum::Error;
+use sqlx::{PgExecutor, Postgres, Transaction};
+
+use crate::models::{
+    audit::{AuditEvent, AuditEventRecord, AuditQuery},
+    repository::PgRepository,
+};
+
+/// The audit log of security relevant actions.
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    async fn record_event(&self, event: &AuditEvent) -> Result<(), Error>;
+
+    /// Newest events first, matching every filter that is set.
+    async fn find_audit_events(&self, query: &AuditQuery, limit: i64) -> Result<Vec<AuditEventRecord>, Error>;
+
+    /// Events the user caused or that concern their account, newest first.
+    async fn find_user_events(
+        &self,
+        user_id: i32,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditEventRecord>, Error>;
+}
+
+/// Inserts the event with `executor`, which may be the transaction of the
+/// change it records.
+pub async fn insert_event(executor: impl PgExecutor<'_>, event: &AuditEvent) -> Result<(), Error> {
+    let event = sqlx::query!(
+        "INSERT INTO audit_events (actor_id, action, target_user_id, details, ip, user_agent)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        event.actor_id,
+        event.action,
+        event.target_user_id,
+        event.details,
+        event.ip,
+        event.user_agent
+    )
+    .execute(executor)
+    .await;
+
+    match event {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::new(format!("Error recording audit event: {}", e))),
+    }
+}
+
+/// Commits an update that changed `rows_affected` rows together with the
+/// event recording it, or leaves both out when the row doesn't exist.
+pub async fn commit_audited(
+    mut tx: Transaction<'_, Postgres>,
+    rows_affected: u64,
+    event: &AuditEvent,
+) -> Result<bool, Error> {
+    if rows_affected == 0 {
+        return Ok(false);
+    }
+    insert_event(&mut *tx, event).await?;
+    tx.commit()
+        .await
+        .map_err(|e| Error::new(format!("Error recording audit event: {}", e)))?;
+    Ok(true)
+}
+
+#[async_trait]
+impl AuditRepository for PgRepository {
+    async fn record_event(&self, event: &AuditEvent) -> Result<(), Error> {
+        insert_event(&self.pool, event).await
+    }
+
+    async fn find_audit_events(&self, query: &AuditQuery, limit: i64) -> Result<Vec<AuditEventRecord>, Error> {
+        let events = sqlx::query_as!(
+            AuditEventRecord,
+            "SELECT id, actor_id, action, target_user_id, details, ip, user_agent, created_at
+             FROM audit_events
+             WHERE ($1::INT IS NULL OR actor_id = $1)
+               AND ($2::INT IS NULL OR target_user_id = $2)
+               AND ($3::TEXT IS NULL OR action = $3 OR action LIKE $3 || '.%')
+               AND ($4::TEXT IS NULL OR ip = $4)
+               AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+               AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)
+               AND ($7::BIGINT IS NULL OR id < $7)
+             ORDER BY id DESC
+             LIMIT $8",
+            query.actor_id,
+            query.target_user_id,
+            query.action,
+            query.ip,
+            query.since,
+            query.until,
+            query.before_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match events {
+            Ok(events) => Ok(events),
+            Err(e) => Err(Error::new(format!("Error finding audit events: {}", e))),
+        }
+    }
+
+    async fn find_user_events(
+        &self,
+        user_id: i32,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditEventRecord>, Error> {
+        let events = sqlx::query_as!(
+            AuditEventRecord,
+            "SELECT id, actor_id, action, target_user_id, details, ip, user_agent, created_at
+             FROM audit_events
+             WHERE (actor_id = $1 OR target_user_id = $1)
+               AND ($2::BIGINT IS NULL OR id < $2)
+             ORDER BY id DESC
+             LIMIT $3",
+            user_id,
+            before_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match events {
+            Ok(events) => Ok(events),
+            Err(e) => Err(Error::new(format!("Error finding audit events: {}", e))),
+        }
+    }
+}
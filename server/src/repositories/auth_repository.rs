@@ -1,63 +1,198 @@
+use async_trait::async_trait;
 use axum::Error;
-use sqlx::PgPool;
-
-use crate::models::auth::Code;
-
-pub async fn find_code_by_code(pool: &PgPool, code: String, user_id: i32) -> Result<Option<Code>, Error> {
-    let code = sqlx::query!(
-        "SELECT * FROM codes WHERE code = $1 AND user_id = $2",
-        code,
-        user_id
-    )
-    .fetch_optional(pool)
-    .await;
-
-    match code {
-        Ok(Some(code)) => Ok(Some(Code {
-            id: code.id,
-            code: code.code,
-            user_id: code.user_id,
-        })),
-        Ok(None) => Ok(None),
-        Err(e) => Err(Error::new(format!("Error finding code: {}", e))),
-    }
+
+use crate::models::{
+    auth::{Code, EmailVerification},
+    repository::PgRepository,
+};
+
+/// One-time secrets sent to users: password reset codes, email verification
+/// links and magic links.
+#[async_trait]
+pub trait AuthRepository: Send + Sync {
+    /// The code of the user, unless it expired.
+    async fn find_code_by_code(&self, code: String, user_id: i32) -> Result<Option<Code>, Error>;
+
+    /// Replaces any earlier code of the user, so that only the latest one is
+    /// valid, for `ttl_secs`.
+    async fn create_code(&self, code: &str, user_id: i32, ttl_secs: i64) -> Result<bool, Error>;
+
+    async fn delete_code(&self, code: String, user_id: i32) -> Result<(), Error>;
+
+    /// Replaces any earlier link of the user, valid for `ttl_secs`.
+    async fn create_email_verification(
+        &self,
+        user_id: i32,
+        email: &str,
+        token: &str,
+        ttl_secs: i64,
+    ) -> Result<EmailVerification, Error>;
+
+    /// The verification with this token, unless it expired.
+    async fn find_email_verification(&self, token: &str) -> Result<Option<EmailVerification>, Error>;
+
+    async fn delete_email_verifications(&self, user_id: i32) -> Result<(), Error>;
+
+    async fn create_magic_link(&self, jti: &str, user_id: i32, ttl_secs: i64) -> Result<(), Error>;
+
+    /// Marks a magic link as used and returns its user, or `None` if the link
+    /// is unknown, expired or was already used.
+    async fn consume_magic_link(&self, jti: &str) -> Result<Option<i32>, Error>;
 }
 
-pub async fn create_code(pool: &PgPool, code: &String, user_id: i32) -> Result<bool, Error> {
-    let check_code = find_code_by_code(pool, code.clone(), user_id).await;
-    if check_code.is_ok() {
-        let update_code = sqlx::query!(
-            "UPDATE codes SET code = $1 WHERE user_id = $2 RETURNING *",
+#[async_trait]
+impl AuthRepository for PgRepository {
+    async fn find_code_by_code(&self, code: String, user_id: i32) -> Result<Option<Code>, Error> {
+        let code = sqlx::query!(
+            "SELECT * FROM codes WHERE code = $1 AND user_id = $2 AND expires_at > NOW()",
             code,
             user_id
         )
-        .fetch_one(pool)
+        .fetch_optional(&self.pool)
         .await;
-        return match update_code {
+
+        match code {
+            Ok(Some(code)) => Ok(Some(Code {
+                id: code.id,
+                code: code.code,
+                user_id: code.user_id,
+                expires_at: code.expires_at,
+            })),
+            Ok(None) => Ok(None),
+            Err(e) => Err(Error::new(format!("Error finding code: {}", e))),
+        }
+    }
+
+    async fn create_code(&self, code: &str, user_id: i32, ttl_secs: i64) -> Result<bool, Error> {
+        let delete_codes = sqlx::query!("DELETE FROM codes WHERE user_id = $1", user_id)
+            .execute(&self.pool)
+            .await;
+        if let Err(e) = delete_codes {
+            return Err(Error::new(format!("Error updating code: {}", e)));
+        }
+
+        let code = sqlx::query!(
+            "INSERT INTO codes (code, user_id, expires_at)
+             VALUES ($1, $2, NOW() + make_interval(secs => $3)) RETURNING *",
+            code,
+            user_id,
+            ttl_secs as f64
+        )
+        .fetch_one(&self.pool)
+        .await;
+        match code {
             Ok(_) => Ok(true),
-            Err(e) => Err(Error::new(format!("Error updating code: {}", e))),
-        };
-    }
-    let code = sqlx::query!(
-        "INSERT INTO codes (code, user_id) VALUES ($1, $2) RETURNING *",
-        code,
-        user_id
-    )
-    .fetch_one(pool)
-    .await;
-    match code {
-        Ok(_) => Ok(true),
-        Err(e) => Err(Error::new(format!("Error creating code: {}", e))),
+            Err(e) => Err(Error::new(format!("Error creating code: {}", e))),
+        }
+    }
+
+    async fn delete_code(&self, code: String, user_id: i32) -> Result<(), Error> {
+        let code = sqlx::query!("DELETE FROM codes WHERE code = $1 AND user_id = $2", code, user_id)
+            .execute(&self.pool)
+            .await;
+        match code {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error deleting code: {}", e))),
+        }
     }
-}
 
+    async fn create_email_verification(
+        &self,
+        user_id: i32,
+        email: &str,
+        token: &str,
+        ttl_secs: i64,
+    ) -> Result<EmailVerification, Error> {
+        // Only the latest link stays valid.
+        let _ = self.delete_email_verifications(user_id).await;
 
-pub async fn delete_code(pool: &PgPool, code: String, user_id: i32) -> Result<(), Error> {
-    let code = sqlx::query!("DELETE FROM codes WHERE code = $1 AND user_id = $2", code, user_id)
-        .execute(pool)
+        let verification = sqlx::query!(
+            "INSERT INTO email_verifications (user_id, email, token, expires_at)
+             VALUES ($1, $2, $3, NOW() + make_interval(secs => $4)) RETURNING *",
+            user_id,
+            email,
+            token,
+            ttl_secs as f64
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match verification {
+            Ok(verification) => Ok(EmailVerification {
+                id: verification.id,
+                user_id: verification.user_id,
+                email: verification.email,
+                token: verification.token,
+                expires_at: verification.expires_at,
+            }),
+            Err(e) => Err(Error::new(format!("Error creating email verification: {}", e))),
+        }
+    }
+
+    async fn find_email_verification(&self, token: &str) -> Result<Option<EmailVerification>, Error> {
+        let verification = sqlx::query!(
+            "SELECT * FROM email_verifications WHERE token = $1 AND expires_at > NOW()",
+            token
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match verification {
+            Ok(Some(verification)) => Ok(Some(EmailVerification {
+                id: verification.id,
+                user_id: verification.user_id,
+                email: verification.email,
+                token: verification.token,
+                expires_at: verification.expires_at,
+            })),
+            Ok(None) => Ok(None),
+            Err(e) => Err(Error::new(format!("Error finding email verification: {}", e))),
+        }
+    }
+
+    async fn delete_email_verifications(&self, user_id: i32) -> Result<(), Error> {
+        let verification = sqlx::query!("DELETE FROM email_verifications WHERE user_id = $1", user_id)
+            .execute(&self.pool)
+            .await;
+        match verification {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error deleting email verifications: {}", e))),
+        }
+    }
+
+    async fn create_magic_link(&self, jti: &str, user_id: i32, ttl_secs: i64) -> Result<(), Error> {
+        let _ = sqlx::query!("DELETE FROM magic_links WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await;
+
+        let link = sqlx::query!(
+            "INSERT INTO magic_links (jti, user_id, expires_at) VALUES ($1, $2, NOW() + make_interval(secs => $3))",
+            jti,
+            user_id,
+            ttl_secs as f64
+        )
+        .execute(&self.pool)
+        .await;
+
+        match link {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error creating magic link: {}", e))),
+        }
+    }
+
+    async fn consume_magic_link(&self, jti: &str) -> Result<Option<i32>, Error> {
+        let link = sqlx::query!(
+            "UPDATE magic_links SET used_at = NOW()
+             WHERE jti = $1 AND used_at IS NULL AND expires_at > NOW()
+             RETURNING user_id",
+            jti
+        )
+        .fetch_optional(&self.pool)
         .await;
-    match code {
-        Ok(_) => Ok(()),
-        Err(e) => Err(Error::new(format!("Error deleting code: {}", e))),
+
+        match link {
+            Ok(link) => Ok(link.map(|link| link.user_id)),
+            Err(e) => Err(Error::new(format!("Error using magic link: {}", e))),
+        }
     }
 }
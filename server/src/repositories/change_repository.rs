@@ -0,0 +1,236 @@
+use async_trait::async_trait;
+use axum::Error;
+use chrono::{DateTime, Utc};
+
+use crate::models::{
+    events::{StreamPosition, StreamedChange},
+    files::{Change, ChangeKind},
+    repository::PgRepository,
+};
+
+/// The feed of changes to the users' personal files and folders, and to the
+/// files of team drives. Postgres records them with triggers, so that every
+/// way of changing an entry shows up.
+#[async_trait]
+pub trait ChangeRepository: Send + Sync {
+    /// Up to `limit` changes of the user's personal entries after `cursor`,
+    /// oldest first.
+    async fn find_changes(&self, user_id: i32, cursor: i64, limit: i64) -> Result<Vec<Change>, Error>;
+
+    /// Up to `limit` changes of the user's personal entries and of the team
+    /// drives they are a member of after `after`, in stream order. Only
+    /// transactions older than any still running are read, so that no change
+    /// can later appear before the last one returned, except up to `through`,
+    /// which is read even while an older transaction runs. Any transaction
+    /// holds the others back, including those that record no change.
+    async fn find_events(
+        &self,
+        user_id: i32,
+        after: StreamPosition,
+        through: Option<StreamPosition>,
+        limit: i64,
+    ) -> Result<Vec<StreamedChange>, Error>;
+
+    /// Where the change `id` is in the streams, `None` if there is no such change.
+    async fn event_position(&self, id: i64) -> Result<Option<StreamPosition>, Error>;
+
+    /// Where a stream starting now is: every change after it is yet to be read.
+    async fn current_event_position(&self) -> Result<StreamPosition, Error>;
+
+    /// Whether the user may see the changes of the drive.
+    async fn is_drive_member(&self, user_id: i32, team_drive_id: i32) -> Result<bool, Error>;
+
+    /// Removes up to `limit` changes older than `retention_days` that are
+    /// deletions or that a later change of their entry supersedes, oldest
+    /// first, and returns how many were removed.
+    async fn compact_changes(&self, retention_days: i64, limit: i64) -> Result<u64, Error>;
+
+    /// The id of the last change removed, `0` before the first one. Cursors
+    /// between the two may have missed changes.
+    async fn compacted_through(&self) -> Result<i64, Error>;
+}
+
+struct ChangeRow {
+    id: i64,
+    kind: String,
+    team_drive_id: Option<i32>,
+    file_id: Option<i32>,
+    folder_id: Option<i32>,
+    name: String,
+    parent_id: Option<i32>,
+    file_size: Option<i32>,
+    content_hash: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<ChangeRow> for Change {
+    fn from(row: ChangeRow) -> Change {
+        Change {
+            id: row.id,
+            kind: ChangeKind::parse(&row.kind),
+            team_drive_id: row.team_drive_id,
+            file_id: row.file_id,
+            folder_id: row.folder_id,
+            name: row.name,
+            parent_id: row.parent_id,
+            file_size: row.file_size,
+            content_hash: row.content_hash,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[async_trait]
+impl ChangeRepository for PgRepository {
+    async fn find_changes(&self, user_id: i32, cursor: i64, limit: i64) -> Result<Vec<Change>, Error> {
+        let changes = sqlx::query_as!(
+            ChangeRow,
+            "SELECT id, kind, team_drive_id, file_id, folder_id, name, parent_id, file_size, content_hash, created_at
+             FROM changes WHERE user_id = $1 AND id > $2 ORDER BY id LIMIT $3",
+            user_id,
+            cursor,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match changes {
+            Ok(changes) => Ok(changes.into_iter().map(Change::from).collect()),
+            Err(e) => Err(Error::new(format!("Error finding changes: {}", e))),
+        }
+    }
+
+    async fn find_events(
+        &self,
+        user_id: i32,
+        after: StreamPosition,
+        through: Option<StreamPosition>,
+        limit: i64,
+    ) -> Result<Vec<StreamedChange>, Error> {
+        let through = through.unwrap_or_default();
+        let changes = sqlx::query!(
+            "SELECT txid, id, kind, team_drive_id, file_id, folder_id, name, parent_id, file_size, content_hash, created_at
+             FROM changes
+             WHERE (txid, id) > ($2, $3)
+               AND (txid < pg_snapshot_xmin(pg_current_snapshot())::text::bigint OR (txid, id) <= ($5, $6))
+               AND (user_id = $1 OR team_drive_id IN (
+                   SELECT team_drives.id FROM team_drives
+                   JOIN organization_members ON organization_members.organization_id = team_drives.organization_id
+                   WHERE organization_members.user_id = $1
+               ))
+             ORDER BY txid, id LIMIT $4",
+            user_id,
+            after.txid,
+            after.id,
+            limit,
+            through.txid,
+            through.id
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match changes {
+            Ok(changes) => Ok(changes
+                .into_iter()
+                .map(|row| StreamedChange {
+                    position: StreamPosition { txid: row.txid, id: row.id },
+                    change: Change::from(ChangeRow {
+                        id: row.id,
+                        kind: row.kind,
+                        team_drive_id: row.team_drive_id,
+                        file_id: row.file_id,
+                        folder_id: row.folder_id,
+                        name: row.name,
+                        parent_id: row.parent_id,
+                        file_size: row.file_size,
+                        content_hash: row.content_hash,
+                        created_at: row.created_at,
+                    }),
+                })
+                .collect()),
+            Err(e) => Err(Error::new(format!("Error finding events: {}", e))),
+        }
+    }
+
+    async fn event_position(&self, id: i64) -> Result<Option<StreamPosition>, Error> {
+        let position = sqlx::query_as!(StreamPosition, "SELECT txid, id FROM changes WHERE id = $1", id)
+            .fetch_optional(&self.pool)
+            .await;
+
+        match position {
+            Ok(position) => Ok(position),
+            Err(e) => Err(Error::new(format!("Error finding the change: {}", e))),
+        }
+    }
+
+    async fn current_event_position(&self) -> Result<StreamPosition, Error> {
+        let horizon =
+            sqlx::query_scalar!(r#"SELECT pg_snapshot_xmin(pg_current_snapshot())::text::bigint AS "horizon!""#)
+                .fetch_one(&self.pool)
+                .await;
+
+        match horizon {
+            Ok(horizon) => Ok(StreamPosition { txid: horizon, id: 0 }),
+            Err(e) => Err(Error::new(format!("Error finding the stream position: {}", e))),
+        }
+    }
+
+    async fn is_drive_member(&self, user_id: i32, team_drive_id: i32) -> Result<bool, Error> {
+        let member = sqlx::query_scalar!(
+            r#"SELECT EXISTS (
+                 SELECT 1 FROM team_drives
+                 JOIN organization_members ON organization_members.organization_id = team_drives.organization_id
+                 WHERE team_drives.id = $2 AND organization_members.user_id = $1
+             ) AS "member!""#,
+            user_id,
+            team_drive_id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match member {
+            Ok(member) => Ok(member),
+            Err(e) => Err(Error::new(format!("Error finding the drive member: {}", e))),
+        }
+    }
+
+    async fn compact_changes(&self, retention_days: i64, limit: i64) -> Result<u64, Error> {
+        let removed = sqlx::query_scalar!(
+            r#"WITH removed AS (
+                 DELETE FROM changes WHERE id IN (
+                     SELECT id FROM changes
+                     WHERE created_at < NOW() - make_interval(secs => $1)
+                       AND (kind = 'deleted' OR EXISTS (
+                           SELECT 1 FROM changes later
+                           WHERE later.id > changes.id
+                             AND (later.file_id = changes.file_id OR later.folder_id = changes.folder_id)
+                       ))
+                     ORDER BY id LIMIT $2
+                 ) RETURNING id
+             )
+             UPDATE change_retention
+             SET compacted_through = GREATEST(compacted_through, (SELECT COALESCE(MAX(id), 0) FROM removed))
+             RETURNING (SELECT COUNT(*) FROM removed) AS "removed!""#,
+            (retention_days * 24 * 60 * 60) as f64,
+            limit
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match removed {
+            Ok(removed) => Ok(removed as u64),
+            Err(e) => Err(Error::new(format!("Error compacting changes: {}", e))),
+        }
+    }
+
+    async fn compacted_through(&self) -> Result<i64, Error> {
+        let compacted = sqlx::query_scalar!("SELECT compacted_through FROM change_retention")
+            .fetch_one(&self.pool)
+            .await;
+
+        match compacted {
+            Ok(compacted) => Ok(compacted),
+            Err(e) => Err(Error::new(format!("Error finding the compacted changes: {}", e))),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use async_trait::async_trait;
+use axum::Error;
+
+use crate::models::{device::DeviceAuthorization, repository::PgRepository};
+
+/// Pending authorizations of the device flow (RFC 8628).
+#[async_trait]
+pub trait DeviceRepository: Send + Sync {
+    async fn create_device_authorization(
+        &self,
+        device_code: &str,
+        user_code: &str,
+        client_id: &str,
+        scope: &str,
+        interval_secs: i32,
+        ttl_secs: i64,
+    ) -> Result<(), Error>;
+
+    /// Finds a pending, unexpired authorization by the code the user typed.
+    async fn find_pending_by_user_code(&self, user_code: &str) -> Result<Option<DeviceAuthorization>, Error>;
+
+    /// Approves or denies a pending authorization. Returns `false` if it was
+    /// already decided or has expired.
+    async fn decide_device_authorization(&self, user_code: &str, user_id: i32, approve: bool) -> Result<bool, Error>;
+
+    async fn find_device_authorization(&self, device_code: &str) -> Result<Option<DeviceAuthorization>, Error>;
+
+    async fn record_poll(&self, device_code: &str, interval_secs: i32) -> Result<(), Error>;
+
+    /// Removes a decided or expired authorization, so that every device code
+    /// yields at most one token. Returns `false` if another poll took it first.
+    async fn delete_device_authorization(&self, device_code: &str) -> Result<bool, Error>;
+}
+
+#[async_trait]
+impl DeviceRepository for PgRepository {
+    async fn create_device_authorization(
+        &self,
+        device_code: &str,
+        user_code: &str,
+        client_id: &str,
+        scope: &str,
+        interval_secs: i32,
+        ttl_secs: i64,
+    ) -> Result<(), Error> {
+        let _ = sqlx::query!("DELETE FROM device_authorizations WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await;
+
+        let authorization = sqlx::query!(
+            "INSERT INTO device_authorizations (device_code, user_code, client_id, scope, interval_secs, expires_at)
+             VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(secs => $6))",
+            device_code,
+            user_code,
+            client_id,
+            scope,
+            interval_secs,
+            ttl_secs as f64
+        )
+        .execute(&self.pool)
+        .await;
+
+        match authorization {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error creating device authorization: {}", e))),
+        }
+    }
+
+    async fn find_pending_by_user_code(&self, user_code: &str) -> Result<Option<DeviceAuthorization>, Error> {
+        let authorization = sqlx::query_as!(
+            DeviceAuthorization,
+            "SELECT * FROM device_authorizations WHERE user_code = $1 AND status = 'pending' AND expires_at > NOW()",
+            user_code
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match authorization {
+            Ok(authorization) => Ok(authorization),
+            Err(e) => Err(Error::new(format!("Error finding device authorization: {}", e))),
+        }
+    }
+
+    async fn decide_device_authorization(&self, user_code: &str, user_id: i32, approve: bool) -> Result<bool, Error> {
+        let status = if approve { "approved" } else { "denied" };
+        let authorization = sqlx::query!(
+            "UPDATE device_authorizations SET status = $1, user_id = $2
+             WHERE user_code = $3 AND status = 'pending' AND expires_at > NOW()",
+            status,
+            user_id,
+            user_code
+        )
+        .execute(&self.pool)
+        .await;
+
+        match authorization {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => Err(Error::new(format!("Error updating device authorization: {}", e))),
+        }
+    }
+
+    async fn find_device_authorization(&self, device_code: &str) -> Result<Option<DeviceAuthorization>, Error> {
+        let authorization = sqlx::query_as!(
+            DeviceAuthorization,
+            "SELECT * FROM device_authorizations WHERE device_code = $1",
+            device_code
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match authorization {
+            Ok(authorization) => Ok(authorization),
+            Err(e) => Err(Error::new(format!("Error finding device authorization: {}", e))),
+        }
+    }
+
+    async fn record_poll(&self, device_code: &str, interval_secs: i32) -> Result<(), Error> {
+        let poll = sqlx::query!(
+            "UPDATE device_authorizations SET last_polled_at = NOW(), interval_secs = $1 WHERE device_code = $2",
+            interval_secs,
+            device_code
+        )
+        .execute(&self.pool)
+        .await;
+
+        match poll {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error updating device authorization: {}", e))),
+        }
+    }
+
+    async fn delete_device_authorization(&self, device_code: &str) -> Result<bool, Error> {
+        let authorization = sqlx::query!("DELETE FROM device_authorizations WHERE device_code = $1", device_code)
+            .execute(&self.pool)
+            .await;
+
+        match authorization {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => Err(Error::new(format!("Error deleting device authorization: {}", e))),
+        }
+    }
+}
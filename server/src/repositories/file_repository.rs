@@ -0,0 +1,199 @@
+use async_trait::async_trait;
+use axum::Error;
+
+use crate::models::{
+    files::{FileContents, FileData, NewFile},
+    repository::PgRepository,
+};
+
+/// Records of stored files, personal ones and those of team drives.
+#[async_trait]
+pub trait FileRepository: Send + Sync {
+    /// Bytes used by the personal files of the user, or by the files of the
+    /// team drive when `team_drive_id` is set.
+    async fn used_bytes(&self, user_id: i32, team_drive_id: Option<i32>) -> Result<i64, Error>;
+
+    async fn create_file(&self, file: NewFile) -> Result<FileData, Error>;
+
+    /// Files of the user's personal space, without the team drive ones.
+    async fn find_personal_files(&self, user_id: i32) -> Result<Vec<FileData>, Error>;
+
+    /// Personal files of the user among `ids`. Files of other users and of
+    /// team drives are left out.
+    async fn find_files_by_ids(&self, user_id: i32, ids: &[i32]) -> Result<Vec<FileData>, Error>;
+
+    /// The file if it is one of the user's personal files.
+    async fn find_personal_file(&self, user_id: i32, id: i32) -> Result<Option<FileData>, Error>;
+
+    async fn find_file_by_id(&self, id: i32) -> Result<Option<FileData>, Error>;
+
+    async fn delete_file(&self, id: i32) -> Result<(), Error>;
+
+    /// Points the file at new contents, unless it no longer has the ones
+    /// stored at `previous_path`. `None` if it was deleted or replaced since.
+    async fn replace_contents(&self, id: i32, previous_path: &str, contents: FileContents) -> Result<Option<FileData>, Error>;
+
+    /// Renames the file and puts it in `folder_id`. `None` if there is no such file.
+    async fn move_file(&self, id: i32, file_name: String, folder_id: Option<i32>) -> Result<Option<FileData>, Error>;
+}
+
+#[async_trait]
+impl FileRepository for PgRepository {
+    async fn used_bytes(&self, user_id: i32, team_drive_id: Option<i32>) -> Result<i64, Error> {
+        let used_bytes = sqlx::query_scalar!(
+            "SELECT COALESCE(SUM(file_size), 0)::BIGINT FROM files
+             WHERE team_drive_id IS NOT DISTINCT FROM $2 AND ($2::INT IS NOT NULL OR user_id = $1)",
+            user_id,
+            team_drive_id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match used_bytes {
+            Ok(used_bytes) => Ok(used_bytes.unwrap_or(0)),
+            Err(e) => Err(Error::new(format!("Error counting used space: {}", e))),
+        }
+    }
+
+    async fn create_file(&self, file: NewFile) -> Result<FileData, Error> {
+        let file = sqlx::query_as!(
+            FileData,
+            "INSERT INTO files (file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id, folder_id, content_hash)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             RETURNING id, file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id, folder_id, content_hash, created_at",
+            file.file_name,
+            file.file_path,
+            file.file_size,
+            file.file_content_type,
+            file.file_type,
+            file.user_id,
+            file.team_drive_id,
+            file.folder_id,
+            file.content_hash
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match file {
+            Ok(file) => Ok(file),
+            Err(e) => Err(Error::new(format!("Error creating file: {}", e))),
+        }
+    }
+
+    async fn find_personal_files(&self, user_id: i32) -> Result<Vec<FileData>, Error> {
+        let files = sqlx::query_as!(
+            FileData,
+            "SELECT id, file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id, folder_id, content_hash, created_at
+             FROM files WHERE user_id = $1 AND team_drive_id IS NULL",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match files {
+            Ok(files) => Ok(files),
+            Err(e) => Err(Error::new(format!("Error finding files: {}", e))),
+        }
+    }
+
+    async fn find_files_by_ids(&self, user_id: i32, ids: &[i32]) -> Result<Vec<FileData>, Error> {
+        let files = sqlx::query_as!(
+            FileData,
+            "SELECT id, file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id, folder_id, content_hash, created_at
+             FROM files WHERE id = ANY($2) AND user_id = $1 AND team_drive_id IS NULL",
+            user_id,
+            ids
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match files {
+            Ok(files) => Ok(files),
+            Err(e) => Err(Error::new(format!("Error finding files: {}", e))),
+        }
+    }
+
+    async fn find_personal_file(&self, user_id: i32, id: i32) -> Result<Option<FileData>, Error> {
+        let file = sqlx::query_as!(
+            FileData,
+            "SELECT id, file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id, folder_id, content_hash, created_at
+             FROM files WHERE id = $2 AND user_id = $1 AND team_drive_id IS NULL",
+            user_id,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match file {
+            Ok(file) => Ok(file),
+            Err(e) => Err(Error::new(format!("Error finding file: {}", e))),
+        }
+    }
+
+    async fn find_file_by_id(&self, id: i32) -> Result<Option<FileData>, Error> {
+        let file = sqlx::query_as!(
+            FileData,
+            "SELECT id, file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id, folder_id, content_hash, created_at
+             FROM files WHERE id = $1",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match file {
+            Ok(file) => Ok(file),
+            Err(e) => Err(Error::new(format!("Error finding file: {}", e))),
+        }
+    }
+
+    async fn delete_file(&self, id: i32) -> Result<(), Error> {
+        let file = sqlx::query!("DELETE FROM files WHERE id = $1", id)
+            .execute(&self.pool)
+            .await;
+
+        match file {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error deleting file: {}", e))),
+        }
+    }
+
+    async fn replace_contents(&self, id: i32, previous_path: &str, contents: FileContents) -> Result<Option<FileData>, Error> {
+        let file = sqlx::query_as!(
+            FileData,
+            "UPDATE files SET file_path = $3, file_size = $4, file_content_type = $5, content_hash = $6
+             WHERE id = $1 AND file_path = $2
+             RETURNING id, file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id, folder_id, content_hash, created_at",
+            id,
+            previous_path,
+            contents.file_path,
+            contents.file_size,
+            contents.file_content_type,
+            contents.content_hash
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match file {
+            Ok(file) => Ok(file),
+            Err(e) => Err(Error::new(format!("Error replacing file: {}", e))),
+        }
+    }
+
+    async fn move_file(&self, id: i32, file_name: String, folder_id: Option<i32>) -> Result<Option<FileData>, Error> {
+        let file = sqlx::query_as!(
+            FileData,
+            "UPDATE files SET file_name = $2, folder_id = $3 WHERE id = $1
+             RETURNING id, file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id, folder_id, content_hash, created_at",
+            id,
+            file_name,
+            folder_id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match file {
+            Ok(file) => Ok(file),
+            Err(e) => Err(Error::new(format!("Error moving file: {}", e))),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use async_trait::async_trait;
+use axum::Error;
+
+use crate::models::{files::Folder, repository::PgRepository};
+
+/// Folders of the users' personal spaces.
+#[async_trait]
+pub trait FolderRepository: Send + Sync {
+    async fn create_folder(&self, user_id: i32, name: String, parent_id: Option<i32>) -> Result<Folder, Error>;
+
+    async fn find_folders(&self, user_id: i32) -> Result<Vec<Folder>, Error>;
+
+    async fn find_folder_by_id(&self, id: i32) -> Result<Option<Folder>, Error>;
+
+    /// Renames the folder and puts it in `parent_id`. `None` if there is no
+    /// such folder.
+    async fn move_folder(&self, id: i32, name: String, parent_id: Option<i32>) -> Result<Option<Folder>, Error>;
+
+    /// Whether the folder has neither files nor folders.
+    async fn is_folder_empty(&self, id: i32) -> Result<bool, Error>;
+
+    /// Deletes the folder, `false` if there is no such folder. Files and
+    /// folders put in it meanwhile are moved to the root.
+    async fn delete_folder(&self, id: i32) -> Result<bool, Error>;
+}
+
+#[async_trait]
+impl FolderRepository for PgRepository {
+    async fn create_folder(&self, user_id: i32, name: String, parent_id: Option<i32>) -> Result<Folder, Error> {
+        let folder = sqlx::query_as!(
+            Folder,
+            "INSERT INTO folders (name, user_id, parent_id) VALUES ($1, $2, $3) RETURNING id, name, user_id, parent_id",
+            name,
+            user_id,
+            parent_id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match folder {
+            Ok(folder) => Ok(folder),
+            Err(e) => Err(Error::new(format!("Error creating folder: {}", e))),
+        }
+    }
+
+    async fn find_folders(&self, user_id: i32) -> Result<Vec<Folder>, Error> {
+        let folders = sqlx::query_as!(
+            Folder,
+            "SELECT id, name, user_id, parent_id FROM folders WHERE user_id = $1 ORDER BY name",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match folders {
+            Ok(folders) => Ok(folders),
+            Err(e) => Err(Error::new(format!("Error finding folders: {}", e))),
+        }
+    }
+
+    async fn find_folder_by_id(&self, id: i32) -> Result<Option<Folder>, Error> {
+        let folder = sqlx::query_as!(Folder, "SELECT id, name, user_id, parent_id FROM folders WHERE id = $1", id)
+            .fetch_optional(&self.pool)
+            .await;
+
+        match folder {
+            Ok(folder) => Ok(folder),
+            Err(e) => Err(Error::new(format!("Error finding folder: {}", e))),
+        }
+    }
+
+    async fn move_folder(&self, id: i32, name: String, parent_id: Option<i32>) -> Result<Option<Folder>, Error> {
+        let folder = sqlx::query_as!(
+            Folder,
+            "UPDATE folders SET name = $2, parent_id = $3 WHERE id = $1 RETURNING id, name, user_id, parent_id",
+            id,
+            name,
+            parent_id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match folder {
+            Ok(folder) => Ok(folder),
+            Err(e) => Err(Error::new(format!("Error moving folder: {}", e))),
+        }
+    }
+
+    async fn is_folder_empty(&self, id: i32) -> Result<bool, Error> {
+        let empty = sqlx::query_scalar!(
+            r#"SELECT NOT EXISTS (SELECT 1 FROM files WHERE folder_id = $1)
+                 AND NOT EXISTS (SELECT 1 FROM folders WHERE parent_id = $1) AS "empty!""#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match empty {
+            Ok(empty) => Ok(empty),
+            Err(e) => Err(Error::new(format!("Error finding folder entries: {}", e))),
+        }
+    }
+
+    async fn delete_folder(&self, id: i32) -> Result<bool, Error> {
+        let folder = sqlx::query!("DELETE FROM folders WHERE id = $1", id).execute(&self.pool).await;
+
+        match folder {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => Err(Error::new(format!("Error deleting folder: {}", e))),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use axum::Error;
+
+use crate::{
+    db::migrations::is_schema_current,
+    models::{metrics::PoolConnections, repository::PgRepository},
+};
+
+/// What the readiness probe and the metrics read of the database.
+#[async_trait]
+pub trait HealthRepository: Send + Sync {
+    /// Fails when the database can't be reached.
+    async fn ping(&self) -> Result<(), Error>;
+
+    /// Whether every migration has been applied.
+    async fn is_schema_current(&self) -> Result<bool, Error>;
+
+    /// `None` when there is no connection pool.
+    fn pool_connections(&self) -> Option<PoolConnections>;
+}
+
+#[async_trait]
+impl HealthRepository for PgRepository {
+    async fn ping(&self) -> Result<(), Error> {
+        let ping = sqlx::query!("SELECT 1 AS one").fetch_one(&self.pool).await;
+
+        match ping {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(e)),
+        }
+    }
+
+    async fn is_schema_current(&self) -> Result<bool, Error> {
+        is_schema_current(&self.pool).await.map_err(Error::new)
+    }
+
+    fn pool_connections(&self) -> Option<PoolConnections> {
+        Some(PoolConnections { size: self.pool.size() as i64, idle: self.pool.num_idle() as i64 })
+    }
+}
@@ -0,0 +1,1689 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use axum::Error;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::{
+    models::{
+        admin::{AdminUser, DailyUploads, SystemStats},
+        audit::{AuditEvent, AuditEventRecord, AuditQuery},
+        auth::{Code, EmailVerification, RegisterUser},
+        device::DeviceAuthorization,
+        events::{StreamPosition, StreamedChange},
+        files::{Change, ChangeKind, FileContents, FileData, Folder, NewFile},
+        mail::{Email, OutboxEmail},
+        metrics::{OutboxStats, PoolConnections},
+        oidc::OidcLogin,
+        org::{Membership, OrgInvitation, OrgMember, OrgRole, Organization, TeamDrive},
+        repository::{
+            Identity, Invitation, MagicLink, Member, MemoryChange, MemoryRepository, MemoryState, PendingOidcLogin,
+            QueuedEmail, StoredTeamDrive, Throttle,
+        },
+        user::User,
+    },
+    repositories::{
+        admin_repository::AdminRepository, audit_repository::AuditRepository, auth_repository::AuthRepository,
+        change_repository::ChangeRepository, device_repository::DeviceRepository, file_repository::FileRepository,
+        folder_repository::FolderRepository, health_repository::HealthRepository, oidc_repository::IdentityRepository,
+        org_repository::OrgRepository, outbox_repository::OutboxRepository, team_drive_repository::TeamDriveRepository,
+        throttle_repository::ThrottleRepository, user_repository::UserRepository,
+    },
+};
+
+impl MemoryState {
+    fn next_id(&mut self) -> i32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn user_mut(&mut self, user_id: i32) -> Option<&mut User> {
+        self.users.iter_mut().find(|user| user.id == user_id)
+    }
+
+    fn next_change_id(&mut self) -> i64 {
+        self.last_change_id += 1;
+        self.last_change_id
+    }
+
+    /// Records a change the way the Postgres triggers do. Changes of team
+    /// drive files belong to the drive, the others to their user.
+    fn record_file_change(&mut self, kind: ChangeKind, file: &FileData) {
+        if file.user_id.is_none() && file.team_drive_id.is_none() {
+            return;
+        }
+        let change = Change {
+            id: self.next_change_id(),
+            kind,
+            team_drive_id: file.team_drive_id,
+            file_id: Some(file.id),
+            folder_id: None,
+            name: file.file_name.clone(),
+            parent_id: file.folder_id,
+            file_size: Some(file.file_size),
+            content_hash: file.content_hash.clone(),
+            created_at: Utc::now(),
+        };
+        let user_id = file.user_id.filter(|_| file.team_drive_id.is_none());
+        self.changes.push(MemoryChange { user_id, change });
+    }
+
+    fn record_folder_change(&mut self, kind: ChangeKind, folder: &Folder) {
+        let change = Change {
+            id: self.next_change_id(),
+            kind,
+            team_drive_id: None,
+            file_id: None,
+            folder_id: Some(folder.id),
+            name: folder.name.clone(),
+            parent_id: folder.parent_id,
+            file_size: None,
+            content_hash: None,
+            created_at: Utc::now(),
+        };
+        self.changes.push(MemoryChange { user_id: Some(folder.user_id), change });
+    }
+
+    fn is_email_taken(&self, email: &str, except_user_id: Option<i32>) -> bool {
+        self.users
+            .iter()
+            .any(|user| Some(user.id) != except_user_id && same_email(&user.email, email))
+    }
+
+    fn is_drive_member(&self, user_id: i32, team_drive_id: i32) -> bool {
+        self.team_drives.iter().any(|drive| {
+            drive.id == team_drive_id
+                && self
+                    .members
+                    .iter()
+                    .any(|member| member.organization_id == drive.organization_id && member.user_id == user_id)
+        })
+    }
+
+    /// Deletes the files of the drives, then the drives, and returns the paths
+    /// of the stored files.
+    fn delete_team_drives(&mut self, drive_ids: &[i32]) -> Vec<String> {
+        let is_deleted = |file: &FileData| file.team_drive_id.is_some_and(|id| drive_ids.contains(&id));
+        let files: Vec<FileData> = self.files.iter().filter(|file| is_deleted(file)).cloned().collect();
+        self.files.retain(|file| !is_deleted(file));
+        for file in &files {
+            self.record_file_change(ChangeKind::Deleted, file);
+        }
+        self.team_drives.retain(|drive| !drive_ids.contains(&drive.id));
+        files.into_iter().map(|file| file.file_path).collect()
+    }
+
+    fn delete_organizations(&mut self, organization_ids: &[i32]) -> Vec<String> {
+        let drive_ids: Vec<i32> = self
+            .team_drives
+            .iter()
+            .filter(|drive| organization_ids.contains(&drive.organization_id))
+            .map(|drive| drive.id)
+            .collect();
+        let file_paths = self.delete_team_drives(&drive_ids);
+        self.members.retain(|member| !organization_ids.contains(&member.organization_id));
+        self.invitations
+            .retain(|invitation| !organization_ids.contains(&invitation.invitation.organization_id));
+        self.organizations
+            .retain(|organization| !organization_ids.contains(&organization.id));
+        file_paths
+    }
+
+    fn team_drive(&self, drive: &StoredTeamDrive, default_quota_bytes: i64) -> TeamDrive {
+        let files = self.files.iter().filter(|file| file.team_drive_id == Some(drive.id));
+        TeamDrive {
+            id: drive.id,
+            organization_id: drive.organization_id,
+            name: drive.name.clone(),
+            quota_bytes: drive.quota_bytes,
+            effective_quota_bytes: drive.quota_bytes.unwrap_or(default_quota_bytes),
+            used_bytes: files.clone().map(|file| file.file_size as i64).sum(),
+            file_count: files.count() as i64,
+            created_at: drive.created_at,
+        }
+    }
+
+    fn admin_user(&self, user: &User, default_quota_bytes: i64) -> AdminUser {
+        let files = self.files.iter().filter(|file| is_personal_file_of(file, user.id));
+        AdminUser {
+            id: user.id,
+            email: user.email.clone(),
+            name: user.name.clone(),
+            is_admin: user.is_admin,
+            email_verified_at: user.email_verified_at,
+            disabled_at: user.disabled_at,
+            password_reset_required: user.password_reset_required,
+            quota_bytes: user.quota_bytes,
+            effective_quota_bytes: user.quota_bytes.unwrap_or(default_quota_bytes),
+            used_bytes: files.clone().map(|file| file.file_size as i64).sum(),
+            file_count: files.count() as i64,
+        }
+    }
+
+    fn record_event(&mut self, event: &AuditEvent) {
+        let id = self.next_id() as i64;
+        self.audit_events.push(AuditEventRecord {
+            id,
+            actor_id: event.actor_id,
+            action: event.action.clone(),
+            target_user_id: event.target_user_id,
+            details: event.details.clone(),
+            ip: event.ip.clone(),
+            user_agent: event.user_agent.clone(),
+            created_at: Utc::now(),
+        });
+    }
+
+    fn throttle_mut(&mut self, action: &str, scope: &str, key: &str) -> Option<&mut Throttle> {
+        self.throttles
+            .iter_mut()
+            .find(|throttle| throttle.action == action && throttle.scope == scope && throttle.key == key)
+    }
+}
+
+impl DeviceAuthorization {
+    fn is_pending(&self, user_code: &str, now: DateTime<Utc>) -> bool {
+        self.user_code == user_code && self.status == "pending" && self.expires_at > now
+    }
+}
+
+impl QueuedEmail {
+    fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.sent_at.is_none() && self.failed_at.is_none() && self.next_attempt_at <= now
+    }
+}
+
+impl MemoryRepository {
+    fn state(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+        // A test that panicked while holding the lock leaves usable data behind.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Emails are unique regardless of case, like `users_email_lower_key`.
+fn same_email(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+/// The user as the Postgres repository returns it, without the password hash.
+fn without_password(user: &User) -> User {
+    User { password: None, ..user.clone() }
+}
+
+fn is_personal_file_of(file: &FileData, user_id: i32) -> bool {
+    file.user_id == Some(user_id) && file.team_drive_id.is_none()
+}
+
+fn not_found(action: &str) -> Error {
+    Error::new(format!("Error {}: no such user", action))
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+    async fn find_user_by_email(&self, email: String) -> Result<Option<User>, Error> {
+        Ok(self.state().users.iter().find(|user| same_email(&user.email, &email)).cloned())
+    }
+
+    async fn find_user_by_id(&self, id: i32) -> Result<Option<User>, Error> {
+        Ok(self.state().users.iter().find(|user| user.id == id).map(without_password))
+    }
+
+    async fn create_user(&self, user: RegisterUser) -> Result<User, Error> {
+        let mut state = self.state();
+        if state.is_email_taken(&user.email, None) {
+            return Err(Error::new("Error creating user: the email is taken"));
+        }
+        let user = User {
+            id: state.next_id(),
+            email: user.email,
+            password: Some(user.password),
+            name: user.name,
+            email_verified_at: None,
+            is_admin: false,
+            disabled_at: None,
+            password_reset_required: false,
+            quota_bytes: None,
+            locale: None,
+        };
+        state.users.push(user.clone());
+        Ok(without_password(&user))
+    }
+
+    async fn update_password(&self, user_id: i32, password: String) -> Result<User, Error> {
+        let mut state = self.state();
+        let user = state.user_mut(user_id).ok_or_else(|| not_found("updating password"))?;
+        user.password = Some(password);
+        user.password_reset_required = false;
+        Ok(user.clone())
+    }
+
+    async fn verify_email(&self, user_id: i32, email: String) -> Result<User, Error> {
+        let mut state = self.state();
+        if state.is_email_taken(&email, Some(user_id)) {
+            return Err(Error::new("Error verifying email: the email is taken"));
+        }
+        let user = state.user_mut(user_id).ok_or_else(|| not_found("verifying email"))?;
+        user.email = email;
+        user.email_verified_at = Some(Utc::now());
+        Ok(without_password(user))
+    }
+
+    async fn find_password_hash(&self, user_id: i32) -> Result<Option<String>, Error> {
+        Ok(self
+            .state()
+            .users
+            .iter()
+            .find(|user| user.id == user_id)
+            .and_then(|user| user.password.clone()))
+    }
+
+    async fn update_user(&self, user_id: i32, name: String, locale: Option<String>) -> Result<User, Error> {
+        let mut state = self.state();
+        let user = state.user_mut(user_id).ok_or_else(|| not_found("updating user"))?;
+        user.name = name;
+        user.locale = locale;
+        Ok(without_password(user))
+    }
+
+    async fn delete_user(&self, user_id: i32) -> Result<Vec<String>, Error> {
+        let mut state = self.state();
+        let sole_organizations: Vec<i32> = state
+            .members
+            .iter()
+            .filter(|member| member.user_id == user_id)
+            .map(|member| member.organization_id)
+            .filter(|&id| {
+                !state
+                    .members
+                    .iter()
+                    .any(|other| other.organization_id == id && other.user_id != user_id)
+            })
+            .collect();
+        let team_files = state.delete_organizations(&sole_organizations);
+        let file_paths: Vec<String> = state
+            .files
+            .iter()
+            .filter(|file| file.user_id == Some(user_id) && file.team_drive_id.is_none())
+            .map(|file| file.file_path.clone())
+            .collect();
+        state
+            .files
+            .retain(|file| file.user_id != Some(user_id) || file.team_drive_id.is_some());
+        for file in state.files.iter_mut().filter(|file| file.user_id == Some(user_id)) {
+            file.user_id = None;
+        }
+        state.folders.retain(|folder| folder.user_id != user_id);
+        state.changes.retain(|change| change.user_id != Some(user_id));
+        state.codes.retain(|code| code.user_id != user_id);
+        state.email_verifications.retain(|verification| verification.user_id != user_id);
+        state.magic_links.retain(|link| link.user_id != user_id);
+        state.identities.retain(|identity| identity.user_id != user_id);
+        state.members.retain(|member| member.user_id != user_id);
+        state.users.retain(|user| user.id != user_id);
+        Ok(file_paths.into_iter().chain(team_files).collect())
+    }
+
+    async fn create_sso_user(&self, email: String, name: String, email_verified: bool) -> Result<User, Error> {
+        let mut state = self.state();
+        if state.is_email_taken(&email, None) {
+            return Err(Error::new("Error creating user: the email is taken"));
+        }
+        let user = User {
+            id: state.next_id(),
+            email,
+            password: None,
+            name,
+            email_verified_at: email_verified.then(Utc::now),
+            is_admin: false,
+            disabled_at: None,
+            password_reset_required: false,
+            quota_bytes: None,
+            locale: None,
+        };
+        state.users.push(user.clone());
+        Ok(user)
+    }
+
+    async fn confirm_email_ownership(&self, user_id: i32) -> Result<User, Error> {
+        let mut state = self.state();
+        let user = state.user_mut(user_id).ok_or_else(|| not_found("confirming email"))?;
+        if user.email_verified_at.is_none() {
+            user.password = None;
+            user.email_verified_at = Some(Utc::now());
+        }
+        Ok(without_password(user))
+    }
+
+    async fn is_user_active(&self, user_id: i32) -> Result<bool, Error> {
+        Ok(self
+            .state()
+            .users
+            .iter()
+            .any(|user| user.id == user_id && user.disabled_at.is_none() && !user.password_reset_required))
+    }
+
+    async fn find_user_locale(&self, user_id: i32) -> Result<Option<String>, Error> {
+        Ok(self
+            .state()
+            .users
+            .iter()
+            .find(|user| user.id == user_id)
+            .and_then(|user| user.locale.clone()))
+    }
+}
+
+#[async_trait]
+impl AuthRepository for MemoryRepository {
+    async fn find_code_by_code(&self, code: String, user_id: i32) -> Result<Option<Code>, Error> {
+        let now = Utc::now();
+        Ok(self
+            .state()
+            .codes
+            .iter()
+            .find(|stored| stored.code == code && stored.user_id == user_id && stored.expires_at > now)
+            .cloned())
+    }
+
+    async fn create_code(&self, code: &str, user_id: i32, ttl_secs: i64) -> Result<bool, Error> {
+        let mut state = self.state();
+        state.codes.retain(|stored| stored.user_id != user_id);
+        let id = state.next_id();
+        let expires_at = Utc::now() + Duration::seconds(ttl_secs);
+        state.codes.push(Code { id, code: code.to_string(), user_id, expires_at });
+        Ok(true)
+    }
+
+    async fn delete_code(&self, code: String, user_id: i32) -> Result<(), Error> {
+        self.state()
+            .codes
+            .retain(|stored| stored.code != code || stored.user_id != user_id);
+        Ok(())
+    }
+
+    async fn create_email_verification(
+        &self,
+        user_id: i32,
+        email: &str,
+        token: &str,
+        ttl_secs: i64,
+    ) -> Result<EmailVerification, Error> {
+        let mut state = self.state();
+        state.email_verifications.retain(|verification| verification.user_id != user_id);
+        let verification = EmailVerification {
+            id: state.next_id(),
+            user_id,
+            email: email.to_string(),
+            token: token.to_string(),
+            expires_at: Utc::now() + Duration::seconds(ttl_secs),
+        };
+        state.email_verifications.push(verification.clone());
+        Ok(verification)
+    }
+
+    async fn find_email_verification(&self, token: &str) -> Result<Option<EmailVerification>, Error> {
+        Ok(self
+            .state()
+            .email_verifications
+            .iter()
+            .find(|verification| verification.token == token && verification.expires_at > Utc::now())
+            .cloned())
+    }
+
+    async fn delete_email_verifications(&self, user_id: i32) -> Result<(), Error> {
+        self.state()
+            .email_verifications
+            .retain(|verification| verification.user_id != user_id);
+        Ok(())
+    }
+
+    async fn create_magic_link(&self, jti: &str, user_id: i32, ttl_secs: i64) -> Result<(), Error> {
+        let mut state = self.state();
+        let now = Utc::now();
+        state.magic_links.retain(|link| link.expires_at >= now);
+        if state.magic_links.iter().any(|link| link.jti == jti) {
+            return Err(Error::new("Error creating magic link: the jti is taken"));
+        }
+        state.magic_links.push(MagicLink {
+            jti: jti.to_string(),
+            user_id,
+            expires_at: now + Duration::seconds(ttl_secs),
+            used_at: None,
+        });
+        Ok(())
+    }
+
+    async fn consume_magic_link(&self, jti: &str) -> Result<Option<i32>, Error> {
+        let now = Utc::now();
+        Ok(self
+            .state()
+            .magic_links
+            .iter_mut()
+            .find(|link| link.jti == jti && link.used_at.is_none() && link.expires_at > now)
+            .map(|link| {
+                link.used_at = Some(now);
+                link.user_id
+            }))
+    }
+}
+
+#[async_trait]
+impl FileRepository for MemoryRepository {
+    async fn used_bytes(&self, user_id: i32, team_drive_id: Option<i32>) -> Result<i64, Error> {
+        Ok(self
+            .state()
+            .files
+            .iter()
+            .filter(|file| file.team_drive_id == team_drive_id && (team_drive_id.is_some() || file.user_id == Some(user_id)))
+            .map(|file| file.file_size as i64)
+            .sum())
+    }
+
+    async fn create_file(&self, file: NewFile) -> Result<FileData, Error> {
+        let mut state = self.state();
+        let file = FileData {
+            id: state.next_id(),
+            file_name: file.file_name,
+            file_path: file.file_path,
+            file_size: file.file_size,
+            file_content_type: file.file_content_type,
+            file_type: file.file_type,
+            user_id: Some(file.user_id),
+            team_drive_id: file.team_drive_id,
+            folder_id: file.folder_id,
+            content_hash: Some(file.content_hash),
+            created_at: Utc::now(),
+        };
+        state.files.push(file.clone());
+        state.record_file_change(ChangeKind::Created, &file);
+        Ok(file)
+    }
+
+    async fn find_personal_files(&self, user_id: i32) -> Result<Vec<FileData>, Error> {
+        Ok(self
+            .state()
+            .files
+            .iter()
+            .filter(|file| is_personal_file_of(file, user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn find_files_by_ids(&self, user_id: i32, ids: &[i32]) -> Result<Vec<FileData>, Error> {
+        Ok(self
+            .state()
+            .files
+            .iter()
+            .filter(|file| ids.contains(&file.id) && is_personal_file_of(file, user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn find_personal_file(&self, user_id: i32, id: i32) -> Result<Option<FileData>, Error> {
+        Ok(self
+            .state()
+            .files
+            .iter()
+            .find(|file| file.id == id && is_personal_file_of(file, user_id))
+            .cloned())
+    }
+
+    async fn find_file_by_id(&self, id: i32) -> Result<Option<FileData>, Error> {
+        Ok(self.state().files.iter().find(|file| file.id == id).cloned())
+    }
+
+    async fn delete_file(&self, id: i32) -> Result<(), Error> {
+        let mut state = self.state();
+        if let Some(index) = state.files.iter().position(|file| file.id == id) {
+            let file = state.files.remove(index);
+            state.record_file_change(ChangeKind::Deleted, &file);
+        }
+        Ok(())
+    }
+
+    async fn replace_contents(&self, id: i32, previous_path: &str, contents: FileContents) -> Result<Option<FileData>, Error> {
+        let mut state = self.state();
+        let Some(file) = state.files.iter_mut().find(|file| file.id == id && file.file_path == previous_path) else {
+            return Ok(None);
+        };
+        file.file_path = contents.file_path;
+        file.file_size = contents.file_size;
+        file.file_content_type = contents.file_content_type;
+        file.content_hash = Some(contents.content_hash);
+        let file = file.clone();
+        state.record_file_change(ChangeKind::Modified, &file);
+        Ok(Some(file))
+    }
+
+    async fn move_file(&self, id: i32, file_name: String, folder_id: Option<i32>) -> Result<Option<FileData>, Error> {
+        let mut state = self.state();
+        let Some(file) = state.files.iter_mut().find(|file| file.id == id) else {
+            return Ok(None);
+        };
+        let moved = file.file_name != file_name || file.folder_id != folder_id;
+        file.file_name = file_name;
+        file.folder_id = folder_id;
+        let file = file.clone();
+        if moved {
+            state.record_file_change(ChangeKind::Moved, &file);
+        }
+        Ok(Some(file))
+    }
+}
+
+#[async_trait]
+impl FolderRepository for MemoryRepository {
+    async fn create_folder(&self, user_id: i32, name: String, parent_id: Option<i32>) -> Result<Folder, Error> {
+        let mut state = self.state();
+        let folder = Folder { id: state.next_id(), name, user_id, parent_id };
+        state.folders.push(folder.clone());
+        state.record_folder_change(ChangeKind::Created, &folder);
+        Ok(folder)
+    }
+
+    async fn find_folders(&self, user_id: i32) -> Result<Vec<Folder>, Error> {
+        let mut folders: Vec<Folder> = self
+            .state()
+            .folders
+            .iter()
+            .filter(|folder| folder.user_id == user_id)
+            .cloned()
+            .collect();
+        folders.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(folders)
+    }
+
+    async fn find_folder_by_id(&self, id: i32) -> Result<Option<Folder>, Error> {
+        Ok(self.state().folders.iter().find(|folder| folder.id == id).cloned())
+    }
+
+    async fn move_folder(&self, id: i32, name: String, parent_id: Option<i32>) -> Result<Option<Folder>, Error> {
+        let mut state = self.state();
+        let Some(folder) = state.folders.iter_mut().find(|folder| folder.id == id) else {
+            return Ok(None);
+        };
+        let moved = folder.name != name || folder.parent_id != parent_id;
+        folder.name = name;
+        folder.parent_id = parent_id;
+        let folder = folder.clone();
+        if moved {
+            state.record_folder_change(ChangeKind::Moved, &folder);
+        }
+        Ok(Some(folder))
+    }
+
+    async fn is_folder_empty(&self, id: i32) -> Result<bool, Error> {
+        let state = self.state();
+        Ok(!state.files.iter().any(|file| file.folder_id == Some(id))
+            && !state.folders.iter().any(|folder| folder.parent_id == Some(id)))
+    }
+
+    async fn delete_folder(&self, id: i32) -> Result<bool, Error> {
+        let mut state = self.state();
+        let Some(index) = state.folders.iter().position(|folder| folder.id == id) else {
+            return Ok(false);
+        };
+        let folder = state.folders.remove(index);
+        state.record_folder_change(ChangeKind::Deleted, &folder);
+        // Like the ON DELETE SET NULL of the foreign keys, recorded after the
+        // deletion and folders first, as the triggers do.
+        let folders: Vec<Folder> = state
+            .folders
+            .iter_mut()
+            .filter(|folder| folder.parent_id == Some(id))
+            .map(|folder| {
+                folder.parent_id = None;
+                folder.clone()
+            })
+            .collect();
+        for folder in &folders {
+            state.record_folder_change(ChangeKind::Moved, folder);
+        }
+        let files: Vec<FileData> = state
+            .files
+            .iter_mut()
+            .filter(|file| file.folder_id == Some(id))
+            .map(|file| {
+                file.folder_id = None;
+                file.clone()
+            })
+            .collect();
+        for file in &files {
+            state.record_file_change(ChangeKind::Moved, file);
+        }
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl ChangeRepository for MemoryRepository {
+    async fn find_changes(&self, user_id: i32, cursor: i64, limit: i64) -> Result<Vec<Change>, Error> {
+        Ok(self
+            .state()
+            .changes
+            .iter()
+            .filter(|change| change.user_id == Some(user_id) && change.change.id > cursor)
+            .take(limit.max(0) as usize)
+            .map(|change| change.change.clone())
+            .collect())
+    }
+
+    /// Without concurrent transactions, ids are the stream order.
+    async fn find_events(
+        &self,
+        user_id: i32,
+        after: StreamPosition,
+        _through: Option<StreamPosition>,
+        limit: i64,
+    ) -> Result<Vec<StreamedChange>, Error> {
+        let state = self.state();
+        Ok(state
+            .changes
+            .iter()
+            .filter(|change| change.change.id > after.id)
+            .filter(|change| match change.change.team_drive_id {
+                Some(team_drive_id) => state.is_drive_member(user_id, team_drive_id),
+                None => change.user_id == Some(user_id),
+            })
+            .take(limit.max(0) as usize)
+            .map(|change| StreamedChange {
+                position: StreamPosition { txid: change.change.id, id: change.change.id },
+                change: change.change.clone(),
+            })
+            .collect())
+    }
+
+    async fn event_position(&self, id: i64) -> Result<Option<StreamPosition>, Error> {
+        let state = self.state();
+        let exists = state.changes.iter().any(|change| change.change.id == id);
+        Ok(exists.then_some(StreamPosition { txid: id, id }))
+    }
+
+    async fn current_event_position(&self) -> Result<StreamPosition, Error> {
+        let last = self.state().last_change_id;
+        Ok(StreamPosition { txid: last, id: last })
+    }
+
+    async fn is_drive_member(&self, user_id: i32, team_drive_id: i32) -> Result<bool, Error> {
+        Ok(self.state().is_drive_member(user_id, team_drive_id))
+    }
+
+    async fn compact_changes(&self, retention_days: i64, limit: i64) -> Result<u64, Error> {
+        let mut state = self.state();
+        let horizon = Utc::now() - Duration::days(retention_days);
+        let changes = &state.changes;
+        let superseded = |index: usize| {
+            let change = &changes[index].change;
+            changes[index + 1..].iter().any(|later| {
+                let later = &later.change;
+                (later.file_id.is_some() && later.file_id == change.file_id)
+                    || (later.folder_id.is_some() && later.folder_id == change.folder_id)
+            })
+        };
+        let removed: Vec<i64> = (0..changes.len())
+            .filter(|&index| {
+                let change = &changes[index].change;
+                change.created_at < horizon && (change.kind == ChangeKind::Deleted || superseded(index))
+            })
+            .take(limit.max(0) as usize)
+            .map(|index| changes[index].change.id)
+            .collect();
+        if let Some(&last) = removed.last() {
+            state.compacted_through = state.compacted_through.max(last);
+        }
+        state.changes.retain(|change| !removed.contains(&change.change.id));
+        Ok(removed.len() as u64)
+    }
+
+    async fn compacted_through(&self) -> Result<i64, Error> {
+        Ok(self.state().compacted_through)
+    }
+}
+
+#[async_trait]
+impl ThrottleRepository for MemoryRepository {
+    async fn find_lock(&self, action: &str, scope: &str, key: &str) -> Result<Option<i64>, Error> {
+        let mut state = self.state();
+        let now = Utc::now();
+        Ok(state
+            .throttle_mut(action, scope, key)
+            .and_then(|throttle| throttle.locked_until)
+            .filter(|locked_until| *locked_until > now)
+            .map(|locked_until| ((locked_until - now).num_milliseconds() + 999) / 1000))
+    }
+
+    async fn register_failure(&self, action: &str, scope: &str, key: &str, window_secs: i64) -> Result<i32, Error> {
+        let mut state = self.state();
+        let now = Utc::now();
+        match state.throttle_mut(action, scope, key) {
+            Some(throttle) => {
+                throttle.failed_attempts = match throttle.last_failed_at < now - Duration::seconds(window_secs) {
+                    true => 1,
+                    false => throttle.failed_attempts + 1,
+                };
+                throttle.last_failed_at = now;
+                Ok(throttle.failed_attempts)
+            }
+            None => {
+                state.throttles.push(Throttle {
+                    action: action.to_string(),
+                    scope: scope.to_string(),
+                    key: key.to_string(),
+                    failed_attempts: 1,
+                    last_failed_at: now,
+                    locked_until: None,
+                });
+                Ok(1)
+            }
+        }
+    }
+
+    async fn lock(&self, action: &str, scope: &str, key: &str, lock_secs: i64) -> Result<(), Error> {
+        if let Some(throttle) = self.state().throttle_mut(action, scope, key) {
+            throttle.locked_until = Some(Utc::now() + Duration::seconds(lock_secs));
+        }
+        Ok(())
+    }
+
+    async fn clear_failures(&self, action: &str, scope: &str, key: &str) -> Result<(), Error> {
+        self.state()
+            .throttles
+            .retain(|throttle| throttle.action != action || throttle.scope != scope || throttle.key != key);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IdentityRepository for MemoryRepository {
+    async fn create_login(&self, login: &OidcLogin, ttl_secs: i64) -> Result<(), Error> {
+        let mut state = self.state();
+        let now = Utc::now();
+        state.oidc_logins.retain(|pending| pending.expires_at >= now);
+        if state.oidc_logins.iter().any(|pending| pending.login.state == login.state) {
+            return Err(Error::new("Error creating login: the state is taken"));
+        }
+        state.oidc_logins.push(PendingOidcLogin {
+            login: login.clone(),
+            expires_at: now + Duration::seconds(ttl_secs),
+        });
+        Ok(())
+    }
+
+    async fn take_login(&self, state: &str, provider: &str) -> Result<Option<OidcLogin>, Error> {
+        let mut memory = self.state();
+        let Some(index) = memory
+            .oidc_logins
+            .iter()
+            .position(|pending| pending.login.state == state && pending.login.provider == provider)
+        else {
+            return Ok(None);
+        };
+        let pending = memory.oidc_logins.remove(index);
+        Ok((pending.expires_at > Utc::now()).then_some(pending.login))
+    }
+
+    async fn find_identity_user_id(&self, provider: &str, subject: &str) -> Result<Option<i32>, Error> {
+        Ok(self
+            .state()
+            .identities
+            .iter()
+            .find(|identity| identity.provider == provider && identity.subject == subject)
+            .map(|identity| identity.user_id))
+    }
+
+    async fn create_identity(&self, user_id: i32, provider: &str, subject: &str) -> Result<(), Error> {
+        let mut state = self.state();
+        if state
+            .identities
+            .iter()
+            .any(|identity| identity.provider == provider && identity.subject == subject)
+        {
+            return Err(Error::new("Error creating identity: the identity is linked"));
+        }
+        state.identities.push(Identity { user_id, provider: provider.to_string(), subject: subject.to_string() });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeviceRepository for MemoryRepository {
+    async fn create_device_authorization(
+        &self,
+        device_code: &str,
+        user_code: &str,
+        client_id: &str,
+        scope: &str,
+        interval_secs: i32,
+        ttl_secs: i64,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        let now = Utc::now();
+        state.device_authorizations.retain(|authorization| authorization.expires_at >= now);
+        if state
+            .device_authorizations
+            .iter()
+            .any(|authorization| authorization.device_code == device_code || authorization.user_code == user_code)
+        {
+            return Err(Error::new("Error creating device authorization: the code is taken"));
+        }
+        state.device_authorizations.push(DeviceAuthorization {
+            device_code: device_code.to_string(),
+            user_code: user_code.to_string(),
+            client_id: client_id.to_string(),
+            scope: scope.to_string(),
+            status: "pending".to_string(),
+            user_id: None,
+            interval_secs,
+            last_polled_at: None,
+            expires_at: now + Duration::seconds(ttl_secs),
+        });
+        Ok(())
+    }
+
+    async fn find_pending_by_user_code(&self, user_code: &str) -> Result<Option<DeviceAuthorization>, Error> {
+        let now = Utc::now();
+        Ok(self
+            .state()
+            .device_authorizations
+            .iter()
+            .find(|authorization| authorization.is_pending(user_code, now))
+            .cloned())
+    }
+
+    async fn decide_device_authorization(&self, user_code: &str, user_id: i32, approve: bool) -> Result<bool, Error> {
+        let now = Utc::now();
+        let mut state = self.state();
+        let Some(authorization) = state
+            .device_authorizations
+            .iter_mut()
+            .find(|authorization| authorization.is_pending(user_code, now))
+        else {
+            return Ok(false);
+        };
+        authorization.status = if approve { "approved" } else { "denied" }.to_string();
+        authorization.user_id = Some(user_id);
+        Ok(true)
+    }
+
+    async fn find_device_authorization(&self, device_code: &str) -> Result<Option<DeviceAuthorization>, Error> {
+        Ok(self
+            .state()
+            .device_authorizations
+            .iter()
+            .find(|authorization| authorization.device_code == device_code)
+            .cloned())
+    }
+
+    async fn record_poll(&self, device_code: &str, interval_secs: i32) -> Result<(), Error> {
+        let mut state = self.state();
+        if let Some(authorization) = state
+            .device_authorizations
+            .iter_mut()
+            .find(|authorization| authorization.device_code == device_code)
+        {
+            authorization.last_polled_at = Some(Utc::now());
+            authorization.interval_secs = interval_secs;
+        }
+        Ok(())
+    }
+
+    async fn delete_device_authorization(&self, device_code: &str) -> Result<bool, Error> {
+        let mut state = self.state();
+        let before = state.device_authorizations.len();
+        state
+            .device_authorizations
+            .retain(|authorization| authorization.device_code != device_code);
+        Ok(state.device_authorizations.len() < before)
+    }
+}
+
+#[async_trait]
+impl AdminRepository for MemoryRepository {
+    async fn search_users(
+        &self,
+        search: Option<&str>,
+        limit: i64,
+        offset: i64,
+        default_quota_bytes: i64,
+    ) -> Result<Vec<AdminUser>, Error> {
+        let state = self.state();
+        let search = search.map(str::to_lowercase);
+        let matches = |text: &str| search.as_ref().is_none_or(|search| text.to_lowercase().contains(search));
+        Ok(state
+            .users
+            .iter()
+            .filter(|user| matches(&user.email) || matches(&user.name))
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .map(|user| state.admin_user(user, default_quota_bytes))
+            .collect())
+    }
+
+    async fn find_admin_user(&self, user_id: i32, default_quota_bytes: i64) -> Result<Option<AdminUser>, Error> {
+        let state = self.state();
+        Ok(state
+            .users
+            .iter()
+            .find(|user| user.id == user_id)
+            .map(|user| state.admin_user(user, default_quota_bytes)))
+    }
+
+    async fn set_user_disabled(&self, user_id: i32, disabled: bool, event: &AuditEvent) -> Result<bool, Error> {
+        let mut state = self.state();
+        let Some(user) = state.user_mut(user_id) else {
+            return Ok(false);
+        };
+        user.disabled_at = match disabled {
+            true => user.disabled_at.or(Some(Utc::now())),
+            false => None,
+        };
+        state.record_event(event);
+        Ok(true)
+    }
+
+    async fn require_password_reset(&self, user_id: i32, event: &AuditEvent) -> Result<bool, Error> {
+        let mut state = self.state();
+        let Some(user) = state.user_mut(user_id) else {
+            return Ok(false);
+        };
+        user.password_reset_required = true;
+        state.record_event(event);
+        Ok(true)
+    }
+
+    async fn set_user_quota(&self, user_id: i32, quota_bytes: Option<i64>, event: &AuditEvent) -> Result<bool, Error> {
+        let mut state = self.state();
+        let Some(user) = state.user_mut(user_id) else {
+            return Ok(false);
+        };
+        user.quota_bytes = quota_bytes;
+        state.record_event(event);
+        Ok(true)
+    }
+
+    async fn set_user_admin(&self, user_id: i32, is_admin: bool, event: &AuditEvent) -> Result<bool, Error> {
+        let mut state = self.state();
+        let Some(user) = state.user_mut(user_id) else {
+            return Ok(false);
+        };
+        user.is_admin = is_admin;
+        state.record_event(event);
+        Ok(true)
+    }
+
+    async fn find_user_files(&self, user_id: i32) -> Result<Vec<FileData>, Error> {
+        Ok(self
+            .state()
+            .files
+            .iter()
+            .filter(|file| file.user_id == Some(user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn system_stats(&self) -> Result<SystemStats, Error> {
+        let state = self.state();
+        let since = Utc::now() - Duration::days(30);
+        let mut uploads_per_day: BTreeMap<NaiveDate, DailyUploads> = BTreeMap::new();
+        for file in state.files.iter().filter(|file| file.created_at > since) {
+            let day = file.created_at.date_naive();
+            let uploads = uploads_per_day.entry(day).or_insert(DailyUploads { day, files: 0, bytes: 0 });
+            uploads.files += 1;
+            uploads.bytes += file.file_size as i64;
+        }
+        Ok(SystemStats {
+            users: state.users.len() as i64,
+            disabled_users: state.users.iter().filter(|user| user.disabled_at.is_some()).count() as i64,
+            files: state.files.len() as i64,
+            bytes: state.files.iter().map(|file| file.file_size as i64).sum(),
+            uploads_per_day: uploads_per_day.into_values().collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl AuditRepository for MemoryRepository {
+    async fn record_event(&self, event: &AuditEvent) -> Result<(), Error> {
+        self.state().record_event(event);
+        Ok(())
+    }
+
+    async fn find_audit_events(&self, query: &AuditQuery, limit: i64) -> Result<Vec<AuditEventRecord>, Error> {
+        let matches = |event: &AuditEventRecord| {
+            query.actor_id.is_none_or(|id| event.actor_id == Some(id))
+                && query.target_user_id.is_none_or(|id| event.target_user_id == Some(id))
+                && query.action.as_ref().is_none_or(|action| {
+                    event.action == *action || event.action.starts_with(&format!("{}.", action))
+                })
+                && query.ip.as_ref().is_none_or(|ip| event.ip.as_ref() == Some(ip))
+                && query.since.is_none_or(|since| event.created_at >= since)
+                && query.until.is_none_or(|until| event.created_at < until)
+                && query.before_id.is_none_or(|before_id| event.id < before_id)
+        };
+        Ok(self
+            .state()
+            .audit_events
+            .iter()
+            .rev()
+            .filter(|event| matches(event))
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_user_events(
+        &self,
+        user_id: i32,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditEventRecord>, Error> {
+        Ok(self
+            .state()
+            .audit_events
+            .iter()
+            .rev()
+            .filter(|event| event.actor_id == Some(user_id) || event.target_user_id == Some(user_id))
+            .filter(|event| before_id.is_none_or(|before_id| event.id < before_id))
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl OrgRepository for MemoryRepository {
+    async fn create_organization(&self, name: &str, owner_id: i32) -> Result<Organization, Error> {
+        let mut state = self.state();
+        let organization = Organization { id: state.next_id(), name: name.to_string(), created_at: Utc::now() };
+        state.organizations.push(organization.clone());
+        state.members.push(Member {
+            organization_id: organization.id,
+            user_id: owner_id,
+            role: OrgRole::Owner,
+            created_at: organization.created_at,
+        });
+        Ok(organization)
+    }
+
+    async fn find_organization(&self, organization_id: i32) -> Result<Option<Organization>, Error> {
+        Ok(self
+            .state()
+            .organizations
+            .iter()
+            .find(|organization| organization.id == organization_id)
+            .cloned())
+    }
+
+    async fn find_memberships(&self, user_id: i32) -> Result<Vec<Membership>, Error> {
+        let state = self.state();
+        let mut memberships: Vec<Membership> = state
+            .members
+            .iter()
+            .filter(|member| member.user_id == user_id)
+            .filter_map(|member| {
+                let organization = state
+                    .organizations
+                    .iter()
+                    .find(|organization| organization.id == member.organization_id)?;
+                Some(Membership {
+                    organization_id: organization.id,
+                    name: organization.name.clone(),
+                    role: member.role,
+                    joined_at: member.created_at,
+                })
+            })
+            .collect();
+        memberships.sort_by(|a, b| (&a.name, a.organization_id).cmp(&(&b.name, b.organization_id)));
+        Ok(memberships)
+    }
+
+    async fn find_member_role(&self, organization_id: i32, user_id: i32) -> Result<Option<OrgRole>, Error> {
+        Ok(self
+            .state()
+            .members
+            .iter()
+            .find(|member| member.organization_id == organization_id && member.user_id == user_id)
+            .map(|member| member.role))
+    }
+
+    async fn rename_organization(&self, organization_id: i32, name: &str) -> Result<Organization, Error> {
+        let mut state = self.state();
+        let organization = state
+            .organizations
+            .iter_mut()
+            .find(|organization| organization.id == organization_id)
+            .ok_or_else(|| Error::new("Error updating organization: no such organization"))?;
+        organization.name = name.to_string();
+        Ok(organization.clone())
+    }
+
+    async fn delete_organization(&self, organization_id: i32) -> Result<Vec<String>, Error> {
+        Ok(self.state().delete_organizations(&[organization_id]))
+    }
+
+    async fn find_members(&self, organization_id: i32) -> Result<Vec<OrgMember>, Error> {
+        let state = self.state();
+        let mut members: Vec<OrgMember> = state
+            .members
+            .iter()
+            .filter(|member| member.organization_id == organization_id)
+            .filter_map(|member| {
+                let user = state.users.iter().find(|user| user.id == member.user_id)?;
+                Some(OrgMember {
+                    user_id: user.id,
+                    email: user.email.clone(),
+                    name: user.name.clone(),
+                    role: member.role,
+                    joined_at: member.created_at,
+                })
+            })
+            .collect();
+        members.sort_by_key(|member| (member.joined_at, member.user_id));
+        Ok(members)
+    }
+
+    async fn count_owners(&self, organization_id: i32) -> Result<i64, Error> {
+        Ok(self
+            .state()
+            .members
+            .iter()
+            .filter(|member| member.organization_id == organization_id && member.role == OrgRole::Owner)
+            .count() as i64)
+    }
+
+    async fn set_member_role(&self, organization_id: i32, user_id: i32, role: OrgRole) -> Result<bool, Error> {
+        let mut state = self.state();
+        let member = state
+            .members
+            .iter_mut()
+            .find(|member| member.organization_id == organization_id && member.user_id == user_id);
+        Ok(member.map(|member| member.role = role).is_some())
+    }
+
+    async fn remove_member(&self, organization_id: i32, user_id: i32) -> Result<bool, Error> {
+        let mut state = self.state();
+        let before = state.members.len();
+        state
+            .members
+            .retain(|member| member.organization_id != organization_id || member.user_id != user_id);
+        Ok(state.members.len() < before)
+    }
+
+    async fn find_sole_owned_organizations(&self, user_id: i32) -> Result<Vec<Organization>, Error> {
+        let state = self.state();
+        let others = |organization_id: i32| {
+            state
+                .members
+                .iter()
+                .filter(move |member| member.organization_id == organization_id && member.user_id != user_id)
+        };
+        Ok(state
+            .organizations
+            .iter()
+            .filter(|organization| {
+                state.members.iter().any(|member| {
+                    member.organization_id == organization.id
+                        && member.user_id == user_id
+                        && member.role == OrgRole::Owner
+                })
+            })
+            .filter(|organization| !others(organization.id).any(|member| member.role == OrgRole::Owner))
+            .filter(|organization| others(organization.id).next().is_some())
+            .cloned()
+            .collect())
+    }
+
+    async fn create_invitation(
+        &self,
+        organization_id: i32,
+        email: &str,
+        role: OrgRole,
+        token: &str,
+        invited_by: i32,
+        ttl_secs: i64,
+    ) -> Result<OrgInvitation, Error> {
+        let mut state = self.state();
+        let now = Utc::now();
+        state.invitations.retain(|stored| {
+            let invitation = &stored.invitation;
+            (invitation.organization_id != organization_id || invitation.email != email) && invitation.expires_at >= now
+        });
+        let invitation = OrgInvitation {
+            id: state.next_id(),
+            organization_id,
+            email: email.to_string(),
+            role,
+            invited_by: Some(invited_by),
+            expires_at: now + Duration::seconds(ttl_secs),
+            created_at: now,
+        };
+        state.invitations.push(Invitation { invitation: invitation.clone(), token: token.to_string() });
+        Ok(invitation)
+    }
+
+    async fn find_invitations(&self, organization_id: i32) -> Result<Vec<OrgInvitation>, Error> {
+        let now = Utc::now();
+        Ok(self
+            .state()
+            .invitations
+            .iter()
+            .map(|stored| &stored.invitation)
+            .filter(|invitation| invitation.organization_id == organization_id && invitation.expires_at > now)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_invitation_by_token(&self, token: &str) -> Result<Option<OrgInvitation>, Error> {
+        let now = Utc::now();
+        Ok(self
+            .state()
+            .invitations
+            .iter()
+            .find(|stored| stored.token == token && stored.invitation.expires_at > now)
+            .map(|stored| stored.invitation.clone()))
+    }
+
+    async fn delete_invitation(&self, organization_id: i32, invitation_id: i32) -> Result<bool, Error> {
+        let mut state = self.state();
+        let before = state.invitations.len();
+        state.invitations.retain(|stored| {
+            stored.invitation.organization_id != organization_id || stored.invitation.id != invitation_id
+        });
+        Ok(state.invitations.len() < before)
+    }
+
+    async fn accept_invitation(&self, invitation: &OrgInvitation, user_id: i32) -> Result<bool, Error> {
+        let mut state = self.state();
+        let before = state.invitations.len();
+        state.invitations.retain(|stored| stored.invitation.id != invitation.id);
+        if state.invitations.len() == before {
+            return Ok(false);
+        }
+        if state
+            .members
+            .iter()
+            .any(|member| member.organization_id == invitation.organization_id && member.user_id == user_id)
+        {
+            return Ok(false);
+        }
+        state.members.push(Member {
+            organization_id: invitation.organization_id,
+            user_id,
+            role: invitation.role,
+            created_at: Utc::now(),
+        });
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl TeamDriveRepository for MemoryRepository {
+    async fn create_team_drive(
+        &self,
+        organization_id: i32,
+        name: &str,
+        default_quota_bytes: i64,
+    ) -> Result<TeamDrive, Error> {
+        let mut state = self.state();
+        let drive = StoredTeamDrive {
+            id: state.next_id(),
+            organization_id,
+            name: name.to_string(),
+            quota_bytes: None,
+            created_at: Utc::now(),
+        };
+        let team_drive = state.team_drive(&drive, default_quota_bytes);
+        state.team_drives.push(drive);
+        Ok(team_drive)
+    }
+
+    async fn find_team_drives(&self, organization_id: i32, default_quota_bytes: i64) -> Result<Vec<TeamDrive>, Error> {
+        let state = self.state();
+        let mut drives: Vec<TeamDrive> = state
+            .team_drives
+            .iter()
+            .filter(|drive| drive.organization_id == organization_id)
+            .map(|drive| state.team_drive(drive, default_quota_bytes))
+            .collect();
+        drives.sort_by(|a, b| (&a.name, a.id).cmp(&(&b.name, b.id)));
+        Ok(drives)
+    }
+
+    async fn find_team_drive(&self, drive_id: i32, default_quota_bytes: i64) -> Result<Option<TeamDrive>, Error> {
+        let state = self.state();
+        Ok(state
+            .team_drives
+            .iter()
+            .find(|drive| drive.id == drive_id)
+            .map(|drive| state.team_drive(drive, default_quota_bytes)))
+    }
+
+    async fn rename_team_drive(&self, drive_id: i32, name: &str) -> Result<bool, Error> {
+        let mut state = self.state();
+        let drive = state.team_drives.iter_mut().find(|drive| drive.id == drive_id);
+        Ok(drive.map(|drive| drive.name = name.to_string()).is_some())
+    }
+
+    async fn set_team_drive_quota(
+        &self,
+        drive_id: i32,
+        quota_bytes: Option<i64>,
+        event: &AuditEvent,
+    ) -> Result<bool, Error> {
+        let mut state = self.state();
+        let Some(drive) = state.team_drives.iter_mut().find(|drive| drive.id == drive_id) else {
+            return Ok(false);
+        };
+        drive.quota_bytes = quota_bytes;
+        state.record_event(event);
+        Ok(true)
+    }
+
+    async fn delete_team_drive(&self, drive_id: i32) -> Result<Vec<String>, Error> {
+        Ok(self.state().delete_team_drives(&[drive_id]))
+    }
+
+    async fn find_team_drive_files(&self, drive_id: i32) -> Result<Vec<FileData>, Error> {
+        Ok(self
+            .state()
+            .files
+            .iter()
+            .filter(|file| file.team_drive_id == Some(drive_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn find_team_drive_file(&self, drive_id: i32, file_id: i32) -> Result<Option<FileData>, Error> {
+        Ok(self
+            .state()
+            .files
+            .iter()
+            .find(|file| file.team_drive_id == Some(drive_id) && file.id == file_id)
+            .cloned())
+    }
+}
+
+#[async_trait]
+impl OutboxRepository for MemoryRepository {
+    async fn insert_email(&self, template: &str, email: &Email, request_id: Option<&str>) -> Result<i64, Error> {
+        let mut state = self.state();
+        let id = state.next_id() as i64;
+        state.emails.push(QueuedEmail {
+            email: OutboxEmail {
+                id,
+                template: template.to_string(),
+                request_id: request_id.map(str::to_string),
+                recipient: email.to.clone(),
+                subject: email.subject.clone(),
+                text_body: email.text_body.clone(),
+                html_body: email.html_body.clone(),
+                attempts: 0,
+            },
+            next_attempt_at: Utc::now(),
+            sent_at: None,
+            failed_at: None,
+            last_error: None,
+        });
+        Ok(id)
+    }
+
+    async fn find_recipient_locale(&self, email: &str) -> Result<Option<String>, Error> {
+        Ok(self
+            .state()
+            .users
+            .iter()
+            .find(|user| same_email(&user.email, email))
+            .and_then(|user| user.locale.clone()))
+    }
+
+    async fn claim_due_emails(&self, limit: i64, lease_secs: i64) -> Result<Vec<OutboxEmail>, Error> {
+        let mut state = self.state();
+        let now = Utc::now();
+        let mut due: Vec<&mut QueuedEmail> = state.emails.iter_mut().filter(|queued| queued.is_due(now)).collect();
+        due.sort_by_key(|queued| (queued.next_attempt_at, queued.email.id));
+        Ok(due
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .map(|queued| {
+                queued.next_attempt_at = now + Duration::seconds(lease_secs);
+                queued.email.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_email_sent(&self, id: i64) -> Result<(), Error> {
+        if let Some(queued) = self.state().emails.iter_mut().find(|queued| queued.email.id == id) {
+            queued.sent_at = Some(Utc::now());
+            queued.email.attempts += 1;
+            queued.last_error = None;
+        }
+        Ok(())
+    }
+
+    async fn mark_email_failed(&self, id: i64, error: &str, retry_in_secs: Option<i64>) -> Result<(), Error> {
+        let now = Utc::now();
+        if let Some(queued) = self.state().emails.iter_mut().find(|queued| queued.email.id == id) {
+            queued.email.attempts += 1;
+            queued.last_error = Some(error.to_string());
+            queued.next_attempt_at = now + Duration::seconds(retry_in_secs.unwrap_or(0));
+            queued.failed_at = retry_in_secs.is_none().then_some(now);
+        }
+        Ok(())
+    }
+
+    async fn outbox_stats(&self) -> Result<OutboxStats, Error> {
+        let state = self.state();
+        let now = Utc::now();
+        let pending = state.emails.iter().filter(|queued| queued.sent_at.is_none() && queued.failed_at.is_none());
+        let oldest_due = state
+            .emails
+            .iter()
+            .filter(|queued| queued.is_due(now))
+            .map(|queued| queued.next_attempt_at)
+            .min();
+        Ok(OutboxStats {
+            pending: pending.count() as i64,
+            failed: state.emails.iter().filter(|queued| queued.failed_at.is_some()).count() as i64,
+            lag_seconds: oldest_due.map_or(0.0, |oldest| (now - oldest).num_milliseconds() as f64 / 1000.0),
+        })
+    }
+}
+
+/// Nothing to reach and no migrations to apply.
+#[async_trait]
+impl HealthRepository for MemoryRepository {
+    async fn ping(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn is_schema_current(&self) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn pool_connections(&self) -> Option<PoolConnections> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn register(email: &str) -> RegisterUser {
+        RegisterUser { email: email.to_string(), password: "hash".to_string(), name: "Test".to_string() }
+    }
+
+    fn new_file(user_id: i32, team_drive_id: Option<i32>, file_size: i32) -> NewFile {
+        NewFile {
+            file_name: "a.txt".to_string(),
+            file_path: format!("uploads/{}-{}.txt", user_id, file_size),
+            file_size,
+            file_content_type: "text/plain".to_string(),
+            file_type: "txt".to_string(),
+            user_id,
+            team_drive_id,
+            folder_id: None,
+            content_hash: "hash".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn emails_are_unique_regardless_of_case() {
+        let repository = MemoryRepository::default();
+        let user = repository.create_user(register("a@x.io")).await.unwrap();
+        assert!(user.password.is_none());
+
+        assert!(repository.create_user(register("A@X.io")).await.is_err());
+        let found = repository.find_user_by_email(" A@x.IO ".to_string()).await.unwrap().unwrap();
+        assert_eq!(found.id, user.id);
+        assert_eq!(found.password.as_deref(), Some("hash"));
+    }
+
+    #[tokio::test]
+    async fn only_the_latest_code_is_valid() {
+        let repository = MemoryRepository::default();
+        repository.create_code("111111", 1, 60).await.unwrap();
+        repository.create_code("222222", 1, 60).await.unwrap();
+
+        assert!(repository.find_code_by_code("111111".to_string(), 1).await.unwrap().is_none());
+        assert!(repository.find_code_by_code("222222".to_string(), 2).await.unwrap().is_none());
+        assert!(repository.find_code_by_code("222222".to_string(), 1).await.unwrap().is_some());
+
+        repository.create_code("333333", 1, -1).await.unwrap();
+        assert!(repository.find_code_by_code("333333".to_string(), 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn magic_links_are_used_once() {
+        let repository = MemoryRepository::default();
+        repository.create_magic_link("jti", 7, 60).await.unwrap();
+        repository.create_magic_link("expired", 7, -1).await.unwrap();
+
+        assert_eq!(repository.consume_magic_link("jti").await.unwrap(), Some(7));
+        assert_eq!(repository.consume_magic_link("jti").await.unwrap(), None);
+        assert_eq!(repository.consume_magic_link("expired").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn used_bytes_are_counted_by_space() {
+        let repository = MemoryRepository::default();
+        repository.create_file(new_file(1, None, 10)).await.unwrap();
+        repository.create_file(new_file(1, Some(5), 20)).await.unwrap();
+        repository.create_file(new_file(2, Some(5), 40)).await.unwrap();
+
+        assert_eq!(repository.used_bytes(1, None).await.unwrap(), 10);
+        assert_eq!(repository.used_bytes(1, Some(5)).await.unwrap(), 60);
+        assert_eq!(repository.used_bytes(2, None).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn deleting_a_user_keeps_team_drive_files() {
+        let repository = MemoryRepository::default();
+        let user = repository.create_user(register("a@x.io")).await.unwrap();
+        let personal = repository.create_file(new_file(user.id, None, 10)).await.unwrap();
+        let shared = repository.create_file(new_file(user.id, Some(5), 20)).await.unwrap();
+        repository.create_folder(user.id, "Docs".to_string(), None).await.unwrap();
+
+        assert_eq!(repository.delete_user(user.id).await.unwrap(), vec![personal.file_path]);
+        assert!(repository.find_user_by_id(user.id).await.unwrap().is_none());
+        assert!(repository.find_folders(user.id).await.unwrap().is_empty());
+        let shared = repository.find_file_by_id(shared.id).await.unwrap().unwrap();
+        assert_eq!(shared.user_id, None);
+    }
+
+    #[tokio::test]
+    async fn changes_are_recorded_in_order_for_personal_entries() {
+        let repository = MemoryRepository::default();
+        let folder = repository.create_folder(1, "Docs".to_string(), None).await.unwrap();
+        let file = repository.create_file(new_file(1, None, 10)).await.unwrap();
+        repository.create_file(new_file(1, Some(5), 20)).await.unwrap();
+        repository.move_file(file.id, "b.txt".to_string(), Some(folder.id)).await.unwrap();
+        assert!(!repository.is_folder_empty(folder.id).await.unwrap());
+        repository.delete_file(file.id).await.unwrap();
+        assert!(repository.is_folder_empty(folder.id).await.unwrap());
+        assert!(repository.delete_folder(folder.id).await.unwrap());
+        assert!(!repository.delete_folder(folder.id).await.unwrap());
+
+        let changes = repository.find_changes(1, 0, 100).await.unwrap();
+        let kinds: Vec<ChangeKind> = changes.iter().map(|change| change.kind).collect();
+        assert_eq!(
+            kinds,
+            [ChangeKind::Created, ChangeKind::Created, ChangeKind::Moved, ChangeKind::Deleted, ChangeKind::Deleted]
+        );
+        assert_eq!(changes[2].parent_id, Some(folder.id));
+        assert_eq!(repository.find_changes(1, changes[1].id, 1).await.unwrap()[0].kind, ChangeKind::Moved);
+        assert!(repository.find_changes(2, 0, 100).await.unwrap().is_empty());
+    }
+    #[tokio::test]
+    async fn compaction_keeps_the_latest_change_of_each_entry() {
+        let repository = MemoryRepository::default();
+        let kept = repository.create_file(new_file(1, None, 10)).await.unwrap();
+        repository.move_file(kept.id, "b.txt".to_string(), None).await.unwrap();
+        let gone = repository.create_file(new_file(1, None, 20)).await.unwrap();
+        repository.delete_file(gone.id).await.unwrap();
+        for change in repository.state().changes.iter_mut() {
+            change.change.created_at -= Duration::days(40);
+        }
+        repository.create_file(new_file(1, None, 30)).await.unwrap();
+
+        assert_eq!(repository.compact_changes(30, 2).await.unwrap(), 2);
+        assert_eq!(repository.compact_changes(30, 100).await.unwrap(), 1);
+        let changes = repository.find_changes(1, 0, 100).await.unwrap();
+        let kinds: Vec<ChangeKind> = changes.iter().map(|change| change.kind).collect();
+        assert_eq!(kinds, [ChangeKind::Moved, ChangeKind::Created]);
+        assert_eq!(repository.compacted_through().await.unwrap(), 4);
+        // Ids aren't handed out again.
+        assert_eq!(repository.current_event_position().await.unwrap().id, 5);
+    }
+
+    #[tokio::test]
+    async fn team_drive_changes_reach_the_members() {
+        let repository = MemoryRepository::default();
+        let owner = repository.create_user(register("a@x.io")).await.unwrap();
+        let outsider = repository.create_user(register("b@x.io")).await.unwrap();
+        let organization = repository.create_organization("Team", owner.id).await.unwrap();
+        let drive = repository.create_team_drive(organization.id, "Shared", 100).await.unwrap();
+        repository.create_file(new_file(owner.id, Some(drive.id), 20)).await.unwrap();
+
+        assert!(repository.is_drive_member(owner.id, drive.id).await.unwrap());
+        assert!(!repository.is_drive_member(outsider.id, drive.id).await.unwrap());
+        let events = repository.find_events(owner.id, StreamPosition::default(), None, 10).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].change.team_drive_id, Some(drive.id));
+        assert!(repository.find_events(outsider.id, StreamPosition::default(), None, 10).await.unwrap().is_empty());
+        let drive = repository.find_team_drive(drive.id, 100).await.unwrap().unwrap();
+        assert_eq!((drive.used_bytes, drive.file_count), (20, 1));
+    }
+
+    #[tokio::test]
+    async fn deleting_the_last_member_deletes_the_organization() {
+        let repository = MemoryRepository::default();
+        let user = repository.create_user(register("a@x.io")).await.unwrap();
+        let organization = repository.create_organization("Team", user.id).await.unwrap();
+        let drive = repository.create_team_drive(organization.id, "Shared", 100).await.unwrap();
+        let shared = repository.create_file(new_file(user.id, Some(drive.id), 20)).await.unwrap();
+
+        assert_eq!(repository.delete_user(user.id).await.unwrap(), vec![shared.file_path]);
+        assert!(repository.find_organization(organization.id).await.unwrap().is_none());
+        assert!(repository.find_team_drive(drive.id, 100).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn claimed_emails_are_leased() {
+        let repository = MemoryRepository::default();
+        let email = Email {
+            to: "a@x.io".to_string(),
+            subject: "Hi".to_string(),
+            text_body: "Hi".to_string(),
+            html_body: "<p>Hi</p>".to_string(),
+        };
+        let id = repository.insert_email("test", &email, None).await.unwrap();
+
+        assert_eq!(repository.claim_due_emails(10, 60).await.unwrap().len(), 1);
+        assert!(repository.claim_due_emails(10, 60).await.unwrap().is_empty());
+        repository.mark_email_failed(id, "timeout", Some(0)).await.unwrap();
+        assert_eq!(repository.claim_due_emails(10, 60).await.unwrap()[0].attempts, 1);
+        repository.mark_email_failed(id, "rejected", None).await.unwrap();
+        let stats = repository.outbox_stats().await.unwrap();
+        assert_eq!((stats.pending, stats.failed), (0, 1));
+    }
+}
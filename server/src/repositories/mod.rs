@@ -1,2 +1,16 @@
 pub mod auth_repository;
 pub mod user_repository;
+pub mod throttle_repository;
+pub mod oidc_repository;
+pub mod device_repository;
+pub mod admin_repository;
+pub mod audit_repository;
+pub mod org_repository;
+pub mod team_drive_repository;
+pub mod outbox_repository;
+pub mod health_repository;
+
+pub mod change_repository;
+pub mod file_repository;
+pub mod folder_repository;
+pub mod memory_repository;
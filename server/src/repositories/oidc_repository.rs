@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use axum::Error;
+
+use crate::models::{oidc::OidcLogin, repository::PgRepository};
+
+/// Pending OpenID Connect logins and the provider identities linked to users.
+#[async_trait]
+pub trait IdentityRepository: Send + Sync {
+    async fn create_login(&self, login: &OidcLogin, ttl_secs: i64) -> Result<(), Error>;
+
+    /// Looks up a pending login and removes it, so that every state is used once.
+    async fn take_login(&self, state: &str, provider: &str) -> Result<Option<OidcLogin>, Error>;
+
+    async fn find_identity_user_id(&self, provider: &str, subject: &str) -> Result<Option<i32>, Error>;
+
+    async fn create_identity(&self, user_id: i32, provider: &str, subject: &str) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl IdentityRepository for PgRepository {
+    async fn create_login(&self, login: &OidcLogin, ttl_secs: i64) -> Result<(), Error> {
+        // Abandoned logins are cleaned up whenever a new one starts.
+        let _ = sqlx::query!("DELETE FROM oidc_logins WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await;
+
+        let login = sqlx::query!(
+            "INSERT INTO oidc_logins (state, provider, nonce, code_verifier, expires_at)
+             VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5))",
+            login.state,
+            login.provider,
+            login.nonce,
+            login.code_verifier,
+            ttl_secs as f64
+        )
+        .execute(&self.pool)
+        .await;
+
+        match login {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error creating login: {}", e))),
+        }
+    }
+
+    async fn take_login(&self, state: &str, provider: &str) -> Result<Option<OidcLogin>, Error> {
+        let login = sqlx::query!(
+            "DELETE FROM oidc_logins WHERE state = $1 AND provider = $2 RETURNING *",
+            state,
+            provider
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match login {
+            Ok(Some(login)) if login.expires_at > chrono::Utc::now() => Ok(Some(OidcLogin {
+                state: login.state,
+                provider: login.provider,
+                nonce: login.nonce,
+                code_verifier: login.code_verifier,
+            })),
+            Ok(_) => Ok(None),
+            Err(e) => Err(Error::new(format!("Error finding login: {}", e))),
+        }
+    }
+
+    async fn find_identity_user_id(&self, provider: &str, subject: &str) -> Result<Option<i32>, Error> {
+        let identity = sqlx::query!(
+            "SELECT user_id FROM user_identities WHERE provider = $1 AND subject = $2",
+            provider,
+            subject
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match identity {
+            Ok(identity) => Ok(identity.map(|identity| identity.user_id)),
+            Err(e) => Err(Error::new(format!("Error finding identity: {}", e))),
+        }
+    }
+
+    async fn create_identity(&self, user_id: i32, provider: &str, subject: &str) -> Result<(), Error> {
+        let identity = sqlx::query!(
+            "INSERT INTO user_identities (user_id, provider, subject) VALUES ($1, $2, $3)",
+            user_id,
+            provider,
+            subject
+        )
+        .execute(&self.pool)
+        .await;
+
+        match identity {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error creating identity: {}", e))),
+        }
+    }
+}
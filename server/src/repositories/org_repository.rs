@@ -0,0 +1,450 @@
+use async_trait::async_trait;
+use axum::Error;
+
+use crate::models::{
+    org::{Membership, OrgInvitation, OrgMember, OrgRole, Organization},
+    repository::PgRepository,
+};
+
+/// Organizations, their members and pending invitations.
+#[async_trait]
+pub trait OrgRepository: Send + Sync {
+    /// Creates an organization with `owner_id` as its first owner.
+    async fn create_organization(&self, name: &str, owner_id: i32) -> Result<Organization, Error>;
+
+    async fn find_organization(&self, organization_id: i32) -> Result<Option<Organization>, Error>;
+
+    /// Lists the organizations the user belongs to.
+    async fn find_memberships(&self, user_id: i32) -> Result<Vec<Membership>, Error>;
+
+    /// Returns `None` if the user is not a member.
+    async fn find_member_role(&self, organization_id: i32, user_id: i32) -> Result<Option<OrgRole>, Error>;
+
+    async fn rename_organization(&self, organization_id: i32, name: &str) -> Result<Organization, Error>;
+
+    /// Deletes the organization with its drives and returns the paths of their
+    /// stored files, which the caller still has to remove from disk.
+    async fn delete_organization(&self, organization_id: i32) -> Result<Vec<String>, Error>;
+
+    async fn find_members(&self, organization_id: i32) -> Result<Vec<OrgMember>, Error>;
+
+    async fn count_owners(&self, organization_id: i32) -> Result<i64, Error>;
+
+    /// Returns `false` if the user is not a member.
+    async fn set_member_role(&self, organization_id: i32, user_id: i32, role: OrgRole) -> Result<bool, Error>;
+
+    /// Returns `false` if the user is not a member.
+    async fn remove_member(&self, organization_id: i32, user_id: i32) -> Result<bool, Error>;
+
+    /// Organizations the user is the only owner of while other members remain.
+    /// The account can't be deleted before ownership is handed over.
+    async fn find_sole_owned_organizations(&self, user_id: i32) -> Result<Vec<Organization>, Error>;
+
+    /// Replaces any pending invitation of the same email to the organization.
+    async fn create_invitation(
+        &self,
+        organization_id: i32,
+        email: &str,
+        role: OrgRole,
+        token: &str,
+        invited_by: i32,
+        ttl_secs: i64,
+    ) -> Result<OrgInvitation, Error>;
+
+    /// Lists the unexpired invitations of the organization.
+    async fn find_invitations(&self, organization_id: i32) -> Result<Vec<OrgInvitation>, Error>;
+
+    async fn find_invitation_by_token(&self, token: &str) -> Result<Option<OrgInvitation>, Error>;
+
+    /// Returns `false` if the invitation doesn't exist.
+    async fn delete_invitation(&self, organization_id: i32, invitation_id: i32) -> Result<bool, Error>;
+
+    /// Consumes the invitation and adds the user with its role. Returns `false`
+    /// if the invitation was already used or the user is already a member.
+    async fn accept_invitation(&self, invitation: &OrgInvitation, user_id: i32) -> Result<bool, Error>;
+}
+
+#[async_trait]
+impl OrgRepository for PgRepository {
+    async fn create_organization(&self, name: &str, owner_id: i32) -> Result<Organization, Error> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::new(format!("Error creating organization: {}", e)))?;
+
+        let organization = sqlx::query_as!(
+            Organization,
+            "INSERT INTO organizations (name) VALUES ($1) RETURNING id, name, created_at",
+            name
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| Error::new(format!("Error creating organization: {}", e)))?;
+        sqlx::query!(
+            "INSERT INTO organization_members (organization_id, user_id, role) VALUES ($1, $2, $3)",
+            organization.id,
+            owner_id,
+            OrgRole::Owner.as_str()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| Error::new(format!("Error adding owner: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| Error::new(format!("Error creating organization: {}", e)))?;
+
+        Ok(organization)
+    }
+
+    async fn find_organization(&self, organization_id: i32) -> Result<Option<Organization>, Error> {
+        let organization = sqlx::query_as!(
+            Organization,
+            "SELECT id, name, created_at FROM organizations WHERE id = $1",
+            organization_id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match organization {
+            Ok(organization) => Ok(organization),
+            Err(e) => Err(Error::new(format!("Error finding organization: {}", e))),
+        }
+    }
+
+    async fn find_memberships(&self, user_id: i32) -> Result<Vec<Membership>, Error> {
+        let memberships = sqlx::query!(
+            "SELECT o.id, o.name, m.role, m.created_at
+             FROM organization_members m
+             JOIN organizations o ON o.id = m.organization_id
+             WHERE m.user_id = $1
+             ORDER BY o.name, o.id",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match memberships {
+            Ok(memberships) => Ok(memberships
+                .into_iter()
+                .map(|membership| Membership {
+                    organization_id: membership.id,
+                    name: membership.name,
+                    role: OrgRole::parse(&membership.role),
+                    joined_at: membership.created_at,
+                })
+                .collect()),
+            Err(e) => Err(Error::new(format!("Error finding organizations: {}", e))),
+        }
+    }
+
+    async fn find_member_role(&self, organization_id: i32, user_id: i32) -> Result<Option<OrgRole>, Error> {
+        let role = sqlx::query_scalar!(
+            "SELECT role FROM organization_members WHERE organization_id = $1 AND user_id = $2",
+            organization_id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match role {
+            Ok(role) => Ok(role.as_deref().map(OrgRole::parse)),
+            Err(e) => Err(Error::new(format!("Error finding member: {}", e))),
+        }
+    }
+
+    async fn rename_organization(&self, organization_id: i32, name: &str) -> Result<Organization, Error> {
+        let organization = sqlx::query_as!(
+            Organization,
+            "UPDATE organizations SET name = $1 WHERE id = $2 RETURNING id, name, created_at",
+            name,
+            organization_id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match organization {
+            Ok(organization) => Ok(organization),
+            Err(e) => Err(Error::new(format!("Error updating organization: {}", e))),
+        }
+    }
+
+    async fn delete_organization(&self, organization_id: i32) -> Result<Vec<String>, Error> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::new(format!("Error deleting organization: {}", e)))?;
+
+        let files = sqlx::query_scalar!(
+            "DELETE FROM files
+             WHERE team_drive_id IN (SELECT id FROM team_drives WHERE organization_id = $1)
+             RETURNING file_path",
+            organization_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| Error::new(format!("Error deleting files: {}", e)))?;
+        sqlx::query!("DELETE FROM organizations WHERE id = $1", organization_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::new(format!("Error deleting organization: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| Error::new(format!("Error deleting organization: {}", e)))?;
+
+        Ok(files)
+    }
+
+    async fn find_members(&self, organization_id: i32) -> Result<Vec<OrgMember>, Error> {
+        let members = sqlx::query!(
+            "SELECT u.id, u.email, u.name, m.role, m.created_at
+             FROM organization_members m
+             JOIN users u ON u.id = m.user_id
+             WHERE m.organization_id = $1
+             ORDER BY m.created_at, u.id",
+            organization_id
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match members {
+            Ok(members) => Ok(members
+                .into_iter()
+                .map(|member| OrgMember {
+                    user_id: member.id,
+                    email: member.email,
+                    name: member.name,
+                    role: OrgRole::parse(&member.role),
+                    joined_at: member.created_at,
+                })
+                .collect()),
+            Err(e) => Err(Error::new(format!("Error finding members: {}", e))),
+        }
+    }
+
+    async fn count_owners(&self, organization_id: i32) -> Result<i64, Error> {
+        let owners = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM organization_members WHERE organization_id = $1 AND role = 'owner'"#,
+            organization_id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match owners {
+            Ok(owners) => Ok(owners),
+            Err(e) => Err(Error::new(format!("Error counting owners: {}", e))),
+        }
+    }
+
+    async fn set_member_role(&self, organization_id: i32, user_id: i32, role: OrgRole) -> Result<bool, Error> {
+        let member = sqlx::query!(
+            "UPDATE organization_members SET role = $1 WHERE organization_id = $2 AND user_id = $3",
+            role.as_str(),
+            organization_id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await;
+
+        match member {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => Err(Error::new(format!("Error updating member: {}", e))),
+        }
+    }
+
+    async fn remove_member(&self, organization_id: i32, user_id: i32) -> Result<bool, Error> {
+        let member = sqlx::query!(
+            "DELETE FROM organization_members WHERE organization_id = $1 AND user_id = $2",
+            organization_id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await;
+
+        match member {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => Err(Error::new(format!("Error removing member: {}", e))),
+        }
+    }
+
+    async fn find_sole_owned_organizations(&self, user_id: i32) -> Result<Vec<Organization>, Error> {
+        let organizations = sqlx::query_as!(
+            Organization,
+            "SELECT o.id, o.name, o.created_at
+             FROM organizations o
+             JOIN organization_members m ON m.organization_id = o.id AND m.user_id = $1 AND m.role = 'owner'
+             WHERE NOT EXISTS (
+                       SELECT 1 FROM organization_members other
+                       WHERE other.organization_id = o.id AND other.user_id <> $1 AND other.role = 'owner'
+                   )
+               AND EXISTS (
+                       SELECT 1 FROM organization_members other
+                       WHERE other.organization_id = o.id AND other.user_id <> $1
+                   )
+             ORDER BY o.id",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match organizations {
+            Ok(organizations) => Ok(organizations),
+            Err(e) => Err(Error::new(format!("Error finding organizations: {}", e))),
+        }
+    }
+
+    async fn create_invitation(
+        &self,
+        organization_id: i32,
+        email: &str,
+        role: OrgRole,
+        token: &str,
+        invited_by: i32,
+        ttl_secs: i64,
+    ) -> Result<OrgInvitation, Error> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::new(format!("Error creating invitation: {}", e)))?;
+
+        sqlx::query!(
+            "DELETE FROM organization_invitations WHERE (organization_id = $1 AND email = $2) OR expires_at < NOW()",
+            organization_id,
+            email
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| Error::new(format!("Error creating invitation: {}", e)))?;
+        let invitation = sqlx::query!(
+            "INSERT INTO organization_invitations (organization_id, email, role, token, invited_by, expires_at)
+             VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(secs => $6))
+             RETURNING id, organization_id, email, role, invited_by, expires_at, created_at",
+            organization_id,
+            email,
+            role.as_str(),
+            token,
+            invited_by,
+            ttl_secs as f64
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| Error::new(format!("Error creating invitation: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| Error::new(format!("Error creating invitation: {}", e)))?;
+
+        Ok(OrgInvitation {
+            id: invitation.id,
+            organization_id: invitation.organization_id,
+            email: invitation.email,
+            role: OrgRole::parse(&invitation.role),
+            invited_by: invitation.invited_by,
+            expires_at: invitation.expires_at,
+            created_at: invitation.created_at,
+        })
+    }
+
+    async fn find_invitations(&self, organization_id: i32) -> Result<Vec<OrgInvitation>, Error> {
+        let invitations = sqlx::query!(
+            "SELECT id, organization_id, email, role, invited_by, expires_at, created_at
+             FROM organization_invitations
+             WHERE organization_id = $1 AND expires_at > NOW()
+             ORDER BY id",
+            organization_id
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match invitations {
+            Ok(invitations) => Ok(invitations
+                .into_iter()
+                .map(|invitation| OrgInvitation {
+                    id: invitation.id,
+                    organization_id: invitation.organization_id,
+                    email: invitation.email,
+                    role: OrgRole::parse(&invitation.role),
+                    invited_by: invitation.invited_by,
+                    expires_at: invitation.expires_at,
+                    created_at: invitation.created_at,
+                })
+                .collect()),
+            Err(e) => Err(Error::new(format!("Error finding invitations: {}", e))),
+        }
+    }
+
+    async fn find_invitation_by_token(&self, token: &str) -> Result<Option<OrgInvitation>, Error> {
+        let invitation = sqlx::query!(
+            "SELECT id, organization_id, email, role, invited_by, expires_at, created_at
+             FROM organization_invitations
+             WHERE token = $1 AND expires_at > NOW()",
+            token
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match invitation {
+            Ok(invitation) => Ok(invitation.map(|invitation| OrgInvitation {
+                id: invitation.id,
+                organization_id: invitation.organization_id,
+                email: invitation.email,
+                role: OrgRole::parse(&invitation.role),
+                invited_by: invitation.invited_by,
+                expires_at: invitation.expires_at,
+                created_at: invitation.created_at,
+            })),
+            Err(e) => Err(Error::new(format!("Error finding invitation: {}", e))),
+        }
+    }
+
+    async fn delete_invitation(&self, organization_id: i32, invitation_id: i32) -> Result<bool, Error> {
+        let invitation = sqlx::query!(
+            "DELETE FROM organization_invitations WHERE organization_id = $1 AND id = $2",
+            organization_id,
+            invitation_id
+        )
+        .execute(&self.pool)
+        .await;
+
+        match invitation {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => Err(Error::new(format!("Error deleting invitation: {}", e))),
+        }
+    }
+
+    async fn accept_invitation(&self, invitation: &OrgInvitation, user_id: i32) -> Result<bool, Error> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::new(format!("Error accepting invitation: {}", e)))?;
+
+        let deleted = sqlx::query!("DELETE FROM organization_invitations WHERE id = $1", invitation.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::new(format!("Error accepting invitation: {}", e)))?;
+        if deleted.rows_affected() == 0 {
+            return Ok(false);
+        }
+        let member = sqlx::query!(
+            "INSERT INTO organization_members (organization_id, user_id, role) VALUES ($1, $2, $3)
+             ON CONFLICT (organization_id, user_id) DO NOTHING",
+            invitation.organization_id,
+            user_id,
+            invitation.role.as_str()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| Error::new(format!("Error adding member: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| Error::new(format!("Error accepting invitation: {}", e)))?;
+
+        Ok(member.rows_affected() > 0)
+    }
+}
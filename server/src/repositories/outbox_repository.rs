@@ -0,0 +1,143 @@
+use async_trait::async_trait;
+use axum::Error;
+
+use crate::models::{
+    mail::{Email, OutboxEmail},
+    metrics::OutboxStats,
+    repository::PgRepository,
+};
+
+/// Emails waiting to be sent by the outbox worker.
+#[async_trait]
+pub trait OutboxRepository: Send + Sync {
+    async fn insert_email(&self, template: &str, email: &Email, request_id: Option<&str>) -> Result<i64, Error>;
+
+    /// Language the recipient chose, if the address belongs to an account.
+    async fn find_recipient_locale(&self, email: &str) -> Result<Option<String>, Error>;
+
+    /// Claims up to `limit` emails that are due. Claimed emails are not due again
+    /// for `lease_secs`, so that a crashed worker's emails are picked up later
+    /// and concurrent workers never claim the same email.
+    async fn claim_due_emails(&self, limit: i64, lease_secs: i64) -> Result<Vec<OutboxEmail>, Error>;
+
+    async fn mark_email_sent(&self, id: i64) -> Result<(), Error>;
+
+    /// Records a failed attempt. The email is retried after `retry_in_secs`, or
+    /// given up on when it is `None`.
+    async fn mark_email_failed(&self, id: i64, error: &str, retry_in_secs: Option<i64>) -> Result<(), Error>;
+
+    async fn outbox_stats(&self) -> Result<OutboxStats, Error>;
+}
+
+#[async_trait]
+impl OutboxRepository for PgRepository {
+    async fn insert_email(&self, template: &str, email: &Email, request_id: Option<&str>) -> Result<i64, Error> {
+        let email = sqlx::query_scalar!(
+            "INSERT INTO email_outbox (template, recipient, subject, text_body, html_body, request_id)
+             VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+            template,
+            email.to,
+            email.subject,
+            email.text_body,
+            email.html_body,
+            request_id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match email {
+            Ok(id) => Ok(id),
+            Err(e) => Err(Error::new(format!("Error queueing email: {}", e))),
+        }
+    }
+
+    async fn find_recipient_locale(&self, email: &str) -> Result<Option<String>, Error> {
+        let locale = sqlx::query_scalar!("SELECT locale FROM users WHERE LOWER(email) = LOWER($1)", email.trim())
+            .fetch_optional(&self.pool)
+            .await;
+
+        match locale {
+            Ok(locale) => Ok(locale.flatten()),
+            Err(e) => Err(Error::new(format!("Error finding user: {}", e))),
+        }
+    }
+
+    async fn claim_due_emails(&self, limit: i64, lease_secs: i64) -> Result<Vec<OutboxEmail>, Error> {
+        let emails = sqlx::query_as!(
+            OutboxEmail,
+            "UPDATE email_outbox SET next_attempt_at = NOW() + make_interval(secs => $2)
+             WHERE id IN (
+                 SELECT id FROM email_outbox
+                 WHERE sent_at IS NULL AND failed_at IS NULL AND next_attempt_at <= NOW()
+                 ORDER BY next_attempt_at, id
+                 LIMIT $1
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING id, template, request_id, recipient, subject, text_body, html_body, attempts",
+            limit,
+            lease_secs as f64
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match emails {
+            Ok(emails) => Ok(emails),
+            Err(e) => Err(Error::new(format!("Error claiming emails: {}", e))),
+        }
+    }
+
+    async fn mark_email_sent(&self, id: i64) -> Result<(), Error> {
+        let email = sqlx::query!(
+            "UPDATE email_outbox SET sent_at = NOW(), attempts = attempts + 1, last_error = NULL WHERE id = $1",
+            id
+        )
+        .execute(&self.pool)
+        .await;
+
+        match email {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error updating email: {}", e))),
+        }
+    }
+
+    async fn mark_email_failed(&self, id: i64, error: &str, retry_in_secs: Option<i64>) -> Result<(), Error> {
+        let email = sqlx::query!(
+            "UPDATE email_outbox
+             SET attempts = attempts + 1,
+                 last_error = $2,
+                 next_attempt_at = NOW() + make_interval(secs => COALESCE($3::FLOAT8, 0)),
+                 failed_at = CASE WHEN $3::FLOAT8 IS NULL THEN NOW() END
+             WHERE id = $1",
+            id,
+            error,
+            retry_in_secs.map(|secs| secs as f64)
+        )
+        .execute(&self.pool)
+        .await;
+
+        match email {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error updating email: {}", e))),
+        }
+    }
+
+    async fn outbox_stats(&self) -> Result<OutboxStats, Error> {
+        let stats = sqlx::query_as!(
+            OutboxStats,
+            r#"SELECT
+                 COUNT(*) FILTER (WHERE sent_at IS NULL AND failed_at IS NULL) AS "pending!",
+                 COUNT(*) FILTER (WHERE failed_at IS NOT NULL) AS "failed!",
+                 COALESCE(EXTRACT(EPOCH FROM NOW() - MIN(next_attempt_at)
+                     FILTER (WHERE sent_at IS NULL AND failed_at IS NULL AND next_attempt_at <= NOW())), 0)::FLOAT8
+                     AS "lag_seconds!"
+               FROM email_outbox"#
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match stats {
+            Ok(stats) => Ok(stats),
+            Err(e) => Err(Error::new(format!("Error reading outbox stats: {}", e))),
+        }
+    }
+}
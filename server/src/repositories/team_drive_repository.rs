@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use axum::Error;
+
+use crate::{
+    models::{audit::AuditEvent, files::FileData, org::TeamDrive, repository::PgRepository},
+    repositories::audit_repository::commit_audited,
+};
+
+/// Drives shared by the members of an organization.
+#[async_trait]
+pub trait TeamDriveRepository: Send + Sync {
+    async fn create_team_drive(
+        &self,
+        organization_id: i32,
+        name: &str,
+        default_quota_bytes: i64,
+    ) -> Result<TeamDrive, Error>;
+
+    /// Lists the drives of the organization with their storage usage.
+    async fn find_team_drives(&self, organization_id: i32, default_quota_bytes: i64) -> Result<Vec<TeamDrive>, Error>;
+
+    async fn find_team_drive(&self, drive_id: i32, default_quota_bytes: i64) -> Result<Option<TeamDrive>, Error>;
+
+    async fn rename_team_drive(&self, drive_id: i32, name: &str) -> Result<bool, Error>;
+
+    /// Recorded as `event` in the same transaction.
+    async fn set_team_drive_quota(
+        &self,
+        drive_id: i32,
+        quota_bytes: Option<i64>,
+        event: &AuditEvent,
+    ) -> Result<bool, Error>;
+
+    /// Deletes the drive and returns the paths of its stored files, which the
+    /// caller still has to remove from disk.
+    async fn delete_team_drive(&self, drive_id: i32) -> Result<Vec<String>, Error>;
+
+    async fn find_team_drive_files(&self, drive_id: i32) -> Result<Vec<FileData>, Error>;
+
+    async fn find_team_drive_file(&self, drive_id: i32, file_id: i32) -> Result<Option<FileData>, Error>;
+}
+
+#[async_trait]
+impl TeamDriveRepository for PgRepository {
+    async fn create_team_drive(
+        &self,
+        organization_id: i32,
+        name: &str,
+        default_quota_bytes: i64,
+    ) -> Result<TeamDrive, Error> {
+        let drive = sqlx::query!(
+            "INSERT INTO team_drives (organization_id, name) VALUES ($1, $2) RETURNING id, created_at",
+            organization_id,
+            name
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match drive {
+            Ok(drive) => Ok(TeamDrive {
+                id: drive.id,
+                organization_id,
+                name: name.to_string(),
+                quota_bytes: None,
+                effective_quota_bytes: default_quota_bytes,
+                used_bytes: 0,
+                file_count: 0,
+                created_at: drive.created_at,
+            }),
+            Err(e) => Err(Error::new(format!("Error creating team drive: {}", e))),
+        }
+    }
+
+    async fn find_team_drives(&self, organization_id: i32, default_quota_bytes: i64) -> Result<Vec<TeamDrive>, Error> {
+        let drives = sqlx::query!(
+            r#"SELECT d.id, d.organization_id, d.name, d.quota_bytes, d.created_at,
+                      COALESCE(SUM(f.file_size), 0)::BIGINT AS "used_bytes!",
+                      COUNT(f.id) AS "file_count!"
+               FROM team_drives d
+               LEFT JOIN files f ON f.team_drive_id = d.id
+               WHERE d.organization_id = $1
+               GROUP BY d.id
+               ORDER BY d.name, d.id"#,
+            organization_id
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match drives {
+            Ok(drives) => Ok(drives
+                .into_iter()
+                .map(|drive| TeamDrive {
+                    id: drive.id,
+                    organization_id: drive.organization_id,
+                    name: drive.name,
+                    quota_bytes: drive.quota_bytes,
+                    effective_quota_bytes: drive.quota_bytes.unwrap_or(default_quota_bytes),
+                    used_bytes: drive.used_bytes,
+                    file_count: drive.file_count,
+                    created_at: drive.created_at,
+                })
+                .collect()),
+            Err(e) => Err(Error::new(format!("Error finding team drives: {}", e))),
+        }
+    }
+
+    async fn find_team_drive(&self, drive_id: i32, default_quota_bytes: i64) -> Result<Option<TeamDrive>, Error> {
+        let drive = sqlx::query!(
+            r#"SELECT d.id, d.organization_id, d.name, d.quota_bytes, d.created_at,
+                      COALESCE(SUM(f.file_size), 0)::BIGINT AS "used_bytes!",
+                      COUNT(f.id) AS "file_count!"
+               FROM team_drives d
+               LEFT JOIN files f ON f.team_drive_id = d.id
+               WHERE d.id = $1
+               GROUP BY d.id"#,
+            drive_id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match drive {
+            Ok(drive) => Ok(drive.map(|drive| TeamDrive {
+                id: drive.id,
+                organization_id: drive.organization_id,
+                name: drive.name,
+                quota_bytes: drive.quota_bytes,
+                effective_quota_bytes: drive.quota_bytes.unwrap_or(default_quota_bytes),
+                used_bytes: drive.used_bytes,
+                file_count: drive.file_count,
+                created_at: drive.created_at,
+            })),
+            Err(e) => Err(Error::new(format!("Error finding team drive: {}", e))),
+        }
+    }
+
+    async fn rename_team_drive(&self, drive_id: i32, name: &str) -> Result<bool, Error> {
+        let drive = sqlx::query!("UPDATE team_drives SET name = $1 WHERE id = $2", name, drive_id)
+            .execute(&self.pool)
+            .await;
+
+        match drive {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => Err(Error::new(format!("Error updating team drive: {}", e))),
+        }
+    }
+
+    async fn set_team_drive_quota(
+        &self,
+        drive_id: i32,
+        quota_bytes: Option<i64>,
+        event: &AuditEvent,
+    ) -> Result<bool, Error> {
+        let error = |e: sqlx::Error| Error::new(format!("Error updating team drive: {}", e));
+        let mut tx = self.pool.begin().await.map_err(error)?;
+        let drive = sqlx::query!("UPDATE team_drives SET quota_bytes = $1 WHERE id = $2", quota_bytes, drive_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(error)?;
+
+        commit_audited(tx, drive.rows_affected(), event).await
+    }
+
+    async fn delete_team_drive(&self, drive_id: i32) -> Result<Vec<String>, Error> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::new(format!("Error deleting team drive: {}", e)))?;
+
+        let files = sqlx::query_scalar!("DELETE FROM files WHERE team_drive_id = $1 RETURNING file_path", drive_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| Error::new(format!("Error deleting files: {}", e)))?;
+        sqlx::query!("DELETE FROM team_drives WHERE id = $1", drive_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::new(format!("Error deleting team drive: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| Error::new(format!("Error deleting team drive: {}", e)))?;
+
+        Ok(files)
+    }
+
+    async fn find_team_drive_files(&self, drive_id: i32) -> Result<Vec<FileData>, Error> {
+        let files = sqlx::query_as!(
+            FileData,
+            "SELECT id, file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id, folder_id, content_hash, created_at
+             FROM files WHERE team_drive_id = $1 ORDER BY id",
+            drive_id
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        match files {
+            Ok(files) => Ok(files),
+            Err(e) => Err(Error::new(format!("Error finding files: {}", e))),
+        }
+    }
+
+    async fn find_team_drive_file(&self, drive_id: i32, file_id: i32) -> Result<Option<FileData>, Error> {
+        let file = sqlx::query_as!(
+            FileData,
+            "SELECT id, file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id, folder_id, content_hash, created_at
+             FROM files WHERE team_drive_id = $1 AND id = $2",
+            drive_id,
+            file_id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match file {
+            Ok(file) => Ok(file),
+            Err(e) => Err(Error::new(format!("Error finding file: {}", e))),
+        }
+    }
+}
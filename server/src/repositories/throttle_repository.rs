@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use axum::Error;
+
+use crate::models::repository::PgRepository;
+
+/// Failed attempts and lockouts of the throttled auth actions, counted by
+/// account and by client address.
+#[async_trait]
+pub trait ThrottleRepository: Send + Sync {
+    async fn find_lock(&self, action: &str, scope: &str, key: &str) -> Result<Option<i64>, Error>;
+
+    async fn register_failure(&self, action: &str, scope: &str, key: &str, window_secs: i64) -> Result<i32, Error>;
+
+    async fn lock(&self, action: &str, scope: &str, key: &str, lock_secs: i64) -> Result<(), Error>;
+
+    async fn clear_failures(&self, action: &str, scope: &str, key: &str) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl ThrottleRepository for PgRepository {
+    async fn find_lock(&self, action: &str, scope: &str, key: &str) -> Result<Option<i64>, Error> {
+        let lock = sqlx::query!(
+            r#"SELECT CEIL(EXTRACT(EPOCH FROM (locked_until - NOW())))::BIGINT AS "remaining!"
+               FROM auth_throttles
+               WHERE action = $1 AND scope = $2 AND key = $3 AND locked_until > NOW()"#,
+            action,
+            scope,
+            key
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match lock {
+            Ok(Some(lock)) => Ok(Some(lock.remaining.max(1))),
+            Ok(None) => Ok(None),
+            Err(e) => Err(Error::new(format!("Error finding lock: {}", e))),
+        }
+    }
+
+    async fn register_failure(&self, action: &str, scope: &str, key: &str, window_secs: i64) -> Result<i32, Error> {
+        let attempts = sqlx::query!(
+            "INSERT INTO auth_throttles (action, scope, key, failed_attempts, last_failed_at)
+             VALUES ($1, $2, $3, 1, NOW())
+             ON CONFLICT (action, scope, key) DO UPDATE SET
+                 failed_attempts = CASE
+                     WHEN auth_throttles.last_failed_at < NOW() - make_interval(secs => $4) THEN 1
+                     ELSE auth_throttles.failed_attempts + 1
+                 END,
+                 last_failed_at = NOW()
+             RETURNING failed_attempts",
+            action,
+            scope,
+            key,
+            window_secs as f64
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match attempts {
+            Ok(attempts) => Ok(attempts.failed_attempts),
+            Err(e) => Err(Error::new(format!("Error registering failure: {}", e))),
+        }
+    }
+
+    async fn lock(&self, action: &str, scope: &str, key: &str, lock_secs: i64) -> Result<(), Error> {
+        let lock = sqlx::query!(
+            "UPDATE auth_throttles SET locked_until = NOW() + make_interval(secs => $4)
+             WHERE action = $1 AND scope = $2 AND key = $3",
+            action,
+            scope,
+            key,
+            lock_secs as f64
+        )
+        .execute(&self.pool)
+        .await;
+
+        match lock {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error locking: {}", e))),
+        }
+    }
+
+    async fn clear_failures(&self, action: &str, scope: &str, key: &str) -> Result<(), Error> {
+        let clear = sqlx::query!(
+            "DELETE FROM auth_throttles WHERE action = $1 AND scope = $2 AND key = $3",
+            action,
+            scope,
+            key
+        )
+        .execute(&self.pool)
+        .await;
+
+        match clear {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::new(format!("Error clearing failures: {}", e))),
+        }
+    }
+}
@@ -1,85 +1,367 @@
+use async_trait::async_trait;
 use axum::Error;
-use sqlx::PgPool;
 
-use crate::models::{auth::RegisterUser, user::User};
+use crate::models::{auth::RegisterUser, repository::PgRepository, user::User};
 
-pub async fn find_user_by_email(pool: &PgPool, email: String) -> Result<Option<User>, Error> {
-    let user = sqlx::query!("SELECT id, email, name FROM users WHERE email = $1", email)
-        .fetch_optional(pool)
+/// Accounts. Emails are matched case-insensitively and are unique. Only
+/// `find_user_by_email` and `update_password` return the password hash.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find_user_by_email(&self, email: String) -> Result<Option<User>, Error>;
+
+    async fn find_user_by_id(&self, id: i32) -> Result<Option<User>, Error>;
+
+    async fn create_user(&self, user: RegisterUser) -> Result<User, Error>;
+
+    async fn update_password(&self, user_id: i32, password: String) -> Result<User, Error>;
+
+    async fn verify_email(&self, user_id: i32, email: String) -> Result<User, Error>;
+
+    async fn find_password_hash(&self, user_id: i32) -> Result<Option<String>, Error>;
+
+    async fn update_user(&self, user_id: i32, name: String, locale: Option<String>) -> Result<User, Error>;
+
+    /// Deletes the user together with everything they own and returns the paths
+    /// of their stored files, which the caller still has to remove from disk.
+    ///
+    /// Organizations the user was the only member of go with the account. Files
+    /// they uploaded to other team drives stay with the drive.
+    async fn delete_user(&self, user_id: i32) -> Result<Vec<String>, Error>;
+
+    /// Creates an account for a single sign-on user, who has no password.
+    async fn create_sso_user(&self, email: String, name: String, email_verified: bool) -> Result<User, Error>;
+
+    /// Marks the email of an account as verified after its owner proved control of
+    /// the address, e.g. through an identity provider or a magic link. An account
+    /// whose email was never verified may have been registered by someone else, so
+    /// its password is dropped.
+    async fn confirm_email_ownership(&self, user_id: i32) -> Result<User, Error>;
+
+    /// Whether tokens of the user are still accepted: the account exists, is not
+    /// disabled and has no pending forced password reset.
+    async fn is_user_active(&self, user_id: i32) -> Result<bool, Error>;
+
+    async fn find_user_locale(&self, user_id: i32) -> Result<Option<String>, Error>;
+}
+
+#[async_trait]
+impl UserRepository for PgRepository {
+    async fn find_user_by_email(&self, email: String) -> Result<Option<User>, Error> {
+        let user = sqlx::query!(
+            "SELECT id, email, password, name, email_verified_at, is_admin, disabled_at, password_reset_required, quota_bytes, locale
+             FROM users WHERE LOWER(email) = LOWER($1)",
+            email.trim()
+        )
+        .fetch_optional(&self.pool)
         .await;
 
-    match user {
-        Ok(Some(user)) => Ok(Some(User {
-            id: user.id,
-            email: user.email,
-            password: None,
-            name: user.name,
-        })),
-        Ok(None) => Ok(None),
-        Err(e) => Err(Error::new(format!("Error finding user: {}", e))),
+        match user {
+            Ok(Some(user)) => Ok(Some(User {
+                id: user.id,
+                email: user.email,
+                password: user.password,
+                name: user.name,
+                email_verified_at: user.email_verified_at,
+                is_admin: user.is_admin,
+                disabled_at: user.disabled_at,
+                password_reset_required: user.password_reset_required,
+                quota_bytes: user.quota_bytes,
+                locale: user.locale,
+            })),
+            Ok(None) => Ok(None),
+            Err(e) => Err(Error::new(format!("Error finding user: {}", e))),
+        }
+    }
+
+    async fn find_user_by_id(&self, id: i32) -> Result<Option<User>, Error> {
+        let user = sqlx::query!("SELECT * FROM users WHERE id = $1", id)
+            .fetch_optional(&self.pool)
+            .await;
+
+        match user {
+            Ok(Some(user)) => Ok(Some(User {
+                id: user.id,
+                email: user.email,
+                password: None,
+                name: user.name,
+                email_verified_at: user.email_verified_at,
+                is_admin: user.is_admin,
+                disabled_at: user.disabled_at,
+                password_reset_required: user.password_reset_required,
+                quota_bytes: user.quota_bytes,
+                locale: user.locale,
+            })),
+            Ok(None) => Ok(None),
+            Err(e) => Err(Error::new(format!("Error finding user: {}", e))),
+        }
     }
-}
 
-pub async fn find_user_by_id(pool: &PgPool, id: i32) -> Result<Option<User>, Error> {
-    let user = sqlx::query!("SELECT * FROM users WHERE id = $1", id)
-        .fetch_optional(pool)
+    async fn create_user(&self, user: RegisterUser) -> Result<User, Error> {
+        let user = sqlx::query!(
+            "INSERT INTO users (email, password, name) VALUES ($1, $2, $3) RETURNING *",
+            user.email,
+            user.password,
+            user.name
+        )
+        .fetch_one(&self.pool)
         .await;
 
-    match user {
-        Ok(Some(user)) => Ok(Some(User {
-            id: user.id,
-            email: user.email,
-            password: None,
-            name: user.name,
-        })),
-        Ok(None) => Ok(None),
-        Err(e) => Err(Error::new(format!("Error finding user: {}", e))),
+        match user {
+            Ok(user) => Ok(User {
+                id: user.id,
+                email: user.email,
+                password: None,
+                name: user.name,
+                email_verified_at: user.email_verified_at,
+                is_admin: user.is_admin,
+                disabled_at: user.disabled_at,
+                password_reset_required: user.password_reset_required,
+                quota_bytes: user.quota_bytes,
+                locale: user.locale,
+            }),
+            Err(e) => Err(Error::new(format!("Error creating user: {}", e))),
+        }
     }
-}
 
-pub async fn create_user(pool: &PgPool, user: RegisterUser) -> Result<User, Error> {
-    let user = sqlx::query!(
-        "INSERT INTO users (email, password, name) VALUES ($1, $2, $3) RETURNING *",
-        user.email,
-        user.password,
-        user.name
-    )
-    .fetch_one(pool)
-    .await;
-
-    match user {
-        Ok(user) => Ok(User {
-            id: user.id,
-            email: user.email,
-            password: None,
-            name: user.name,
-        }),
-        Err(e) => Err(Error::new(format!("Error creating user: {}", e))),
+    async fn update_password(&self, user_id: i32, password: String) -> Result<User, Error> {
+        let user = self.find_user_by_id(user_id).await;
+        let _ = match user {
+            Ok(user) => user,
+            Err(e) => return Err(Error::new(format!("Error updating password: {}", e))),
+        };
+
+        let user = sqlx::query!(
+            "UPDATE users SET password = $1, password_reset_required = FALSE WHERE id = $2 RETURNING *",
+            password,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match user {
+            Ok(user) => Ok(User {
+                id: user.id,
+                email: user.email,
+                password: user.password,
+                name: user.name,
+                email_verified_at: user.email_verified_at,
+                is_admin: user.is_admin,
+                disabled_at: user.disabled_at,
+                password_reset_required: user.password_reset_required,
+                quota_bytes: user.quota_bytes,
+                locale: user.locale,
+            }),
+            Err(e) => Err(Error::new(format!("Error updating password: {}", e))),
+        }
     }
-}
 
-pub async fn update_password(pool: &PgPool, user_id: i32, password: String) -> Result<User, Error> {
-    let user = find_user_by_id(pool, user_id).await;
-    let _ = match user {
-        Ok(user) => user,
-        Err(e) => return Err(Error::new(format!("Error updating password: {}", e))),
-    };
-
-    let user = sqlx::query!(
-        "UPDATE users SET password = $1 WHERE id = $2 RETURNING *",
-        password,
-        user_id
-    )
-    .fetch_one(pool)
-    .await;
-
-    match user {
-        Ok(user) => Ok(User {
-            id: user.id,
-            email: user.email,
-            password: Some(user.password),
-            name: user.name,
-        }),
-        Err(e) => Err(Error::new(format!("Error updating password: {}", e))),
+    async fn verify_email(&self, user_id: i32, email: String) -> Result<User, Error> {
+        let user = sqlx::query!(
+            "UPDATE users SET email = $1, email_verified_at = NOW() WHERE id = $2 RETURNING *",
+            email,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match user {
+            Ok(user) => Ok(User {
+                id: user.id,
+                email: user.email,
+                password: None,
+                name: user.name,
+                email_verified_at: user.email_verified_at,
+                is_admin: user.is_admin,
+                disabled_at: user.disabled_at,
+                password_reset_required: user.password_reset_required,
+                quota_bytes: user.quota_bytes,
+                locale: user.locale,
+            }),
+            Err(e) => Err(Error::new(format!("Error verifying email: {}", e))),
+        }
+    }
+
+    async fn find_password_hash(&self, user_id: i32) -> Result<Option<String>, Error> {
+        let user = sqlx::query!("SELECT password FROM users WHERE id = $1", user_id)
+            .fetch_optional(&self.pool)
+            .await;
+
+        match user {
+            Ok(user) => Ok(user.and_then(|user| user.password)),
+            Err(e) => Err(Error::new(format!("Error finding user: {}", e))),
+        }
+    }
+
+    async fn update_user(&self, user_id: i32, name: String, locale: Option<String>) -> Result<User, Error> {
+        let user = sqlx::query!(
+            "UPDATE users SET name = $1, locale = $2 WHERE id = $3 RETURNING *",
+            name,
+            locale,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match user {
+            Ok(user) => Ok(User {
+                id: user.id,
+                email: user.email,
+                password: None,
+                name: user.name,
+                email_verified_at: user.email_verified_at,
+                is_admin: user.is_admin,
+                disabled_at: user.disabled_at,
+                password_reset_required: user.password_reset_required,
+                quota_bytes: user.quota_bytes,
+                locale: user.locale,
+            }),
+            Err(e) => Err(Error::new(format!("Error updating user: {}", e))),
+        }
+    }
+
+    async fn delete_user(&self, user_id: i32) -> Result<Vec<String>, Error> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::new(format!("Error deleting user: {}", e)))?;
+
+        let sole_organizations = sqlx::query_scalar!(
+            "SELECT organization_id FROM organization_members m
+             WHERE user_id = $1
+               AND NOT EXISTS (
+                       SELECT 1 FROM organization_members other
+                       WHERE other.organization_id = m.organization_id AND other.user_id <> $1
+                   )",
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| Error::new(format!("Error finding organizations: {}", e)))?;
+        let team_files = sqlx::query_scalar!(
+            "DELETE FROM files
+             WHERE team_drive_id IN (SELECT id FROM team_drives WHERE organization_id = ANY($1))
+             RETURNING file_path",
+            &sole_organizations
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| Error::new(format!("Error deleting files: {}", e)))?;
+        sqlx::query!("DELETE FROM organizations WHERE id = ANY($1)", &sole_organizations)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::new(format!("Error deleting organizations: {}", e)))?;
+        sqlx::query!(
+            "UPDATE files SET user_id = NULL WHERE user_id = $1 AND team_drive_id IS NOT NULL",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| Error::new(format!("Error updating files: {}", e)))?;
+
+        let files = sqlx::query_scalar!("DELETE FROM files WHERE user_id = $1 RETURNING file_path", user_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| Error::new(format!("Error deleting files: {}", e)))?;
+        sqlx::query!("DELETE FROM folders WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::new(format!("Error deleting folders: {}", e)))?;
+        sqlx::query!("DELETE FROM codes WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::new(format!("Error deleting codes: {}", e)))?;
+        sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::new(format!("Error deleting user: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| Error::new(format!("Error deleting user: {}", e)))?;
+
+        Ok(files.into_iter().chain(team_files).collect())
+    }
+
+    async fn create_sso_user(&self, email: String, name: String, email_verified: bool) -> Result<User, Error> {
+        let user = sqlx::query!(
+            "INSERT INTO users (email, password, name, email_verified_at)
+             VALUES ($1, NULL, $2, CASE WHEN $3 THEN NOW() END) RETURNING *",
+            email,
+            name,
+            email_verified
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match user {
+            Ok(user) => Ok(User {
+                id: user.id,
+                email: user.email,
+                password: None,
+                name: user.name,
+                email_verified_at: user.email_verified_at,
+                is_admin: user.is_admin,
+                disabled_at: user.disabled_at,
+                password_reset_required: user.password_reset_required,
+                quota_bytes: user.quota_bytes,
+                locale: user.locale,
+            }),
+            Err(e) => Err(Error::new(format!("Error creating user: {}", e))),
+        }
+    }
+
+    async fn confirm_email_ownership(&self, user_id: i32) -> Result<User, Error> {
+        let user = sqlx::query!(
+            "UPDATE users SET
+                 password = CASE WHEN email_verified_at IS NULL THEN NULL ELSE password END,
+                 email_verified_at = COALESCE(email_verified_at, NOW())
+             WHERE id = $1 RETURNING *",
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match user {
+            Ok(user) => Ok(User {
+                id: user.id,
+                email: user.email,
+                password: None,
+                name: user.name,
+                email_verified_at: user.email_verified_at,
+                is_admin: user.is_admin,
+                disabled_at: user.disabled_at,
+                password_reset_required: user.password_reset_required,
+                quota_bytes: user.quota_bytes,
+                locale: user.locale,
+            }),
+            Err(e) => Err(Error::new(format!("Error confirming email: {}", e))),
+        }
+    }
+
+    async fn is_user_active(&self, user_id: i32) -> Result<bool, Error> {
+        let user = sqlx::query!(
+            "SELECT disabled_at, password_reset_required FROM users WHERE id = $1",
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match user {
+            Ok(Some(user)) => Ok(user.disabled_at.is_none() && !user.password_reset_required),
+            Ok(None) => Ok(false),
+            Err(e) => Err(Error::new(format!("Error finding user: {}", e))),
+        }
+    }
+
+    async fn find_user_locale(&self, user_id: i32) -> Result<Option<String>, Error> {
+        let locale = sqlx::query_scalar!("SELECT locale FROM users WHERE id = $1", user_id)
+            .fetch_optional(&self.pool)
+            .await;
+
+        match locale {
+            Ok(locale) => Ok(locale.flatten()),
+            Err(e) => Err(Error::new(format!("Error finding user: {}", e))),
+        }
     }
 }
@@ -0,0 +1,25 @@
+use axum::{routing::{get, post, put}, Router};
+use crate::{
+    models::app::AppState,
+    services::admin_service::{
+        disable_user, enable_user, export_audit_events, list_audit_events, force_password_reset, get_stats, get_user, get_user_files, list_users, set_admin,
+        set_quota, set_drive_quota,
+    },
+};
+
+pub fn admin_router(state: &AppState) -> Router {
+    Router::new()
+        .route("/users", get(list_users))
+        .route("/users/{id}", get(get_user))
+        .route("/users/{id}/disable", post(disable_user))
+        .route("/users/{id}/enable", post(enable_user))
+        .route("/users/{id}/force-password-reset", post(force_password_reset))
+        .route("/users/{id}/quota", put(set_quota))
+        .route("/users/{id}/admin", put(set_admin))
+        .route("/users/{id}/files", get(get_user_files))
+        .route("/team-drives/{id}/quota", put(set_drive_quota))
+        .route("/stats", get(get_stats))
+        .route("/audit", get(list_audit_events))
+        .route("/audit/export", get(export_audit_events))
+        .with_state(state.clone())
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use axum::{extract::DefaultBodyLimit, http::HeaderValue, middleware, Router};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{
+    config::{i18n::localize, logging::trace_request, metrics::track_metrics, openapi::ApiDoc},
+    models::{app::AppState, settings::Config},
+    routes::{
+        admin_router::admin_router, auth_router::auth_router, files_router::files_router,
+        folders_router::folders_router, health_router::health_router, metrics_router::metrics_router,
+        org_router::org_router, user_router::user_router, well_known_router::well_known_router,
+    },
+};
+
+fn cors_layer(config: &Config) -> Option<CorsLayer> {
+    let origins = &config.cors.allowed_origins;
+    if origins.is_empty() {
+        return None;
+    }
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(origins.iter().filter_map(|origin| HeaderValue::from_str(origin).ok()))
+    };
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .max_age(Duration::from_secs(config.cors.max_age_secs)),
+    )
+}
+
+/// Every route of the API with its middleware. `/metrics` is included unless
+/// it is served on its own address.
+pub fn app_router(state: &AppState) -> Router {
+    let config = &state.config;
+    let mut app = Router::new()
+        .nest("/auth", auth_router(state))
+        .nest("/files", files_router(state))
+        .nest("/folders", folders_router(state))
+        .nest("/user", user_router(state))
+        .nest("/orgs", org_router(state))
+        .nest("/admin", admin_router(state))
+        .nest("/.well-known", well_known_router(state))
+        .merge(health_router(state))
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()));
+    if config.metrics.enabled && config.metrics.bind.is_none() {
+        app = app.merge(metrics_router(state));
+    }
+    app = app
+        .layer(middleware::from_fn_with_state(state.clone(), localize))
+        .layer(middleware::from_fn(track_metrics))
+        .layer(middleware::from_fn(trace_request))
+        .layer(DefaultBodyLimit::max(config.limits.max_upload_bytes));
+    if let Some(cors) = cors_layer(config) {
+        app = app.layer(cors);
+    }
+    app
+}
\ No newline at end of file
@@ -1,12 +1,30 @@
-use crate::{models::app::AppState, services::auth_service::{forgot_password, login, register, reset_password}};
-use axum::{routing::post, Router};
-use sqlx::PgPool;
+use crate::{
+    models::app::AppState,
+    services::auth_service::{
+        confirm_email, forgot_password, login, magic_link_login, refresh_session, register, request_magic_link,
+        resend_verification, reset_password,
+    },
+    services::device_service::{device_approve, device_code, device_lookup, device_token},
+    services::oidc_service::{oidc_callback, oidc_login, oidc_providers},
+};
+use axum::{routing::{get, post}, Router};
 
-pub fn auth_router(pool: &PgPool) -> Router {
+pub fn auth_router(state: &AppState) -> Router {
     Router::new()
         .route("/login", post(login))
+        .route("/refresh", post(refresh_session))
         .route("/register", post(register))
         .route("/forgot-password", post(forgot_password))
         .route("/reset-password", post(reset_password))
-        .with_state(AppState { pool: pool.clone() })
+        .route("/verify-email", get(confirm_email))
+        .route("/resend-verification", post(resend_verification))
+        .route("/magic-link", post(request_magic_link))
+        .route("/magic-link/login", get(magic_link_login))
+        .route("/device/code", post(device_code))
+        .route("/device/token", post(device_token))
+        .route("/device", get(device_lookup).post(device_approve))
+        .route("/oidc/providers", get(oidc_providers))
+        .route("/oidc/{provider}/login", get(oidc_login))
+        .route("/oidc/{provider}/callback", get(oidc_callback))
+        .with_state(state.clone())
 }
\ No newline at end of file
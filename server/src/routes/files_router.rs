@@ -1,11 +1,16 @@
-use axum::{routing::post, Router};
-use sqlx::PgPool;
-use crate::{models::app::AppState, services::files_service::{upload_file, get_files, delete_file}};
+use axum::{routing::{get, post}, Router};
+use crate::{models::app::AppState, services::files_service::{upload_file, replace_file, move_file, get_files, delete_file, download_file, get_quota, get_changes, get_events}};
 
-pub fn files_router(pool: &PgPool) -> Router {
+pub fn files_router(state: &AppState) -> Router {
     Router::new()
         .route("/upload", post(upload_file))
+        .route("/replace", post(replace_file))
+        .route("/move", post(move_file))
         .route("/get", post(get_files))
-        .route("/delete", post(delete_file))
-        .with_state(AppState { pool: pool.clone() })
+        .route("/download", get(download_file))
+        .route("/delete", post(delete_file).delete(delete_file))
+        .route("/quota", get(get_quota))
+        .route("/changes", get(get_changes))
+        .route("/events", get(get_events))
+        .with_state(state.clone())
 }
\ No newline at end of file
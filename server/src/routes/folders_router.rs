@@ -0,0 +1,12 @@
+use axum::{routing::{get, patch}, Router};
+use crate::{
+    models::app::AppState,
+    services::folders_service::{create_folder, delete_folder, list_folders, update_folder},
+};
+
+pub fn folders_router(state: &AppState) -> Router {
+    Router::new()
+        .route("/", get(list_folders).post(create_folder))
+        .route("/{id}", patch(update_folder).delete(delete_folder))
+        .with_state(state.clone())
+}
\ No newline at end of file
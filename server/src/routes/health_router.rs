@@ -0,0 +1,9 @@
+use axum::{routing::get, Router};
+use crate::{models::app::AppState, services::health_service::{healthz, readyz}};
+
+pub fn health_router(state: &AppState) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(state.clone())
+}
\ No newline at end of file
@@ -0,0 +1,8 @@
+use axum::{routing::get, Router};
+use crate::{config::metrics::metrics_handler, models::app::AppState};
+
+pub fn metrics_router(state: &AppState) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(state.clone())
+}
\ No newline at end of file
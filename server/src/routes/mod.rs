@@ -1,3 +1,11 @@
 pub mod auth_router;
 pub mod files_router;
 pub mod user_router;
+pub mod well_known_router;
+
+pub mod admin_router;
+pub mod org_router;
+pub mod metrics_router;
+pub mod health_router;
+pub mod app_router;
+pub mod folders_router;
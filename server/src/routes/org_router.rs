@@ -0,0 +1,30 @@
+use axum::{routing::{delete, get, patch, post, put}, Router};
+use crate::{
+    models::app::AppState,
+    services::{
+        org_service::{
+            accept_org_invitation, create_org, delete_org, get_org, invite_member, list_invitations, list_orgs,
+            lookup_invitation, remove_org_member, revoke_invitation, set_role, update_org,
+        },
+        team_drive_service::{
+            create_drive, delete_drive, delete_drive_file, download_drive_file, list_drive_files, list_drives,
+            update_drive, upload_drive_file,
+        },
+    },
+};
+
+pub fn org_router(state: &AppState) -> Router {
+    Router::new()
+        .route("/", post(create_org).get(list_orgs))
+        .route("/invitations", get(lookup_invitation))
+        .route("/invitations/accept", post(accept_org_invitation))
+        .route("/{id}", get(get_org).patch(update_org).delete(delete_org))
+        .route("/{id}/invitations", post(invite_member).get(list_invitations))
+        .route("/{id}/invitations/{invitation_id}", delete(revoke_invitation))
+        .route("/{id}/members/{user_id}", put(set_role).delete(remove_org_member))
+        .route("/{id}/drives", post(create_drive).get(list_drives))
+        .route("/{id}/drives/{drive_id}", patch(update_drive).delete(delete_drive))
+        .route("/{id}/drives/{drive_id}/files", post(upload_drive_file).get(list_drive_files))
+        .route("/{id}/drives/{drive_id}/files/{file_id}", get(download_drive_file).delete(delete_drive_file))
+        .with_state(state.clone())
+}
@@ -1,9 +1,15 @@
-use axum::{routing::get, Router};
-use sqlx::PgPool;
-use crate::{models::app::AppState, services::user_service::get_user};
+use axum::{routing::{get, patch, post}, Router};
+use crate::{
+    models::app::AppState,
+    services::user_service::{change_email, change_password, delete_me, get_activity, get_user, update_me},
+};
 
-pub fn user_router(pool: &PgPool) -> Router {
+pub fn user_router(state: &AppState) -> Router {
     Router::new()
         .route("/{id}", get(get_user))
-        .with_state(AppState { pool: pool.clone() })
+        .route("/me", patch(update_me).delete(delete_me))
+        .route("/me/password", post(change_password))
+        .route("/me/email", post(change_email))
+        .route("/me/activity", get(get_activity))
+        .with_state(state.clone())
 }
\ No newline at end of file
@@ -0,0 +1,8 @@
+use axum::{routing::get, Router};
+use crate::{models::app::AppState, services::auth_service::jwks};
+
+pub fn well_known_router(state: &AppState) -> Router {
+    Router::new()
+        .route("/jwks.json", get(jwks))
+        .with_state(state.clone())
+}
\ No newline at end of file
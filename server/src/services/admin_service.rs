@@ -0,0 +1,581 @@
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response as HttpResponse},
+    Json,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+
+use crate::{
+    config::outbox::enqueue_email,
+    models::{
+        admin::{AdminUser, SetAdmin, SetQuota, SystemStats, UserPage, UserSearch},
+        api::{ApiError, ApiResponse, Response},
+        app::AppState,
+        audit::{AuditEvent, AuditEventPage, AuditEventRecord, AuditQuery},
+        auth::Auth,
+        files::FileList,
+        i18n::Message,
+        mail::EmailTemplate,
+        org::TeamDrive,
+        user::User,
+    },
+    services::user_service::current_user,
+};
+
+const MAX_PAGE_SIZE: i64 = 200;
+const MAX_EXPORT_ROWS: i64 = 100_000;
+
+/// Resolves the admin behind the bearer token.
+async fn current_admin(app_state: &AppState, headers: &HeaderMap) -> Result<User, Response> {
+    let user = current_user(app_state, headers).await?;
+    if !user.is_admin {
+        return Err(Response {
+            code: 403,
+            message: Some("forbidden".into()),
+            data: None,
+        });
+    }
+    Ok(user)
+}
+
+fn server_error(e: axum::Error) -> Response {
+    Response {
+        code: 500,
+        message: Some(Message::server_error(e)),
+        data: None,
+    }
+}
+
+fn user_not_found() -> Response {
+    Response {
+        code: 404,
+        message: Some("user_not_found".into()),
+        data: None,
+    }
+}
+
+/// Answers an admin change, recorded with it, with the updated user.
+async fn changed_user(app_state: &AppState, user_id: i32, message: &'static str) -> Response {
+    match app_state.admin.find_admin_user(user_id, app_state.config.storage.quota_bytes).await {
+        Ok(Some(user)) => Response {
+            code: 200,
+            message: Some(message.into()),
+            data: Some(json!(user)),
+        },
+        Ok(None) => user_not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users",
+    params(UserSearch),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Список пользователей с занятым местом", body = ApiResponse<UserPage>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn list_users(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<UserSearch>,
+) -> Response {
+    if let Err(response) = current_admin(&app_state, &headers).await {
+        return response;
+    }
+
+    let search = query.search.as_deref().map(str::trim).filter(|search| !search.is_empty());
+    let limit = query.limit.unwrap_or(50).clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+    match app_state.admin.search_users(search, limit, offset, app_state.config.storage.quota_bytes).await {
+        Ok(users) => Response {
+            code: 200,
+            message: Some("users_fetched".into()),
+            data: Some(json!(UserPage { users, limit, offset })),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}",
+    params(("id" = i32, Path, description = "ID пользователя")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Пользователь с занятым местом", body = ApiResponse<AdminUser>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 404, description = "Пользователь не найден", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn get_user(State(app_state): State<AppState>, headers: HeaderMap, Path(id): Path<i32>) -> Response {
+    if let Err(response) = current_admin(&app_state, &headers).await {
+        return response;
+    }
+
+    match app_state.admin.find_admin_user(id, app_state.config.storage.quota_bytes).await {
+        Ok(Some(user)) => Response {
+            code: 200,
+            message: Some("user_fetched".into()),
+            data: Some(json!(user)),
+        },
+        Ok(None) => user_not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/disable",
+    params(("id" = i32, Path, description = "ID пользователя")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Аккаунт заблокирован, его токены больше не принимаются", body = ApiResponse<AdminUser>),
+        (status = 400, description = "Нельзя заблокировать себя", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 404, description = "Пользователь не найден", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn disable_user(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+) -> Response {
+    let admin = match current_admin(&app_state, &headers).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+    if admin.id == id {
+        return Response {
+            code: 400,
+            message: Some("cannot_disable_self".into()),
+            data: None,
+        };
+    }
+
+    let event = AuditEvent::new("admin.user_disabled", Some(admin.id), &addr, &headers).target(id);
+    match app_state.admin.set_user_disabled(id, true, &event).await {
+        Ok(true) => changed_user(&app_state, id, "user_disabled").await,
+        Ok(false) => user_not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/enable",
+    params(("id" = i32, Path, description = "ID пользователя")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Аккаунт разблокирован", body = ApiResponse<AdminUser>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 404, description = "Пользователь не найден", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn enable_user(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+) -> Response {
+    let admin = match current_admin(&app_state, &headers).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    let event = AuditEvent::new("admin.user_enabled", Some(admin.id), &addr, &headers).target(id);
+    match app_state.admin.set_user_disabled(id, false, &event).await {
+        Ok(true) => changed_user(&app_state, id, "user_enabled").await,
+        Ok(false) => user_not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/force-password-reset",
+    params(("id" = i32, Path, description = "ID пользователя")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Аккаунт заблокирован до сброса пароля, код отправлен на email", body = ApiResponse<AdminUser>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 404, description = "Пользователь не найден", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn force_password_reset(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+) -> Response {
+    let admin = match current_admin(&app_state, &headers).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+    let user = match app_state.users.find_user_by_id(id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return user_not_found(),
+        Err(e) => return server_error(e),
+    };
+
+    let event = AuditEvent::new("admin.password_reset_forced", Some(admin.id), &addr, &headers).target(user.id);
+    match app_state.admin.require_password_reset(user.id, &event).await {
+        Ok(true) => {}
+        Ok(false) => return user_not_found(),
+        Err(e) => return server_error(e),
+    }
+    let code = Auth::generate_code();
+    if let Err(e) = app_state.codes.create_code(&code, user.id, app_state.config.tokens.reset_code_secs).await {
+        return server_error(e);
+    }
+    let template = EmailTemplate::PasswordResetRequired { code };
+    if let Err(e) = enqueue_email(&*app_state.outbox, &user.email, template).await {
+        return server_error(e);
+    }
+
+    changed_user(&app_state, user.id, "password_reset_forced").await
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/users/{id}/quota",
+    params(("id" = i32, Path, description = "ID пользователя")),
+    request_body = SetQuota,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Квота изменена", body = ApiResponse<AdminUser>),
+        (status = 400, description = "Отрицательная квота", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 404, description = "Пользователь не найден", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn set_quota(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Json(body): Json<SetQuota>,
+) -> Response {
+    let admin = match current_admin(&app_state, &headers).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+    if body.quota_bytes.is_some_and(|quota_bytes| quota_bytes < 0) {
+        return Response {
+            code: 400,
+            message: Some("negative_quota".into()),
+            data: None,
+        };
+    }
+
+    let event = AuditEvent::new("admin.quota_changed", Some(admin.id), &addr, &headers)
+        .target(id)
+        .details(json!({ "quota_bytes": body.quota_bytes }));
+    match app_state.admin.set_user_quota(id, body.quota_bytes, &event).await {
+        Ok(true) => changed_user(&app_state, id, "quota_updated").await,
+        Ok(false) => user_not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/team-drives/{id}/quota",
+    params(("id" = i32, Path, description = "ID командного диска")),
+    request_body = SetQuota,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Квота диска изменена", body = ApiResponse<TeamDrive>),
+        (status = 400, description = "Отрицательная квота", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 404, description = "Диск не найден", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn set_drive_quota(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Json(body): Json<SetQuota>,
+) -> Response {
+    let admin = match current_admin(&app_state, &headers).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+    if body.quota_bytes.is_some_and(|quota_bytes| quota_bytes < 0) {
+        return Response {
+            code: 400,
+            message: Some("negative_quota".into()),
+            data: None,
+        };
+    }
+
+    let team_drive_not_found = || {
+        Response {
+            code: 404,
+            message: Some("team_drive_not_found".into()),
+            data: None,
+        }
+    };
+    let event = AuditEvent::new("admin.team_drive_quota_changed", Some(admin.id), &addr, &headers)
+        .details(json!({ "team_drive_id": id, "quota_bytes": body.quota_bytes }));
+    match app_state.drives.set_team_drive_quota(id, body.quota_bytes, &event).await {
+        Ok(true) => {}
+        Ok(false) => return team_drive_not_found(),
+        Err(e) => return server_error(e),
+    }
+
+    match app_state.drives.find_team_drive(id, app_state.config.storage.team_drive_quota_bytes).await {
+        Ok(Some(drive)) => Response {
+            code: 200,
+            message: Some("quota_updated".into()),
+            data: Some(json!(drive)),
+        },
+        Ok(None) => team_drive_not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/users/{id}/admin",
+    params(("id" = i32, Path, description = "ID пользователя")),
+    request_body = SetAdmin,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Роль изменена", body = ApiResponse<AdminUser>),
+        (status = 400, description = "Нельзя снять роль с себя", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 404, description = "Пользователь не найден", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn set_admin(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Json(body): Json<SetAdmin>,
+) -> Response {
+    let admin = match current_admin(&app_state, &headers).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+    // Keeps at least the acting admin, so the instance can't be left without one.
+    if admin.id == id && !body.is_admin {
+        return Response {
+            code: 400,
+            message: Some("cannot_revoke_own_admin".into()),
+            data: None,
+        };
+    }
+
+    let event = AuditEvent::new("admin.role_changed", Some(admin.id), &addr, &headers)
+        .target(id)
+        .details(json!({ "is_admin": body.is_admin }));
+    match app_state.admin.set_user_admin(id, body.is_admin, &event).await {
+        Ok(true) => changed_user(&app_state, id, "role_updated").await,
+        Ok(false) => user_not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}/files",
+    params(("id" = i32, Path, description = "ID пользователя")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Метаданные файлов пользователя", body = ApiResponse<FileList>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 404, description = "Пользователь не найден", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn get_user_files(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+) -> Response {
+    let admin = match current_admin(&app_state, &headers).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+    match app_state.users.find_user_by_id(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return user_not_found(),
+        Err(e) => return server_error(e),
+    }
+
+    match app_state.admin.find_user_files(id).await {
+        Ok(files) => {
+            // Looking into someone else's files is recorded like any other
+            // change, and they aren't shown when that fails.
+            let event = AuditEvent::new("admin.files_viewed", Some(admin.id), &addr, &headers).target(id);
+            if let Err(e) = event.record(&*app_state.audit).await {
+                return server_error(e);
+            }
+            Response {
+                code: 200,
+                message: Some("files_fetched".into()),
+                data: Some(json!(FileList { files })),
+            }
+        }
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/stats",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Статистика системы", body = ApiResponse<SystemStats>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn get_stats(State(app_state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(response) = current_admin(&app_state, &headers).await {
+        return response;
+    }
+
+    match app_state.admin.system_stats().await {
+        Ok(stats) => Response {
+            code: 200,
+            message: Some("stats_fetched".into()),
+            data: Some(json!(stats)),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/audit",
+    params(AuditQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "События журнала аудита, новые первыми", body = ApiResponse<AuditEventPage>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn list_audit_events(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditQuery>,
+) -> Response {
+    if let Err(response) = current_admin(&app_state, &headers).await {
+        return response;
+    }
+
+    let limit = query.limit.unwrap_or(100).clamp(1, MAX_PAGE_SIZE);
+    match app_state.audit.find_audit_events(&query, limit).await {
+        Ok(events) => Response {
+            code: 200,
+            message: Some("audit_events_fetched".into()),
+            data: Some(json!(AuditEventPage::new(events, limit))),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/audit/export",
+    params(AuditQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (
+            status = 200,
+            description = "Выгрузка журнала аудита в JSONL или CSV",
+            content((String = "application/x-ndjson"), (String = "text/csv"))
+        ),
+        (status = 400, description = "Неизвестный формат", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn export_audit_events(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<AuditQuery>,
+) -> HttpResponse {
+    let admin = match current_admin(&app_state, &headers).await {
+        Ok(admin) => admin,
+        Err(response) => return response.into_response(),
+    };
+    let format = query.format.clone().unwrap_or_else(|| "jsonl".to_string());
+    if format != "jsonl" && format != "csv" {
+        return Response {
+            code: 400,
+            message: Some("invalid_export_format".into()),
+            data: None,
+        }
+        .into_response();
+    }
+
+    let limit = query.limit.unwrap_or(MAX_EXPORT_ROWS).clamp(1, MAX_EXPORT_ROWS);
+    let events = match app_state.audit.find_audit_events(&query, limit).await {
+        Ok(events) => events,
+        Err(e) => return server_error(e).into_response(),
+    };
+    let event = AuditEvent::new("admin.audit_exported", Some(admin.id), &addr, &headers)
+        .details(json!({ "format": format, "filters": query, "events": events.len() }));
+    if let Err(e) = event.record(&*app_state.audit).await {
+        return server_error(e).into_response();
+    }
+
+    let (content_type, body) = match format.as_str() {
+        "csv" => {
+            let rows: String = events.iter().map(AuditEventRecord::to_csv_row).collect();
+            ("text/csv; charset=utf-8", format!("{}{}", AuditEventRecord::CSV_HEADER, rows))
+        }
+        _ => ("application/x-ndjson", events.iter().map(AuditEventRecord::to_json_line).collect()),
+    };
+    let disposition = format!("attachment; filename=\"audit-events.{}\"", format);
+    (
+        [(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        body,
+    )
+        .into_response()
+}
@@ -1,69 +1,149 @@
 use crate::{
-    config::send_email::send_email,
+    config::{
+        api::{auth_header, bearer_token},
+        email_verification::send_verification_email,
+        outbox::enqueue_email,
+    },
     models::{
-        api::Response,
+        api::{ApiError, ApiMessage, ApiResponse, Response},
         app::AppState,
-        auth::{Auth, ForgotPassword, LoginUser, RegisterUser, ResetPassword},
-    },
-    repositories::{
-        auth_repository::{create_code, delete_code, find_code_by_code},
-        user_repository::{create_user, find_user_by_email, update_password},
+        audit::AuditEvent,
+        auth::{
+            Auth, ForgotPassword, LoginUser, MagicLinkLogin, MagicLinkRequest, RegisterUser, Registered, ResetPassword,
+            Throttle, ThrottleAction, Token, VerifiedEmail, VerifyEmail,
+        },
+        i18n::Message,
+        mail::EmailTemplate,
     },
+    services::user_service::current_user,
 };
 
 use axum::{
-    extract::{Json, State},
+    extract::{ConnectInfo, Json, Query, State},
+    http::HeaderMap,
     response::IntoResponse,
 };
+use std::net::SocketAddr;
+
+const MAGIC_LINK_PURPOSE: &str = "magic_link";
+
+fn too_many_attempts(retry_after: i64) -> Response {
+    Response {
+        code: 429,
+        message: Some("too_many_attempts".into()),
+        data: Some(serde_json::json!({ "retry_after": retry_after })),
+    }
+}
 
 #[utoipa::path(
     post,
     path = "/auth/login",
     request_body = LoginUser,
     responses(
-        (status = 200, description = "Успешная аутентификация"),
-        (status = 401, description = "Неверные учетные данные"),
-        (status = 404, description = "Пользователь не найден"),
-        (status = 500, description = "Ошибка сервера")
+        (status = 200, description = "Успешная аутентификация", body = ApiResponse<Token>),
+        (status = 401, description = "Неверные учетные данные", body = ApiError),
+        (status = 403, description = "Аккаунт заблокирован или требует сброса пароля", body = ApiError),
+        (status = 429, description = "Слишком много попыток", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
     ),
     tag = "auth"
 )]
 pub async fn login(
     State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(body): Json<LoginUser>,
 ) -> impl IntoResponse {
-    let check_user = find_user_by_email(&app_state.pool, body.email.clone()).await;
+    let throttle = Throttle::new(ThrottleAction::Login, &body.email, &addr.ip().to_string());
+    match throttle.retry_after(&app_state).await {
+        Ok(Some(retry_after)) => return too_many_attempts(retry_after),
+        Ok(None) => {}
+        Err(e) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(e)),
+                data: None,
+            }
+        }
+    }
 
-    let auth = Auth::new();
+    let check_user = app_state.users.find_user_by_email(body.email.clone()).await;
 
-    match check_user {
-        Ok(Some(user)) => {
-            let hashed_password = auth.verify_password(&body.password, &user.password.unwrap());
-            if hashed_password {
-                let token = auth.generate_jwt(user.id);
-                axum::Json(Response {
-                    code: 200,
-                    message: Some("Successful authorization".to_string()),
-                    data: Some(serde_json::json!({ "token": token })),
-                })
-            } else {
-                axum::Json(Response {
-                    code: 401,
-                    message: Some("Invalid email or password".to_string()),
-                    data: None,
-                })
+    let auth = &app_state.auth;
+    let known_user = check_user.as_ref().ok().and_then(|user| user.as_ref().map(|user| user.id));
+
+    let user = match check_user {
+        Ok(Some(user)) if user.password.is_some() => {
+            let hashed_password = user.password.clone().unwrap_or_default();
+            auth.verify_password(&body.password, &hashed_password).await.then_some(user)
+        }
+        // Unknown accounts and single sign-on accounts without a password.
+        Ok(_) => {
+            auth.verify_dummy_password(&body.password).await;
+            None
+        }
+        Err(e) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(e)),
+                data: None,
+            }
+        }
+    };
+
+    let failed_login = |reason: &str, user_id: Option<i32>| {
+        let event = AuditEvent::new("auth.login_failed", None, &addr, &headers)
+            .details(serde_json::json!({ "method": "password", "email": body.email, "reason": reason }));
+        match user_id {
+            Some(user_id) => event.target(user_id),
+            None => event,
+        }
+    };
+
+    match user {
+        Some(user) if user.disabled_at.is_some() => {
+            let _ = failed_login("account_disabled", Some(user.id)).record(&*app_state.audit).await;
+            Response {
+                code: 403,
+                message: Some("account_disabled".into()),
+                data: None,
+            }
+        }
+        Some(user) if user.password_reset_required => {
+            let _ = failed_login("password_reset_required", Some(user.id)).record(&*app_state.audit).await;
+            Response {
+                code: 403,
+                message: Some("password_reset_required".into()),
+                data: None,
+            }
+        }
+        Some(user) => {
+            let _ = throttle.succeed(&app_state).await;
+            let _ = AuditEvent::new("auth.login_succeeded", Some(user.id), &addr, &headers)
+                .details(serde_json::json!({ "method": "password" }))
+                .record(&*app_state.audit)
+                .await;
+            // Upgrade bcrypt and outdated Argon2 hashes while the plain password is at hand.
+            if auth.password_needs_rehash(user.password.as_deref().unwrap_or_default()) {
+                let hashed_password = auth.hash_password(&body.password).await;
+                let _ = app_state.users.update_password(user.id, hashed_password).await;
+            }
+            let token = auth.generate_jwt(user.id);
+            Response {
+                code: 200,
+                message: Some("login_succeeded".into()),
+                data: Some(serde_json::json!(Token { token })),
+            }
+        }
+        None => {
+            let _ = throttle.fail(&app_state).await;
+            let _ = failed_login("invalid_credentials", known_user).record(&*app_state.audit).await;
+            Response {
+                code: 401,
+                message: Some("invalid_credentials".into()),
+                data: None,
             }
         }
-        Ok(None) => axum::Json(Response {
-            code: 404,
-            message: Some("User not found".to_string()),
-            data: None,
-        }),
-        Err(_) => axum::Json(Response {
-            code: 500,
-            message: Some("Server error".to_string()),
-            data: None,
-        }),
     }
 }
 
@@ -72,59 +152,225 @@ pub async fn login(
     path = "/auth/register",
     request_body = RegisterUser,
     responses(
-        (status = 200, description = "Успешная регистрация"),
-        (status = 400, description = "Неверные учетные данные"),
-        (status = 404, description = "Пользователь не найден"),
-        (status = 500, description = "Ошибка сервера")
+        (status = 201, description = "Успешная регистрация, письмо для подтверждения email отправлено", body = ApiResponse<Registered>),
+        (status = 400, description = "Неверные учетные данные или слишком слабый пароль", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
     ),
     tag = "auth"
 )]
 pub async fn register(
     State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(body): Json<RegisterUser>,
 ) -> impl IntoResponse {
     if body.password.is_empty() || body.email.is_empty() || body.name.is_empty() {
-        return axum::Json(Response {
+        return Response {
+            code: 400,
+            message: Some("registration_fields_required".into()),
+            data: None,
+        };
+    }
+    let email = match Auth::normalize_email(&body.email) {
+        Some(email) => email,
+        None => {
+            return Response {
+                code: 400,
+                message: Some("invalid_email".into()),
+                data: None,
+            };
+        }
+    };
+    if let Err(message) = app_state.auth.check_password_policy(&body.password) {
+        return Response {
             code: 400,
-            message: Some("Password, email and name cannot be empty".to_string()),
+            message: Some(message),
             data: None,
-        });
+        };
     }
-    println!(
-        "Registering user - email: {}, name: {}",
-        body.email, body.name
-    );
-    let auth = Auth::new();
-    let hashed_password = auth.hash_password(&body.password);
-    let check_user = find_user_by_email(&app_state.pool, body.email.clone()).await;
+    let auth = &app_state.auth;
+    let hashed_password = auth.hash_password(&body.password).await;
+    let check_user = app_state.users.find_user_by_email(email.clone()).await;
     if let Ok(Some(_)) = check_user {
-        return axum::Json(Response {
+        return Response {
             code: 400,
-            message: Some("User is already registered".to_string()),
+            message: Some("user_exists".into()),
             data: None,
-        });
+        };
     }
 
-    match create_user(
-        &app_state.pool,
-        RegisterUser {
-            email: body.email,
+    match app_state
+        .users
+        .create_user(RegisterUser {
+            email: email.clone(),
             password: hashed_password,
             name: body.name,
-        },
-    )
-    .await
+        })
+        .await
     {
-        Ok(user) => axum::Json(Response {
-            code: 201,
-            message: Some("User registered successfully".to_string()),
-            data: Some(serde_json::json!({ "user_id": user.id })),
-        }),
-        Err(err) => axum::Json(Response {
+        Ok(user) => {
+            let _ = AuditEvent::new("user.registered", Some(user.id), &addr, &headers)
+                .details(serde_json::json!({ "method": "password", "email": email }))
+                .record(&*app_state.audit)
+                .await;
+            let _ = send_verification_email(&app_state, user.id, &email).await;
+            Response {
+                code: 201,
+                message: Some("user_registered".into()),
+                data: Some(serde_json::json!(Registered { user_id: user.id })),
+            }
+        }
+        Err(err) => Response {
+            code: 500,
+            message: Some(Message::server_error(err)),
+            data: None,
+        },
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/verify-email",
+    params(VerifyEmail),
+    responses(
+        (status = 200, description = "Email подтвержден", body = ApiResponse<VerifiedEmail>),
+        (status = 400, description = "Ссылка недействительна или устарела", body = ApiError),
+        (status = 409, description = "Email уже используется", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "auth"
+)]
+pub async fn confirm_email(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<VerifyEmail>,
+) -> impl IntoResponse {
+    let verification = match app_state.codes.find_email_verification(&query.token).await {
+        Ok(Some(verification)) => verification,
+        Ok(None) => {
+            return Response {
+                code: 400,
+                message: Some("invalid_link".into()),
+                data: None,
+            };
+        }
+        Err(err) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(err)),
+                data: None,
+            };
+        }
+    };
+
+    // The address may have been taken by someone else since the link was sent.
+    if let Ok(Some(owner)) = app_state.users.find_user_by_email(verification.email.clone()).await {
+        if owner.id != verification.user_id {
+            return Response {
+                code: 409,
+                message: Some("email_in_use".into()),
+                data: None,
+            };
+        }
+    }
+
+    match app_state.users.verify_email(verification.user_id, verification.email).await {
+        Ok(user) => {
+            let _ = app_state.codes.delete_email_verifications(user.id).await;
+            let _ = AuditEvent::new("user.email_verified", None, &addr, &headers)
+                .target(user.id)
+                .details(serde_json::json!({ "email": user.email }))
+                .record(&*app_state.audit)
+                .await;
+            Response {
+                code: 200,
+                message: Some("email_verified".into()),
+                data: Some(serde_json::json!(VerifiedEmail { email: user.email })),
+            }
+        }
+        Err(err) => Response {
+            code: 500,
+            message: Some(Message::server_error(err)),
+            data: None,
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/resend-verification",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Письмо для подтверждения отправлено", body = ApiMessage),
+        (status = 400, description = "Email уже подтвержден", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 429, description = "Слишком много попыток", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "auth"
+)]
+pub async fn resend_verification(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let verify = auth_header(&app_state.auth, &*app_state.users, &headers).await;
+    let user = match verify.user_id {
+        Some(user_id) if verify.authorized && verify.is_session() => app_state.users.find_user_by_id(user_id).await,
+        _ => Ok(None),
+    };
+    let user = match user {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return Response {
+                code: 401,
+                message: Some("unauthorized".into()),
+                data: None,
+            };
+        }
+        Err(err) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(err)),
+                data: None,
+            };
+        }
+    };
+
+    if user.email_verified_at.is_some() {
+        return Response {
+            code: 400,
+            message: Some("email_already_verified".into()),
+            data: None,
+        };
+    }
+
+    let throttle = Throttle::new(ThrottleAction::VerifyEmail, &user.email, &addr.ip().to_string());
+    match throttle.retry_after(&app_state).await {
+        Ok(Some(retry_after)) => return too_many_attempts(retry_after),
+        Ok(None) => {}
+        Err(err) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(err)),
+                data: None,
+            }
+        }
+    }
+    let _ = throttle.fail(&app_state).await;
+
+    match send_verification_email(&app_state, user.id, &user.email).await {
+        Ok(_) => Response {
+            code: 200,
+            message: Some("verification_email_sent".into()),
+            data: None,
+        },
+        Err(err) => Response {
             code: 500,
-            message: Some(format!("Server error: {}", err)),
+            message: Some(Message::server_error(err)),
             data: None,
-        }),
+        },
     }
 }
 
@@ -133,50 +379,74 @@ pub async fn register(
     path = "/auth/forgot-password",
     request_body = ForgotPassword,
     responses(
-        (status = 200, description = "Успешная аутентификация"),
-        (status = 401, description = "Неверные учетные данные"),
-        (status = 404, description = "Пользователь не найден"),
-        (status = 500, description = "Ошибка сервера")
+        (status = 200, description = "Код отправлен, если пользователь существует", body = ApiMessage),
+        (status = 400, description = "Неверные данные", body = ApiError),
+        (status = 429, description = "Слишком много попыток", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
     ),
     tag = "auth"
 )]
 pub async fn forgot_password(
     State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(body): Json<ForgotPassword>,
 ) -> impl IntoResponse {
     if body.email.is_empty() {
-        return axum::Json(Response {
+        return Response {
             code: 400,
-            message: Some("Email cannot be empty".to_string()),
+            message: Some("email_required".into()),
             data: None,
-        });
+        };
     }
     let email = body.email.to_string();
 
-    let check_user = find_user_by_email(&app_state.pool, email.clone()).await;
+    // Every request counts against the limit, since each one sends an email.
+    let throttle = Throttle::new(ThrottleAction::ForgotPassword, &email, &addr.ip().to_string());
+    match throttle.retry_after(&app_state).await {
+        Ok(Some(retry_after)) => return too_many_attempts(retry_after),
+        Ok(None) => {}
+        Err(err) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(err)),
+                data: None,
+            }
+        }
+    }
+    let _ = throttle.fail(&app_state).await;
+
+    let check_user = app_state.users.find_user_by_email(email.clone()).await;
     match check_user {
-        Ok(Some(user)) => {
-            let code = Auth::generate_code();
-            let _ = create_code(&app_state.pool, &code, user.id).await;
-            let message = format!("Your code is: {}", &code);
-            let _ = send_email(&email, "Forgot password".to_string(), message);
+        Ok(user) => {
+            if let Some(user) = user {
+                let _ = AuditEvent::new("auth.password_reset_requested", None, &addr, &headers)
+                    .target(user.id)
+                    .record(&*app_state.audit)
+                    .await;
+                let code = Auth::generate_code();
+                let _ = app_state.codes.create_code(&code, user.id, app_state.config.tokens.reset_code_secs).await;
+                let template = EmailTemplate::PasswordReset { code };
+                if let Err(err) = enqueue_email(&*app_state.outbox, &user.email, template).await {
+                    return Response {
+                        code: 500,
+                        message: Some(Message::server_error(err)),
+                        data: None,
+                    };
+                }
+            }
 
-            axum::Json(Response {
+            Response {
                 code: 200,
-                message: Some("Email sent successfully".to_string()),
+                message: Some("reset_code_sent".into()),
                 data: None,
-            })
+            }
         }
-        Ok(None) => axum::Json(Response {
-            code: 404,
-            message: Some("User not found".to_string()),
-            data: None,
-        }),
-        Err(err) => axum::Json(Response {
+        Err(err) => Response {
             code: 500,
-            message: Some(format!("Server error: {}", err)),
+            message: Some(Message::server_error(err)),
             data: None,
-        }),
+        },
     }
 }
 
@@ -185,67 +455,321 @@ pub async fn forgot_password(
     path = "/auth/reset-password",
     request_body = ResetPassword,
     responses(
-        (status = 200, description = "Успешная сброс пароля"),
-        (status = 401, description = "Неверные учетные данные"),
-        (status = 404, description = "Пользователь не найден"),
-        (status = 500, description = "Ошибка сервера")
+        (status = 200, description = "Успешная сброс пароля", body = ApiMessage),
+        (status = 400, description = "Неверный email, код или слишком слабый пароль", body = ApiError),
+        (status = 429, description = "Слишком много попыток", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
     ),
     tag = "auth"
 )]
 pub async fn reset_password(
     State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(body): Json<ResetPassword>,
 ) -> impl IntoResponse {
     if body.email.is_empty() || body.code.is_empty() || body.new_password.is_empty() {
-        return axum::Json(Response {
+        return Response {
             code: 400,
-            message: Some("Email, code and password cannot be empty".to_string()),
+            message: Some("reset_fields_required".into()),
             data: None,
-        });
+        };
     }
-    let user = find_user_by_email(&app_state.pool, body.email.clone()).await;
-    let user = match user {
+    if let Err(message) = app_state.auth.check_password_policy(&body.new_password) {
+        return Response {
+            code: 400,
+            message: Some(message),
+            data: None,
+        };
+    }
+
+    let throttle = Throttle::new(ThrottleAction::ResetPassword, &body.email, &addr.ip().to_string());
+    match throttle.retry_after(&app_state).await {
+        Ok(Some(retry_after)) => return too_many_attempts(retry_after),
+        Ok(None) => {}
+        Err(err) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(err)),
+                data: None,
+            }
+        }
+    }
+
+    let invalid_code = Response {
+        code: 400,
+        message: Some("invalid_email_or_code".into()),
+        data: None,
+    };
+
+    let user = match app_state.users.find_user_by_email(body.email.clone()).await {
         Ok(Some(user)) => user,
-        _ => {
-            return axum::Json(Response {
-                code: 404,
-                message: Some("User not found".to_string()),
+        Ok(None) => {
+            let _ = throttle.fail(&app_state).await;
+            let _ = AuditEvent::new("auth.password_reset_failed", None, &addr, &headers)
+                .details(serde_json::json!({ "email": body.email }))
+                .record(&*app_state.audit)
+                .await;
+            return invalid_code;
+        }
+        Err(err) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(err)),
                 data: None,
-            });
+            }
         }
     };
     let code = body.code.to_string();
-    let check_code = find_code_by_code(&app_state.pool, code.clone(), user.id.clone()).await;
+    let check_code = app_state.codes.find_code_by_code(code.clone(), user.id).await;
     match check_code {
         Ok(Some(code)) => {
-            let auth = Auth::new();
-            let hashed_password = auth.hash_password(&body.new_password);
-            let result = update_password(&app_state.pool, user.id, hashed_password).await;
+            let auth = &app_state.auth;
+            let hashed_password = auth.hash_password(&body.new_password).await;
+            let result = app_state.users.update_password(user.id, hashed_password).await;
 
-            let _ = delete_code(&app_state.pool, code.code, user.id.clone()).await;
+            let _ = app_state.codes.delete_code(code.code, user.id).await;
 
             match result {
-                Ok(_) => axum::Json(Response {
-                    code: 200,
-                    message: Some("Password reset successfully".to_string()),
-                    data: None,
-                }),
-                Err(err) => axum::Json(Response {
+                Ok(_) => {
+                    let _ = throttle.succeed(&app_state).await;
+                    let _ = AuditEvent::new("auth.password_reset", None, &addr, &headers)
+                        .target(user.id)
+                        .record(&*app_state.audit)
+                        .await;
+                    let _ = Throttle::new(ThrottleAction::Login, &body.email, &addr.ip().to_string())
+                        .succeed(&app_state)
+                        .await;
+                    Response {
+                        code: 200,
+                        message: Some("password_reset".into()),
+                        data: None,
+                    }
+                }
+                Err(err) => Response {
                     code: 500,
-                    message: Some(format!("Server error: {}", err)),
+                    message: Some(Message::server_error(err)),
                     data: None,
-                }),
+                },
             }
         }
-        Ok(None) => axum::Json(Response {
-            code: 404,
-            message: Some("Code not found".to_string()),
+        Ok(None) => {
+            let _ = throttle.fail(&app_state).await;
+            let _ = AuditEvent::new("auth.password_reset_failed", None, &addr, &headers)
+                .target(user.id)
+                .details(serde_json::json!({ "email": body.email }))
+                .record(&*app_state.audit)
+                .await;
+            invalid_code
+        }
+        Err(err) => Response {
+            code: 500,
+            message: Some(Message::server_error(err)),
+            data: None,
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/magic-link",
+    request_body = MagicLinkRequest,
+    responses(
+        (status = 200, description = "Ссылка для входа отправлена, если пользователь существует", body = ApiMessage),
+        (status = 400, description = "Неверные данные", body = ApiError),
+        (status = 429, description = "Слишком много попыток", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "auth"
+)]
+pub async fn request_magic_link(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<MagicLinkRequest>,
+) -> impl IntoResponse {
+    if body.email.is_empty() {
+        return Response {
+            code: 400,
+            message: Some("email_required".into()),
             data: None,
-        }),
-        Err(err) => axum::Json(Response {
+        };
+    }
+
+    let throttle = Throttle::new(ThrottleAction::MagicLink, &body.email, &addr.ip().to_string());
+    match throttle.retry_after(&app_state).await {
+        Ok(Some(retry_after)) => return too_many_attempts(retry_after),
+        Ok(None) => {}
+        Err(err) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(err)),
+                data: None,
+            }
+        }
+    }
+    let _ = throttle.fail(&app_state).await;
+
+    match app_state.users.find_user_by_email(body.email.clone()).await {
+        Ok(user) => {
+            if let Some(user) = user {
+                let _ = AuditEvent::new("auth.magic_link_requested", None, &addr, &headers)
+                    .target(user.id)
+                    .record(&*app_state.audit)
+                    .await;
+                let jti = Auth::generate_token();
+                if let Err(err) = app_state.codes.create_magic_link(&jti, user.id, app_state.config.tokens.magic_link_secs).await {
+                    return Response {
+                        code: 500,
+                        message: Some(Message::server_error(err)),
+                        data: None,
+                    };
+                }
+                let token = app_state
+                    .auth
+                    .generate_purpose_token(user.id, MAGIC_LINK_PURPOSE, &jti, app_state.config.tokens.magic_link_secs);
+                let base_url = app_state.config.server.magic_link_url();
+                let link = format!("{}?token={}", base_url, token);
+                let template = EmailTemplate::MagicLink { link };
+                if let Err(err) = enqueue_email(&*app_state.outbox, &user.email, template).await {
+                    return Response {
+                        code: 500,
+                        message: Some(Message::server_error(err)),
+                        data: None,
+                    };
+                }
+            }
+
+            Response {
+                code: 200,
+                message: Some("magic_link_sent".into()),
+                data: None,
+            }
+        }
+        Err(err) => Response {
+            code: 500,
+            message: Some(Message::server_error(err)),
+            data: None,
+        },
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/magic-link/login",
+    params(MagicLinkLogin),
+    responses(
+        (status = 200, description = "Успешная аутентификация", body = ApiResponse<Token>),
+        (status = 400, description = "Ссылка недействительна, устарела или уже использована", body = ApiError),
+        (status = 403, description = "Аккаунт заблокирован или требует сброса пароля", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "auth"
+)]
+pub async fn magic_link_login(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<MagicLinkLogin>,
+) -> impl IntoResponse {
+    let invalid_link = Response {
+        code: 400,
+        message: Some("invalid_link".into()),
+        data: None,
+    };
+
+    let claims = match app_state.auth.verify_purpose_token(&query.token, MAGIC_LINK_PURPOSE) {
+        Ok(claims) => claims,
+        Err(_) => return invalid_link,
+    };
+    let jti = claims.jti.unwrap_or_default();
+    let user_id = match app_state.codes.consume_magic_link(&jti).await {
+        Ok(Some(user_id)) if user_id == claims.sub => user_id,
+        Ok(_) => return invalid_link,
+        Err(err) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(err)),
+                data: None,
+            }
+        }
+    };
+
+    match app_state.users.is_user_active(user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Response {
+                code: 403,
+                message: Some("account_inactive".into()),
+                data: None,
+            }
+        }
+        Err(err) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(err)),
+                data: None,
+            }
+        }
+    }
+
+    // Following the link proves control of the address.
+    if let Err(err) = app_state.users.confirm_email_ownership(user_id).await {
+        return Response {
             code: 500,
-            message: Some(format!("Server error: {}", err)),
+            message: Some(Message::server_error(err)),
             data: None,
-        }),
+        };
+    }
+
+    let _ = AuditEvent::new("auth.login_succeeded", Some(user_id), &addr, &headers)
+        .details(serde_json::json!({ "method": "magic_link" }))
+        .record(&*app_state.audit)
+        .await;
+    Response {
+        code: 200,
+        message: Some("login_succeeded".into()),
+        data: Some(serde_json::json!(Token { token: app_state.auth.generate_jwt(user_id) })),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Новый токен сессии", body = ApiResponse<Token>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токены устройств не продлеваются", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "auth"
+)]
+pub async fn refresh_session(State(app_state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    // The login stays as old as it was, renewing is no reauthentication.
+    let auth_time = bearer_token(&headers)
+        .and_then(|token| app_state.auth.verify_jwt(token).ok())
+        .and_then(|claims| claims.auth_time);
+    let token = app_state.auth.generate_jwt_authenticated_at(user.id, auth_time);
+    Response {
+        code: 200,
+        message: Some("session_renewed".into()),
+        data: Some(serde_json::json!(Token { token })),
     }
 }
+
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "Открытые ключи для проверки токенов (JWKS)", body = Object)
+    ),
+    tag = "auth"
+)]
+pub async fn jwks(State(app_state): State<AppState>) -> impl IntoResponse {
+    axum::Json(app_state.auth.jwks())
+}
@@ -0,0 +1,318 @@
+use axum::{
+    extract::{ConnectInfo, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response as HttpResponse},
+    Form, Json,
+};
+use chrono::{Duration, Utc};
+use std::net::SocketAddr;
+
+use crate::{
+    config::device::{
+        generate_user_code, normalize_user_code, parse_scope, DEVICE_CODE_GRANT_TYPE, POLL_INTERVAL_SECS,
+        SLOW_DOWN_SECS,
+    },
+    models::{
+        api::{ApiError, ApiMessage, ApiResponse, Response},
+        app::AppState,
+        audit::AuditEvent,
+        auth::{Auth, Throttle, ThrottleAction},
+        device::{
+            DeviceApproval, DeviceCodeRequest, DeviceCodeResponse, DeviceLookup, DeviceTokenRequest,
+            DeviceTokenResponse, OAuthError, PendingDeviceAuthorization,
+        },
+        i18n::Message,
+        user::User,
+    },
+    services::user_service::current_user,
+};
+
+/// The device endpoints talk to OAuth clients, which expect the error in
+/// the HTTP status and body rather than in our `Response` envelope.
+fn oauth_error(status: StatusCode, error: &str, description: Option<String>) -> HttpResponse {
+    let body = OAuthError {
+        error: error.to_string(),
+        error_description: description,
+    };
+    (status, [(header::CACHE_CONTROL, "no-store")], Json(body)).into_response()
+}
+
+fn server_error(e: axum::Error) -> HttpResponse {
+    tracing::error!(error = %e, "server error");
+    oauth_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error", Some(e.to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/device/code",
+    request_body(content = DeviceCodeRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Код устройства и код пользователя выданы", body = DeviceCodeResponse),
+        (status = 400, description = "Неверный client_id или scope", body = OAuthError)
+    ),
+    tag = "auth"
+)]
+pub async fn device_code(State(app_state): State<AppState>, Form(body): Form<DeviceCodeRequest>) -> HttpResponse {
+    let client_id = body.client_id.trim();
+    if client_id.is_empty() || client_id.len() > 100 {
+        return oauth_error(StatusCode::BAD_REQUEST, "invalid_client", Some("client_id is required".to_string()));
+    }
+    let scope = match parse_scope(body.scope.as_deref()) {
+        Ok(scope) => scope,
+        Err(e) => return oauth_error(StatusCode::BAD_REQUEST, "invalid_scope", Some(e)),
+    };
+
+    let device_code = Auth::generate_token();
+    let user_code = generate_user_code();
+    if let Err(e) = app_state
+        .devices
+        .create_device_authorization(
+            &device_code,
+            &user_code,
+            client_id,
+            &scope,
+            POLL_INTERVAL_SECS,
+            app_state.config.tokens.device_code_secs,
+        )
+        .await
+    {
+        return server_error(e);
+    }
+
+    let verification_uri = app_state.config.server.device_verification_url();
+    let response = DeviceCodeResponse {
+        verification_uri_complete: format!("{}?user_code={}", verification_uri, user_code),
+        verification_uri,
+        device_code,
+        user_code,
+        expires_in: app_state.config.tokens.device_code_secs,
+        interval: POLL_INTERVAL_SECS,
+    };
+    ([(header::CACHE_CONTROL, "no-store")], Json(response)).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/device/token",
+    request_body(content = DeviceTokenRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Токен доступа выдан", body = DeviceTokenResponse),
+        (status = 400, description = "authorization_pending, slow_down, access_denied, expired_token или invalid_grant", body = OAuthError)
+    ),
+    tag = "auth"
+)]
+pub async fn device_token(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Form(body): Form<DeviceTokenRequest>,
+) -> HttpResponse {
+    if body.grant_type != DEVICE_CODE_GRANT_TYPE {
+        return oauth_error(StatusCode::BAD_REQUEST, "unsupported_grant_type", None);
+    }
+
+    let authorization = match app_state.devices.find_device_authorization(&body.device_code).await {
+        Ok(Some(authorization)) if authorization.client_id == body.client_id => authorization,
+        Ok(_) => return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", None),
+        Err(e) => return server_error(e),
+    };
+
+    let now = Utc::now();
+    if authorization.expires_at <= now {
+        let _ = app_state.devices.delete_device_authorization(&authorization.device_code).await;
+        return oauth_error(StatusCode::BAD_REQUEST, "expired_token", None);
+    }
+
+    match (authorization.status.as_str(), authorization.user_id) {
+        ("approved", Some(user_id)) => {
+            // Only the poll that removes the authorization gets the token.
+            match app_state.devices.delete_device_authorization(&authorization.device_code).await {
+                Ok(true) => {}
+                Ok(false) => return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", None),
+                Err(e) => return server_error(e),
+            }
+            match app_state.users.is_user_active(user_id).await {
+                Ok(true) => {}
+                Ok(false) => return oauth_error(StatusCode::BAD_REQUEST, "access_denied", None),
+                Err(e) => return server_error(e),
+            }
+            let _ = AuditEvent::new("auth.access_token_created", Some(user_id), &addr, &headers)
+                .details(serde_json::json!({
+                    "method": "device",
+                    "client_id": authorization.client_id,
+                    "scope": authorization.scope,
+                }))
+                .record(&*app_state.audit)
+                .await;
+            let response = DeviceTokenResponse {
+                access_token: app_state
+                    .auth
+                    .generate_access_token(user_id, &authorization.scope, app_state.config.tokens.access_token_secs),
+                token_type: "Bearer".to_string(),
+                expires_in: app_state.config.tokens.access_token_secs,
+                scope: authorization.scope,
+            };
+            ([(header::CACHE_CONTROL, "no-store")], Json(response)).into_response()
+        }
+        ("denied", _) => {
+            let _ = app_state.devices.delete_device_authorization(&authorization.device_code).await;
+            oauth_error(StatusCode::BAD_REQUEST, "access_denied", None)
+        }
+        _ => {
+            let too_fast = authorization
+                .last_polled_at
+                .is_some_and(|polled_at| now < polled_at + Duration::seconds(authorization.interval_secs as i64));
+            let interval_secs = match too_fast {
+                true => authorization.interval_secs + SLOW_DOWN_SECS,
+                false => authorization.interval_secs,
+            };
+            if let Err(e) = app_state.devices.record_poll(&authorization.device_code, interval_secs).await {
+                return server_error(e);
+            }
+            match too_fast {
+                true => oauth_error(StatusCode::BAD_REQUEST, "slow_down", None),
+                false => oauth_error(StatusCode::BAD_REQUEST, "authorization_pending", None),
+            }
+        }
+    }
+}
+
+/// Counts every code that doesn't match a pending authorization, so that
+/// codes can't be guessed.
+async fn check_throttle(app_state: &AppState, user: &User, addr: SocketAddr) -> Result<Throttle, Response> {
+    let throttle = Throttle::new(ThrottleAction::DeviceApproval, &user.email, &addr.ip().to_string());
+    match throttle.retry_after(app_state).await {
+        Ok(None) => Ok(throttle),
+        Ok(Some(retry_after)) => Err(Response {
+            code: 429,
+            message: Some("too_many_attempts".into()),
+            data: Some(serde_json::json!({ "retry_after": retry_after })),
+        }),
+        Err(e) => Err(Response {
+            code: 500,
+            message: Some(Message::server_error(e)),
+            data: None,
+        }),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/device",
+    params(DeviceLookup),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Запрос устройства на вход", body = ApiResponse<PendingDeviceAuthorization>),
+        (status = 400, description = "Код недействителен или устарел", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен устройства не может подтверждать вход", body = ApiError),
+        (status = 429, description = "Слишком много попыток", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "auth"
+)]
+pub async fn device_lookup(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<DeviceLookup>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let throttle = match check_throttle(&app_state, &user, addr).await {
+        Ok(throttle) => throttle,
+        Err(response) => return response,
+    };
+
+    match app_state.devices.find_pending_by_user_code(&normalize_user_code(&query.user_code)).await {
+        Ok(Some(authorization)) => Response {
+            code: 200,
+            message: Some("device_authorization_fetched".into()),
+            data: Some(serde_json::json!(PendingDeviceAuthorization {
+                user_code: authorization.user_code,
+                client_id: authorization.client_id,
+                scope: authorization.scope,
+                expires_at: authorization.expires_at,
+            })),
+        },
+        Ok(None) => {
+            let _ = throttle.fail(&app_state).await;
+            Response {
+                code: 400,
+                message: Some("invalid_code".into()),
+                data: None,
+            }
+        }
+        Err(e) => Response {
+            code: 500,
+            message: Some(Message::server_error(e)),
+            data: None,
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/device",
+    request_body = DeviceApproval,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Вход устройства подтвержден или отклонен", body = ApiMessage),
+        (status = 400, description = "Код недействителен или устарел", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен устройства не может подтверждать вход", body = ApiError),
+        (status = 429, description = "Слишком много попыток", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "auth"
+)]
+pub async fn device_approve(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<DeviceApproval>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let throttle = match check_throttle(&app_state, &user, addr).await {
+        Ok(throttle) => throttle,
+        Err(response) => return response,
+    };
+
+    let user_code = normalize_user_code(&body.user_code);
+    match app_state.devices.decide_device_authorization(&user_code, user.id, body.approve).await {
+        Ok(true) => {
+            let _ = throttle.succeed(&app_state).await;
+            let (action, message) = match body.approve {
+                true => ("auth.device_approved", "device_approved"),
+                false => ("auth.device_denied", "device_denied"),
+            };
+            let _ = AuditEvent::new(action, Some(user.id), &addr, &headers)
+                .details(serde_json::json!({ "user_code": user_code }))
+                .record(&*app_state.audit)
+                .await;
+            Response {
+                code: 200,
+                message: Some(message.into()),
+                data: None,
+            }
+        }
+        Ok(false) => {
+            let _ = throttle.fail(&app_state).await;
+            Response {
+                code: 400,
+                message: Some("invalid_code".into()),
+                data: None,
+            }
+        }
+        Err(e) => Response {
+            code: 500,
+            message: Some(Message::server_error(e)),
+            data: None,
+        },
+    }
+}
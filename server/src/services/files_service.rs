@@ -1,66 +1,140 @@
-use crate::{config::api::auth_header, models::files::{FileResponse, FileUploadRequest}, repositories::user_repository::find_user_by_id};
+use crate::{
+    config::{
+        api::{auth_header, bearer_token},
+        changes::is_cursor_expired,
+        events::event_stream,
+        folders::normalize_entry_name,
+        metrics::metrics,
+    },
+    models::events::StreamPosition,
+    models::files::{
+        Change, ChangePage, ChangeQuery, EventQuery, FileData, FileList, FileUpload, MoveFile, ReplaceQuery,
+        StorageQuota, UploadQuery, UploadedFile,
+    },
+    services::{folders_service::own_folder, org_service::server_error, team_drive_service::scoped_user},
+};
 use axum::{
-    extract::{multipart::Multipart, Query, State},
-    http::HeaderMap,
-    response::IntoResponse,
-    Json
+    body::Body,
+    extract::{multipart::Multipart, ConnectInfo, Query, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::{
+        sse::{KeepAlive, Sse},
+        IntoResponse, Response as HttpResponse,
+    },
+    Json,
 };
+use chrono::Utc;
 use serde_json::json;
-use crate::models::api::Response;
+use std::{net::SocketAddr, time::Duration};
+use tokio::{fs::File, time::Instant};
+use tokio_util::io::ReaderStream;
+use crate::models::api::{ApiError, ApiMessage, ApiResponse, Response};
 use crate::models::app::AppState;
-use crate::models::files::{FileAction, GetFiles};
+use crate::models::audit::AuditEvent;
+use crate::models::files::{FileAction, FileQuery, GetFiles};
+use crate::models::i18n::Message;
 
+fn error_response(code: i32, message: Message) -> HttpResponse {
+    Response {
+        code,
+        message: Some(message),
+        data: None,
+    }
+    .into_response()
+}
 
+/// Загрузка личного файла
 #[utoipa::path(
     post,
     path = "/files/upload",
-    request_body = FileUploadRequest,
+    params(UploadQuery),
+    request_body(content = FileUpload, content_type = "multipart/form-data"),
+    security(("bearer_auth" = [])),
     responses(
-        (status = 200, description = "File successfully uploaded", body = FileResponse),
-        (status = 401, description = "Unauthorized access"),
-        (status = 404, description = "User not found"),
-        (status = 500, description = "Server error")
+        (status = 200, description = "Файл загружен", body = ApiResponse<UploadedFile>),
+        (status = 400, description = "Нет файла или превышена квота", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Email не подтверждён или токен не имеет права files:write", body = ApiError),
+        (status = 404, description = "Папка не найдена", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
     ),
     tag = "files"
 )]
 #[axum::debug_handler]
 pub async fn upload_file(
-    State(pool): State<AppState>,
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
+    Query(query): Query<UploadQuery>,
     multipart: Multipart,
 ) -> impl IntoResponse {
-    let verify = auth_header(&headers).await;
+    let verify = auth_header(&app_state.auth, &*app_state.users, &headers).await;
     if !verify.authorized || verify.user_id.is_none() {
-        return Json(Response {
+        return Response {
             code: 401,
-            message: Some("Unauthorized".to_string()),
+            message: Some("unauthorized".into()),
             data: None,
-        });
+        };
     }
-    let check_user = find_user_by_id(&pool.pool, verify.user_id.unwrap()).await;
+    if !verify.allows("files:write") {
+        return Response {
+            code: 403,
+            message: Some("token_scope_denied".into()),
+            data: None,
+        };
+    }
+    let check_user = app_state.users.find_user_by_id(verify.user_id.unwrap()).await;
     if check_user.is_err() {
-        return Json(Response {
+        return Response {
             code: 401,
-            message: Some("User not found".to_string()),
+            message: Some("user_not_found".into()),
             data: None,
-        });
+        };
+    }
+    let mut quota_bytes = app_state.config.storage.quota_bytes;
+    if let Ok(Some(user)) = &check_user {
+        if user.email_verified_at.is_none() {
+            return Response {
+                code: 403,
+                message: Some("email_not_verified".into()),
+                data: None,
+            };
+        }
+        quota_bytes = user.quota_bytes.unwrap_or(quota_bytes);
+    }
+    if let Some(folder_id) = query.folder_id {
+        if let Err(response) = own_folder(&app_state, verify.user_id.unwrap(), folder_id).await {
+            return response;
+        }
     }
 
-    let file_response =
-        FileAction::upload_file(&pool.pool, multipart, verify.user_id.unwrap()).await;
+    let file_response = FileAction::upload_file(
+        &*app_state.files,
+        &app_state.config.storage.root,
+        multipart,
+        verify.user_id.unwrap(),
+        None,
+        query.folder_id,
+        quota_bytes,
+    )
+    .await;
     if file_response.is_error {
-        return Json(Response {
+        return Response {
             code: 400,
-            message: Some(file_response.error_message),
+            message: file_response.error_message,
             data: None,
-        });
+        };
     }
 
-    Json(Response {
+    let _ = AuditEvent::new("file.uploaded", verify.user_id, &addr, &headers)
+        .details(file_response.data.clone().unwrap_or_default())
+        .record(&*app_state.audit)
+        .await;
+    Response {
         code: 200,
-        message: Some("File uploaded".to_string()),
+        message: Some("file_uploaded".into()),
         data: Some(json!(file_response.data.unwrap())),
-    })
+    }
 }
 
 /// Получение списка файлов
@@ -68,106 +142,521 @@ pub async fn upload_file(
     post,
     path = "/files/get",
     request_body = GetFiles,
+    security(("bearer_auth" = [])),
     responses(
-        (status = 200, description = "Файлы успешно найдены", body = FilesResponse),
-        (status = 401, description = "Неавторизованный доступ"),
-        (status = 404, description = "Пользователь не найден"),
-        (status = 500, description = "Ошибка сервера")
+        (status = 200, description = "Файлы успешно найдены", body = ApiResponse<FileList>),
+        (status = 400, description = "Файлы не найдены среди личных файлов пользователя", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:read", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
     ),
     tag = "files"
 )]
 #[axum::debug_handler]
 pub async fn get_files(
-    State(pool): State<AppState>,
+    State(app_state): State<AppState>,
     headers: HeaderMap,
     body: Json<GetFiles>,
 ) -> impl IntoResponse {
-    let verify = auth_header(&headers).await;
+    let verify = auth_header(&app_state.auth, &*app_state.users, &headers).await;
     if !verify.authorized || verify.user_id.is_none() {
-        return Json(Response {
+        return Response {
             code: 401,
-            message: Some("Unauthorized".to_string()),
+            message: Some("unauthorized".into()),
+            data: None,
+        };
+    }
+    if !verify.allows("files:read") {
+        return Response {
+            code: 403,
+            message: Some("token_scope_denied".into()),
             data: None,
-        });
+        };
     }
 
-    let check_user = find_user_by_id(&pool.pool, verify.user_id.unwrap()).await;
+    let check_user = app_state.users.find_user_by_id(verify.user_id.unwrap()).await;
     if check_user.is_err() {
-        return Json(Response {
+        return Response {
             code: 401,
-            message: Some("User not found".to_string()),
+            message: Some("user_not_found".into()),
             data: None,
-        });
+        };
     }
 
-    let files = FileAction::get_files(&pool.pool, verify.user_id.unwrap(), &body.file_ids).await;
+    let files = FileAction::get_files(&*app_state.files, verify.user_id.unwrap(), &body.file_ids).await;
     if files.is_error {
-        return Json(Response {
+        return Response {
             code: 400,
-            message: Some(files.error_message),
+            message: files.error_message,
             data: None,
-        });
+        };
     }
 
-    Json(Response {
+    Response {
         code: 200,
-        message: Some("Files found".to_string()),
+        message: Some("files_found".into()),
         data: files.data,
-    })
+    }
 }
 
 /// Удаление файла
 #[utoipa::path(
-    delete,
+    method(delete, post),
     path = "/files/delete",
-    params(
-        ("file_id" = i32, description = "ID удаляемого файла", example = 123)
-    ),
+    params(FileQuery),
+    security(("bearer_auth" = [])),
     responses(
-        (status = 200, description = "Файл успешно удалён", body = FileResponse),
-        (status = 401, description = "Неавторизованный доступ"),
-        (status = 404, description = "Пользователь не найден"),
-        (status = 500, description = "Ошибка сервера")
+        (status = 200, description = "Файл успешно удалён", body = ApiMessage),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:write", body = ApiError),
+        (status = 404, description = "Файл не найден среди личных файлов пользователя", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
     ),
     tag = "files"
 )]
 #[axum::debug_handler]
 pub async fn delete_file(
-    State(pool): State<AppState>,
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
-    file_id: Query<i32>,
+    Query(query): Query<FileQuery>,
 ) -> impl IntoResponse {
-    let verify = auth_header(&headers).await;
+    let verify = auth_header(&app_state.auth, &*app_state.users, &headers).await;
     if !verify.authorized || verify.user_id.is_none() {
-        return Json(Response {
+        return Response {
             code: 401,
-            message: Some("Unauthorized".to_string()),
+            message: Some("unauthorized".into()),
             data: None,
-        });
+        };
+    }
+    if !verify.allows("files:write") {
+        return Response {
+            code: 403,
+            message: Some("token_scope_denied".into()),
+            data: None,
+        };
     }
 
-    let check_user = find_user_by_id(&pool.pool, verify.user_id.unwrap()).await;
+    let check_user = app_state.users.find_user_by_id(verify.user_id.unwrap()).await;
 
     if check_user.is_err() {
-        return Json(Response {
+        return Response {
             code: 401,
-            message: Some("User not found".to_string()),
+            message: Some("user_not_found".into()),
             data: None,
-        });
+        };
+    }
+
+    // Files of other users look the same as missing ones.
+    match app_state.files.find_personal_file(verify.user_id.unwrap(), query.file_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return Response {
+                code: 404,
+                message: Some("file_not_found".into()),
+                data: None,
+            };
+        }
+        Err(e) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(e)),
+                data: None,
+            };
+        }
     }
 
-    let file_response = FileAction::delete_file(&pool.pool, *file_id).await;
+    let file_response = FileAction::delete_file(&*app_state.files, query.file_id).await;
     if file_response.is_error {
-        return Json(Response {
+        return Response {
             code: 400,
-            message: Some(file_response.error_message),
+            message: file_response.error_message,
             data: None,
-        });
+        };
     }
 
-    Json(Response {
+    let _ = AuditEvent::new("file.deleted", verify.user_id, &addr, &headers)
+        .details(json!({ "file_id": query.file_id }))
+        .record(&*app_state.audit)
+        .await;
+    Response {
         code: 200,
-        message: Some("File deleted".to_string()),
-        data: Some(json!(file_response.data.unwrap())),
-    })
+        message: Some("file_deleted".into()),
+        data: None,
+    }
+}
+
+/// Скачивание личного файла
+#[utoipa::path(
+    get,
+    path = "/files/download",
+    params(FileQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Содержимое файла", content_type = "application/octet-stream"),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:read", body = ApiError),
+        (status = 404, description = "Файл не найден среди личных файлов пользователя", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "files"
+)]
+pub async fn download_file(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<FileQuery>,
+) -> HttpResponse {
+    let verify = auth_header(&app_state.auth, &*app_state.users, &headers).await;
+    let Some(user_id) = verify.user_id.filter(|_| verify.authorized) else {
+        return error_response(401, "unauthorized".into());
+    };
+    if !verify.allows("files:read") {
+        return error_response(403, "token_scope_denied".into());
+    }
+
+    match app_state.files.find_personal_file(user_id, query.file_id).await {
+        Ok(Some(file)) => stream_file(&file, "personal").await,
+        Ok(None) => error_response(404, "file_not_found".into()),
+        Err(e) => error_response(500, Message::server_error(e)),
+    }
+}
+
+/// Sends the stored contents of a file of `space`, `personal` or `team_drive`,
+/// as a download.
+pub async fn stream_file(file: &FileData, space: &str) -> HttpResponse {
+    let stored = match File::open(&file.file_path).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            metrics().storage_errors.with_label_values(&["read"]).inc();
+            return error_response(500, Message::server_error(e));
+        }
+    };
+
+    metrics().download_bytes.with_label_values(&[space]).inc_by(file.file_size as u64);
+    let content_type = HeaderValue::from_str(&file.file_content_type)
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CONTENT_LENGTH, HeaderValue::from(file.file_size)),
+            (header::CONTENT_DISPOSITION, FileAction::content_disposition(&file.file_name)),
+            // The type was chosen by the uploader, browsers must not guess another one.
+            (header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+        ],
+        Body::from_stream(ReaderStream::new(stored)),
+    )
+        .into_response()
+}
+
+/// Занятое личными файлами место и квота
+#[utoipa::path(
+    get,
+    path = "/files/quota",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Занятое место и квота в байтах", body = ApiResponse<StorageQuota>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:read", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "files"
+)]
+pub async fn get_quota(State(app_state): State<AppState>, headers: HeaderMap) -> HttpResponse {
+    let verify = auth_header(&app_state.auth, &*app_state.users, &headers).await;
+    let Some(user_id) = verify.user_id.filter(|_| verify.authorized) else {
+        return error_response(401, "unauthorized".into());
+    };
+    if !verify.allows("files:read") {
+        return error_response(403, "token_scope_denied".into());
+    }
+
+    let user = match app_state.users.find_user_by_id(user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return error_response(401, "user_not_found".into()),
+        Err(e) => return error_response(500, Message::server_error(e)),
+    };
+    let used_bytes = match app_state.files.used_bytes(user_id, None).await {
+        Ok(used_bytes) => used_bytes,
+        Err(e) => return error_response(500, Message::server_error(e)),
+    };
+    let quota = StorageQuota {
+        used_bytes,
+        quota_bytes: user.quota_bytes.unwrap_or(app_state.config.storage.quota_bytes),
+    };
+    Response {
+        code: 200,
+        message: Some("storage_quota".into()),
+        data: Some(json!(quota)),
+    }
+    .into_response()
+}
+
+/// Finds a personal file of the user. Files of other users look the same as missing ones.
+async fn own_file(app_state: &AppState, user_id: i32, file_id: i32) -> Result<FileData, Response> {
+    match app_state.files.find_personal_file(user_id, file_id).await {
+        Ok(Some(file)) => Ok(file),
+        Ok(None) => Err(Response {
+            code: 404,
+            message: Some("file_not_found".into()),
+            data: None,
+        }),
+        Err(e) => Err(server_error(e)),
+    }
+}
+
+/// Замена содержимого личного файла
+#[utoipa::path(
+    post,
+    path = "/files/replace",
+    params(ReplaceQuery),
+    request_body(content = FileUpload, content_type = "multipart/form-data"),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Содержимое файла заменено", body = ApiResponse<UploadedFile>),
+        (status = 400, description = "Нет файла или превышена квота", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Email не подтверждён или токен не имеет права files:write", body = ApiError),
+        (status = 404, description = "Файл не найден среди личных файлов пользователя", body = ApiError),
+        (status = 409, description = "Содержимое файла изменилось с expected_hash", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "files"
+)]
+pub async fn replace_file(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<ReplaceQuery>,
+    multipart: Multipart,
+) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:write").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    if user.email_verified_at.is_none() {
+        return Response {
+            code: 403,
+            message: Some("email_not_verified".into()),
+            data: None,
+        };
+    }
+    let file = match own_file(&app_state, user.id, query.file_id).await {
+        Ok(file) => file,
+        Err(response) => return response,
+    };
+
+    let quota_bytes = user.quota_bytes.unwrap_or(app_state.config.storage.quota_bytes);
+    let file_response = FileAction::replace_file(
+        &*app_state.files,
+        &app_state.config.storage.root,
+        multipart,
+        user.id,
+        file,
+        query.expected_hash.as_deref(),
+        quota_bytes,
+    )
+    .await;
+    if file_response.is_error {
+        let changed = file_response.error_message.as_ref().is_some_and(|message| message.key == "file_changed");
+        return Response {
+            code: if changed { 409 } else { 400 },
+            message: file_response.error_message,
+            data: None,
+        };
+    }
+
+    let _ = AuditEvent::new("file.replaced", Some(user.id), &addr, &headers)
+        .details(file_response.data.clone().unwrap_or_default())
+        .record(&*app_state.audit)
+        .await;
+    Response {
+        code: 200,
+        message: Some("file_replaced".into()),
+        data: file_response.data,
+    }
+}
+
+/// Переименование или перемещение личного файла
+#[utoipa::path(
+    post,
+    path = "/files/move",
+    request_body = MoveFile,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Файл перемещён", body = ApiResponse<FileData>),
+        (status = 400, description = "Недопустимое имя файла", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:write", body = ApiError),
+        (status = 404, description = "Файл или папка не найдены", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "files"
+)]
+pub async fn move_file(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<MoveFile>,
+) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:write").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let file = match own_file(&app_state, user.id, body.file_id).await {
+        Ok(file) => file,
+        Err(response) => return response,
+    };
+    let Some(file_name) = normalize_entry_name(&body.file_name) else {
+        return Response {
+            code: 400,
+            message: Some("invalid_entry_name".into()),
+            data: None,
+        };
+    };
+    if let Some(folder_id) = body.folder_id {
+        if let Err(response) = own_folder(&app_state, user.id, folder_id).await {
+            return response;
+        }
+    }
+
+    let moved = match app_state.files.move_file(file.id, file_name, body.folder_id).await {
+        Ok(Some(moved)) => moved,
+        Ok(None) => {
+            return Response {
+                code: 404,
+                message: Some("file_not_found".into()),
+                data: None,
+            };
+        }
+        Err(e) => return server_error(e),
+    };
+    let _ = AuditEvent::new("file.moved", Some(user.id), &addr, &headers)
+        .details(json!({ "file_id": moved.id, "file_name": moved.file_name, "folder_id": moved.folder_id }))
+        .record(&*app_state.audit)
+        .await;
+    Response {
+        code: 200,
+        message: Some("file_moved".into()),
+        data: Some(json!(moved)),
+    }
+}
+
+fn cursor_expired() -> Response {
+    Response {
+        code: 410,
+        message: Some("cursor_expired".into()),
+        data: None,
+    }
+}
+
+/// Изменения личных файлов и папок после курсора
+#[utoipa::path(
+    get,
+    path = "/files/changes",
+    params(ChangeQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Изменения по порядку, курсор для следующего запроса", body = ApiResponse<ChangePage>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:read", body = ApiError),
+        (status = 410, description = "Изменения после курсора удалены по сроку хранения, синхронизация начинается заново с курсора 0", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "files"
+)]
+pub async fn get_changes(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ChangeQuery>,
+) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:read").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let cursor = query.cursor.unwrap_or(0).max(0);
+    let limit = query.limit.unwrap_or(500).clamp(1, 1000);
+    match is_cursor_expired(&*app_state.changes, cursor).await {
+        Ok(false) => {}
+        Ok(true) => return cursor_expired(),
+        Err(e) => return server_error(e),
+    }
+
+    // One more change than asked for tells whether there are more.
+    let mut changes = match app_state.changes.find_changes(user.id, cursor, limit + 1).await {
+        Ok(changes) => changes,
+        Err(e) => return server_error(e),
+    };
+    let has_more = changes.len() as i64 > limit;
+    changes.truncate(limit as usize);
+    let page = ChangePage {
+        cursor: changes.last().map_or(cursor, |change| change.id),
+        changes,
+        has_more,
+    };
+    Response {
+        code: 200,
+        message: Some("changes_found".into()),
+        data: Some(json!(page)),
+    }
+}
+
+/// События об изменениях файлов и папок (Server-Sent Events)
+#[utoipa::path(
+    get,
+    path = "/files/events",
+    params(
+        EventQuery,
+        ("Last-Event-ID" = Option<i64>, Header, description = "Id последнего полученного события, поток продолжается после него")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Поток text/event-stream: id — курсор изменения, event — его вид (created, modified, moved, deleted), data — изменение. Поток закрывается, когда истекает токен", body = Change, content_type = "text/event-stream"),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:read", body = ApiError),
+        (status = 410, description = "Событие курсора удалено по сроку хранения или не существует", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "files"
+)]
+pub async fn get_events(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<EventQuery>,
+) -> HttpResponse {
+    let user = match scoped_user(&app_state, &headers, "files:read").await {
+        Ok(user) => user,
+        Err(response) => return response.into_response(),
+    };
+    // Subscribed before the cursor is read, so that no change falls in between.
+    let notices = app_state.notices.subscribe();
+    // Browsers send the id of the last event when they reconnect.
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok());
+    let position = match last_event_id.or(query.cursor).map(|cursor| cursor.max(0)) {
+        Some(0) => StreamPosition::default(),
+        Some(cursor) => match is_cursor_expired(&*app_state.changes, cursor).await {
+            Ok(false) => match app_state.changes.event_position(cursor).await {
+                Ok(Some(position)) => position,
+                Ok(None) => return cursor_expired().into_response(),
+                Err(e) => return server_error(e).into_response(),
+            },
+            Ok(true) => return cursor_expired().into_response(),
+            Err(e) => return server_error(e).into_response(),
+        },
+        None => match app_state.changes.current_event_position().await {
+            Ok(position) => position,
+            Err(e) => return server_error(e).into_response(),
+        },
+    };
+
+    // The stream outlives no token: clients reconnect with a new one.
+    let expires_in = bearer_token(&headers)
+        .and_then(|token| app_state.auth.verify_jwt(token).ok())
+        .map_or(0, |claims| claims.exp as i64 - Utc::now().timestamp());
+    let ends_at = Instant::now() + Duration::from_secs(expires_in.max(0) as u64);
+    Sse::new(event_stream(app_state, notices, user.id, position, ends_at))
+        .keep_alive(KeepAlive::default())
+        .into_response()
 }
@@ -0,0 +1,223 @@
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use serde_json::json;
+
+use crate::{
+    config::folders::{is_within, normalize_entry_name},
+    models::{
+        api::{ApiError, ApiMessage, ApiResponse, Response},
+        app::AppState,
+        files::{CreateFolder, Folder, FolderList, MoveFolder},
+    },
+    services::{org_service::server_error, team_drive_service::scoped_user},
+};
+
+fn folder_not_found() -> Response {
+    Response {
+        code: 404,
+        message: Some("folder_not_found".into()),
+        data: None,
+    }
+}
+
+fn invalid_entry_name() -> Response {
+    Response {
+        code: 400,
+        message: Some("invalid_entry_name".into()),
+        data: None,
+    }
+}
+
+/// Finds a folder of the user. Folders of other users look the same as missing ones.
+pub async fn own_folder(app_state: &AppState, user_id: i32, folder_id: i32) -> Result<Folder, Response> {
+    match app_state.folders.find_folder_by_id(folder_id).await {
+        Ok(Some(folder)) if folder.user_id == user_id => Ok(folder),
+        Ok(_) => Err(folder_not_found()),
+        Err(e) => Err(server_error(e)),
+    }
+}
+
+/// Список папок личного пространства
+#[utoipa::path(
+    get,
+    path = "/folders",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Все папки пользователя", body = ApiResponse<FolderList>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:read", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "folders"
+)]
+pub async fn list_folders(State(app_state): State<AppState>, headers: HeaderMap) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:read").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    match app_state.folders.find_folders(user.id).await {
+        Ok(folders) => Response {
+            code: 200,
+            message: Some("folders_found".into()),
+            data: Some(json!(FolderList { folders })),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+/// Создание папки
+#[utoipa::path(
+    post,
+    path = "/folders",
+    request_body = CreateFolder,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Папка создана", body = ApiResponse<Folder>),
+        (status = 400, description = "Недопустимое имя папки", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:write", body = ApiError),
+        (status = 404, description = "Родительская папка не найдена", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "folders"
+)]
+pub async fn create_folder(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<CreateFolder>,
+) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:write").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let Some(name) = normalize_entry_name(&body.name) else {
+        return invalid_entry_name();
+    };
+    if let Some(parent_id) = body.parent_id {
+        if let Err(response) = own_folder(&app_state, user.id, parent_id).await {
+            return response;
+        }
+    }
+
+    match app_state.folders.create_folder(user.id, name, body.parent_id).await {
+        Ok(folder) => Response {
+            code: 200,
+            message: Some("folder_created".into()),
+            data: Some(json!(folder)),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+/// Переименование или перемещение папки
+#[utoipa::path(
+    patch,
+    path = "/folders/{id}",
+    params(("id" = i32, Path, description = "ID папки")),
+    request_body = MoveFolder,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Папка изменена", body = ApiResponse<Folder>),
+        (status = 400, description = "Недопустимое имя, или папка перемещается в саму себя", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:write", body = ApiError),
+        (status = 404, description = "Папка или родительская папка не найдена", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "folders"
+)]
+pub async fn update_folder(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Json(body): Json<MoveFolder>,
+) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:write").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    if let Err(response) = own_folder(&app_state, user.id, id).await {
+        return response;
+    }
+    let Some(name) = normalize_entry_name(&body.name) else {
+        return invalid_entry_name();
+    };
+    if let Some(parent_id) = body.parent_id {
+        if let Err(response) = own_folder(&app_state, user.id, parent_id).await {
+            return response;
+        }
+        let folders = match app_state.folders.find_folders(user.id).await {
+            Ok(folders) => folders,
+            Err(e) => return server_error(e),
+        };
+        if is_within(&folders, parent_id, id) {
+            return Response {
+                code: 400,
+                message: Some("folder_cycle".into()),
+                data: None,
+            };
+        }
+    }
+
+    match app_state.folders.move_folder(id, name, body.parent_id).await {
+        Ok(Some(folder)) => Response {
+            code: 200,
+            message: Some("folder_updated".into()),
+            data: Some(json!(folder)),
+        },
+        Ok(None) => folder_not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+/// Удаление пустой папки
+#[utoipa::path(
+    delete,
+    path = "/folders/{id}",
+    params(("id" = i32, Path, description = "ID папки")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Папка удалена", body = ApiMessage),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:write", body = ApiError),
+        (status = 404, description = "Папка не найдена", body = ApiError),
+        (status = 409, description = "В папке есть файлы или папки", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "folders"
+)]
+pub async fn delete_folder(State(app_state): State<AppState>, headers: HeaderMap, Path(id): Path<i32>) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:write").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    if let Err(response) = own_folder(&app_state, user.id, id).await {
+        return response;
+    }
+
+    // Only empty folders are deleted: their entries are never deleted along.
+    match app_state.folders.is_folder_empty(id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Response {
+                code: 409,
+                message: Some("folder_not_empty".into()),
+                data: None,
+            }
+        }
+        Err(e) => return server_error(e),
+    }
+
+    match app_state.folders.delete_folder(id).await {
+        Ok(true) => Response {
+            code: 200,
+            message: Some("folder_deleted".into()),
+            data: None,
+        },
+        Ok(false) => folder_not_found(),
+        Err(e) => server_error(e),
+    }
+}
@@ -0,0 +1,83 @@
+use std::sync::atomic::Ordering;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use rand::Rng;
+
+use crate::models::{
+    app::AppState,
+    health::{Liveness, Readiness, ReadinessChecks},
+};
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "Процесс работает", body = Liveness)
+    ),
+    tag = "health"
+)]
+pub async fn healthz() -> impl IntoResponse {
+    Json(Liveness { status: "ok".to_string() })
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "Сервер готов принимать запросы", body = Readiness),
+        (status = 503, description = "База данных, хранилище или миграции не готовы, или сервер останавливается", body = Readiness)
+    ),
+    tag = "health"
+)]
+pub async fn readyz(State(app_state): State<AppState>) -> impl IntoResponse {
+    let checks = ReadinessChecks {
+        database: check_database(&app_state).await,
+        storage: check_storage(&app_state).await,
+        migrations: check_migrations(&app_state).await,
+    };
+    let all_ok = [&checks.database, &checks.storage, &checks.migrations]
+        .iter()
+        .all(|check| check.as_str() == "ok");
+    let status = match (app_state.shutting_down.load(Ordering::Relaxed), all_ok) {
+        (true, _) => "shutting_down",
+        (false, true) => "ready",
+        (false, false) => "not_ready",
+    };
+    let code = match status {
+        "ready" => StatusCode::OK,
+        _ => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (code, Json(Readiness { status: status.to_string(), checks }))
+}
+
+async fn check_database(app_state: &AppState) -> String {
+    match app_state.health.ping().await {
+        Ok(_) => "ok".to_string(),
+        Err(e) => format!("unreachable: {}", e),
+    }
+}
+
+/// Writes and removes a small file in the storage root.
+async fn check_storage(app_state: &AppState) -> String {
+    let path = app_state
+        .config
+        .storage
+        .root
+        .join(format!(".readyz-{}", rand::thread_rng().gen::<u32>()));
+    let result = match tokio::fs::write(&path, b"ok").await {
+        Ok(()) => tokio::fs::remove_file(&path).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => "ok".to_string(),
+        Err(e) => format!("not writable: {}", e),
+    }
+}
+
+async fn check_migrations(app_state: &AppState) -> String {
+    match app_state.health.is_schema_current().await {
+        Ok(true) => "ok".to_string(),
+        Ok(false) => "pending migrations".to_string(),
+        Err(e) => format!("unknown: {}", e),
+    }
+}
@@ -1,3 +1,9 @@
 pub mod auth_service;
 pub mod user_service;
-pub mod files_service;
\ No newline at end of file
+pub mod files_service;
+pub mod oidc_service;pub mod device_service;
+pub mod admin_service;
+pub mod org_service;
+pub mod team_drive_service;
+pub mod health_service;
+pub mod folders_service;
\ No newline at end of file
@@ -0,0 +1,258 @@
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Redirect, Response as HttpResponse},
+};
+use chrono::Utc;
+use std::net::SocketAddr;
+
+use crate::{
+    models::{
+        api::{ApiError, ApiResponse, Response},
+        app::AppState,
+        audit::AuditEvent,
+        auth::{Auth, Token},
+        i18n::Message,
+        oidc::{IdTokenClaims, OidcCallback, OidcLogin, OidcLoginQuery, OidcProviderInfo, OidcProviderList},
+    },
+};
+
+/// Holds the `state` of the login the browser started, signed, so that a
+/// callback can't complete a login started in another browser.
+const STATE_COOKIE: &str = "oidc_state";
+const STATE_PURPOSE: &str = "oidc_state";
+
+fn error_response(code: i32, message: Message) -> HttpResponse {
+    Response {
+        code,
+        message: Some(message),
+        data: None,
+    }
+    .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/providers",
+    responses(
+        (status = 200, description = "Список провайдеров единого входа", body = ApiResponse<OidcProviderList>)
+    ),
+    tag = "auth"
+)]
+pub async fn oidc_providers(State(app_state): State<AppState>) -> impl IntoResponse {
+    let providers: Vec<OidcProviderInfo> = app_state
+        .oidc
+        .providers
+        .iter()
+        .map(|provider| OidcProviderInfo {
+            name: provider.name.clone(),
+            login_url: format!("{}/auth/oidc/{}/login", app_state.config.server.app_url, provider.name),
+        })
+        .collect();
+
+    Response {
+        code: 200,
+        message: Some("providers_fetched".into()),
+        data: Some(serde_json::json!(OidcProviderList { providers })),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/{provider}/login",
+    params(("provider" = String, Path, description = "Имя провайдера"), OidcLoginQuery),
+    responses(
+        (status = 303, description = "Перенаправление к провайдеру, state сохраняется в cookie"),
+        (status = 404, description = "Провайдер не найден", body = ApiError),
+        (status = 502, description = "Провайдер недоступен", body = ApiError)
+    ),
+    tag = "auth"
+)]
+pub async fn oidc_login(
+    State(app_state): State<AppState>,
+    Path(provider): Path<String>,
+    Query(query): Query<OidcLoginQuery>,
+) -> HttpResponse {
+    let provider = match app_state.oidc.get(&provider) {
+        Some(provider) => provider,
+        None => return error_response(404, "provider_not_found".into()),
+    };
+
+    let login = OidcLogin {
+        state: Auth::generate_token(),
+        provider: provider.name.clone(),
+        nonce: Auth::generate_token(),
+        code_verifier: Auth::generate_token(),
+    };
+    let url = match provider.authorization_url(&login, query.reauthenticate.unwrap_or(false)).await {
+        Ok(url) => url,
+        Err(e) => return error_response(502, Message::new("identity_provider_error").arg("error", e)),
+    };
+    let ttl_secs = app_state.config.tokens.oidc_login_secs;
+    if let Err(e) = app_state.identities.create_login(&login, ttl_secs).await {
+        return error_response(500, Message::server_error(e));
+    }
+
+    let state = app_state.auth.generate_purpose_token(0, STATE_PURPOSE, &login.state, ttl_secs);
+    let secure = if app_state.config.server.app_url.starts_with("https://") { "; Secure" } else { "" };
+    let cookie = format!(
+        "{}={}; Path=/auth/oidc; Max-Age={}; HttpOnly; SameSite=Lax{}",
+        STATE_COOKIE, state, ttl_secs, secure
+    );
+    ([(header::SET_COOKIE, cookie)], Redirect::to(&url)).into_response()
+}
+
+/// Whether the state cookie of the request is for `state`.
+fn state_matches_cookie(app_state: &AppState, headers: &HeaderMap, state: &str) -> bool {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().strip_prefix(STATE_COOKIE)?.strip_prefix('='))
+        .filter_map(|token| app_state.auth.verify_purpose_token(token, STATE_PURPOSE).ok())
+        .any(|claims| claims.jti.as_deref() == Some(state))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/{provider}/callback",
+    params(("provider" = String, Path, description = "Имя провайдера"), OidcCallback),
+    responses(
+        (status = 200, description = "Успешная аутентификация", body = ApiResponse<Token>),
+        (status = 303, description = "Перенаправление с токеном в URL"),
+        (status = 400, description = "Вход отменён, устарел или начат в другом браузере", body = ApiError),
+        (status = 401, description = "Недействительный ID токен", body = ApiError),
+        (status = 403, description = "Аккаунт заблокирован или требует сброса пароля", body = ApiError),
+        (status = 409, description = "Аккаунт с таким email уже существует", body = ApiError),
+        (status = 502, description = "Провайдер недоступен", body = ApiError)
+    ),
+    tag = "auth"
+)]
+pub async fn oidc_callback(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(provider): Path<String>,
+    Query(query): Query<OidcCallback>,
+) -> HttpResponse {
+    let provider = match app_state.oidc.get(&provider) {
+        Some(provider) => provider,
+        None => return error_response(404, "provider_not_found".into()),
+    };
+
+    if let Some(error) = query.error {
+        let description = query.error_description.unwrap_or_default();
+        return error_response(400, Message::new("oidc_login_failed").arg("error", format!("{} {}", error, description).trim()));
+    }
+    let (code, state) = match (query.code, query.state) {
+        (Some(code), Some(state)) => (code, state),
+        _ => return error_response(400, "code_and_state_required".into()),
+    };
+    if !state_matches_cookie(&app_state, &headers, &state) {
+        return error_response(400, "invalid_login".into());
+    }
+
+    let login = match app_state.identities.take_login(&state, &provider.name).await {
+        Ok(Some(login)) => login,
+        Ok(None) => return error_response(400, "invalid_login".into()),
+        Err(e) => return error_response(500, Message::server_error(e)),
+    };
+
+    let id_token = match provider.exchange_code(&code, &login.code_verifier).await {
+        Ok(id_token) => id_token,
+        Err(e) => return error_response(502, Message::new("identity_provider_error").arg("error", e)),
+    };
+    let claims = match provider.verify_id_token(&id_token, &login.nonce).await {
+        Ok(claims) => claims,
+        Err(e) => {
+            let _ = AuditEvent::new("auth.login_failed", None, &addr, &headers)
+                .details(serde_json::json!({ "method": "oidc", "provider": provider.name, "reason": e }))
+                .record(&*app_state.audit)
+                .await;
+            return error_response(401, Message::new("oidc_login_failed").arg("error", &e));
+        }
+    };
+
+    let user_id = match app_state.identities.find_identity_user_id(&provider.name, &claims.sub).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => match link_or_create_user(&app_state, &provider.name, &claims, &addr, &headers).await {
+            Ok(user_id) => user_id,
+            Err(response) => return response,
+        },
+        Err(e) => return error_response(500, Message::server_error(e)),
+    };
+
+    match app_state.users.is_user_active(user_id).await {
+        Ok(true) => {}
+        Ok(false) => return error_response(403, "account_inactive".into()),
+        Err(e) => return error_response(500, Message::server_error(e)),
+    }
+
+    let _ = AuditEvent::new("auth.login_succeeded", Some(user_id), &addr, &headers)
+        .details(serde_json::json!({ "method": "oidc", "provider": provider.name }))
+        .record(&*app_state.audit)
+        .await;
+    // The login at the provider may be older than this one. Without its time
+    // the session doesn't count as a recent login.
+    let now = Utc::now().timestamp();
+    let auth_time = claims.auth_time.map(|auth_time| auth_time.min(now));
+    let token = app_state.auth.generate_jwt_authenticated_at(user_id, auth_time);
+    match &provider.post_login_redirect {
+        Some(url) => Redirect::to(&format!("{}#token={}", url, token)).into_response(),
+        None => Response {
+            code: 200,
+            message: Some("login_succeeded".into()),
+            data: Some(serde_json::json!(Token { token })),
+        }
+        .into_response(),
+    }
+}
+
+/// Links a new identity to the account with the same email, which is only
+/// allowed when the provider has verified the address, or creates an account
+/// just in time.
+async fn link_or_create_user(
+    app_state: &AppState,
+    provider: &str,
+    claims: &IdTokenClaims,
+    addr: &SocketAddr,
+    headers: &HeaderMap,
+) -> Result<i32, HttpResponse> {
+    let email = match claims.email.as_deref().and_then(Auth::normalize_email) {
+        Some(email) => email,
+        None => return Err(error_response(400, "provider_email_missing".into())),
+    };
+    let email_verified = claims.email_verified.unwrap_or(false);
+
+    let (user, action) = match app_state.users.find_user_by_email(email.clone()).await {
+        Ok(Some(user)) if email_verified => (app_state.users.confirm_email_ownership(user.id).await, "auth.identity_linked"),
+        Ok(Some(_)) => {
+            return Err(error_response(
+                409,
+                "provider_email_unverified".into(),
+            ));
+        }
+        Ok(None) => {
+            let name = claims
+                .name
+                .clone()
+                .or(claims.preferred_username.clone())
+                .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_string());
+            (app_state.users.create_sso_user(email, name, email_verified).await, "user.registered")
+        }
+        Err(e) => return Err(error_response(500, Message::server_error(e))),
+    };
+
+    let user = user.map_err(|e| error_response(500, Message::server_error(e)))?;
+    app_state
+        .identities
+        .create_identity(user.id, provider, &claims.sub)
+        .await
+        .map_err(|e| error_response(500, Message::server_error(e)))?;
+    let _ = AuditEvent::new(action, Some(user.id), addr, headers)
+        .details(serde_json::json!({ "method": "oidc", "provider": provider, "email": user.email }))
+        .record(&*app_state.audit)
+        .await;
+    Ok(user.id)
+}
@@ -0,0 +1,761 @@
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+
+use crate::{
+    config::{
+        org::{invitation_link, normalize_name},
+        outbox::enqueue_email,
+    },
+    models::{
+        api::{ApiError, ApiMessage, ApiResponse, Response},
+        app::AppState,
+        audit::AuditEvent,
+        auth::Auth,
+        files::FileAction,
+        i18n::Message,
+        mail::EmailTemplate,
+        org::{
+            AcceptInvitation, CreateInvitation, CreateOrganization, InvitationList, InvitationLookup, InvitationPreview,
+            MemberList, Membership, MembershipList, OrgInvitation, OrgRole, Organization, OrganizationDetails,
+            SetMemberRole, UpdateOrganization,
+        },
+    },
+    services::user_service::current_user,
+};
+
+pub fn server_error(e: axum::Error) -> Response {
+    Response {
+        code: 500,
+        message: Some(Message::server_error(e)),
+        data: None,
+    }
+}
+
+/// Resolves the role of the user in the organization. Outsiders get the same
+/// answer as for an organization that doesn't exist.
+pub async fn member_role(app_state: &AppState, organization_id: i32, user_id: i32) -> Result<OrgRole, Response> {
+    match app_state.orgs.find_member_role(organization_id, user_id).await {
+        Ok(Some(role)) => Ok(role),
+        Ok(None) => Err(org_not_found()),
+        Err(e) => Err(server_error(e)),
+    }
+}
+
+fn org_not_found() -> Response {
+    Response {
+        code: 404,
+        message: Some("organization_not_found".into()),
+        data: None,
+    }
+}
+
+pub fn require_role(role: OrgRole, minimum: OrgRole) -> Result<(), Response> {
+    match role >= minimum {
+        true => Ok(()),
+        false => Err(Response {
+            code: 403,
+            message: Some("forbidden".into()),
+            data: None,
+        }),
+    }
+}
+
+fn invalid_name() -> Response {
+    Response {
+        code: 400,
+        message: Some("invalid_name_length".into()),
+        data: None,
+    }
+}
+
+fn last_owner() -> Response {
+    Response {
+        code: 400,
+        message: Some("owner_required".into()),
+        data: None,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/orgs",
+    request_body = CreateOrganization,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Организация создана, создатель стал владельцем", body = ApiResponse<Membership>),
+        (status = 400, description = "Неверное название", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен устройства не может управлять организациями", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn create_org(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<CreateOrganization>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let name = match normalize_name(&body.name) {
+        Some(name) => name,
+        None => return invalid_name(),
+    };
+
+    match app_state.orgs.create_organization(&name, user.id).await {
+        Ok(organization) => {
+            let _ = AuditEvent::new("org.created", Some(user.id), &addr, &headers)
+                .details(json!({ "organization_id": organization.id, "name": organization.name }))
+                .record(&*app_state.audit)
+                .await;
+            Response {
+                code: 200,
+                message: Some("organization_created".into()),
+                data: Some(json!(Membership {
+                    organization_id: organization.id,
+                    name: organization.name,
+                    role: OrgRole::Owner,
+                    joined_at: organization.created_at,
+                })),
+            }
+        }
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Организации пользователя с его ролью", body = ApiResponse<MembershipList>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен устройства не может управлять организациями", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn list_orgs(State(app_state): State<AppState>, headers: HeaderMap) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    match app_state.orgs.find_memberships(user.id).await {
+        Ok(organizations) => Response {
+            code: 200,
+            message: Some("organizations_fetched".into()),
+            data: Some(json!(MembershipList { organizations })),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/{id}",
+    params(("id" = i32, Path, description = "ID организации")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Организация, роль пользователя и участники", body = ApiResponse<OrganizationDetails>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен устройства не может управлять организациями", body = ApiError),
+        (status = 404, description = "Организация не найдена", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn get_org(State(app_state): State<AppState>, headers: HeaderMap, Path(id): Path<i32>) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+
+    let organization = match app_state.orgs.find_organization(id).await {
+        Ok(Some(organization)) => organization,
+        Ok(None) => return org_not_found(),
+        Err(e) => return server_error(e),
+    };
+    match app_state.orgs.find_members(id).await {
+        Ok(members) => Response {
+            code: 200,
+            message: Some("organization_fetched".into()),
+            data: Some(json!(OrganizationDetails { organization, role, members })),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/orgs/{id}",
+    params(("id" = i32, Path, description = "ID организации")),
+    request_body = UpdateOrganization,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Организация переименована", body = ApiResponse<Organization>),
+        (status = 400, description = "Неверное название", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов организации", body = ApiError),
+        (status = 404, description = "Организация не найдена", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn update_org(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Json(body): Json<UpdateOrganization>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    if let Err(response) = require_role(role, OrgRole::Admin) {
+        return response;
+    }
+    let name = match normalize_name(&body.name) {
+        Some(name) => name,
+        None => return invalid_name(),
+    };
+
+    match app_state.orgs.rename_organization(id, &name).await {
+        Ok(organization) => Response {
+            code: 200,
+            message: Some("organization_updated".into()),
+            data: Some(json!(organization)),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orgs/{id}",
+    params(("id" = i32, Path, description = "ID организации")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Организация удалена вместе с командными дисками", body = ApiMessage),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для владельцев", body = ApiError),
+        (status = 404, description = "Организация не найдена", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn delete_org(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    if let Err(response) = require_role(role, OrgRole::Owner) {
+        return response;
+    }
+
+    let file_paths = match app_state.orgs.delete_organization(id).await {
+        Ok(file_paths) => file_paths,
+        Err(e) => return server_error(e),
+    };
+    let _ = AuditEvent::new("org.deleted", Some(user.id), &addr, &headers)
+        .details(json!({ "organization_id": id, "files": file_paths.len() }))
+        .record(&*app_state.audit)
+        .await;
+
+    // The organization is gone at this point, so leftovers on disk are only logged.
+    let removed = FileAction::remove_stored_files(&file_paths).await;
+    if removed.is_error {
+        tracing::warn!(org_id = id, files = ?removed.data, "error deleting some files from disk for deleted organization");
+    }
+
+    Response {
+        code: 200,
+        message: Some("organization_deleted".into()),
+        data: None,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/orgs/{id}/invitations",
+    params(("id" = i32, Path, description = "ID организации")),
+    request_body = CreateInvitation,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Приглашение отправлено на email, оно действует 7 дней", body = ApiResponse<OrgInvitation>),
+        (status = 400, description = "Неверный email", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов, владельцев приглашают только владельцы", body = ApiError),
+        (status = 404, description = "Организация не найдена", body = ApiError),
+        (status = 409, description = "Пользователь уже состоит в организации", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn invite_member(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Json(body): Json<CreateInvitation>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    let invited_role = body.role.unwrap_or(OrgRole::Member);
+    // Nobody can hand out a role above their own.
+    if let Err(response) = require_role(role, OrgRole::Admin.max(invited_role)) {
+        return response;
+    }
+    let email = match Auth::normalize_email(&body.email) {
+        Some(email) => email,
+        None => {
+            return Response {
+                code: 400,
+                message: Some("invalid_email".into()),
+                data: None,
+            };
+        }
+    };
+
+    if let Ok(Some(invitee)) = app_state.users.find_user_by_email(email.clone()).await {
+        match app_state.orgs.find_member_role(id, invitee.id).await {
+            Ok(Some(_)) => {
+                return Response {
+                    code: 409,
+                    message: Some("user_already_member".into()),
+                    data: None,
+                };
+            }
+            Ok(None) => {}
+            Err(e) => return server_error(e),
+        }
+    }
+    let organization = match app_state.orgs.find_organization(id).await {
+        Ok(Some(organization)) => organization,
+        Ok(None) => return org_not_found(),
+        Err(e) => return server_error(e),
+    };
+
+    let token = Auth::generate_token();
+    let invitation = match app_state
+        .orgs
+        .create_invitation(id, &email, invited_role, &token, user.id, app_state.config.tokens.invitation_secs)
+        .await
+    {
+        Ok(invitation) => invitation,
+        Err(e) => return server_error(e),
+    };
+    let template = EmailTemplate::OrgInvitation {
+        inviter: user.name,
+        organization: organization.name,
+        role: invited_role.as_str().to_string(),
+        link: invitation_link(&app_state.config, &token),
+    };
+    if let Err(e) = enqueue_email(&*app_state.outbox, &email, template).await {
+        return server_error(e);
+    }
+
+    let _ = AuditEvent::new("org.invitation_created", Some(user.id), &addr, &headers)
+        .details(json!({ "organization_id": id, "email": email, "role": invited_role }))
+        .record(&*app_state.audit)
+        .await;
+    Response {
+        code: 200,
+        message: Some("invitation_sent".into()),
+        data: Some(json!(invitation)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/{id}/invitations",
+    params(("id" = i32, Path, description = "ID организации")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Действующие приглашения", body = ApiResponse<InvitationList>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов организации", body = ApiError),
+        (status = 404, description = "Организация не найдена", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn list_invitations(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    if let Err(response) = require_role(role, OrgRole::Admin) {
+        return response;
+    }
+
+    match app_state.orgs.find_invitations(id).await {
+        Ok(invitations) => Response {
+            code: 200,
+            message: Some("invitations_fetched".into()),
+            data: Some(json!(InvitationList { invitations })),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orgs/{id}/invitations/{invitation_id}",
+    params(
+        ("id" = i32, Path, description = "ID организации"),
+        ("invitation_id" = i32, Path, description = "ID приглашения")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Приглашение отозвано", body = ApiMessage),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов организации", body = ApiError),
+        (status = 404, description = "Организация или приглашение не найдены", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn revoke_invitation(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path((id, invitation_id)): Path<(i32, i32)>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    if let Err(response) = require_role(role, OrgRole::Admin) {
+        return response;
+    }
+
+    match app_state.orgs.delete_invitation(id, invitation_id).await {
+        Ok(true) => {
+            let _ = AuditEvent::new("org.invitation_revoked", Some(user.id), &addr, &headers)
+                .details(json!({ "organization_id": id, "invitation_id": invitation_id }))
+                .record(&*app_state.audit)
+                .await;
+            Response {
+                code: 200,
+                message: Some("invitation_revoked".into()),
+                data: None,
+            }
+        }
+        Ok(false) => Response {
+            code: 404,
+            message: Some("invitation_not_found".into()),
+            data: None,
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+fn invalid_invitation() -> Response {
+    Response {
+        code: 400,
+        message: Some("invalid_invitation".into()),
+        data: None,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/invitations",
+    params(InvitationLookup),
+    responses(
+        (status = 200, description = "Организация и роль, в которую приглашают", body = ApiResponse<InvitationPreview>),
+        (status = 400, description = "Приглашение недействительно или устарело", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn lookup_invitation(State(app_state): State<AppState>, Query(query): Query<InvitationLookup>) -> Response {
+    let invitation = match app_state.orgs.find_invitation_by_token(&query.token).await {
+        Ok(Some(invitation)) => invitation,
+        Ok(None) => return invalid_invitation(),
+        Err(e) => return server_error(e),
+    };
+
+    match app_state.orgs.find_organization(invitation.organization_id).await {
+        Ok(Some(organization)) => Response {
+            code: 200,
+            message: Some("invitation_fetched".into()),
+            data: Some(json!(InvitationPreview {
+                organization: organization.name,
+                email: invitation.email,
+                role: invitation.role,
+                expires_at: invitation.expires_at,
+            })),
+        },
+        Ok(None) => invalid_invitation(),
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/orgs/invitations/accept",
+    request_body = AcceptInvitation,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Пользователь вступил в организацию", body = ApiResponse<Membership>),
+        (status = 400, description = "Приглашение недействительно или устарело", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Приглашение отправлено на другой или неподтверждённый email", body = ApiError),
+        (status = 409, description = "Пользователь уже состоит в организации", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn accept_org_invitation(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<AcceptInvitation>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let invitation = match app_state.orgs.find_invitation_by_token(&body.token).await {
+        Ok(Some(invitation)) => invitation,
+        Ok(None) => return invalid_invitation(),
+        Err(e) => return server_error(e),
+    };
+    // The link alone is not enough, it has to be opened by the owner of the address.
+    if user.email_verified_at.is_none() || !user.email.eq_ignore_ascii_case(&invitation.email) {
+        return Response {
+            code: 403,
+            message: Some("invitation_email_mismatch".into()),
+            data: None,
+        };
+    }
+
+    match app_state.orgs.accept_invitation(&invitation, user.id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Response {
+                code: 409,
+                message: Some("already_member".into()),
+                data: None,
+            };
+        }
+        Err(e) => return server_error(e),
+    }
+    let _ = AuditEvent::new("org.invitation_accepted", Some(user.id), &addr, &headers)
+        .details(json!({ "organization_id": invitation.organization_id, "role": invitation.role }))
+        .record(&*app_state.audit)
+        .await;
+
+    match app_state.orgs.find_memberships(user.id).await {
+        Ok(memberships) => Response {
+            code: 200,
+            message: Some("invitation_accepted".into()),
+            data: memberships
+                .into_iter()
+                .find(|membership| membership.organization_id == invitation.organization_id)
+                .map(|membership| json!(membership)),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/orgs/{id}/members/{user_id}",
+    params(
+        ("id" = i32, Path, description = "ID организации"),
+        ("user_id" = i32, Path, description = "ID участника")
+    ),
+    request_body = SetMemberRole,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Роль изменена", body = ApiResponse<MemberList>),
+        (status = 400, description = "У организации должен остаться владелец", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов, владельцами управляют только владельцы", body = ApiError),
+        (status = 404, description = "Организация или участник не найдены", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn set_role(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path((id, user_id)): Path<(i32, i32)>,
+    Json(body): Json<SetMemberRole>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    let current_role = match app_state.orgs.find_member_role(id, user_id).await {
+        Ok(Some(current_role)) => current_role,
+        Ok(None) => return member_not_found(),
+        Err(e) => return server_error(e),
+    };
+    if let Err(response) = require_role(role, OrgRole::Admin.max(current_role).max(body.role)) {
+        return response;
+    }
+    if current_role == OrgRole::Owner && body.role != OrgRole::Owner {
+        match app_state.orgs.count_owners(id).await {
+            Ok(owners) if owners > 1 => {}
+            Ok(_) => return last_owner(),
+            Err(e) => return server_error(e),
+        }
+    }
+
+    match app_state.orgs.set_member_role(id, user_id, body.role).await {
+        Ok(true) => {
+            let _ = AuditEvent::new("org.member_role_changed", Some(user.id), &addr, &headers)
+                .target(user_id)
+                .details(json!({ "organization_id": id, "from": current_role, "to": body.role }))
+                .record(&*app_state.audit)
+                .await;
+            members_response(&app_state, id, "role_updated").await
+        }
+        Ok(false) => member_not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orgs/{id}/members/{user_id}",
+    params(
+        ("id" = i32, Path, description = "ID организации"),
+        ("user_id" = i32, Path, description = "ID участника")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Участник удалён или покинул организацию", body = ApiMessage),
+        (status = 400, description = "У организации должен остаться владелец", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Удалять других могут администраторы, владельцев только владельцы", body = ApiError),
+        (status = 404, description = "Организация или участник не найдены", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn remove_org_member(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    let current_role = match app_state.orgs.find_member_role(id, user_id).await {
+        Ok(Some(current_role)) => current_role,
+        Ok(None) => return member_not_found(),
+        Err(e) => return server_error(e),
+    };
+    // Members may always leave.
+    if user_id != user.id {
+        if let Err(response) = require_role(role, OrgRole::Admin.max(current_role)) {
+            return response;
+        }
+    }
+    if current_role == OrgRole::Owner {
+        match app_state.orgs.count_owners(id).await {
+            Ok(owners) if owners > 1 => {}
+            Ok(_) => return last_owner(),
+            Err(e) => return server_error(e),
+        }
+    }
+
+    match app_state.orgs.remove_member(id, user_id).await {
+        Ok(true) => {
+            let action = match user_id == user.id {
+                true => "org.member_left",
+                false => "org.member_removed",
+            };
+            let _ = AuditEvent::new(action, Some(user.id), &addr, &headers)
+                .target(user_id)
+                .details(json!({ "organization_id": id, "role": current_role }))
+                .record(&*app_state.audit)
+                .await;
+            Response {
+                code: 200,
+                message: Some("member_removed".into()),
+                data: None,
+            }
+        }
+        Ok(false) => member_not_found(),
+        Err(e) => server_error(e),
+    }
+}
+
+fn member_not_found() -> Response {
+    Response {
+        code: 404,
+        message: Some("member_not_found".into()),
+        data: None,
+    }
+}
+
+async fn members_response(app_state: &AppState, organization_id: i32, message: &'static str) -> Response {
+    match app_state.orgs.find_members(organization_id).await {
+        Ok(members) => Response {
+            code: 200,
+            message: Some(message.into()),
+            data: Some(json!(MemberList { members })),
+        },
+        Err(e) => server_error(e),
+    }
+}
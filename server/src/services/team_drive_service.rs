@@ -0,0 +1,518 @@
+use axum::{
+    extract::{multipart::Multipart, ConnectInfo, Path, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response as HttpResponse},
+    Json,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+
+use crate::{
+    config::{
+        api::auth_header,
+        org::normalize_name,
+    },
+    models::{
+        api::{ApiError, ApiMessage, ApiResponse, Response},
+        app::AppState,
+        audit::AuditEvent,
+        files::{FileAction, FileList, FileUpload, UploadedFile},
+        org::{CreateTeamDrive, OrgRole, TeamDrive, TeamDriveList, UpdateTeamDrive},
+        user::User,
+    },
+    services::{
+        org_service::{member_role, require_role, server_error},
+        files_service::stream_file,
+        user_service::current_user,
+    },
+};
+
+/// Resolves the user behind a session or an access token carrying `scope`,
+/// which is enough to work with the files of a drive.
+pub async fn scoped_user(app_state: &AppState, headers: &HeaderMap, scope: &str) -> Result<User, Response> {
+    let verify = auth_header(&app_state.auth, &*app_state.users, headers).await;
+    let user_id = match verify.user_id {
+        Some(user_id) if verify.authorized => user_id,
+        _ => {
+            return Err(Response {
+                code: 401,
+                message: Some("unauthorized".into()),
+                data: None,
+            });
+        }
+    };
+    if !verify.allows(scope) {
+        return Err(Response {
+            code: 403,
+            message: Some("token_scope_denied".into()),
+            data: None,
+        });
+    }
+
+    match app_state.users.find_user_by_id(user_id).await {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(Response {
+            code: 401,
+            message: Some("user_not_found".into()),
+            data: None,
+        }),
+        Err(e) => Err(server_error(e)),
+    }
+}
+
+/// Finds a drive of the organization. Drives of other organizations are not found.
+async fn org_drive(app_state: &AppState, organization_id: i32, drive_id: i32) -> Result<TeamDrive, Response> {
+    match app_state.drives.find_team_drive(drive_id, app_state.config.storage.team_drive_quota_bytes).await {
+        Ok(Some(drive)) if drive.organization_id == organization_id => Ok(drive),
+        Ok(_) => Err(Response {
+            code: 404,
+            message: Some("team_drive_not_found".into()),
+            data: None,
+        }),
+        Err(e) => Err(server_error(e)),
+    }
+}
+
+fn invalid_name() -> Response {
+    Response {
+        code: 400,
+        message: Some("invalid_name_length".into()),
+        data: None,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/orgs/{id}/drives",
+    params(("id" = i32, Path, description = "ID организации")),
+    request_body = CreateTeamDrive,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Командный диск создан", body = ApiResponse<TeamDrive>),
+        (status = 400, description = "Неверное название", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов организации", body = ApiError),
+        (status = 404, description = "Организация не найдена", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn create_drive(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Json(body): Json<CreateTeamDrive>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    if let Err(response) = require_role(role, OrgRole::Admin) {
+        return response;
+    }
+    let name = match normalize_name(&body.name) {
+        Some(name) => name,
+        None => return invalid_name(),
+    };
+
+    match app_state.drives.create_team_drive(id, &name, app_state.config.storage.team_drive_quota_bytes).await {
+        Ok(drive) => {
+            let _ = AuditEvent::new("team_drive.created", Some(user.id), &addr, &headers)
+                .details(json!({ "organization_id": id, "team_drive_id": drive.id, "name": drive.name }))
+                .record(&*app_state.audit)
+                .await;
+            Response {
+                code: 200,
+                message: Some("team_drive_created".into()),
+                data: Some(json!(drive)),
+            }
+        }
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/{id}/drives",
+    params(("id" = i32, Path, description = "ID организации")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Командные диски с занятым местом", body = ApiResponse<TeamDriveList>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:read", body = ApiError),
+        (status = 404, description = "Организация не найдена", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn list_drives(State(app_state): State<AppState>, headers: HeaderMap, Path(id): Path<i32>) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:read").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    if let Err(response) = member_role(&app_state, id, user.id).await {
+        return response;
+    }
+
+    match app_state.drives.find_team_drives(id, app_state.config.storage.team_drive_quota_bytes).await {
+        Ok(drives) => Response {
+            code: 200,
+            message: Some("team_drives_fetched".into()),
+            data: Some(json!(TeamDriveList { drives })),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/orgs/{id}/drives/{drive_id}",
+    params(
+        ("id" = i32, Path, description = "ID организации"),
+        ("drive_id" = i32, Path, description = "ID командного диска")
+    ),
+    request_body = UpdateTeamDrive,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Командный диск переименован", body = ApiResponse<TeamDrive>),
+        (status = 400, description = "Неверное название", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов организации", body = ApiError),
+        (status = 404, description = "Организация или диск не найдены", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn update_drive(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path((id, drive_id)): Path<(i32, i32)>,
+    Json(body): Json<UpdateTeamDrive>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    if let Err(response) = require_role(role, OrgRole::Admin) {
+        return response;
+    }
+    let drive = match org_drive(&app_state, id, drive_id).await {
+        Ok(drive) => drive,
+        Err(response) => return response,
+    };
+    let name = match normalize_name(&body.name) {
+        Some(name) => name,
+        None => return invalid_name(),
+    };
+
+    match app_state.drives.rename_team_drive(drive.id, &name).await {
+        Ok(_) => Response {
+            code: 200,
+            message: Some("team_drive_updated".into()),
+            data: Some(json!(TeamDrive { name, ..drive })),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orgs/{id}/drives/{drive_id}",
+    params(
+        ("id" = i32, Path, description = "ID организации"),
+        ("drive_id" = i32, Path, description = "ID командного диска")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Командный диск удалён вместе с файлами", body = ApiMessage),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Доступ только для администраторов организации", body = ApiError),
+        (status = 404, description = "Организация или диск не найдены", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn delete_drive(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path((id, drive_id)): Path<(i32, i32)>,
+) -> Response {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    if let Err(response) = require_role(role, OrgRole::Admin) {
+        return response;
+    }
+    let drive = match org_drive(&app_state, id, drive_id).await {
+        Ok(drive) => drive,
+        Err(response) => return response,
+    };
+
+    let file_paths = match app_state.drives.delete_team_drive(drive.id).await {
+        Ok(file_paths) => file_paths,
+        Err(e) => return server_error(e),
+    };
+    let _ = AuditEvent::new("team_drive.deleted", Some(user.id), &addr, &headers)
+        .details(json!({ "organization_id": id, "team_drive_id": drive.id, "name": drive.name, "files": file_paths.len() }))
+        .record(&*app_state.audit)
+        .await;
+
+    let removed = FileAction::remove_stored_files(&file_paths).await;
+    if removed.is_error {
+        tracing::warn!(drive_id = drive.id, files = ?removed.data, "error deleting some files from disk for deleted team drive");
+    }
+
+    Response {
+        code: 200,
+        message: Some("team_drive_deleted".into()),
+        data: None,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/orgs/{id}/drives/{drive_id}/files",
+    params(
+        ("id" = i32, Path, description = "ID организации"),
+        ("drive_id" = i32, Path, description = "ID командного диска")
+    ),
+    request_body(content = FileUpload, content_type = "multipart/form-data"),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Файл загружен на командный диск", body = ApiResponse<UploadedFile>),
+        (status = 400, description = "Нет файла или превышена квота диска", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Email не подтверждён или токен не имеет права files:write", body = ApiError),
+        (status = 404, description = "Организация или диск не найдены", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn upload_drive_file(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path((id, drive_id)): Path<(i32, i32)>,
+    multipart: Multipart,
+) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:write").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    if user.email_verified_at.is_none() {
+        return Response {
+            code: 403,
+            message: Some("email_not_verified".into()),
+            data: None,
+        };
+    }
+    if let Err(response) = member_role(&app_state, id, user.id).await {
+        return response;
+    }
+    let drive = match org_drive(&app_state, id, drive_id).await {
+        Ok(drive) => drive,
+        Err(response) => return response,
+    };
+
+    let file_response =
+        FileAction::upload_file(&*app_state.files, &app_state.config.storage.root, multipart, user.id, Some(drive.id), None, drive.effective_quota_bytes).await;
+    if file_response.is_error {
+        return Response {
+            code: 400,
+            message: file_response.error_message,
+            data: None,
+        };
+    }
+
+    let mut details = file_response.data.clone().unwrap_or_default();
+    details["team_drive_id"] = json!(drive.id);
+    let _ = AuditEvent::new("file.uploaded", Some(user.id), &addr, &headers)
+        .details(details)
+        .record(&*app_state.audit)
+        .await;
+    Response {
+        code: 200,
+        message: Some("file_uploaded".into()),
+        data: file_response.data,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/{id}/drives/{drive_id}/files",
+    params(
+        ("id" = i32, Path, description = "ID организации"),
+        ("drive_id" = i32, Path, description = "ID командного диска")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Файлы командного диска", body = ApiResponse<FileList>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:read", body = ApiError),
+        (status = 404, description = "Организация или диск не найдены", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn list_drive_files(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path((id, drive_id)): Path<(i32, i32)>,
+) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:read").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    if let Err(response) = member_role(&app_state, id, user.id).await {
+        return response;
+    }
+    let drive = match org_drive(&app_state, id, drive_id).await {
+        Ok(drive) => drive,
+        Err(response) => return response,
+    };
+
+    match app_state.drives.find_team_drive_files(drive.id).await {
+        Ok(files) => Response {
+            code: 200,
+            message: Some("files_found".into()),
+            data: Some(json!(FileList { files })),
+        },
+        Err(e) => server_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/{id}/drives/{drive_id}/files/{file_id}",
+    params(
+        ("id" = i32, Path, description = "ID организации"),
+        ("drive_id" = i32, Path, description = "ID командного диска"),
+        ("file_id" = i32, Path, description = "ID файла")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Содержимое файла", content_type = "application/octet-stream"),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен не имеет права files:read", body = ApiError),
+        (status = 404, description = "Организация, диск или файл не найдены", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn download_drive_file(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path((id, drive_id, file_id)): Path<(i32, i32, i32)>,
+) -> HttpResponse {
+    let user = match scoped_user(&app_state, &headers, "files:read").await {
+        Ok(user) => user,
+        Err(response) => return response.into_response(),
+    };
+    if let Err(response) = member_role(&app_state, id, user.id).await {
+        return response.into_response();
+    }
+    let drive = match org_drive(&app_state, id, drive_id).await {
+        Ok(drive) => drive,
+        Err(response) => return response.into_response(),
+    };
+
+    match app_state.drives.find_team_drive_file(drive.id, file_id).await {
+        Ok(Some(file)) => stream_file(&file, "team_drive").await,
+        Ok(None) => Response {
+            code: 404,
+            message: Some("file_not_found".into()),
+            data: None,
+        }
+        .into_response(),
+        Err(e) => server_error(e).into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orgs/{id}/drives/{drive_id}/files/{file_id}",
+    params(
+        ("id" = i32, Path, description = "ID организации"),
+        ("drive_id" = i32, Path, description = "ID командного диска"),
+        ("file_id" = i32, Path, description = "ID файла")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Файл удалён", body = ApiMessage),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Удалять чужие файлы могут только администраторы организации", body = ApiError),
+        (status = 404, description = "Организация, диск или файл не найдены", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "orgs"
+)]
+pub async fn delete_drive_file(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path((id, drive_id, file_id)): Path<(i32, i32, i32)>,
+) -> Response {
+    let user = match scoped_user(&app_state, &headers, "files:write").await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let role = match member_role(&app_state, id, user.id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    let drive = match org_drive(&app_state, id, drive_id).await {
+        Ok(drive) => drive,
+        Err(response) => return response,
+    };
+    let file = match app_state.drives.find_team_drive_file(drive.id, file_id).await {
+        Ok(Some(file)) => file,
+        Ok(None) => {
+            return Response {
+                code: 404,
+                message: Some("file_not_found".into()),
+                data: None,
+            };
+        }
+        Err(e) => return server_error(e),
+    };
+    // Members remove their own uploads, admins anything on the drive.
+    if file.user_id != Some(user.id) {
+        if let Err(response) = require_role(role, OrgRole::Admin) {
+            return response;
+        }
+    }
+
+    let file_response = FileAction::delete_file(&*app_state.files, file.id).await;
+    if file_response.is_error {
+        return Response {
+            code: 400,
+            message: file_response.error_message,
+            data: None,
+        };
+    }
+
+    let _ = AuditEvent::new("file.deleted", Some(user.id), &addr, &headers)
+        .details(json!({ "file_id": file.id, "team_drive_id": drive.id }))
+        .record(&*app_state.audit)
+        .await;
+    Response {
+        code: 200,
+        message: Some("file_deleted".into()),
+        data: file_response.data,
+    }
+}
@@ -1,56 +1,524 @@
 use axum::{
-    extract::{Path, State},
+    extract::{ConnectInfo, Path, Query, State},
     http::HeaderMap,
     response::IntoResponse,
     Json,
 };
+use chrono::Utc;
+use std::net::SocketAddr;
 
 use crate::{
-    config::api::auth_header,
-    models::{api::Response, app::AppState}, 
-    repositories::user_repository::find_user_by_id
+    config::{api::{auth_header, bearer_token}, email_verification::send_verification_email, outbox::enqueue_email},
+    models::{
+        api::{ApiError, ApiMessage, ApiResponse, Response},
+        app::AppState,
+        audit::{ActivityQuery, AuditEvent, AuditEventPage},
+        auth::{Auth, Throttle, ThrottleAction},
+        files::FileAction,
+        i18n::{Locale, Message},
+        mail::EmailTemplate,
+        user::{ChangeEmail, ChangePassword, DeleteUser, UpdateUser, User},
+    },
 };
 
+/// Resolves the user behind the bearer token, which must be a full session
+/// rather than a scoped device token.
+pub async fn current_user(app_state: &AppState, headers: &HeaderMap) -> Result<User, Response> {
+    let verify = auth_header(&app_state.auth, &*app_state.users, headers).await;
+    let user_id = match verify.user_id {
+        Some(user_id) if verify.authorized => user_id,
+        _ => {
+            return Err(Response {
+                code: 401,
+                message: Some("unauthorized".into()),
+                data: None,
+            });
+        }
+    };
+    if !verify.is_session() {
+        return Err(Response {
+            code: 403,
+            message: Some("token_scope_denied".into()),
+            data: None,
+        });
+    }
+
+    match app_state.users.find_user_by_id(user_id).await {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(Response {
+            code: 401,
+            message: Some("user_not_found".into()),
+            data: None,
+        }),
+        Err(e) => Err(Response {
+            code: 500,
+            message: Some(Message::server_error(e)),
+            data: None,
+        }),
+    }
+}
+
+/// Confirms that the logged in user is at the keyboard before a sensitive
+/// change: with the password when the account has one. Accounts without a
+/// password, which only log in with an identity provider or magic links,
+/// confirm with a session from a login within `tokens.reauthentication_secs`.
+/// Wrong passwords count towards the same lockout as failed logins.
+async fn reauthenticate(
+    app_state: &AppState,
+    user: &User,
+    password: Option<&str>,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+) -> Result<(), Response> {
+    let hashed_password = match app_state.users.find_password_hash(user.id).await {
+        Ok(hashed_password) => hashed_password,
+        Err(e) => {
+            return Err(Response {
+                code: 500,
+                message: Some(Message::server_error(e)),
+                data: None,
+            });
+        }
+    };
+    let Some(hashed_password) = hashed_password else {
+        let auth_time = bearer_token(headers)
+            .and_then(|token| app_state.auth.verify_jwt(token).ok())
+            .and_then(|claims| claims.auth_time);
+        let max_age = app_state.config.tokens.reauthentication_secs;
+        if auth_time.is_some_and(|auth_time| Utc::now().timestamp() - auth_time <= max_age) {
+            return Ok(());
+        }
+        return Err(Response {
+            code: 401,
+            message: Some("reauthentication_required".into()),
+            data: None,
+        });
+    };
+    let Some(password) = password else {
+        return Err(Response {
+            code: 401,
+            message: Some("reauthentication_required".into()),
+            data: None,
+        });
+    };
+
+    let throttle = Throttle::new(ThrottleAction::Login, &user.email, &addr.ip().to_string());
+    match throttle.retry_after(app_state).await {
+        Ok(None) => {}
+        Ok(Some(retry_after)) => {
+            return Err(Response {
+                code: 429,
+                message: Some("too_many_attempts".into()),
+                data: Some(serde_json::json!({ "retry_after": retry_after })),
+            });
+        }
+        Err(e) => {
+            return Err(Response {
+                code: 500,
+                message: Some(Message::server_error(e)),
+                data: None,
+            });
+        }
+    }
+
+    if !app_state.auth.verify_password(password, &hashed_password).await {
+        let _ = throttle.fail(app_state).await;
+        return Err(Response {
+            code: 401,
+            message: Some("invalid_password".into()),
+            data: None,
+        });
+    }
+
+    let _ = throttle.succeed(app_state).await;
+    Ok(())
+}
+
+/// Получение своего профиля по ID
+#[utoipa::path(
+    get,
+    path = "/user/{id}",
+    params(("id" = i32, Path, description = "ID пользователя")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Профиль пользователя", body = ApiResponse<User>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Можно получить только свой профиль", body = ApiError),
+        (status = 404, description = "Пользователь не найден", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "user"
+)]
 #[axum::debug_handler]
 pub async fn get_user(
     State(app_state): State<AppState>,
     Path(id): Path<i32>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    let verify = auth_header(&headers).await;
+    let verify = auth_header(&app_state.auth, &*app_state.users, &headers).await;
     if !verify.authorized {
-        return Json(Response {
+        return Response {
             code: 401,
-            message: Some("Unauthorized".to_string()),
+            message: Some("unauthorized".into()),
             data: None,
-        });
+        };
     }
 
-    let user = find_user_by_id(&app_state.pool, id).await;
+    let user = app_state.users.find_user_by_id(id).await;
     match user {
         Ok(Some(user)) => {
             if verify.user_id.unwrap() != user.id {
-                return Json(Response {
+                return Response {
                     code: 403,
-                    message: Some("Forbidden".to_string()),
+                    message: Some("forbidden".into()),
                     data: None,
-                });
+                };
             }
-            Json(Response {
+            Response {
                 code: 200,
-                message: Some("User fetched successfully".to_string()),
+                message: Some("user_fetched".into()),
                 data: Some(serde_json::to_value(user).unwrap()),
-            })
+            }
         }
-        Ok(None) => Json(Response {
+        Ok(None) => Response {
             code: 404,
-            message: Some("User not found".to_string()),
+            message: Some("user_not_found".into()),
             data: None,
-        }),
-        Err(e) => Json(Response {
+        },
+        Err(e) => Response {
             code: 500,
-            message: Some(format!("Internal server error: {}", e)),
+            message: Some(Message::server_error(e)),
             data: None,
-        }),
+        },
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/user/me",
+    request_body = UpdateUser,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Профиль обновлён", body = ApiResponse<User>),
+        (status = 400, description = "Неверные данные", body = ApiError),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен устройства не может управлять аккаунтом", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "user"
+)]
+#[axum::debug_handler]
+pub async fn update_me(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<UpdateUser>,
+) -> impl IntoResponse {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let name = match body.name {
+        Some(name) if name.trim().is_empty() => {
+            return Response {
+                code: 400,
+                message: Some("name_required".into()),
+                data: None,
+            };
+        }
+        Some(name) => name.trim().to_string(),
+        None => user.name,
+    };
+    let locale = match body.locale {
+        Some(tag) if tag.trim().is_empty() => None,
+        Some(tag) => match Locale::parse(&tag) {
+            Some(locale) => Some(locale.as_str().to_string()),
+            None => {
+                let locales = Locale::ALL.map(|locale| locale.as_str()).join(", ");
+                return Response {
+                    code: 400,
+                    message: Some(Message::new("invalid_locale").arg("locales", locales)),
+                    data: None,
+                };
+            }
+        },
+        None => user.locale,
+    };
+
+    match app_state.users.update_user(user.id, name, locale).await {
+        Ok(user) => {
+            let _ = AuditEvent::new("user.profile_updated", Some(user.id), &addr, &headers)
+                .record(&*app_state.audit)
+                .await;
+            Response {
+                code: 200,
+                message: Some("user_updated".into()),
+                data: Some(serde_json::to_value(user).unwrap()),
+            }
+        }
+        Err(e) => Response {
+            code: 500,
+            message: Some(Message::server_error(e)),
+            data: None,
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/user/me/password",
+    request_body = ChangePassword,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Пароль изменён", body = ApiMessage),
+        (status = 400, description = "Слишком слабый пароль", body = ApiError),
+        (status = 401, description = "Неверный пароль или нужен повторный вход", body = ApiError),
+        (status = 403, description = "Токен устройства не может управлять аккаунтом", body = ApiError),
+        (status = 429, description = "Слишком много попыток", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "user"
+)]
+#[axum::debug_handler]
+pub async fn change_password(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<ChangePassword>,
+) -> impl IntoResponse {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    if let Err(message) = app_state.auth.check_password_policy(&body.new_password) {
+        return Response {
+            code: 400,
+            message: Some(message),
+            data: None,
+        };
+    }
+
+    if let Err(response) = reauthenticate(&app_state, &user, body.current_password.as_deref(), &headers, addr).await {
+        return response;
+    }
+
+    let hashed_password = app_state.auth.hash_password(&body.new_password).await;
+    match app_state.users.update_password(user.id, hashed_password).await {
+        Ok(_) => {
+            let _ = AuditEvent::new("user.password_changed", Some(user.id), &addr, &headers)
+                .record(&*app_state.audit)
+                .await;
+            let _ = enqueue_email(&*app_state.outbox, &user.email, EmailTemplate::PasswordChanged).await;
+            Response {
+                code: 200,
+                message: Some("password_changed".into()),
+                data: None,
+            }
+        }
+        Err(e) => Response {
+            code: 500,
+            message: Some(Message::server_error(e)),
+            data: None,
+        },
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/user/me",
+    request_body = DeleteUser,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Аккаунт и все файлы удалены", body = ApiMessage),
+        (status = 401, description = "Неверный пароль или нужен повторный вход", body = ApiError),
+        (status = 403, description = "Токен устройства не может управлять аккаунтом", body = ApiError),
+        (status = 409, description = "Пользователь единственный владелец организации с другими участниками", body = ApiError),
+        (status = 429, description = "Слишком много попыток", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "user"
+)]
+#[axum::debug_handler]
+pub async fn delete_me(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<DeleteUser>,
+) -> impl IntoResponse {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = reauthenticate(&app_state, &user, body.password.as_deref(), &headers, addr).await {
+        return response;
+    }
+
+    // Teammates would lose their drives, so ownership has to be handed over first.
+    match app_state.orgs.find_sole_owned_organizations(user.id).await {
+        Ok(organizations) if organizations.is_empty() => {}
+        Ok(organizations) => {
+            return Response {
+                code: 409,
+                message: Some("sole_owner".into()),
+                data: Some(serde_json::json!({ "organizations": organizations })),
+            };
+        }
+        Err(e) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(e)),
+                data: None,
+            };
+        }
+    }
+
+    let file_paths = match app_state.users.delete_user(user.id).await {
+        Ok(file_paths) => file_paths,
+        Err(e) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(e)),
+                data: None,
+            };
+        }
+    };
+
+    let _ = AuditEvent::new("user.deleted", Some(user.id), &addr, &headers)
+        .details(serde_json::json!({ "email": user.email, "files": file_paths.len() }))
+        .record(&*app_state.audit)
+        .await;
+
+    // The account is gone at this point, so leftovers on disk are only logged.
+    let removed = FileAction::remove_stored_files(&file_paths).await;
+    if removed.is_error {
+        tracing::warn!(files = ?removed.data, "error deleting some files from disk for deleted user");
+    }
+
+    Response {
+        code: 200,
+        message: Some("user_deleted".into()),
+        data: None,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/user/me/email",
+    request_body = ChangeEmail,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Письмо для подтверждения нового email отправлено", body = ApiMessage),
+        (status = 400, description = "Неверный email", body = ApiError),
+        (status = 401, description = "Неверный пароль или нужен повторный вход", body = ApiError),
+        (status = 403, description = "Токен устройства не может управлять аккаунтом", body = ApiError),
+        (status = 409, description = "Email уже используется", body = ApiError),
+        (status = 429, description = "Слишком много попыток", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "user"
+)]
+#[axum::debug_handler]
+pub async fn change_email(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<ChangeEmail>,
+) -> impl IntoResponse {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let email = match Auth::normalize_email(&body.email) {
+        Some(email) if email != user.email.to_lowercase() => email,
+        _ => {
+            return Response {
+                code: 400,
+                message: Some("invalid_email".into()),
+                data: None,
+            };
+        }
+    };
+
+    if let Err(response) = reauthenticate(&app_state, &user, body.password.as_deref(), &headers, addr).await {
+        return response;
+    }
+
+    match app_state.users.find_user_by_email(email.clone()).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return Response {
+                code: 409,
+                message: Some("email_in_use".into()),
+                data: None,
+            };
+        }
+        Err(e) => {
+            return Response {
+                code: 500,
+                message: Some(Message::server_error(e)),
+                data: None,
+            };
+        }
+    }
+
+    if let Err(e) = send_verification_email(&app_state, user.id, &email).await {
+        return Response {
+            code: 500,
+            message: Some(Message::server_error(e)),
+            data: None,
+        };
+    }
+    let _ = AuditEvent::new("user.email_change_requested", Some(user.id), &addr, &headers)
+        .details(serde_json::json!({ "email": email }))
+        .record(&*app_state.audit)
+        .await;
+    let _ = enqueue_email(&*app_state.outbox, &user.email, EmailTemplate::EmailChangeRequested { email }).await;
+
+    Response {
+        code: 200,
+        message: Some("email_change_requested".into()),
+        data: None,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/user/me/activity",
+    params(ActivityQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Действия пользователя и события его аккаунта, новые первыми", body = ApiResponse<AuditEventPage>),
+        (status = 401, description = "Неавторизованный доступ", body = ApiError),
+        (status = 403, description = "Токен устройства не может управлять аккаунтом", body = ApiError),
+        (status = 500, description = "Ошибка сервера", body = ApiError)
+    ),
+    tag = "user"
+)]
+pub async fn get_activity(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ActivityQuery>,
+) -> impl IntoResponse {
+    let user = match current_user(&app_state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    match app_state.audit.find_user_events(user.id, query.before_id, limit).await {
+        Ok(events) => Response {
+            code: 200,
+            message: Some("activity_fetched".into()),
+            data: Some(serde_json::json!(AuditEventPage::new(events, limit))),
+        },
+        Err(e) => Response {
+            code: 500,
+            message: Some(Message::server_error(e)),
+            data: None,
+        },
     }
 }
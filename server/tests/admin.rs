@@ -0,0 +1,153 @@
+mod common;
+
+use axum::http::Method;
+use common::{text_after, TestApp, TestResponse, PASSWORD};
+use serde_json::json;
+
+/// Registers an account and grants it admin rights the way the readme does.
+async fn admin(app: &TestApp, email: &str) -> String {
+    let token = app.verified_user(email).await;
+    for user in app.data().users.iter_mut().filter(|user| user.email == email) {
+        user.is_admin = true;
+    }
+    token
+}
+
+fn user_id(app: &TestApp, token: &str) -> i32 {
+    app.state.auth.verify_jwt(token).unwrap().sub
+}
+
+async fn set_admin(app: &TestApp, token: &str, id: i32, is_admin: bool) -> TestResponse {
+    app.request(
+        Method::PUT,
+        &format!("/admin/users/{}/admin", id),
+        Some(token),
+        Some(json!({ "is_admin": is_admin })),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn the_admin_api_is_for_admins_only() {
+    let app = TestApp::new();
+    let bob = app.verified_user("bob@example.com").await;
+    let bob_id = user_id(&app, &bob);
+
+    for uri in ["/admin/users", "/admin/stats", "/admin/audit", &format!("/admin/users/{}/files", bob_id)] {
+        assert_eq!(app.request(Method::GET, uri, None, None).await.code(), 401, "{}", uri);
+        let refused = app.request(Method::GET, uri, Some(&bob), None).await;
+        assert_eq!(refused.code(), 403, "{}", uri);
+        assert_eq!(refused.json()["error"], "forbidden");
+    }
+    assert_eq!(set_admin(&app, &bob, bob_id, true).await.code(), 403);
+
+    let alice = admin(&app, "alice@example.com").await;
+    let users = app.request(Method::GET, "/admin/users?search=bob", Some(&alice), None).await;
+    assert_eq!(users.code(), 200, "{}", users.json());
+    assert_eq!(users.json()["data"]["users"].as_array().unwrap().len(), 1);
+    assert_eq!(users.json()["data"]["users"][0]["email"], "bob@example.com");
+}
+
+#[tokio::test]
+async fn disabled_accounts_lose_their_sessions() {
+    let app = TestApp::new();
+    let alice = admin(&app, "alice@example.com").await;
+    let bob = app.verified_user("bob@example.com").await;
+    let bob_id = user_id(&app, &bob);
+
+    let myself = app
+        .request(Method::POST, &format!("/admin/users/{}/disable", user_id(&app, &alice)), Some(&alice), None)
+        .await;
+    assert_eq!(myself.json()["error"], "cannot_disable_self");
+
+    let disabled = app
+        .request(Method::POST, &format!("/admin/users/{}/disable", bob_id), Some(&alice), None)
+        .await;
+    assert_eq!(disabled.code(), 200, "{}", disabled.json());
+    assert!(disabled.json()["data"]["disabled_at"].is_string());
+    assert_eq!(app.request(Method::GET, "/files/quota", Some(&bob), None).await.code(), 401);
+    let login = app.login("bob@example.com", PASSWORD).await;
+    assert_eq!(login.code(), 403);
+    assert_eq!(login.json()["error"], "account_disabled");
+    let event = app.data().audit_events.iter().find(|event| event.action == "admin.user_disabled").cloned();
+    assert_eq!(event.unwrap().target_user_id, Some(bob_id));
+
+    let enabled = app
+        .request(Method::POST, &format!("/admin/users/{}/enable", bob_id), Some(&alice), None)
+        .await;
+    assert_eq!(enabled.code(), 200);
+    assert_eq!(app.login("bob@example.com", PASSWORD).await.code(), 200);
+}
+
+#[tokio::test]
+async fn a_forced_reset_blocks_the_account_until_the_password_changes() {
+    const NEW_PASSWORD: &str = "Nw5$kTq9@xLm";
+    let app = TestApp::new();
+    let alice = admin(&app, "alice@example.com").await;
+    let bob = app.verified_user("bob@example.com").await;
+
+    let forced = app
+        .request(
+            Method::POST,
+            &format!("/admin/users/{}/force-password-reset", user_id(&app, &bob)),
+            Some(&alice),
+            None,
+        )
+        .await;
+    assert_eq!(forced.code(), 200, "{}", forced.json());
+    assert_eq!(forced.json()["data"]["password_reset_required"], true);
+    assert_eq!(app.login("bob@example.com", PASSWORD).await.json()["error"], "password_reset_required");
+
+    let emails = app.emails_to("bob@example.com").await;
+    let code = text_after(&emails.last().unwrap().text_body, "this code: ");
+    let reset = app
+        .request(
+            Method::POST,
+            "/auth/reset-password",
+            None,
+            Some(json!({ "email": "bob@example.com", "code": code, "new_password": NEW_PASSWORD })),
+        )
+        .await;
+    assert_eq!(reset.code(), 200, "{}", reset.json());
+    assert_eq!(app.login("bob@example.com", NEW_PASSWORD).await.code(), 200);
+}
+
+#[tokio::test]
+async fn quotas_are_set_per_user() {
+    let app = TestApp::new();
+    let alice = admin(&app, "alice@example.com").await;
+    let bob = app.verified_user("bob@example.com").await;
+    let quota_uri = format!("/admin/users/{}/quota", user_id(&app, &bob));
+    let set_quota = |quota_bytes| {
+        app.request(Method::PUT, &quota_uri, Some(&alice), Some(json!({ "quota_bytes": quota_bytes })))
+    };
+
+    assert_eq!(set_quota(json!(-1)).await.json()["error"], "negative_quota");
+    let limited = set_quota(json!(5)).await;
+    assert_eq!(limited.code(), 200, "{}", limited.json());
+    assert_eq!(limited.json()["data"]["effective_quota_bytes"], 5);
+    let upload = app.upload(&bob, "notes.txt", b"more than five bytes").await;
+    assert_eq!(upload.json()["error"], "quota_exceeded");
+
+    let restored = set_quota(json!(null)).await;
+    assert_eq!(restored.json()["data"]["effective_quota_bytes"], app.state.config.storage.quota_bytes);
+    assert_eq!(app.upload(&bob, "notes.txt", b"more than five bytes").await.code(), 200);
+
+    let stats = app.request(Method::GET, "/admin/stats", Some(&alice), None).await.json();
+    assert_eq!(stats["data"]["users"], 2);
+    assert_eq!(stats["data"]["files"], 1);
+    assert_eq!(stats["data"]["bytes"], 20);
+}
+
+#[tokio::test]
+async fn admins_grant_the_role_but_cannot_drop_their_own() {
+    let app = TestApp::new();
+    let alice = admin(&app, "alice@example.com").await;
+    let bob = app.verified_user("bob@example.com").await;
+    let own = set_admin(&app, &alice, user_id(&app, &alice), false).await;
+    assert_eq!(own.json()["error"], "cannot_revoke_own_admin");
+    assert_eq!(set_admin(&app, &alice, user_id(&app, &bob), true).await.json()["data"]["is_admin"], true);
+    assert_eq!(app.request(Method::GET, "/admin/users", Some(&bob), None).await.code(), 200);
+    assert_eq!(set_admin(&app, &bob, user_id(&app, &alice), false).await.json()["data"]["is_admin"], false);
+    assert_eq!(app.request(Method::GET, "/admin/users", Some(&alice), None).await.code(), 403);
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request},
+};
+use common::{TestApp, PASSWORD};
+use serde_json::{json, Value};
+
+async fn admin(app: &TestApp, email: &str) -> String {
+    let token = app.verified_user(email).await;
+    for user in app.data().users.iter_mut().filter(|user| user.email == email) {
+        user.is_admin = true;
+    }
+    token
+}
+
+fn actions(page: &Value) -> Vec<String> {
+    let events = page["data"]["events"].as_array().unwrap();
+    events.iter().map(|event| event["action"].as_str().unwrap().to_string()).collect()
+}
+
+#[tokio::test]
+async fn the_csv_export_defuses_formulas() {
+    let app = TestApp::new();
+    let alice = admin(&app, "alice@example.com").await;
+    let login = Request::builder()
+        .method(Method::POST)
+        .uri("/auth/login")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::USER_AGENT, r#"=HYPERLINK("http://evil.example","x")"#)
+        .body(Body::from(json!({ "email": "alice@example.com", "password": "wrong" }).to_string()))
+        .unwrap();
+    assert_eq!(app.send(login).await.code(), 401);
+
+    let unknown = app
+        .request(Method::GET, "/admin/audit/export?format=xlsx", Some(&alice), None)
+        .await;
+    assert_eq!(unknown.json()["error"], "invalid_export_format");
+    let export = app
+        .request(Method::GET, "/admin/audit/export?format=csv&action=auth.login_failed", Some(&alice), None)
+        .await;
+    assert_eq!(export.headers[header::CONTENT_TYPE], "text/csv; charset=utf-8");
+    let csv = String::from_utf8(export.body).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("id,created_at,actor_id,action,target_user_id,ip,user_agent,details"));
+    let row = lines.next().unwrap();
+    assert!(row.contains(r#","'=HYPERLINK(""http://evil.example"",""x"")","#), "{}", row);
+    assert_eq!(lines.next(), None);
+
+    // Exports are audited too.
+    let exports = app
+        .request(Method::GET, "/admin/audit/export?action=admin.audit_exported", Some(&alice), None)
+        .await;
+    let lines: Vec<Value> = String::from_utf8(exports.body)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["details"]["format"], "csv");
+}
+
+#[tokio::test]
+async fn audit_events_are_filtered_and_paged() {
+    let app = TestApp::new();
+    let alice = admin(&app, "alice@example.com").await;
+    app.verified_user("bob@example.com").await;
+    app.login("bob@example.com", "wrong").await;
+
+    let auth = app.request(Method::GET, "/admin/audit?action=auth", Some(&alice), None).await.json();
+    assert!(actions(&auth).iter().all(|action| action.starts_with("auth.")));
+    assert!(actions(&auth).contains(&"auth.login_failed".to_string()));
+
+    let first = app.request(Method::GET, "/admin/audit?limit=2", Some(&alice), None).await.json();
+    assert_eq!(actions(&first).len(), 2);
+    let before_id = first["data"]["next_before_id"].as_i64().unwrap();
+    let next = app
+        .request(Method::GET, &format!("/admin/audit?limit=2&before_id={}", before_id), Some(&alice), None)
+        .await
+        .json();
+    let events = next["data"]["events"].as_array().unwrap();
+    assert!(!events.is_empty());
+    assert!(events.iter().all(|event| event["id"].as_i64().unwrap() < before_id));
+}
+
+#[tokio::test]
+async fn users_see_the_activity_of_their_own_account() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+    let bob = app.verified_user("bob@example.com").await;
+    app.login("alice@example.com", "wrong").await;
+    app.upload(&alice, "notes.txt", b"hello").await;
+    app.login("bob@example.com", PASSWORD).await;
+
+    let activity = app.request(Method::GET, "/user/me/activity", Some(&alice), None).await.json();
+    let actions = actions(&activity);
+    // Newest first.
+    assert_eq!(actions[0], "file.uploaded");
+    assert_eq!(actions[1], "auth.login_failed");
+    assert!(actions.contains(&"user.registered".to_string()));
+    let bob_id = app.state.auth.verify_jwt(&bob).unwrap().sub;
+    let events = activity["data"]["events"].as_array().unwrap();
+    assert!(events.iter().all(|event| event["actor_id"] != bob_id && event["target_user_id"] != bob_id));
+}
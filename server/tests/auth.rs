@@ -0,0 +1,299 @@
+mod common;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use axum::http::Method;
+use chrono::{Duration, Utc};
+use common::{text_after, TestApp, TestResponse, PASSWORD};
+use serde_json::json;
+
+const NEW_PASSWORD: &str = "Nw5$kTq9@xLm";
+
+async fn verify_email(app: &TestApp, token: &str) -> TestResponse {
+    app.request(Method::GET, &format!("/auth/verify-email?token={}", token), None, None).await
+}
+
+#[tokio::test]
+async fn password_reset_with_the_emailed_code() {
+    let app = TestApp::new();
+    app.verified_user("alice@example.com").await;
+
+    let forgot = app
+        .request(Method::POST, "/auth/forgot-password", None, Some(json!({ "email": "alice@example.com" })))
+        .await;
+    assert_eq!(forgot.code(), 200, "{}", forgot.json());
+    let emails = app.emails_to("alice@example.com").await;
+    let code = text_after(&emails.last().unwrap().text_body, "code is: ");
+
+    let reset = app
+        .request(
+            Method::POST,
+            "/auth/reset-password",
+            None,
+            Some(json!({ "email": "alice@example.com", "code": code, "new_password": NEW_PASSWORD })),
+        )
+        .await;
+    assert_eq!(reset.code(), 200, "{}", reset.json());
+
+    assert_eq!(app.login("alice@example.com", PASSWORD).await.code(), 401);
+    assert_eq!(app.login("alice@example.com", NEW_PASSWORD).await.code(), 200);
+
+    // The code is used up.
+    let reused = app
+        .request(
+            Method::POST,
+            "/auth/reset-password",
+            None,
+            Some(json!({ "email": "alice@example.com", "code": code, "new_password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(reused.code(), 400);
+}
+
+#[tokio::test]
+async fn password_reset_rejects_a_wrong_or_expired_code() {
+    let app = TestApp::new();
+    app.verified_user("alice@example.com").await;
+    app.request(Method::POST, "/auth/forgot-password", None, Some(json!({ "email": "alice@example.com" })))
+        .await;
+    let emails = app.emails_to("alice@example.com").await;
+    let code = text_after(&emails.last().unwrap().text_body, "code is: ");
+    assert_eq!(code.len(), 8);
+    let wrong_code = if code == "00000000" { "00000001" } else { "00000000" };
+    let reset = |code: String| {
+        app.request(
+            Method::POST,
+            "/auth/reset-password",
+            None,
+            Some(json!({ "email": "alice@example.com", "code": code, "new_password": NEW_PASSWORD })),
+        )
+    };
+
+    let wrong = reset(wrong_code.to_string()).await;
+    assert_eq!(wrong.code(), 400);
+    assert_eq!(wrong.json()["error"], "invalid_email_or_code");
+
+    app.data().codes[0].expires_at = Utc::now() - Duration::seconds(1);
+    let expired = reset(code).await;
+    assert_eq!(expired.code(), 400);
+    assert_eq!(expired.json()["error"], "invalid_email_or_code");
+    assert_eq!(app.login("alice@example.com", PASSWORD).await.code(), 200);
+}
+
+#[tokio::test]
+async fn login_rejects_a_wrong_password() {
+    let app = TestApp::new();
+    app.verified_user("alice@example.com").await;
+
+    let login = app.login("alice@example.com", NEW_PASSWORD).await;
+    assert_eq!(login.code(), 401);
+    assert_eq!(login.json()["error"], "invalid_credentials");
+}
+
+#[tokio::test]
+async fn repeated_failures_lock_the_account_for_doubling_periods() {
+    let app = TestApp::new();
+    app.verified_user("alice@example.com").await;
+
+    for _ in 0..5 {
+        assert_eq!(app.login("alice@example.com", NEW_PASSWORD).await.code(), 401);
+    }
+    // Even the right password waits out the lockout.
+    let locked = app.login("alice@example.com", PASSWORD).await;
+    assert_eq!(locked.code(), 429);
+    assert_eq!(locked.json()["error"], "too_many_attempts");
+    let retry_after = locked.json()["data"]["retry_after"].as_i64().unwrap();
+    assert!((55..=60).contains(&retry_after), "{}", retry_after);
+    let emails = app.emails_to("alice@example.com").await;
+    assert_eq!(emails.last().unwrap().subject, "Your account has been temporarily locked");
+
+    let lift_locks = || {
+        for throttle in app.data().throttles.iter_mut() {
+            throttle.locked_until = Some(Utc::now() - Duration::seconds(1));
+        }
+    };
+    lift_locks();
+    assert_eq!(app.login("alice@example.com", NEW_PASSWORD).await.code(), 401);
+    let retry_after = app.login("alice@example.com", PASSWORD).await.json()["data"]["retry_after"].as_i64().unwrap();
+    assert!((115..=120).contains(&retry_after), "{}", retry_after);
+    // Only the first lockout is emailed.
+    assert_eq!(app.emails_to("alice@example.com").await.len(), emails.len());
+
+    lift_locks();
+    assert_eq!(app.login("alice@example.com", PASSWORD).await.code(), 200);
+    assert!(app.data().throttles.iter().all(|throttle| throttle.scope != "account"));
+}
+
+#[tokio::test]
+async fn waiting_out_the_longest_lockout_keeps_the_count() {
+    let app = TestApp::new();
+    app.verified_user("alice@example.com").await;
+
+    // Five failures lock, every further one doubles the lockout up to an hour.
+    for _ in 0..11 {
+        for throttle in app.data().throttles.iter_mut() {
+            throttle.locked_until = None;
+        }
+        assert_eq!(app.login("alice@example.com", NEW_PASSWORD).await.code(), 401);
+    }
+    let retry_after = app.login("alice@example.com", PASSWORD).await.json()["data"]["retry_after"].as_i64().unwrap();
+    assert!((3595..=3600).contains(&retry_after), "{}", retry_after);
+
+    for throttle in app.data().throttles.iter_mut() {
+        throttle.last_failed_at -= Duration::minutes(61);
+        throttle.locked_until = Some(Utc::now() - Duration::minutes(1));
+    }
+    assert_eq!(app.login("alice@example.com", NEW_PASSWORD).await.code(), 401);
+    let retry_after = app.login("alice@example.com", PASSWORD).await.json()["data"]["retry_after"].as_i64().unwrap();
+    assert!((3595..=3600).contains(&retry_after), "{}", retry_after);
+}
+
+#[tokio::test]
+async fn a_session_is_renewed_without_a_new_login() {
+    let app = TestApp::new();
+    let token = app.verified_user("alice@example.com").await;
+    let id = app.state.auth.verify_jwt(&token).unwrap().sub;
+    let logged_in_at = Utc::now().timestamp() - 3600;
+    let old = app.state.auth.generate_jwt_authenticated_at(id, Some(logged_in_at));
+
+    let renewed = app.request(Method::POST, "/auth/refresh", Some(&old), None).await;
+    assert_eq!(renewed.code(), 200, "{}", renewed.json());
+    let renewed = renewed.json()["data"]["token"].as_str().unwrap().to_string();
+    assert_eq!(app.state.auth.verify_jwt(&renewed).unwrap().auth_time, Some(logged_in_at));
+    assert_eq!(app.request(Method::GET, &format!("/user/{}", id), Some(&renewed), None).await.code(), 200);
+
+    let device = app.state.auth.generate_access_token(id, "files:read", 60);
+    assert_eq!(app.request(Method::POST, "/auth/refresh", Some(&device), None).await.code(), 403);
+    assert_eq!(app.request(Method::POST, "/auth/refresh", Some("forged"), None).await.code(), 401);
+}
+
+#[tokio::test]
+async fn verification_links_expire_and_work_once() {
+    let app = TestApp::new();
+    let registered = app
+        .request(
+            Method::POST,
+            "/auth/register",
+            None,
+            Some(json!({ "email": "alice@example.com", "password": PASSWORD, "name": "Alice" })),
+        )
+        .await;
+    assert_eq!(registered.code(), 201);
+    let emails = app.emails_to("alice@example.com").await;
+    let expired = text_after(&emails.last().unwrap().text_body, "token=");
+    for verification in app.data().email_verifications.iter_mut() {
+        verification.expires_at = Utc::now() - Duration::seconds(1);
+    }
+    let refused = verify_email(&app, &expired).await;
+    assert_eq!(refused.code(), 400);
+    assert_eq!(refused.json()["error"], "invalid_link");
+
+    let session = app.login("alice@example.com", PASSWORD).await.json()["data"]["token"].clone();
+    let resent = app
+        .request(Method::POST, "/auth/resend-verification", session.as_str(), None)
+        .await;
+    assert_eq!(resent.code(), 200, "{}", resent.json());
+    let emails = app.emails_to("alice@example.com").await;
+    let token = text_after(&emails.last().unwrap().text_body, "token=");
+    assert_ne!(token, expired);
+
+    let verified = verify_email(&app, &token).await;
+    assert_eq!(verified.code(), 200, "{}", verified.json());
+    assert_eq!(verified.json()["data"]["email"], "alice@example.com");
+    assert_eq!(verify_email(&app, &token).await.code(), 400);
+    let again = app
+        .request(Method::POST, "/auth/resend-verification", session.as_str(), None)
+        .await;
+    assert_eq!(again.json()["error"], "email_already_verified");
+}
+
+#[tokio::test]
+async fn logins_upgrade_bcrypt_and_outdated_argon2_hashes() {
+    let app = TestApp::new();
+    app.verified_user("alice@example.com").await;
+    let stored_hash = |app: &TestApp| app.data().users[0].password.clone().unwrap();
+    let current_hash = stored_hash(&app);
+    assert!(!app.state.auth.password_needs_rehash(&current_hash));
+
+    let bcrypt_hash = bcrypt::hash(PASSWORD, 4).unwrap();
+    let params = Params::new(16, 1, 1, None).unwrap();
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password(PASSWORD.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+    for outdated in [bcrypt_hash, argon2_hash] {
+        app.data().users[0].password = Some(outdated.clone());
+        assert_eq!(app.login("alice@example.com", PASSWORD).await.code(), 200);
+        let upgraded = stored_hash(&app);
+        assert_ne!(upgraded, outdated);
+        assert!(upgraded.starts_with("$argon2id$v=19$m=8,t=1,p=1$"), "{}", upgraded);
+        assert_eq!(app.login("alice@example.com", PASSWORD).await.code(), 200);
+    }
+
+    // A current hash is left alone.
+    let current_hash = stored_hash(&app);
+    app.login("alice@example.com", PASSWORD).await;
+    assert_eq!(stored_hash(&app), current_hash);
+}
+
+/// The token of the last sign-in link sent to `email`.
+async fn magic_link_token(app: &TestApp, email: &str) -> String {
+    let emails = app.emails_to(email).await;
+    let text = &emails.last().expect("no sign-in link").text_body;
+    let start = text.find("token=").expect("no token in the link") + "token=".len();
+    text[start..].split_whitespace().next().unwrap().to_string()
+}
+
+async fn magic_link_login(app: &TestApp, token: &str) -> TestResponse {
+    app.request(Method::GET, &format!("/auth/magic-link/login?token={}", token), None, None).await
+}
+
+#[tokio::test]
+async fn magic_links_log_in_once_before_they_expire() {
+    let app = TestApp::new();
+    let request = |email: &str| app.request(Method::POST, "/auth/magic-link", None, Some(json!({ "email": email })));
+    // Unknown addresses get the same answer and no email.
+    assert_eq!(request("nobody@example.com").await.code(), 200);
+    assert!(app.emails_to("nobody@example.com").await.is_empty());
+
+    app.verified_user("alice@example.com").await;
+    assert_eq!(request("alice@example.com").await.code(), 200);
+    let token = magic_link_token(&app, "alice@example.com").await;
+    let tampered = format!("{}x", token);
+    assert_eq!(magic_link_login(&app, &tampered).await.json()["error"], "invalid_link");
+
+    let login = magic_link_login(&app, &token).await;
+    assert_eq!(login.code(), 200, "{}", login.json());
+    let session = login.json()["data"]["token"].as_str().unwrap().to_string();
+    assert_eq!(app.request(Method::GET, "/files/quota", Some(&session), None).await.code(), 200);
+    let reused = magic_link_login(&app, &token).await;
+    assert_eq!(reused.code(), 400);
+    assert_eq!(reused.json()["error"], "invalid_link");
+
+    request("alice@example.com").await;
+    let token = magic_link_token(&app, "alice@example.com").await;
+    for link in app.data().magic_links.iter_mut() {
+        link.expires_at = Utc::now() - Duration::seconds(1);
+    }
+    assert_eq!(magic_link_login(&app, &token).await.json()["error"], "invalid_link");
+}
+
+#[tokio::test]
+async fn a_magic_link_verifies_the_address() {
+    let app = TestApp::new();
+    app.request(
+        Method::POST,
+        "/auth/register",
+        None,
+        Some(json!({ "email": "alice@example.com", "password": PASSWORD, "name": "Alice" })),
+    )
+    .await;
+    app.request(Method::POST, "/auth/magic-link", None, Some(json!({ "email": "alice@example.com" })))
+        .await;
+    let login = magic_link_login(&app, &magic_link_token(&app, "alice@example.com").await).await;
+    let session = login.json()["data"]["token"].as_str().unwrap().to_string();
+    assert_eq!(app.upload(&session, "notes.txt", b"hello").await.code(), 200);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use axum::http::Method;
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn requests_without_a_valid_token_are_rejected() {
+    let app = TestApp::new();
+
+    for token in [None, Some("not-a-token")] {
+        let listed = app
+            .request(Method::POST, "/files/get", token, Some(json!({ "file_ids": [-1] })))
+            .await;
+        assert_eq!(listed.code(), 401);
+        let downloaded = app.request(Method::GET, "/files/download?file_id=1", token, None).await;
+        assert_eq!(downloaded.code(), 401);
+        let deleted = app.request(Method::DELETE, "/files/delete?file_id=1", token, None).await;
+        assert_eq!(deleted.code(), 401);
+    }
+    assert_eq!(app.upload("not-a-token", "notes.txt", b"hello").await.code(), 401);
+}
+
+#[tokio::test]
+async fn files_of_other_users_are_out_of_reach() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+    let bob = app.verified_user("bob@example.com").await;
+    let uploaded = app.upload(&alice, "secret.txt", b"alice's").await;
+    let file_id = uploaded.json()["data"]["id"].as_i64().unwrap();
+
+    let listed = app
+        .request(Method::POST, "/files/get", Some(&bob), Some(json!({ "file_ids": [file_id] })))
+        .await;
+    assert_eq!(listed.code(), 400);
+    assert_eq!(listed.json()["error"], "files_not_found");
+
+    let downloaded = app
+        .request(Method::GET, &format!("/files/download?file_id={}", file_id), Some(&bob), None)
+        .await;
+    assert_eq!(downloaded.code(), 404);
+
+    let deleted = app
+        .request(Method::DELETE, &format!("/files/delete?file_id={}", file_id), Some(&bob), None)
+        .await;
+    assert_eq!(deleted.code(), 404);
+
+    let downloaded = app
+        .request(Method::GET, &format!("/files/download?file_id={}", file_id), Some(&alice), None)
+        .await;
+    assert_eq!(downloaded.body, b"alice's");
+}
+
+#[tokio::test]
+async fn files_with_the_same_name_are_kept_apart() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+    let bob = app.verified_user("bob@example.com").await;
+    let alice_file = app.upload(&alice, "report.txt", b"alice's report").await.json()["data"]["id"].clone();
+    let bob_file = app.upload(&bob, "report.txt", b"bob's report").await.json()["data"]["id"].clone();
+
+    let downloaded = app
+        .request(Method::GET, &format!("/files/download?file_id={}", alice_file), Some(&alice), None)
+        .await;
+    assert_eq!(downloaded.body, b"alice's report");
+    let downloaded = app
+        .request(Method::GET, &format!("/files/download?file_id={}", bob_file), Some(&bob), None)
+        .await;
+    assert_eq!(downloaded.body, b"bob's report");
+}
+
+#[tokio::test]
+async fn uploaded_names_cannot_leave_the_storage_root() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+
+    let uploaded = app.upload(&alice, "../escaped.txt", b"hello").await;
+    assert_eq!(uploaded.code(), 200);
+    assert!(!app.storage.path().parent().unwrap().join("escaped.txt").exists());
+    assert_eq!(std::fs::read_dir(app.storage.path()).unwrap().count(), 1);
+}
@@ -0,0 +1,258 @@
+mod common;
+
+use axum::http::Method;
+use chrono::Duration;
+use common::TestApp;
+use serde_json::{json, Value};
+use server::config::changes::compact_changes;
+
+async fn changes(app: &TestApp, token: &str, cursor: i64, limit: i64) -> Value {
+    let response = app
+        .request(Method::GET, &format!("/files/changes?cursor={}&limit={}", cursor, limit), Some(token), None)
+        .await;
+    assert_eq!(response.code(), 200, "{}", response.json());
+    response.json()["data"].clone()
+}
+
+fn kinds(page: &Value) -> Vec<(String, String)> {
+    page["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| (change["kind"].as_str().unwrap().to_string(), change["name"].as_str().unwrap().to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn the_feed_follows_files_and_folders() {
+    let app = TestApp::new();
+    let token = app.verified_user("alice@example.com").await;
+    let other = app.verified_user("bob@example.com").await;
+
+    let folder = app
+        .request(Method::POST, "/folders", Some(&token), Some(json!({ "name": " Photos ", "parent_id": null })))
+        .await;
+    assert_eq!(folder.code(), 200, "{}", folder.json());
+    assert_eq!(folder.json()["data"]["name"], "Photos");
+    let folder_id = folder.json()["data"]["id"].as_i64().unwrap();
+
+    let uploaded = app
+        .upload_to(&token, &format!("/files/upload?folder_id={}", folder_id), "a.txt", b"one")
+        .await;
+    assert_eq!(uploaded.code(), 200, "{}", uploaded.json());
+    let file_id = uploaded.json()["data"]["id"].as_i64().unwrap();
+    let hash = uploaded.json()["data"]["content_hash"].as_str().unwrap().to_string();
+    assert_eq!(hash, "7692c3ad3540bb803c020b3aee66cd8887123234ea0c6e7143c0add73ff431ed");
+    app.upload(&other, "secret.txt", b"bob").await;
+
+    let replaced = app
+        .upload_to(&token, &format!("/files/replace?file_id={}&expected_hash={}", file_id, hash), "a.txt", b"two")
+        .await;
+    assert_eq!(replaced.code(), 200, "{}", replaced.json());
+    let moved = app
+        .request(
+            Method::POST,
+            "/files/move",
+            Some(&token),
+            Some(json!({ "file_id": file_id, "file_name": "b.txt", "folder_id": null })),
+        )
+        .await;
+    assert_eq!(moved.code(), 200, "{}", moved.json());
+    assert_eq!(moved.json()["data"]["folder_id"], Value::Null);
+    app.request(Method::DELETE, &format!("/files/delete?file_id={}", file_id), Some(&token), None)
+        .await;
+    let deleted = app
+        .request(Method::DELETE, &format!("/folders/{}", folder_id), Some(&token), None)
+        .await;
+    assert_eq!(deleted.code(), 200, "{}", deleted.json());
+
+    let page = changes(&app, &token, 0, 1000).await;
+    let expected = [
+        ("created", "Photos"),
+        ("created", "a.txt"),
+        ("modified", "a.txt"),
+        ("moved", "b.txt"),
+        ("deleted", "b.txt"),
+        ("deleted", "Photos"),
+    ];
+    let expected: Vec<(String, String)> = expected.iter().map(|(kind, name)| (kind.to_string(), name.to_string())).collect();
+    assert_eq!(kinds(&page), expected);
+    assert_eq!(page["has_more"], false);
+    assert_eq!(page["changes"][1]["parent_id"], folder_id);
+    assert_eq!(page["changes"][2]["file_size"], 3);
+    assert_ne!(page["changes"][2]["content_hash"], json!(hash));
+
+    // Pages continue from their cursor, an empty page keeps it.
+    let first = changes(&app, &token, 0, 4).await;
+    assert_eq!(first["has_more"], true);
+    let rest = changes(&app, &token, first["cursor"].as_i64().unwrap(), 4).await;
+    assert_eq!(kinds(&rest), expected[4..]);
+    assert_eq!(rest["has_more"], false);
+    let empty = changes(&app, &token, rest["cursor"].as_i64().unwrap(), 4).await;
+    assert_eq!(empty["changes"], json!([]));
+    assert_eq!(empty["cursor"], rest["cursor"]);
+
+    let others = changes(&app, &other, 0, 1000).await;
+    assert_eq!(kinds(&others), [("created".to_string(), "secret.txt".to_string())]);
+}
+
+#[tokio::test]
+async fn stale_replacements_conflict() {
+    let app = TestApp::new();
+    let token = app.verified_user("alice@example.com").await;
+    let uploaded = app.upload(&token, "a.txt", b"one").await;
+    let file_id = uploaded.json()["data"]["id"].as_i64().unwrap();
+    let hash = uploaded.json()["data"]["content_hash"].as_str().unwrap().to_string();
+
+    let replaced = app
+        .upload_to(&token, &format!("/files/replace?file_id={}&expected_hash={}", file_id, hash), "a.txt", b"two")
+        .await;
+    assert_eq!(replaced.code(), 200);
+    let stale = app
+        .upload_to(&token, &format!("/files/replace?file_id={}&expected_hash={}", file_id, hash), "a.txt", b"three")
+        .await;
+    assert_eq!(stale.code(), 409);
+    assert_eq!(stale.json()["error"], "file_changed");
+
+    let downloaded = app
+        .request(Method::GET, &format!("/files/download?file_id={}", file_id), Some(&token), None)
+        .await;
+    assert_eq!(downloaded.body, b"two");
+    // The replaced contents are gone from disk.
+    assert_eq!(std::fs::read_dir(app.storage.path()).unwrap().count(), 1);
+}
+
+#[tokio::test]
+async fn folders_nest_without_cycles() {
+    let app = TestApp::new();
+    let token = app.verified_user("alice@example.com").await;
+    let other = app.verified_user("bob@example.com").await;
+    let create = |name: &str, parent_id: Option<i64>| {
+        app.request(Method::POST, "/folders", Some(&token), Some(json!({ "name": name, "parent_id": parent_id })))
+    };
+
+    let outer = create("outer", None).await.json()["data"]["id"].as_i64().unwrap();
+    let inner = create("inner", Some(outer)).await.json()["data"]["id"].as_i64().unwrap();
+    assert_eq!(create("../x", None).await.json()["error"], "invalid_entry_name");
+
+    let cycle = app
+        .request(
+            Method::PATCH,
+            &format!("/folders/{}", outer),
+            Some(&token),
+            Some(json!({ "name": "outer", "parent_id": inner })),
+        )
+        .await;
+    assert_eq!(cycle.code(), 400);
+    assert_eq!(cycle.json()["error"], "folder_cycle");
+
+    let not_empty = app
+        .request(Method::DELETE, &format!("/folders/{}", outer), Some(&token), None)
+        .await;
+    assert_eq!(not_empty.code(), 409);
+    assert_eq!(not_empty.json()["error"], "folder_not_empty");
+
+    let renamed = app
+        .request(
+            Method::PATCH,
+            &format!("/folders/{}", inner),
+            Some(&token),
+            Some(json!({ "name": "moved", "parent_id": null })),
+        )
+        .await;
+    assert_eq!(renamed.code(), 200, "{}", renamed.json());
+    let listed = app.request(Method::GET, "/folders", Some(&token), None).await.json();
+    let names: Vec<&str> = listed["data"]["folders"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|folder| folder["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["moved", "outer"]);
+
+    // Folders of other users look the same as missing ones.
+    let foreign = app
+        .upload_to(&other, &format!("/files/upload?folder_id={}", outer), "a.txt", b"x")
+        .await;
+    assert_eq!(foreign.code(), 404);
+    assert_eq!(foreign.json()["error"], "folder_not_found");
+    let foreign = app
+        .request(Method::DELETE, &format!("/folders/{}", outer), Some(&other), None)
+        .await;
+    assert_eq!(foreign.code(), 404);
+}
+
+#[tokio::test]
+async fn old_changes_are_compacted() {
+    let app = TestApp::new();
+    let token = app.verified_user("alice@example.com").await;
+    let folder = app
+        .request(Method::POST, "/folders", Some(&token), Some(json!({ "name": "Photos", "parent_id": null })))
+        .await;
+    let folder_id = folder.json()["data"]["id"].as_i64().unwrap();
+    let first = changes(&app, &token, 0, 1000).await["cursor"].as_i64().unwrap();
+    let file_id = app.upload(&token, "a.txt", b"a").await.json()["data"]["id"].as_i64().unwrap();
+    app.request(
+        Method::POST,
+        "/files/move",
+        Some(&token),
+        Some(json!({ "file_id": file_id, "file_name": "a.txt", "folder_id": folder_id })),
+    )
+    .await;
+    let gone = app.upload(&token, "b.txt", b"b").await.json()["data"]["id"].as_i64().unwrap();
+    app.request(Method::DELETE, &format!("/files/delete?file_id={}", gone), Some(&token), None)
+        .await;
+    let last = changes(&app, &token, 0, 1000).await["cursor"].as_i64().unwrap();
+    for change in app.data().changes.iter_mut() {
+        change.change.created_at -= Duration::days(40);
+    }
+    app.upload(&token, "c.txt", b"c").await;
+
+    // The creation of a.txt is superseded by its move, b.txt is deleted.
+    assert_eq!(compact_changes(&*app.state.changes, 30).await.unwrap(), 3);
+    assert_eq!(compact_changes(&*app.state.changes, 30).await.unwrap(), 0);
+    let page = changes(&app, &token, 0, 1000).await;
+    let expected = [("created", "Photos"), ("moved", "a.txt"), ("created", "c.txt")];
+    let expected: Vec<(String, String)> = expected.iter().map(|(kind, name)| (kind.to_string(), name.to_string())).collect();
+    assert_eq!(kinds(&page), expected);
+
+    for uri in [format!("/files/changes?cursor={}", first), format!("/files/events?cursor={}", first)] {
+        let expired = app.request(Method::GET, &uri, Some(&token), None).await;
+        assert_eq!(expired.code(), 410);
+        assert_eq!(expired.json()["error"], "cursor_expired");
+    }
+    assert_eq!(kinds(&changes(&app, &token, last, 1000).await), expected[2..]);
+}
+
+/// Done by the foreign key and the triggers of Postgres.
+#[cfg(feature = "postgres-tests")]
+#[sqlx::test]
+async fn entries_left_in_a_deleted_folder_move_to_the_root(pool: sqlx::PgPool) {
+    let app = TestApp::postgres(pool.clone());
+    let token = app.verified_user("alice@example.com").await;
+    let create = |name: &str, parent_id: Option<i64>| {
+        app.request(Method::POST, "/folders", Some(&token), Some(json!({ "name": name, "parent_id": parent_id })))
+    };
+    let outer = create("outer", None).await.json()["data"]["id"].as_i64().unwrap();
+    create("inner", Some(outer)).await;
+    let file = app.upload_to(&token, &format!("/files/upload?folder_id={}", outer), "a.txt", b"a").await;
+    let file_id = file.json()["data"]["id"].as_i64().unwrap();
+
+    // As when they are put in it while it is deleted.
+    sqlx::query("DELETE FROM folders WHERE id = $1")
+        .bind(outer as i32)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let folder_id: Option<i32> = sqlx::query_scalar("SELECT folder_id FROM files WHERE id = $1")
+        .bind(file_id as i32)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(folder_id, None);
+    let page = changes(&app, &token, 0, 1000).await;
+    let kinds = kinds(&page);
+    let expected = [("deleted", "outer"), ("moved", "inner"), ("moved", "a.txt")];
+    let expected: Vec<(String, String)> = expected.iter().map(|(kind, name)| (kind.to_string(), name.to_string())).collect();
+    assert_eq!(kinds[kinds.len() - 3..], expected);
+}
@@ -0,0 +1,237 @@
+//! Runs the whole API in process, over the in-memory repositories or the
+//! isolated database `#[sqlx::test]` creates for each test, a temporary storage
+//! directory and the in-memory mail transport.
+
+#![allow(dead_code)]
+
+pub mod oidc;
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, MutexGuard},
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::connect_info::MockConnectInfo,
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use server::{
+    config::outbox::deliver_due_emails,
+    models::{
+        app::AppState,
+        auth::Auth,
+        mail::{Email, MailTransport, Mailer},
+        oidc::OidcProviders,
+        repository::{MemoryRepository, MemoryState},
+        settings::{Config, MailTransportKind},
+    },
+    routes::app_router::app_router,
+};
+use sqlx::PgPool;
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+pub const PASSWORD: &str = "Zq8!vLp3#rT";
+const BOUNDARY: &str = "files-box-test-boundary";
+
+pub struct TestApp {
+    pub router: Router,
+    pub state: AppState,
+    /// The repositories of the app, `None` when it runs on Postgres.
+    pub memory: Option<Arc<MemoryRepository>>,
+    pub mailer: Mailer,
+    /// Removed with its files when the test ends.
+    pub storage: TempDir,
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl TestResponse {
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).expect("the response is not JSON")
+    }
+
+    /// The `code` of the JSON body, which carries the outcome.
+    pub fn code(&self) -> i64 {
+        self.json()["code"].as_i64().expect("the response has no code")
+    }
+}
+
+impl TestApp {
+    /// The API over the in-memory repositories.
+    pub fn new() -> TestApp {
+        TestApp::new_with(|_| {})
+    }
+
+    /// The API over the in-memory repositories, with `configure` applied to
+    /// the test configuration.
+    pub fn new_with(configure: impl FnOnce(&mut Config)) -> TestApp {
+        let memory = Arc::new(MemoryRepository::default());
+        let repository = memory.clone();
+        TestApp::build(Some(memory), configure, |config, auth, oidc| {
+            AppState::with_repository(repository, config, auth, oidc)
+        })
+    }
+
+    /// The API over the database of a `#[sqlx::test]`, for the tests of what
+    /// only Postgres does.
+    pub fn postgres(pool: PgPool) -> TestApp {
+        TestApp::postgres_with(pool, |_| {})
+    }
+
+    /// The API over the database of a `#[sqlx::test]`, with `configure`
+    /// applied to the test configuration.
+    pub fn postgres_with(pool: PgPool, configure: impl FnOnce(&mut Config)) -> TestApp {
+        TestApp::build(None, configure, |config, auth, oidc| AppState::new(pool, config, auth, oidc))
+    }
+
+    fn build(
+        memory: Option<Arc<MemoryRepository>>,
+        configure: impl FnOnce(&mut Config),
+        state: impl FnOnce(Config, Auth, OidcProviders) -> AppState,
+    ) -> TestApp {
+        let storage = TempDir::new().expect("cannot create the storage directory");
+        let mut config = Config::default();
+        config.auth.secret_key = Some("test-secret".to_string());
+        config.storage.root = storage.path().to_path_buf();
+        config.mail.transport = Some(MailTransportKind::Memory);
+        // Hashing with the default parameters takes seconds in debug builds.
+        config.password.argon2_memory_kib = 8;
+        config.password.argon2_iterations = 1;
+        configure(&mut config);
+
+        let auth = Auth::load(&config).expect("cannot load the token keys");
+        let oidc = OidcProviders::load(&config);
+        let mailer = Mailer::load(&config.mail).expect("cannot set up the mail transport");
+        let state = state(config, auth, oidc);
+        let router = app_router(&state).layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        TestApp { router, state, memory, mailer, storage }
+    }
+
+    /// The data of the in-memory app, to set up what the API can't.
+    pub fn data(&self) -> MutexGuard<'_, MemoryState> {
+        let memory = self.memory.as_ref().expect("the app runs on Postgres");
+        memory.state.lock().unwrap()
+    }
+
+    pub async fn send(&self, request: Request<Body>) -> TestResponse {
+        let response = self.router.clone().oneshot(request).await.expect("the router failed");
+        let (status, headers) = (response.status(), response.headers().clone());
+        let body = to_bytes(response.into_body(), usize::MAX).await.expect("cannot read the body").to_vec();
+        TestResponse { status, headers, body }
+    }
+
+    pub async fn request(&self, method: Method, uri: &str, token: Option<&str>, body: Option<Value>) -> TestResponse {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let body = match body {
+            Some(body) => {
+                request = request.header(header::CONTENT_TYPE, "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+        self.send(request.body(body).unwrap()).await
+    }
+
+    pub async fn upload(&self, token: &str, file_name: &str, contents: &[u8]) -> TestResponse {
+        self.upload_to(token, "/files/upload", file_name, contents).await
+    }
+
+    /// Posts `fields` as a form, as OAuth clients do. The values are sent as
+    /// they are, so they must not need encoding.
+    pub async fn post_form(&self, uri: &str, fields: &[(&str, &str)]) -> TestResponse {
+        let body = fields
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("&");
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body))
+            .unwrap();
+        self.send(request).await
+    }
+
+    /// Posts a file as the multipart form of uploads to `uri`.
+    pub async fn upload_to(&self, token: &str, uri: &str, file_name: &str, contents: &[u8]) -> TestResponse {
+        let mut body = format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\n\
+             Content-Type: text/plain\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(contents);
+        body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={BOUNDARY}"))
+            .body(Body::from(body))
+            .unwrap();
+        self.send(request).await
+    }
+
+    /// Sends the queued emails, as the outbox worker would, and returns every
+    /// email sent to `to` so far, oldest first.
+    pub async fn emails_to(&self, to: &str) -> Vec<Email> {
+        while deliver_due_emails(&*self.state.outbox, &self.mailer).await > 0 {}
+        let MailTransport::Memory(sent) = &self.mailer.transport else {
+            panic!("the mail transport is not the in-memory one");
+        };
+        let sent = sent.lock().unwrap();
+        sent.iter().filter(|email| email.to == to).cloned().collect()
+    }
+
+    pub async fn login(&self, email: &str, password: &str) -> TestResponse {
+        self.request(
+            Method::POST,
+            "/auth/login",
+            None,
+            Some(json!({ "email": email, "password": password })),
+        )
+        .await
+    }
+
+    /// Registers an account, follows the link of its verification email and
+    /// returns a session token.
+    pub async fn verified_user(&self, email: &str) -> String {
+        let registered = self
+            .request(
+                Method::POST,
+                "/auth/register",
+                None,
+                Some(json!({ "email": email, "password": PASSWORD, "name": "Test" })),
+            )
+            .await;
+        assert_eq!(registered.code(), 201, "{}", registered.json());
+
+        let emails = self.emails_to(email).await;
+        let token = text_after(&emails.last().expect("no verification email").text_body, "token=");
+        let verified = self
+            .request(Method::GET, &format!("/auth/verify-email?token={}", token), None, None)
+            .await;
+        assert_eq!(verified.code(), 200, "{}", verified.json());
+
+        let login = self.login(email, PASSWORD).await;
+        assert_eq!(login.code(), 200, "{}", login.json());
+        login.json()["data"]["token"].as_str().expect("no token").to_string()
+    }
+}
+
+/// The word that follows `marker` in an email, e.g. a code or a token.
+pub fn text_after(text: &str, marker: &str) -> String {
+    let start = text.find(marker).unwrap_or_else(|| panic!("{:?} not found in {:?}", marker, text)) + marker.len();
+    text[start..].chars().take_while(|c| c.is_ascii_alphanumeric()).collect()
+}
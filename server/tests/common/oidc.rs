@@ -0,0 +1,197 @@
+//! An OpenID provider served on a local port, with discovery, JWKS,
+//! authorization and token endpoints, that logs in whoever the test names.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    body::Body,
+    extract::{Form, Query, State},
+    http::{header, Method, Request, StatusCode},
+    response::{IntoResponse, Redirect},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use reqwest::Url;
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use serde_json::{json, Value};
+use server::{config::oidc::code_challenge, models::settings::OidcProviderConfig};
+use tokio::net::TcpListener;
+
+use super::{TestApp, TestResponse};
+
+pub const CLIENT_ID: &str = "files-box";
+const KEY_ID: &str = "mock-key";
+
+/// A code handed out by the authorization endpoint.
+struct Grant {
+    claims: Value,
+    client_id: String,
+    redirect_uri: String,
+    code_challenge: String,
+}
+
+struct Issuer {
+    url: String,
+    key: EncodingKey,
+    jwk: Value,
+    /// The claims of the user the next authorization logs in.
+    user: Mutex<Value>,
+    grants: Mutex<HashMap<String, Grant>>,
+}
+
+pub struct MockIssuer {
+    issuer: Arc<Issuer>,
+}
+
+impl MockIssuer {
+    pub async fn start() -> MockIssuer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        // An uncompressed point: 0x04, then x and y.
+        let point = key_pair.public_key().as_ref();
+        let jwk = json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "kid": KEY_ID,
+            "use": "sig",
+            "alg": "ES256",
+            "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+        });
+        let issuer = Arc::new(Issuer {
+            url,
+            key: EncodingKey::from_ec_der(pkcs8.as_ref()),
+            jwk,
+            user: Mutex::new(Value::Null),
+            grants: Mutex::new(HashMap::new()),
+        });
+
+        let router = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/jwks", get(jwks))
+            .route("/authorize", get(authorize))
+            .route("/token", post(token))
+            .with_state(issuer.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        MockIssuer { issuer }
+    }
+
+    /// The provider configuration of an app that trusts this issuer.
+    pub fn provider(&self) -> OidcProviderConfig {
+        OidcProviderConfig {
+            issuer: self.issuer.url.clone(),
+            client_id: CLIENT_ID.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Makes the next authorization log in the user with these claims, e.g.
+    /// `sub`, `email` and `email_verified`.
+    pub fn log_in_as(&self, claims: Value) {
+        *self.issuer.user.lock().unwrap() = claims;
+    }
+}
+
+async fn discovery(State(issuer): State<Arc<Issuer>>) -> Json<Value> {
+    Json(json!({
+        "issuer": issuer.url,
+        "authorization_endpoint": format!("{}/authorize", issuer.url),
+        "token_endpoint": format!("{}/token", issuer.url),
+        "jwks_uri": format!("{}/jwks", issuer.url),
+    }))
+}
+
+async fn jwks(State(issuer): State<Arc<Issuer>>) -> Json<Value> {
+    Json(json!({ "keys": [issuer.jwk] }))
+}
+
+/// Logs the user in without asking and sends the browser back with a code.
+async fn authorize(State(issuer): State<Arc<Issuer>>, Query(query): Query<HashMap<String, String>>) -> Redirect {
+    let now = Utc::now().timestamp();
+    let mut claims = issuer.user.lock().unwrap().clone();
+    claims["iss"] = json!(issuer.url);
+    claims["aud"] = json!(query["client_id"]);
+    claims["nonce"] = json!(query["nonce"]);
+    claims["iat"] = json!(now);
+    claims["exp"] = json!(now + 300);
+    // A user logged in with `"auth_time": null` gets no login time.
+    if claims.get("auth_time").is_none() {
+        claims["auth_time"] = json!(now);
+    }
+
+    let code = format!("code-{}", issuer.grants.lock().unwrap().len());
+    let grant = Grant {
+        claims,
+        client_id: query["client_id"].clone(),
+        redirect_uri: query["redirect_uri"].clone(),
+        code_challenge: query["code_challenge"].clone(),
+    };
+    issuer.grants.lock().unwrap().insert(code.clone(), grant);
+    let redirect = Url::parse_with_params(&query["redirect_uri"], [("code", &code), ("state", &query["state"])]);
+    Redirect::to(redirect.unwrap().as_str())
+}
+
+/// Redeems a code once, checking the client, the redirect URI and PKCE.
+async fn token(State(issuer): State<Arc<Issuer>>, Form(form): Form<HashMap<String, String>>) -> impl IntoResponse {
+    let grant = issuer.grants.lock().unwrap().remove(&form["code"]);
+    let grant = grant.filter(|grant| {
+        grant.client_id == form["client_id"]
+            && grant.redirect_uri == form["redirect_uri"]
+            && grant.code_challenge == code_challenge(&form["code_verifier"])
+    });
+    let Some(grant) = grant else {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid_grant" })));
+    };
+
+    let mut header = Header::new(Algorithm::ES256);
+    header.kid = Some(KEY_ID.to_string());
+    let id_token = encode(&header, &grant.claims, &issuer.key).unwrap();
+    (StatusCode::OK, Json(json!({ "access_token": "unused", "token_type": "Bearer", "id_token": id_token })))
+}
+
+/// The `name=value` part of a `Set-Cookie` header.
+fn cookie_pair(response: &TestResponse) -> String {
+    let cookie = response.headers[header::SET_COOKIE].to_str().unwrap();
+    cookie.split(';').next().unwrap().to_string()
+}
+
+impl TestApp {
+    /// Logs in through `provider` like a browser: starts the login, lets the
+    /// issuer authorize it and follows the callback with the state cookie.
+    pub async fn oidc_login(&self, provider: &str) -> TestResponse {
+        let (callback, cookie) = self.oidc_authorize(provider).await;
+        self.oidc_callback(&callback, Some(&cookie)).await
+    }
+
+    /// Starts a login and returns the callback the issuer redirects to, with
+    /// the state cookie the app set.
+    pub async fn oidc_authorize(&self, provider: &str) -> (String, String) {
+        let login = self.request(Method::GET, &format!("/auth/oidc/{}/login", provider), None, None).await;
+        let authorization_url = login.headers[header::LOCATION].to_str().unwrap();
+        let http = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap();
+        let authorized = http.get(authorization_url).send().await.unwrap();
+        let callback = Url::parse(authorized.headers()[header::LOCATION].to_str().unwrap()).unwrap();
+        (format!("{}?{}", callback.path(), callback.query().unwrap()), cookie_pair(&login))
+    }
+
+    pub async fn oidc_callback(&self, callback: &str, cookie: Option<&str>) -> TestResponse {
+        let mut request = Request::builder().method(Method::GET).uri(callback);
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, cookie);
+        }
+        self.send(request.body(Body::empty()).unwrap()).await
+    }
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use chrono::{Duration, Utc};
+use common::{TestApp, TestResponse};
+use serde_json::{json, Value};
+
+const GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Starts a device login and returns the device code response.
+async fn request_code(app: &TestApp, scope: &str) -> Value {
+    let code = app.post_form("/auth/device/code", &[("client_id", "cli"), ("scope", scope)]).await;
+    assert_eq!(code.status, StatusCode::OK, "{}", code.json());
+    code.json()
+}
+
+async fn poll(app: &TestApp, device_code: &Value) -> TestResponse {
+    let device_code = device_code.as_str().unwrap();
+    app.post_form(
+        "/auth/device/token",
+        &[("grant_type", GRANT_TYPE), ("device_code", device_code), ("client_id", "cli")],
+    )
+    .await
+}
+
+async fn decide(app: &TestApp, session: &str, user_code: &Value, approve: bool) -> TestResponse {
+    app.request(
+        Method::POST,
+        "/auth/device",
+        Some(session),
+        Some(json!({ "user_code": user_code, "approve": approve })),
+    )
+    .await
+}
+
+/// Lets the next poll through as if the client had waited its interval.
+fn wait_interval(app: &TestApp) {
+    for authorization in app.data().device_authorizations.iter_mut() {
+        authorization.last_polled_at = Some(Utc::now() - Duration::seconds(authorization.interval_secs as i64));
+    }
+}
+
+#[tokio::test]
+async fn an_approved_device_gets_a_token_limited_to_its_scope() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+    let code = request_code(&app, "files:read").await;
+
+    let pending = poll(&app, &code["device_code"]).await;
+    assert_eq!(pending.status, StatusCode::BAD_REQUEST);
+    assert_eq!(pending.json()["error"], "authorization_pending");
+
+    let user_code = code["user_code"].as_str().unwrap();
+    let lookup = app
+        .request(Method::GET, &format!("/auth/device?user_code={}", user_code), Some(&alice), None)
+        .await;
+    assert_eq!(lookup.json()["data"]["client_id"], "cli");
+    assert_eq!(lookup.json()["data"]["scope"], "files:read");
+    assert_eq!(decide(&app, &alice, &code["user_code"], true).await.code(), 200);
+
+    wait_interval(&app);
+    let granted = poll(&app, &code["device_code"]).await;
+    assert_eq!(granted.status, StatusCode::OK, "{}", granted.json());
+    assert_eq!(granted.json()["scope"], "files:read");
+    let token = granted.json()["access_token"].as_str().unwrap().to_string();
+    assert_eq!(app.request(Method::GET, "/files/quota", Some(&token), None).await.code(), 200);
+    let upload = app.upload(&token, "notes.txt", b"hello").await;
+    assert_eq!(upload.code(), 403);
+    assert_eq!(upload.json()["error"], "token_scope_denied");
+    let approval = decide(&app, &token, &code["user_code"], true).await;
+    assert_eq!(approval.json()["error"], "token_scope_denied");
+
+    // The code is redeemed once.
+    assert_eq!(poll(&app, &code["device_code"]).await.json()["error"], "invalid_grant");
+}
+
+#[tokio::test]
+async fn polling_faster_than_the_interval_slows_the_client_down() {
+    let app = TestApp::new();
+    let code = request_code(&app, "files:read+files:write").await;
+    assert_eq!(code["interval"], 5);
+
+    assert_eq!(poll(&app, &code["device_code"]).await.json()["error"], "authorization_pending");
+    let too_fast = poll(&app, &code["device_code"]).await;
+    assert_eq!(too_fast.status, StatusCode::BAD_REQUEST);
+    assert_eq!(too_fast.json()["error"], "slow_down");
+    assert_eq!(app.data().device_authorizations[0].interval_secs, 10);
+
+    // Waiting the old interval is no longer enough.
+    app.data().device_authorizations[0].last_polled_at = Some(Utc::now() - Duration::seconds(6));
+    assert_eq!(poll(&app, &code["device_code"]).await.json()["error"], "slow_down");
+    wait_interval(&app);
+    assert_eq!(poll(&app, &code["device_code"]).await.json()["error"], "authorization_pending");
+}
+
+#[tokio::test]
+async fn expired_and_denied_codes_end_the_login() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+
+    let expired = request_code(&app, "files:read").await;
+    for authorization in app.data().device_authorizations.iter_mut() {
+        authorization.expires_at = Utc::now() - Duration::seconds(1);
+    }
+    assert_eq!(poll(&app, &expired["device_code"]).await.json()["error"], "expired_token");
+    assert_eq!(poll(&app, &expired["device_code"]).await.json()["error"], "invalid_grant");
+    let late = decide(&app, &alice, &expired["user_code"], true).await;
+    assert_eq!(late.json()["error"], "invalid_code");
+
+    let denied = request_code(&app, "files:read").await;
+    assert_eq!(decide(&app, &alice, &denied["user_code"], false).await.code(), 200);
+    wait_interval(&app);
+    assert_eq!(poll(&app, &denied["device_code"]).await.json()["error"], "access_denied");
+    assert_eq!(poll(&app, &denied["device_code"]).await.json()["error"], "invalid_grant");
+}
+
+#[tokio::test]
+async fn unknown_clients_and_scopes_are_refused() {
+    let app = TestApp::new();
+    let unknown_scope = app.post_form("/auth/device/code", &[("client_id", "cli"), ("scope", "admin")]).await;
+    assert_eq!(unknown_scope.status, StatusCode::BAD_REQUEST);
+    assert_eq!(unknown_scope.json()["error"], "invalid_scope");
+    let no_client = app.post_form("/auth/device/code", &[("client_id", "")]).await;
+    assert_eq!(no_client.json()["error"], "invalid_client");
+
+    let code = request_code(&app, "files:read").await;
+    let device_code = code["device_code"].as_str().unwrap();
+    let other_client = app
+        .post_form(
+            "/auth/device/token",
+            &[("grant_type", GRANT_TYPE), ("device_code", device_code), ("client_id", "other")],
+        )
+        .await;
+    assert_eq!(other_client.json()["error"], "invalid_grant");
+}
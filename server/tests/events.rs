@@ -0,0 +1,260 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::{
+    body::{Body, BodyDataStream},
+    http::{header, Method, Request},
+};
+use common::TestApp;
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+struct ServerEvent {
+    id: i64,
+    event: String,
+    data: Value,
+}
+
+/// Reads the `text/event-stream` body of `GET /files/events`.
+struct EventReader {
+    body: BodyDataStream,
+    buffer: String,
+}
+
+impl EventReader {
+    async fn open(app: &TestApp, uri: &str, token: &str, last_event_id: Option<i64>) -> EventReader {
+        let mut request = Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token));
+        if let Some(id) = last_event_id {
+            request = request.header("last-event-id", id.to_string());
+        }
+        let response = app.router.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/event-stream");
+        EventReader { body: response.into_body().into_data_stream(), buffer: String::new() }
+    }
+
+    /// The next event, skipping keep-alive comments, or `None` once the
+    /// stream ends.
+    async fn next(&mut self) -> Option<ServerEvent> {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let block: String = self.buffer.drain(..end + 2).collect();
+                let field = |name: &str| {
+                    block
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name).map(|value| value.trim_start().to_string()))
+                };
+                let Some(data) = field("data:") else {
+                    continue;
+                };
+                return Some(ServerEvent {
+                    id: field("id:").unwrap().parse().unwrap(),
+                    event: field("event:").unwrap(),
+                    data: serde_json::from_str(&data).unwrap(),
+                });
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), self.body.next())
+                .await
+                .expect("no event in time")?;
+            self.buffer.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+        }
+    }
+}
+
+#[tokio::test]
+async fn streams_resume_after_the_last_event_id() {
+    let app = TestApp::new();
+    let token = app.verified_user("alice@example.com").await;
+    let first = app.upload(&token, "a.txt", b"a").await.json()["data"]["id"].as_i64().unwrap();
+    app.upload(&token, "b.txt", b"b").await;
+    app.request(
+        Method::POST,
+        "/files/move",
+        Some(&token),
+        Some(json!({ "file_id": first, "file_name": "c.txt", "folder_id": null })),
+    )
+    .await;
+
+    let mut events = EventReader::open(&app, "/files/events", &token, Some(0)).await;
+    let mut seen = Vec::new();
+    for _ in 0..3 {
+        let event = events.next().await.unwrap();
+        seen.push((event.id, event.event, event.data["name"].as_str().unwrap().to_string()));
+    }
+    let names: Vec<(&str, &str)> = seen.iter().map(|(_, kind, name)| (kind.as_str(), name.as_str())).collect();
+    assert_eq!(names, [("created", "a.txt"), ("created", "b.txt"), ("moved", "c.txt")]);
+
+    // After a reconnect, from the header or the query.
+    let mut events = EventReader::open(&app, "/files/events", &token, Some(seen[0].0)).await;
+    assert_eq!(events.next().await.unwrap().id, seen[1].0);
+    let mut events = EventReader::open(&app, &format!("/files/events?cursor={}", seen[1].0), &token, None).await;
+    assert_eq!(events.next().await.unwrap().id, seen[2].0);
+}
+
+/// What needs the notifications of Postgres.
+#[cfg(feature = "postgres-tests")]
+mod postgres {
+    use std::time::Duration;
+
+    use axum::http::Method;
+    use serde_json::json;
+    use server::{config::events::run_change_listener, models::events::Notice};
+    use sqlx::{PgExecutor, PgPool};
+
+    use super::{EventReader, TestApp};
+
+    /// Starts the change listener of the app and waits until it listens.
+    async fn listening(app: &TestApp, pool: &PgPool) {
+        let mut notices = app.state.notices.subscribe();
+        tokio::spawn(run_change_listener(pool.clone(), app.state.notices.clone()));
+        loop {
+            let notice = tokio::time::timeout(Duration::from_secs(5), notices.recv()).await;
+            if let Ok(Notice::Missed) = notice.expect("the listener didn't start") {
+                return;
+            }
+        }
+    }
+
+    #[sqlx::test]
+    async fn events_follow_personal_and_drive_files(pool: PgPool) {
+        let app = TestApp::postgres(pool.clone());
+        listening(&app, &pool).await;
+        let alice = app.verified_user("alice@example.com").await;
+        let bob = app.verified_user("bob@example.com").await;
+        let mut alice_events = EventReader::open(&app, "/files/events", &alice, None).await;
+        let mut bob_events = EventReader::open(&app, "/files/events", &bob, None).await;
+
+        app.upload(&alice, "a.txt", b"a").await;
+        let org = app.request(Method::POST, "/orgs", Some(&alice), Some(json!({ "name": "Acme" }))).await;
+        let org_id = org.json()["data"]["organization_id"].as_i64().unwrap();
+        let drive = app
+            .request(Method::POST, &format!("/orgs/{}/drives", org_id), Some(&alice), Some(json!({ "name": "Shared" })))
+            .await;
+        let drive_id = drive.json()["data"]["id"].as_i64().unwrap();
+        let uploaded = app
+            .upload_to(&alice, &format!("/orgs/{}/drives/{}/files", org_id, drive_id), "d.txt", b"d")
+            .await;
+        assert_eq!(uploaded.code(), 200, "{}", uploaded.json());
+        app.upload(&bob, "b.txt", b"b").await;
+
+        let event = alice_events.next().await.unwrap();
+        assert_eq!(event.event, "created");
+        assert_eq!(event.data["name"], "a.txt");
+        assert_eq!(event.data["id"], event.id);
+        let event = alice_events.next().await.unwrap();
+        assert_eq!(event.data["name"], "d.txt");
+        assert_eq!(event.data["team_drive_id"], drive_id);
+        // Bob sees neither Alice's files nor her drive.
+        let event = bob_events.next().await.unwrap();
+        assert_eq!(event.data["name"], "b.txt");
+        assert_eq!(event.data.get("team_drive_id"), None);
+
+        let _ = app.state.notices.send(Notice::ShuttingDown);
+        assert!(alice_events.next().await.is_none());
+    }
+
+    #[sqlx::test]
+    async fn instances_share_events(pool: PgPool) {
+        let first = TestApp::postgres(pool.clone());
+        let second = TestApp::postgres(pool.clone());
+        listening(&second, &pool).await;
+        let token = first.verified_user("alice@example.com").await;
+        let mut events = EventReader::open(&second, "/files/events", &token, None).await;
+
+        // Only the notification of the first instance's commit can bring it.
+        first.upload(&token, "a.txt", b"a").await;
+        let event = events.next().await.unwrap();
+        assert_eq!((event.event.as_str(), &event.data["name"]), ("created", &json!("a.txt")));
+
+        let unauthorized = second.request(Method::GET, "/files/events", None, None).await;
+        assert_eq!(unauthorized.code(), 401);
+    }
+
+    async fn insert_file(executor: impl PgExecutor<'_>, user_id: i32, team_drive_id: Option<i32>, name: &str) {
+        sqlx::query(
+            "INSERT INTO files (file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id)
+             VALUES ($1, $1, 1, 'text/plain', 'txt', $2, $3)",
+        )
+        .bind(name)
+        .bind(user_id)
+        .bind(team_drive_id)
+        .execute(executor)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn events_follow_the_order_of_transactions(pool: PgPool) {
+        let app = TestApp::postgres(pool.clone());
+        listening(&app, &pool).await;
+        let token = app.verified_user("alice@example.com").await;
+        let user_id: i32 = sqlx::query_scalar("SELECT id FROM users").fetch_one(&pool).await.unwrap();
+        let org = app.request(Method::POST, "/orgs", Some(&token), Some(json!({ "name": "Acme" }))).await;
+        let org_id = org.json()["data"]["organization_id"].as_i64().unwrap();
+        let drive = app
+            .request(Method::POST, &format!("/orgs/{}/drives", org_id), Some(&token), Some(json!({ "name": "Shared" })))
+            .await;
+        let drive_id = drive.json()["data"]["id"].as_i64().unwrap() as i32;
+        let mut events = EventReader::open(&app, "/files/events", &token, None).await;
+
+        // A transaction starts, another records a personal change, and a change
+        // of the drive commits without waiting for the lock of the personal one.
+        let mut older = pool.begin().await.unwrap();
+        sqlx::query("SELECT pg_current_xact_id()").execute(&mut *older).await.unwrap();
+        let mut personal = pool.begin().await.unwrap();
+        insert_file(&mut *personal, user_id, None, "second.txt").await;
+        tokio::time::timeout(Duration::from_secs(2), insert_file(&pool, user_id, Some(drive_id), "third.txt"))
+            .await
+            .expect("the drive's change waited for the lock of a personal one");
+        let early = tokio::time::timeout(Duration::from_millis(500), events.next()).await;
+        assert!(early.is_err(), "sent before an older transaction ended");
+        personal.commit().await.unwrap();
+        // The oldest transaction records its change last, with the highest id.
+        insert_file(&mut *older, user_id, None, "first.txt").await;
+        older.commit().await.unwrap();
+
+        let mut sent = Vec::new();
+        for _ in 0..3 {
+            let event = events.next().await.unwrap();
+            sent.push((event.id, event.data["name"].as_str().unwrap().to_string()));
+        }
+        let names: Vec<&str> = sent.iter().map(|(_, name)| name.as_str()).collect();
+        assert_eq!(names, ["first.txt", "second.txt", "third.txt"]);
+        assert!(sent[0].0 > sent[2].0);
+        // Resuming after the first one sends the others, though their ids are lower.
+        let mut resumed = EventReader::open(&app, "/files/events", &token, Some(sent[0].0)).await;
+        assert_eq!(resumed.next().await.unwrap().id, sent[1].0);
+        assert_eq!(resumed.next().await.unwrap().id, sent[2].0);
+    }
+
+    #[sqlx::test]
+    async fn a_long_transaction_holds_the_events_back_for_a_while(pool: PgPool) {
+        let app = TestApp::postgres_with(pool.clone(), |config| config.changes.stream_wait_secs = 1);
+        listening(&app, &pool).await;
+        let token = app.verified_user("alice@example.com").await;
+        let user_id: i32 = sqlx::query_scalar("SELECT id FROM users").fetch_one(&pool).await.unwrap();
+        let mut events = EventReader::open(&app, "/files/events", &token, None).await;
+
+        // A transaction that records nothing yet, e.g. a session left idle.
+        let mut idle = pool.begin().await.unwrap();
+        sqlx::query("SELECT pg_current_xact_id()").execute(&mut *idle).await.unwrap();
+        insert_file(&pool, user_id, None, "held.txt").await;
+        let early = tokio::time::timeout(Duration::from_millis(500), events.next()).await;
+        assert!(early.is_err(), "sent before an older transaction ended");
+        let held = events.next().await.unwrap();
+        assert_eq!(held.data["name"], "held.txt");
+
+        // What it records is sent once committed, though it comes first in the
+        // stream order.
+        insert_file(&mut *idle, user_id, None, "late.txt").await;
+        idle.commit().await.unwrap();
+        let late = events.next().await.unwrap();
+        assert_eq!(late.data["name"], "late.txt");
+        insert_file(&pool, user_id, None, "next.txt").await;
+        assert_eq!(events.next().await.unwrap().data["name"], "next.txt");
+    }
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use axum::http::{header, Method};
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn upload_list_download_and_delete() {
+    let app = TestApp::new();
+    let token = app.verified_user("alice@example.com").await;
+
+    let uploaded = app.upload(&token, "notes.txt", b"hello").await;
+    assert_eq!(uploaded.code(), 200, "{}", uploaded.json());
+    let file_id = uploaded.json()["data"]["id"].as_i64().unwrap();
+
+    let listed = app
+        .request(Method::POST, "/files/get", Some(&token), Some(json!({ "file_ids": [-1] })))
+        .await;
+    assert_eq!(listed.code(), 200);
+    let files = listed.json()["data"]["files"].clone();
+    assert_eq!(files.as_array().unwrap().len(), 1);
+    assert_eq!(files[0]["id"], file_id);
+    assert_eq!(files[0]["file_name"], "notes.txt");
+    assert_eq!(files[0]["file_size"], 5);
+
+    let downloaded = app
+        .request(Method::GET, &format!("/files/download?file_id={}", file_id), Some(&token), None)
+        .await;
+    assert_eq!(downloaded.body, b"hello");
+    assert_eq!(downloaded.headers[header::CONTENT_TYPE], "text/plain");
+    assert_eq!(
+        downloaded.headers[header::CONTENT_DISPOSITION],
+        "attachment; filename*=UTF-8''notes.txt"
+    );
+
+    let deleted = app
+        .request(Method::DELETE, &format!("/files/delete?file_id={}", file_id), Some(&token), None)
+        .await;
+    assert_eq!(deleted.code(), 200, "{}", deleted.json());
+
+    let downloaded = app
+        .request(Method::GET, &format!("/files/download?file_id={}", file_id), Some(&token), None)
+        .await;
+    assert_eq!(downloaded.code(), 404);
+    let listed = app
+        .request(Method::POST, "/files/get", Some(&token), Some(json!({ "file_ids": [-1] })))
+        .await;
+    assert_eq!(listed.json()["data"]["files"], json!([]));
+    assert_eq!(std::fs::read_dir(app.storage.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn names_are_encoded_in_content_disposition() {
+    let app = TestApp::new();
+    let token = app.verified_user("alice@example.com").await;
+
+    let uploaded = app.upload(&token, "отчёт 1.txt", b"report").await;
+    let file_id = uploaded.json()["data"]["id"].as_i64().unwrap();
+
+    let downloaded = app
+        .request(Method::GET, &format!("/files/download?file_id={}", file_id), Some(&token), None)
+        .await;
+    assert_eq!(
+        downloaded.headers[header::CONTENT_DISPOSITION],
+        "attachment; filename*=UTF-8''%D0%BE%D1%82%D1%87%D1%91%D1%82%201.txt"
+    );
+}
+
+#[tokio::test]
+async fn unverified_users_cannot_upload() {
+    let app = TestApp::new();
+    let registered = app
+        .request(
+            Method::POST,
+            "/auth/register",
+            None,
+            Some(json!({ "email": "alice@example.com", "password": common::PASSWORD, "name": "Alice" })),
+        )
+        .await;
+    assert_eq!(registered.code(), 201);
+    let login = app.login("alice@example.com", common::PASSWORD).await;
+    let token = login.json()["data"]["token"].as_str().unwrap().to_string();
+
+    let uploaded = app.upload(&token, "notes.txt", b"hello").await;
+    assert_eq!(uploaded.code(), 403);
+    assert_eq!(uploaded.json()["error"], "email_not_verified");
+}
+
+#[tokio::test]
+async fn quota_reports_the_space_taken() {
+    let app = TestApp::new();
+    let token = app.verified_user("alice@example.com").await;
+
+    let quota = app.request(Method::GET, "/files/quota", Some(&token), None).await;
+    assert_eq!(quota.code(), 200, "{}", quota.json());
+    assert_eq!(quota.json()["data"], json!({ "used_bytes": 0, "quota_bytes": 10i64 * 1024 * 1024 * 1024 }));
+
+    app.upload(&token, "notes.txt", b"hello").await;
+    app.data().users.iter_mut().for_each(|user| user.quota_bytes = Some(100));
+    let quota = app.request(Method::GET, "/files/quota", Some(&token), None).await;
+    assert_eq!(quota.json()["data"], json!({ "used_bytes": 5, "quota_bytes": 100 }));
+
+    let anonymous = app.request(Method::GET, "/files/quota", None, None).await;
+    assert_eq!(anonymous.code(), 401);
+}
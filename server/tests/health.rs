@@ -0,0 +1,68 @@
+mod common;
+
+use std::{fs, sync::atomic::Ordering};
+
+use axum::http::{Method, StatusCode};
+use common::{TestApp, TestResponse};
+use serde_json::Value;
+
+async fn readyz(app: &TestApp) -> (StatusCode, Value) {
+    let TestResponse { status, body, .. } = app.request(Method::GET, "/readyz", None, None).await;
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn a_healthy_server_is_alive_and_ready() {
+    let app = TestApp::new();
+    let alive = app.request(Method::GET, "/healthz", None, None).await;
+    assert_eq!(alive.status, StatusCode::OK);
+    assert_eq!(alive.json()["status"], "ok");
+
+    let (status, ready) = readyz(&app).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(ready["status"], "ready");
+    for check in ["database", "storage", "migrations"] {
+        assert_eq!(ready["checks"][check], "ok", "{}", check);
+    }
+    // The probe file is removed.
+    assert_eq!(fs::read_dir(app.storage.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn an_unwritable_storage_is_not_ready() {
+    let app = TestApp::new();
+    fs::remove_dir(app.storage.path()).unwrap();
+
+    let (status, ready) = readyz(&app).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(ready["status"], "not_ready");
+    assert!(ready["checks"]["storage"].as_str().unwrap().starts_with("not writable"), "{}", ready);
+    assert_eq!(ready["checks"]["database"], "ok");
+    // Still alive, so that it isn't restarted for a disk that isn't there.
+    assert_eq!(app.request(Method::GET, "/healthz", None, None).await.status, StatusCode::OK);
+    fs::create_dir(app.storage.path()).unwrap();
+}
+
+#[tokio::test]
+async fn a_stopping_server_is_not_ready() {
+    let app = TestApp::new();
+    app.state.shutting_down.store(true, Ordering::Relaxed);
+
+    let (status, ready) = readyz(&app).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(ready["status"], "shutting_down");
+    assert_eq!(app.request(Method::GET, "/healthz", None, None).await.status, StatusCode::OK);
+}
+
+#[cfg(feature = "postgres-tests")]
+#[sqlx::test(migrations = false)]
+async fn pending_migrations_are_not_ready(pool: sqlx::PgPool) {
+    let app = TestApp::postgres(pool.clone());
+    let (status, ready) = readyz(&app).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(ready["checks"]["database"], "ok");
+    assert_eq!(ready["checks"]["migrations"], "pending migrations");
+
+    server::db::migrations::run_migrations(&pool).await.unwrap();
+    assert_eq!(readyz(&app).await.0, StatusCode::OK);
+}
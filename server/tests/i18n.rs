@@ -0,0 +1,78 @@
+mod common;
+
+use std::collections::{BTreeSet, HashMap};
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request},
+};
+use common::{TestApp, TestResponse, PASSWORD};
+use serde_json::json;
+use server::models::i18n::Locale;
+
+async fn login_in(app: &TestApp, accept_language: Option<&str>) -> TestResponse {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri("/auth/login")
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(accept_language) = accept_language {
+        request = request.header(header::ACCEPT_LANGUAGE, accept_language);
+    }
+    let body = json!({ "email": "nobody@example.com", "password": "wrong" }).to_string();
+    app.send(request.body(Body::from(body)).unwrap()).await
+}
+
+fn catalog_keys(source: &str) -> BTreeSet<String> {
+    serde_json::from_str::<HashMap<String, String>>(source).unwrap().into_keys().collect()
+}
+
+#[test]
+fn the_catalogs_have_the_same_keys() {
+    let en = catalog_keys(include_str!("../src/config/locales/en.json"));
+    let ru = catalog_keys(include_str!("../src/config/locales/ru.json"));
+    assert_eq!(en.difference(&ru).collect::<Vec<_>>(), Vec::<&String>::new(), "missing in ru.json");
+    assert_eq!(ru.difference(&en).collect::<Vec<_>>(), Vec::<&String>::new(), "missing in en.json");
+}
+
+#[tokio::test]
+async fn messages_follow_accept_language() {
+    let app = TestApp::new();
+    for (accept_language, locale) in [
+        (None, Locale::En),
+        (Some("ru-RU,en;q=0.5"), Locale::Ru),
+        (Some("fr, ru;q=0.3"), Locale::Ru),
+        (Some("ru;q=0, en"), Locale::En),
+        (Some("de"), Locale::En),
+    ] {
+        let login = login_in(&app, accept_language).await;
+        assert_eq!(login.headers[header::CONTENT_LANGUAGE], locale.as_str(), "{:?}", accept_language);
+        // The key stays the same in every language.
+        assert_eq!(login.json()["error"], "invalid_credentials");
+        assert_eq!(login.json()["message"], locale.text("invalid_credentials"));
+    }
+}
+
+#[tokio::test]
+async fn the_profile_locale_wins_and_applies_to_emails() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+    app.request(Method::PATCH, "/user/me", Some(&alice), Some(json!({ "locale": "ru" }))).await;
+
+    let short = app
+        .request(Method::POST, "/user/me/password", Some(&alice), Some(json!({ "new_password": "short" })))
+        .await;
+    assert_eq!(short.headers[header::CONTENT_LANGUAGE], "ru");
+    assert_eq!(short.json()["message"], "Пароль должен содержать не менее 8 символов");
+
+    let changed = app
+        .request(
+            Method::POST,
+            "/user/me/password",
+            Some(&alice),
+            Some(json!({ "current_password": PASSWORD, "new_password": "Nw5$kTq9@xLm" })),
+        )
+        .await;
+    assert_eq!(changed.code(), 200, "{}", changed.json());
+    let emails = app.emails_to("alice@example.com").await;
+    assert_eq!(emails.last().unwrap().subject, Locale::Ru.text("email.password_changed.subject"));
+}
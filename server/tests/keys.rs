@@ -0,0 +1,79 @@
+mod common;
+
+use std::path::Path;
+
+use axum::http::Method;
+use common::TestApp;
+use jsonwebtoken::decode_header;
+use ring::{rand::SystemRandom, signature::Ed25519KeyPair};
+use server::models::{auth::Auth, settings::Config};
+use tempfile::TempDir;
+
+/// Writes a new Ed25519 private key as PEM and returns its path.
+fn write_key(dir: &Path, name: &str) -> String {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()))).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn auth(signing_key_file: Option<&str>, verification_key_files: &[&str], secret_key: Option<&str>) -> Auth {
+    let mut config = Config::default();
+    config.auth.signing_key_file = signing_key_file.map(str::to_string);
+    config.auth.verification_key_files = verification_key_files.iter().map(|path| path.to_string()).collect();
+    config.auth.secret_key = secret_key.map(str::to_string);
+    Auth::load(&config).unwrap()
+}
+
+#[tokio::test]
+async fn the_jwks_publishes_the_signing_and_verification_keys() {
+    let keys = TempDir::new().unwrap();
+    let (old_key, new_key) = (write_key(keys.path(), "old.pem"), write_key(keys.path(), "new.pem"));
+    let app = TestApp::new_with(|config| {
+        config.auth.signing_key_file = Some(new_key.clone());
+        config.auth.verification_key_files = vec![old_key.clone()];
+    });
+
+    let session = app.verified_user("alice@example.com").await;
+    assert_eq!(app.request(Method::GET, "/files/quota", Some(&session), None).await.code(), 200);
+
+    let jwks = app.request(Method::GET, "/.well-known/jwks.json", None, None).await.json();
+    let published = jwks["keys"].as_array().unwrap();
+    // The shared secret is never published.
+    assert_eq!(published.len(), 2);
+    assert!(published.iter().all(|key| key["kty"] == "OKP" && key["alg"] == "EdDSA" && key.get("d").is_none()));
+    let kid = decode_header(&session).unwrap().kid.unwrap();
+    assert_eq!(published[0]["kid"], kid.as_str());
+    assert_ne!(published[1]["kid"], kid.as_str());
+}
+
+#[test]
+fn tokens_outlive_a_rotation_until_their_key_is_dropped() {
+    let keys = TempDir::new().unwrap();
+    let (old_key, new_key) = (write_key(keys.path(), "old.pem"), write_key(keys.path(), "new.pem"));
+
+    let before = auth(Some(&old_key), &[], None);
+    let old_token = before.generate_jwt(7);
+    // The key id is the thumbprint, so the same file always gets the same one.
+    assert_eq!(decode_header(&old_token).unwrap().kid, decode_header(&before.generate_jwt(7)).unwrap().kid);
+
+    let rotating = auth(Some(&new_key), &[&old_key], None);
+    assert_eq!(rotating.verify_jwt(&old_token).unwrap().sub, 7);
+    let new_token = rotating.generate_jwt(7);
+    assert_ne!(decode_header(&new_token).unwrap().kid, decode_header(&old_token).unwrap().kid);
+
+    let after = auth(Some(&new_key), &[], None);
+    assert!(after.verify_jwt(&old_token).is_err());
+    assert_eq!(after.verify_jwt(&new_token).unwrap().sub, 7);
+}
+
+#[test]
+fn secret_signed_tokens_stay_valid_after_moving_to_a_key_file() {
+    let keys = TempDir::new().unwrap();
+    let key = write_key(keys.path(), "signing.pem");
+    let hs256_token = auth(None, &[], Some("old-secret")).generate_jwt(7);
+
+    assert_eq!(auth(Some(&key), &[], Some("old-secret")).verify_jwt(&hs256_token).unwrap().sub, 7);
+    assert!(auth(Some(&key), &[], None).verify_jwt(&hs256_token).is_err());
+    assert!(auth(None, &[], Some("another-secret")).verify_jwt(&hs256_token).is_err());
+}
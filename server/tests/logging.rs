@@ -0,0 +1,66 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request},
+};
+use common::{TestApp, TestResponse, PASSWORD};
+use serde_json::json;
+use server::config::logging::X_REQUEST_ID;
+
+async fn health_with(app: &TestApp, request_id: Option<&str>) -> TestResponse {
+    let mut request = Request::builder().method(Method::GET).uri("/healthz");
+    if let Some(request_id) = request_id {
+        request = request.header(X_REQUEST_ID, request_id);
+    }
+    app.send(request.body(Body::empty()).unwrap()).await
+}
+
+fn request_id(response: &TestResponse) -> String {
+    response.headers[X_REQUEST_ID].to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn every_response_carries_a_request_id() {
+    let app = TestApp::new();
+    let first = request_id(&health_with(&app, None).await);
+    let second = request_id(&health_with(&app, None).await);
+    assert_eq!(first.len(), 20);
+    assert_ne!(first, second);
+
+    // Failures carry one too, so that users can quote it.
+    let failed = app.login("nobody@example.com", "wrong").await;
+    assert_eq!(failed.code(), 401);
+    assert_eq!(request_id(&failed).len(), 20);
+}
+
+#[tokio::test]
+async fn the_id_of_a_proxy_is_kept_when_valid() {
+    let app = TestApp::new();
+    assert_eq!(request_id(&health_with(&app, Some("edge-42/abc")).await), "edge-42/abc");
+
+    for invalid in ["", "with space", &"x".repeat(129)] {
+        let replaced = request_id(&health_with(&app, Some(invalid)).await);
+        assert_eq!(replaced.len(), 20, "{:?}", invalid);
+        assert_ne!(replaced, invalid);
+    }
+}
+
+#[tokio::test]
+async fn queued_emails_keep_the_id_of_their_request() {
+    let app = TestApp::new();
+    let body = json!({ "email": "alice@example.com", "password": PASSWORD, "name": "Alice" });
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/auth/register")
+        .header(X_REQUEST_ID, "register-1")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let registered = app.send(request).await;
+    assert_eq!(registered.code(), 201, "{}", registered.json());
+
+    let data = app.data();
+    let queued = data.emails.iter().find(|queued| queued.email.recipient == "alice@example.com").unwrap();
+    assert_eq!(queued.email.request_id.as_deref(), Some("register-1"));
+}
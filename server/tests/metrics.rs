@@ -0,0 +1,47 @@
+mod common;
+
+use axum::http::{header, Method, StatusCode};
+use common::{TestApp, TestResponse};
+
+const TOKEN: &str = "scrape-token";
+
+async fn scrape(app: &TestApp, token: Option<&str>) -> TestResponse {
+    app.request(Method::GET, "/metrics", token, None).await
+}
+
+#[tokio::test]
+async fn scraping_needs_the_token_when_one_is_set() {
+    let app = TestApp::new_with(|config| config.metrics.token = Some(TOKEN.to_string()));
+    assert_eq!(scrape(&app, None).await.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(scrape(&app, Some("wrong")).await.status, StatusCode::UNAUTHORIZED);
+
+    let scraped = scrape(&app, Some(TOKEN)).await;
+    assert_eq!(scraped.status, StatusCode::OK);
+    assert_eq!(scraped.headers[header::CONTENT_TYPE], "text/plain; version=0.0.4");
+    let body = String::from_utf8(scraped.body).unwrap();
+    for name in ["http_requests_total", "db_pool_max_connections", "email_outbox_pending"] {
+        assert!(body.contains(name), "{} missing", name);
+    }
+}
+
+#[tokio::test]
+async fn metrics_are_not_served_on_the_api_when_disabled_or_elsewhere() {
+    let disabled = TestApp::new_with(|config| config.metrics.enabled = false);
+    assert_eq!(scrape(&disabled, None).await.status, StatusCode::NOT_FOUND);
+    let elsewhere = TestApp::new_with(|config| config.metrics.bind = Some("127.0.0.1:0".parse().unwrap()));
+    assert_eq!(scrape(&elsewhere, None).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn requests_are_counted_by_route_template_and_api_code() {
+    let app = TestApp::new();
+    let token = app.verified_user("alice@example.com").await;
+    app.request(Method::GET, "/user/987654", Some(&token), None).await;
+    app.request(Method::GET, "/no-such-page-5d1c", None, None).await;
+
+    let body = String::from_utf8(scrape(&app, None).await.body).unwrap();
+    assert!(body.contains(r#"method="GET",route="/user/{id}",status="404""#), "{}", body);
+    assert!(body.contains(r#"route="unmatched""#), "{}", body);
+    // Ids and unknown paths never become labels.
+    assert!(!body.contains("987654") && !body.contains("5d1c"));
+}
@@ -0,0 +1,136 @@
+//! Runs the migrations on the databases `#[sqlx::test]` creates, in the
+//! Postgres run only.
+#![cfg(feature = "postgres-tests")]
+
+use server::db::migrations::{pending_migrations, run_migrations, MIGRATOR};
+use sqlx::PgPool;
+
+/// The baseline tables as they were created before migrations were tracked.
+const LEGACY_TABLES: &str = "CREATE TABLE users (id SERIAL PRIMARY KEY, email VARCHAR(255) NOT NULL,
+                                                password VARCHAR(255) NOT NULL, name VARCHAR(255) NOT NULL);
+    CREATE TABLE codes (id SERIAL PRIMARY KEY, code VARCHAR(255) NOT NULL, user_id INT NOT NULL,
+                        FOREIGN KEY (user_id) REFERENCES users(id));
+    CREATE TABLE files (id SERIAL PRIMARY KEY, file_name VARCHAR(255) NOT NULL, file_path VARCHAR(255) NOT NULL,
+                        file_size INT NOT NULL, file_content_type VARCHAR(255) NOT NULL,
+                        file_type VARCHAR(255) NOT NULL, user_id INT NOT NULL,
+                        FOREIGN KEY (user_id) REFERENCES users(id));
+    CREATE TABLE folders (id SERIAL PRIMARY KEY, name VARCHAR(255) NOT NULL, user_id INT NOT NULL,
+                          FOREIGN KEY (user_id) REFERENCES users(id));";
+
+#[sqlx::test(migrations = false)]
+async fn a_new_database_gets_every_migration(pool: PgPool) {
+    let pending = pending_migrations(&pool).await.unwrap();
+    assert_eq!(pending.len(), MIGRATOR.iter().count());
+
+    let applied = run_migrations(&pool).await.unwrap();
+    assert_eq!(applied.len(), pending.len());
+    assert!(pending_migrations(&pool).await.unwrap().is_empty());
+    // A second run has nothing to do.
+    assert!(run_migrations(&pool).await.unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = false)]
+async fn an_untracked_database_keeps_its_data(pool: PgPool) {
+    sqlx::raw_sql(LEGACY_TABLES).execute(&pool).await.unwrap();
+    sqlx::query("INSERT INTO users (email, password, name) VALUES ('alice@example.com', 'hash', 'Alice')")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let pending = pending_migrations(&pool).await.unwrap();
+    assert_eq!(pending.len(), MIGRATOR.iter().count() - 1, "the baseline would run");
+    run_migrations(&pool).await.unwrap();
+
+    let emails: Vec<String> = sqlx::query_scalar("SELECT email FROM users").fetch_all(&pool).await.unwrap();
+    assert_eq!(emails, ["alice@example.com"]);
+    assert!(pending_migrations(&pool).await.unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = false)]
+async fn existing_users_stay_verified(pool: PgPool) {
+    sqlx::raw_sql(LEGACY_TABLES).execute(&pool).await.unwrap();
+    sqlx::query("INSERT INTO users (email, password, name) VALUES ('alice@example.com', 'hash', 'Alice')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    run_migrations(&pool).await.unwrap();
+
+    let verified: bool = sqlx::query_scalar("SELECT email_verified_at IS NOT NULL FROM users")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(verified);
+}
+
+#[sqlx::test(migrations = false)]
+async fn emails_differing_in_case_stop_the_run(pool: PgPool) {
+    sqlx::raw_sql(LEGACY_TABLES).execute(&pool).await.unwrap();
+    sqlx::query(
+        "INSERT INTO users (email, password, name)
+         VALUES ('alice@example.com', 'hash', 'Alice'), ('Alice@Example.com', 'hash', 'Alice')",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let error = run_migrations(&pool).await.unwrap_err();
+    assert!(error.to_string().contains("alice@example.com"), "{}", error);
+    // Nothing was applied, so the run can be repeated once they are merged.
+    assert_eq!(pending_migrations(&pool).await.unwrap().len(), MIGRATOR.iter().count() - 1);
+
+    sqlx::query("UPDATE users SET email = 'alice.old@example.com' WHERE email = 'Alice@Example.com'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    run_migrations(&pool).await.unwrap();
+}
+
+#[sqlx::test(migrations = false)]
+async fn deleting_a_user_follows_the_foreign_keys(pool: PgPool) {
+    run_migrations(&pool).await.unwrap();
+    sqlx::raw_sql(
+        "INSERT INTO users (id, email, password, name) VALUES (1, 'alice@example.com', 'hash', 'Alice'),
+                                                              (2, 'bob@example.com', 'hash', 'Bob');
+         INSERT INTO organizations (id, name) VALUES (1, 'Acme');
+         INSERT INTO team_drives (id, organization_id, name) VALUES (1, 1, 'Shared');
+         INSERT INTO folders (name, user_id) VALUES ('Alice', 1);
+         INSERT INTO codes (code, user_id, expires_at) VALUES ('123456', 1, NOW());
+         INSERT INTO files (file_name, file_path, file_size, file_content_type, file_type, user_id, team_drive_id)
+         VALUES ('shared.txt', 'a', 1, 'text/plain', 'file', 1, 1),
+                ('own.txt', 'b', 1, 'text/plain', 'file', 2, NULL);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query("DELETE FROM users WHERE id = 1").execute(&pool).await.unwrap();
+    let count = |table: &str| {
+        let pool = pool.clone();
+        let query = format!("SELECT COUNT(*) FROM {} WHERE user_id = 1", table);
+        async move { sqlx::query_scalar::<_, i64>(&query).fetch_one(&pool).await.unwrap() }
+    };
+    assert_eq!(count("folders").await, 0);
+    assert_eq!(count("codes").await, 0);
+    // The team drive keeps the file of a deleted member.
+    let uploader: Option<i32> = sqlx::query_scalar("SELECT user_id FROM files WHERE file_name = 'shared.txt'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(uploader, None);
+
+    // Personal files have to be deleted first, with their content.
+    let error = sqlx::query("DELETE FROM users WHERE id = 2").execute(&pool).await.unwrap_err();
+    assert!(error.to_string().contains("files_owner_check"), "{}", error);
+}
+
+#[sqlx::test(migrations = false)]
+async fn lookups_by_owner_and_email_are_indexed(pool: PgPool) {
+    run_migrations(&pool).await.unwrap();
+    let indexes: Vec<String> = sqlx::query_scalar("SELECT indexname::TEXT FROM pg_indexes WHERE schemaname = 'public'")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    for index in ["files_user_id_idx", "folders_user_id_idx", "codes_user_id_idx", "users_email_lower_key"] {
+        assert!(indexes.iter().any(|name| name == index), "{} missing", index);
+    }
+}
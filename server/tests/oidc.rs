@@ -0,0 +1,136 @@
+mod common;
+
+use axum::http::{header, Method};
+use chrono::Utc;
+use common::{oidc::MockIssuer, TestApp};
+use serde_json::json;
+
+async fn app_with_issuer() -> (TestApp, MockIssuer) {
+    let issuer = MockIssuer::start().await;
+    let provider = issuer.provider();
+    let app = TestApp::new_with(|config| {
+        config.oidc.insert("mock".to_string(), provider);
+    });
+    (app, issuer)
+}
+
+fn user_id(app: &TestApp, token: &str) -> i32 {
+    app.state.auth.verify_jwt(token).unwrap().sub
+}
+
+#[tokio::test]
+async fn a_provider_login_creates_the_account_once() {
+    let (app, issuer) = app_with_issuer().await;
+    issuer.log_in_as(json!({
+        "sub": "alice-1",
+        "email": "Alice@Example.com",
+        "email_verified": true,
+        "name": "Alice",
+    }));
+
+    let first = app.oidc_login("mock").await;
+    assert_eq!(first.code(), 200, "{}", first.json());
+    let token = first.json()["data"]["token"].as_str().unwrap().to_string();
+    let id = user_id(&app, &token);
+    let profile = app.request(Method::GET, &format!("/user/{}", id), Some(&token), None).await;
+    assert_eq!(profile.json()["data"]["email"], "alice@example.com");
+    assert_eq!(profile.json()["data"]["name"], "Alice");
+
+    let again = app.oidc_login("mock").await;
+    assert_eq!(user_id(&app, again.json()["data"]["token"].as_str().unwrap()), id);
+    assert_eq!(app.data().users.len(), 1);
+}
+
+#[tokio::test]
+async fn only_a_verified_email_links_an_existing_account() {
+    let (app, issuer) = app_with_issuer().await;
+    let token = app.verified_user("bob@example.com").await;
+    let id = user_id(&app, &token);
+
+    issuer.log_in_as(json!({ "sub": "bob-1", "email": "bob@example.com", "email_verified": false }));
+    let unverified = app.oidc_login("mock").await;
+    assert_eq!(unverified.code(), 409);
+    assert_eq!(unverified.json()["error"], "provider_email_unverified");
+
+    issuer.log_in_as(json!({ "sub": "bob-1", "email": "bob@example.com", "email_verified": true }));
+    let linked = app.oidc_login("mock").await;
+    assert_eq!(linked.code(), 200, "{}", linked.json());
+    assert_eq!(user_id(&app, linked.json()["data"]["token"].as_str().unwrap()), id);
+    assert!(app.data().audit_events.iter().any(|event| event.action == "auth.identity_linked"));
+
+    // Once linked, the identity logs in even if the email changes there.
+    issuer.log_in_as(json!({ "sub": "bob-1", "email": "robert@example.com", "email_verified": false }));
+    let again = app.oidc_login("mock").await;
+    assert_eq!(user_id(&app, again.json()["data"]["token"].as_str().unwrap()), id);
+}
+
+#[tokio::test]
+async fn the_callback_needs_the_state_cookie_of_the_browser() {
+    let (app, issuer) = app_with_issuer().await;
+    issuer.log_in_as(json!({ "sub": "carol-1", "email": "carol@example.com", "email_verified": true }));
+    let (callback, cookie) = app.oidc_authorize("mock").await;
+    assert!(cookie.starts_with("oidc_state="));
+
+    let without = app.oidc_callback(&callback, None).await;
+    assert_eq!(without.code(), 400);
+    assert_eq!(without.json()["error"], "invalid_login");
+    // The cookie of a login started elsewhere, e.g. by an attacker.
+    let (_, other_cookie) = app.oidc_authorize("mock").await;
+    let other = app.oidc_callback(&callback, Some(&other_cookie)).await;
+    assert_eq!(other.json()["error"], "invalid_login");
+    let forged = app.oidc_callback(&callback, Some("oidc_state=forged")).await;
+    assert_eq!(forged.json()["error"], "invalid_login");
+
+    let login = app.oidc_callback(&callback, Some(&cookie)).await;
+    assert_eq!(login.code(), 200, "{}", login.json());
+    let replayed = app.oidc_callback(&callback, Some(&cookie)).await;
+    assert_eq!(replayed.json()["error"], "invalid_login");
+}
+
+#[tokio::test]
+async fn accounts_without_a_password_reauthenticate_by_logging_in_again() {
+    let (app, issuer) = app_with_issuer().await;
+    issuer.log_in_as(json!({ "sub": "dave-1", "email": "dave@example.com", "email_verified": true }));
+    let token = app.oidc_login("mock").await.json()["data"]["token"].as_str().unwrap().to_string();
+    let id = user_id(&app, &token);
+
+    let changed = app
+        .request(Method::POST, "/user/me/email", Some(&token), Some(json!({ "email": "david@example.com" })))
+        .await;
+    assert_eq!(changed.code(), 200, "{}", changed.json());
+
+    // A session from a login an hour ago.
+    let stale = app.state.auth.generate_jwt_authenticated_at(id, Some(Utc::now().timestamp() - 3600));
+    let refused = app.request(Method::DELETE, "/user/me", Some(&stale), Some(json!({}))).await;
+    assert_eq!(refused.code(), 401);
+    assert_eq!(refused.json()["error"], "reauthentication_required");
+    let guessed = app
+        .request(Method::DELETE, "/user/me", Some(&stale), Some(json!({ "password": "guess" })))
+        .await;
+    assert_eq!(guessed.json()["error"], "reauthentication_required");
+
+    let login = app
+        .request(Method::GET, "/auth/oidc/mock/login?reauthenticate=true", None, None)
+        .await;
+    assert!(login.headers[header::LOCATION].to_str().unwrap().contains("max_age=0"));
+    let fresh = app.oidc_login("mock").await.json()["data"]["token"].as_str().unwrap().to_string();
+    let deleted = app.request(Method::DELETE, "/user/me", Some(&fresh), Some(json!({}))).await;
+    assert_eq!(deleted.code(), 200, "{}", deleted.json());
+}
+
+#[tokio::test]
+async fn a_login_without_its_time_is_not_recent() {
+    let (app, issuer) = app_with_issuer().await;
+    issuer.log_in_as(json!({
+        "sub": "erin-1",
+        "email": "erin@example.com",
+        "email_verified": true,
+        "auth_time": null,
+    }));
+    let token = app.oidc_login("mock").await.json()["data"]["token"].as_str().unwrap().to_string();
+    assert_eq!(app.state.auth.verify_jwt(&token).unwrap().auth_time, None);
+
+    let refused = app.request(Method::DELETE, "/user/me", Some(&token), Some(json!({}))).await;
+    assert_eq!(refused.code(), 401);
+    assert_eq!(refused.json()["error"], "reauthentication_required");
+}
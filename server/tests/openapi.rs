@@ -0,0 +1,184 @@
+//! Keeps the OpenAPI document in step with the routers: every route must be
+//! documented, and everything documented must be routed.
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use serde_json::Value;
+use server::config::openapi::ApiDoc;
+use utoipa::OpenApi;
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+fn spec() -> Value {
+    serde_json::to_value(ApiDoc::openapi()).unwrap()
+}
+
+fn read_routes_file(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/routes").join(name);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e))
+}
+
+/// The first string literal of `line` after `prefix`.
+fn literal_after<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    let start = line.find(prefix)? + prefix.len();
+    let end = line[start..].find('"')?;
+    Some(&line[start..start + end])
+}
+
+/// `(method, path)` of every `.route(...)` of a router file, under `prefix`.
+fn routes_of(file: &str, prefix: &str) -> Vec<(String, String)> {
+    let mut routes = Vec::new();
+    for line in read_routes_file(file).lines() {
+        let Some(route) = literal_after(line, ".route(\"") else {
+            continue;
+        };
+        let path = format!("{}{}", prefix, route).trim_end_matches('/').to_string();
+        // The method routers follow the path, e.g. `post(delete_file).delete(delete_file)`.
+        let handlers = &line[line.find(".route(\"").unwrap() + 8 + route.len() + 1..];
+        for method in METHODS {
+            let called = handlers.match_indices(&format!("{}(", method)).any(|(at, _)| {
+                at == 0 || !handlers.as_bytes()[at - 1].is_ascii_alphanumeric() && handlers.as_bytes()[at - 1] != b'_'
+            });
+            if called {
+                routes.push((method.to_string(), path.clone()));
+            }
+        }
+    }
+    routes
+}
+
+/// Every route the app serves, found by following the `nest` and `merge`
+/// calls of `app_router` to the router files.
+fn routed() -> BTreeSet<(String, String)> {
+    let mut routes = BTreeSet::new();
+    for line in read_routes_file("app_router.rs").lines() {
+        let (prefix, call) = match literal_after(line, ".nest(\"") {
+            Some(prefix) => (prefix, &line[line.find(", ").expect("nest without a router") + 2..]),
+            None if line.contains(".merge(") => ("", &line[line.find(".merge(").unwrap() + 7..]),
+            None => continue,
+        };
+        let Some(router) = call.split('(').next().filter(|name| name.ends_with("_router")) else {
+            continue;
+        };
+        routes.extend(routes_of(&format!("{}.rs", router), prefix));
+    }
+    routes
+}
+
+fn documented(spec: &Value) -> BTreeSet<(String, String)> {
+    let mut operations = BTreeSet::new();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in METHODS {
+            if item.get(method).is_some() {
+                operations.insert((method.to_string(), path.clone()));
+            }
+        }
+    }
+    operations
+}
+
+fn collect_refs(value: &Value, refs: &mut BTreeSet<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("$ref", Value::String(reference)) => {
+                        refs.insert(reference.clone());
+                    }
+                    _ => collect_refs(value, refs),
+                }
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|value| collect_refs(value, refs)),
+        _ => {}
+    }
+}
+
+#[test]
+fn the_router_files_are_found() {
+    let routes = routed();
+    assert!(routes.contains(&("post".to_string(), "/auth/login".to_string())));
+    assert!(routes.contains(&("delete".to_string(), "/files/delete".to_string())));
+    assert!(routes.contains(&("post".to_string(), "/orgs".to_string())));
+    assert!(routes.contains(&("get".to_string(), "/healthz".to_string())));
+    assert!(routes.contains(&("get".to_string(), "/metrics".to_string())));
+}
+
+#[test]
+fn every_route_is_documented() {
+    let missing: Vec<_> = routed().difference(&documented(&spec())).cloned().collect();
+    assert!(missing.is_empty(), "routes missing from ApiDoc: {:?}", missing);
+}
+
+#[test]
+fn every_documented_operation_is_routed() {
+    let unrouted: Vec<_> = documented(&spec()).difference(&routed()).cloned().collect();
+    assert!(unrouted.is_empty(), "ApiDoc documents routes that don't exist: {:?}", unrouted);
+}
+
+#[test]
+fn every_referenced_schema_is_published() {
+    let spec = spec();
+    let mut refs = BTreeSet::new();
+    collect_refs(&spec, &mut refs);
+    let schemas = spec["components"]["schemas"].as_object().unwrap();
+    let missing: Vec<_> = refs
+        .iter()
+        .filter(|reference| {
+            let name = reference.strip_prefix("#/components/schemas/").unwrap_or(reference);
+            !schemas.contains_key(name)
+        })
+        .collect();
+    assert!(missing.is_empty(), "schemas missing from components: {:?}", missing);
+}
+
+#[test]
+fn operations_behind_a_token_declare_it() {
+    let spec = spec();
+    let schemes = spec["components"]["securitySchemes"].as_object().unwrap();
+    assert_eq!(schemes["bearer_auth"]["scheme"], "bearer");
+
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in METHODS {
+            let Some(operation) = item.get(method) else {
+                continue;
+            };
+            let unauthorized = &operation["responses"]["401"]["description"];
+            if unauthorized.as_str().is_some_and(|description| description.starts_with("Неавторизованный")) {
+                assert!(
+                    operation["security"].to_string().contains("bearer_auth"),
+                    "{} {} answers 401 but declares no bearer token",
+                    method,
+                    path
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn failures_document_the_error_body() {
+    let spec = spec();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in METHODS {
+            let Some(operation) = item.get(method) else {
+                continue;
+            };
+            for (status, response) in operation["responses"].as_object().unwrap() {
+                if status.starts_with('2') || status.starts_with('3') || path == "/metrics" {
+                    continue;
+                }
+                let schema = &response["content"]["application/json"]["schema"]["$ref"];
+                assert!(
+                    schema.as_str().is_some_and(|schema| schema.ends_with("/ApiError")
+                        || schema.ends_with("/OAuthError")
+                        || schema.ends_with("/Readiness")),
+                    "{} {} {} has no error schema",
+                    method,
+                    path,
+                    status
+                );
+            }
+        }
+    }
+}
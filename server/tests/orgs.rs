@@ -0,0 +1,170 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{text_after, TestApp, TestResponse, PASSWORD};
+use serde_json::json;
+
+fn user_id(app: &TestApp, token: &str) -> i32 {
+    app.state.auth.verify_jwt(token).unwrap().sub
+}
+
+async fn create_org(app: &TestApp, token: &str, name: &str) -> i64 {
+    let created = app.request(Method::POST, "/orgs", Some(token), Some(json!({ "name": name }))).await;
+    assert_eq!(created.code(), 200, "{}", created.json());
+    created.json()["data"]["organization_id"].as_i64().unwrap()
+}
+
+async fn invite(app: &TestApp, org_id: i64, token: &str, email: &str, role: &str) -> TestResponse {
+    app.request(
+        Method::POST,
+        &format!("/orgs/{}/invitations", org_id),
+        Some(token),
+        Some(json!({ "email": email, "role": role })),
+    )
+    .await
+}
+
+async fn accept(app: &TestApp, token: &str, invitation_token: &str) -> TestResponse {
+    app.request(Method::POST, "/orgs/invitations/accept", Some(token), Some(json!({ "token": invitation_token })))
+        .await
+}
+
+async fn set_role(app: &TestApp, org_id: i64, token: &str, user_id: i32, role: &str) -> TestResponse {
+    app.request(
+        Method::PUT,
+        &format!("/orgs/{}/members/{}", org_id, user_id),
+        Some(token),
+        Some(json!({ "role": role })),
+    )
+    .await
+}
+
+/// Registers `email` and lets it join the organization with `role`.
+async fn member(app: &TestApp, org_id: i64, owner: &str, email: &str, role: &str) -> String {
+    let token = app.verified_user(email).await;
+    assert_eq!(invite(app, org_id, owner, email, role).await.code(), 200);
+    let emails = app.emails_to(email).await;
+    let invitation_token = text_after(&emails.last().unwrap().text_body, "token=");
+    assert_eq!(accept(app, &token, &invitation_token).await.code(), 200);
+    token
+}
+
+#[tokio::test]
+async fn invitations_are_accepted_by_the_invited_address_only() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+    let carol = app.verified_user("carol@example.com").await;
+    let org_id = create_org(&app, &alice, "Acme").await;
+
+    assert_eq!(invite(&app, org_id, &alice, "bob@example.com", "member").await.code(), 200);
+    let emails = app.emails_to("bob@example.com").await;
+    let invitation_token = text_after(&emails.last().unwrap().text_body, "token=");
+    let lookup = app
+        .request(Method::GET, &format!("/orgs/invitations?token={}", invitation_token), None, None)
+        .await;
+    assert_eq!(lookup.json()["data"]["role"], "member");
+
+    let forwarded = accept(&app, &carol, &invitation_token).await;
+    assert_eq!(forwarded.code(), 403);
+    assert_eq!(forwarded.json()["error"], "invitation_email_mismatch");
+
+    let bob = app.verified_user("bob@example.com").await;
+    let joined = accept(&app, &bob, &invitation_token).await;
+    assert_eq!(joined.code(), 200, "{}", joined.json());
+    assert_eq!(joined.json()["data"]["role"], "member");
+    assert_eq!(accept(&app, &bob, &invitation_token).await.json()["error"], "invalid_invitation");
+    let again = invite(&app, org_id, &alice, "bob@example.com", "member").await;
+    assert_eq!(again.json()["error"], "user_already_member");
+
+    // Members don't invite, admins don't hand out ownership.
+    assert_eq!(invite(&app, org_id, &bob, "dave@example.com", "member").await.code(), 403);
+    let admin = member(&app, org_id, &alice, "erin@example.com", "admin").await;
+    assert_eq!(invite(&app, org_id, &admin, "dave@example.com", "owner").await.code(), 403);
+    assert_eq!(invite(&app, org_id, &admin, "dave@example.com", "member").await.code(), 200);
+    // Outsiders don't learn that the organization exists.
+    let outsider = app.request(Method::GET, &format!("/orgs/{}", org_id), Some(&carol), None).await;
+    assert_eq!(outsider.code(), 404);
+}
+
+#[tokio::test]
+async fn a_sole_owner_hands_over_before_leaving() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+    let org_id = create_org(&app, &alice, "Acme").await;
+    let bob = member(&app, org_id, &alice, "bob@example.com", "member").await;
+    let delete_alice = || {
+        app.request(Method::DELETE, "/user/me", Some(&alice), Some(json!({ "password": PASSWORD })))
+    };
+
+    let refused = delete_alice().await;
+    assert_eq!(refused.code(), 409);
+    assert_eq!(refused.json()["error"], "sole_owner");
+    assert_eq!(refused.json()["data"]["organizations"][0]["id"], org_id);
+    let demoted = set_role(&app, org_id, &alice, user_id(&app, &alice), "admin").await;
+    assert_eq!(demoted.json()["error"], "owner_required");
+    let leaving = app
+        .request(Method::DELETE, &format!("/orgs/{}/members/{}", org_id, user_id(&app, &alice)), Some(&alice), None)
+        .await;
+    assert_eq!(leaving.json()["error"], "owner_required");
+
+    assert_eq!(set_role(&app, org_id, &alice, user_id(&app, &bob), "owner").await.code(), 200);
+    assert_eq!(delete_alice().await.code(), 200);
+    let org = app.request(Method::GET, &format!("/orgs/{}", org_id), Some(&bob), None).await;
+    assert_eq!(org.code(), 200);
+    assert_eq!(org.json()["data"]["members"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn drive_files_belong_to_the_team() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+    let org_id = create_org(&app, &alice, "Acme").await;
+    let bob = member(&app, org_id, &alice, "bob@example.com", "member").await;
+    let carol = member(&app, org_id, &alice, "carol@example.com", "member").await;
+    let outsider = app.verified_user("dave@example.com").await;
+
+    let drives = format!("/orgs/{}/drives", org_id);
+    let by_member = app.request(Method::POST, &drives, Some(&bob), Some(json!({ "name": "Shared" }))).await;
+    assert_eq!(by_member.code(), 403);
+    let drive = app.request(Method::POST, &drives, Some(&alice), Some(json!({ "name": "Shared" }))).await;
+    assert_eq!(drive.code(), 200, "{}", drive.json());
+    let files = format!("{}/{}/files", drives, drive.json()["data"]["id"]);
+
+    let uploaded = app.upload_to(&bob, &files, "plan.txt", b"the plan").await;
+    assert_eq!(uploaded.code(), 200, "{}", uploaded.json());
+    let file = format!("{}/{}", files, uploaded.json()["data"]["id"]);
+    let listed = app.request(Method::GET, &files, Some(&carol), None).await;
+    assert_eq!(listed.json()["data"]["files"][0]["file_name"], "plan.txt");
+    assert_eq!(app.request(Method::GET, &files, Some(&outsider), None).await.code(), 404);
+    assert_eq!(app.upload_to(&outsider, &files, "spam.txt", b"spam").await.code(), 404);
+    let draft = app.upload_to(&bob, &files, "draft.txt", b"draft").await;
+    let draft = format!("{}/{}", files, draft.json()["data"]["id"]);
+
+    // Only the uploader or an admin removes a file.
+    assert_eq!(app.request(Method::DELETE, &draft, Some(&carol), None).await.code(), 403);
+    assert_eq!(app.request(Method::DELETE, &draft, Some(&bob), None).await.code(), 200);
+    assert_eq!(app.request(Method::GET, &draft, Some(&bob), None).await.code(), 404);
+
+    // The file outlives the account of its uploader.
+    let left = app.request(Method::DELETE, "/user/me", Some(&bob), Some(json!({ "password": PASSWORD }))).await;
+    assert_eq!(left.code(), 200, "{}", left.json());
+    let downloaded = app.request(Method::GET, &file, Some(&carol), None).await;
+    assert_eq!(downloaded.status, StatusCode::OK);
+    assert_eq!(downloaded.body, b"the plan");
+    assert_eq!(app.request(Method::GET, &file, Some(&outsider), None).await.code(), 404);
+    assert_eq!(app.request(Method::DELETE, &file, Some(&alice), None).await.code(), 200);
+
+    // The drive's own quota applies, not the uploader's.
+    let admin = app.verified_user("root@example.com").await;
+    app.data().users.iter_mut().for_each(|user| user.is_admin = user.email == "root@example.com");
+    let quota = app
+        .request(
+            Method::PUT,
+            &format!("/admin/team-drives/{}/quota", drive.json()["data"]["id"]),
+            Some(&admin),
+            Some(json!({ "quota_bytes": 4 })),
+        )
+        .await;
+    assert_eq!(quota.code(), 200, "{}", quota.json());
+    assert_eq!(app.upload_to(&carol, &files, "plan.txt", b"the plan").await.json()["error"], "quota_exceeded");
+}
@@ -0,0 +1,103 @@
+//! Runs the outbox worker over the in-memory repositories and a Maildir.
+
+use std::fs;
+
+use chrono::{Duration, Utc};
+use server::{
+    config::outbox::{deliver_due_emails, enqueue_email},
+    models::{
+        mail::{EmailTemplate, Mailer},
+        repository::MemoryRepository,
+        settings::{Config, MailTransportKind},
+    },
+};
+use tempfile::TempDir;
+
+fn maildir_mailer(dir: &TempDir) -> Mailer {
+    let mut config = Config::default().mail;
+    config.transport = Some(MailTransportKind::Maildir);
+    config.dir = dir.path().to_path_buf();
+    Mailer::load(&config).unwrap()
+}
+
+fn delivered(dir: &TempDir) -> usize {
+    fs::read_dir(dir.path().join("new")).map_or(0, |entries| entries.count())
+}
+
+#[tokio::test]
+async fn failed_emails_are_retried_later() {
+    let outbox = MemoryRepository::default();
+    let dir = TempDir::new().unwrap();
+    let mailer = maildir_mailer(&dir);
+    enqueue_email(&outbox, "alice@example.com", EmailTemplate::PasswordChanged).await.unwrap();
+
+    // The mail directory is gone, as when a disk is unmounted.
+    fs::remove_dir_all(dir.path().join("new")).unwrap();
+    fs::remove_dir_all(dir.path().join("tmp")).unwrap();
+    assert_eq!(deliver_due_emails(&outbox, &mailer).await, 1);
+    {
+        let state = outbox.state.lock().unwrap();
+        let queued = &state.emails[0];
+        assert_eq!(queued.email.attempts, 1);
+        assert!(queued.last_error.is_some());
+        assert!(queued.sent_at.is_none() && queued.failed_at.is_none());
+        let retry_in = queued.next_attempt_at - Utc::now();
+        assert!(retry_in > Duration::seconds(25) && retry_in <= Duration::seconds(30), "{}", retry_in);
+    }
+    // Not due yet.
+    assert_eq!(deliver_due_emails(&outbox, &mailer).await, 0);
+
+    for sub_dir in ["new", "tmp"] {
+        fs::create_dir_all(dir.path().join(sub_dir)).unwrap();
+    }
+    outbox.state.lock().unwrap().emails[0].next_attempt_at = Utc::now();
+    assert_eq!(deliver_due_emails(&outbox, &mailer).await, 1);
+    assert_eq!(delivered(&dir), 1);
+    let state = outbox.state.lock().unwrap();
+    assert!(state.emails[0].sent_at.is_some());
+    assert_eq!(state.emails[0].email.attempts, 2);
+}
+
+#[tokio::test]
+async fn emails_that_cannot_be_sent_are_given_up() {
+    let outbox = MemoryRepository::default();
+    let dir = TempDir::new().unwrap();
+    let mailer = maildir_mailer(&dir);
+
+    // Permanent failures are not retried.
+    enqueue_email(&outbox, "not an address", EmailTemplate::PasswordChanged).await.unwrap();
+    deliver_due_emails(&outbox, &mailer).await;
+    assert!(outbox.state.lock().unwrap().emails[0].failed_at.is_some());
+
+    // Temporary ones are, up to 8 attempts.
+    enqueue_email(&outbox, "alice@example.com", EmailTemplate::PasswordChanged).await.unwrap();
+    fs::remove_dir_all(dir.path().join("tmp")).unwrap();
+    outbox.state.lock().unwrap().emails[1].email.attempts = 7;
+    deliver_due_emails(&outbox, &mailer).await;
+    let state = outbox.state.lock().unwrap();
+    assert_eq!(state.emails[1].email.attempts, 8);
+    assert!(state.emails[1].failed_at.is_some());
+    assert_eq!(delivered(&dir), 0);
+}
+
+#[tokio::test]
+async fn queued_emails_are_rendered_with_their_template() {
+    let outbox = MemoryRepository::default();
+    let dir = TempDir::new().unwrap();
+    let link = "https://files.example.com/auth/verify-email?token=abc".to_string();
+    enqueue_email(&outbox, "alice@example.com", EmailTemplate::VerifyEmail { link: link.clone() })
+        .await
+        .unwrap();
+
+    let queued = outbox.state.lock().unwrap().emails[0].email.clone();
+    assert_eq!(queued.template, "verify_email");
+    assert_eq!(queued.subject, "Confirm your email address");
+    assert!(queued.text_body.contains(&link));
+    assert!(queued.html_body.contains(&link));
+
+    deliver_due_emails(&outbox, &maildir_mailer(&dir)).await;
+    let entry = fs::read_dir(dir.path().join("new")).unwrap().next().unwrap().unwrap();
+    let message = fs::read_to_string(entry.path()).unwrap();
+    assert!(message.contains("To: alice@example.com"));
+    assert!(message.contains("Subject: Confirm your email address"));
+}
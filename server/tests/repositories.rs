@@ -0,0 +1,106 @@
+//! The same scenarios on the in-memory repositories, which the other tests
+//! use, and on Postgres, so that the two stay interchangeable.
+
+use server::{
+    models::{auth::RegisterUser, files::NewFile, repository::MemoryRepository},
+    repositories::{auth_repository::AuthRepository, file_repository::FileRepository, user_repository::UserRepository},
+};
+
+fn register(email: &str) -> RegisterUser {
+    RegisterUser { email: email.to_string(), password: "hash".to_string(), name: "Test".to_string() }
+}
+
+fn new_file(user_id: i32, file_size: i32) -> NewFile {
+    NewFile {
+        file_name: "a.txt".to_string(),
+        file_path: format!("uploads/{}-{}.txt", user_id, file_size),
+        file_size,
+        file_content_type: "text/plain".to_string(),
+        file_type: "txt".to_string(),
+        user_id,
+        team_drive_id: None,
+        folder_id: None,
+        content_hash: "hash".to_string(),
+    }
+}
+
+async fn users_are_found_by_email_regardless_of_case(repository: &impl UserRepository) {
+    let user = repository.create_user(register("Alice@Example.com")).await.unwrap();
+    assert!(repository.create_user(register("ALICE@example.com")).await.is_err());
+
+    let found = repository.find_user_by_email(" alice@EXAMPLE.com ".to_string()).await.unwrap().unwrap();
+    assert_eq!(found.id, user.id);
+    assert_eq!(repository.find_password_hash(user.id).await.unwrap().as_deref(), Some("hash"));
+    assert!(repository.find_user_by_email("bob@example.com".to_string()).await.unwrap().is_none());
+}
+
+async fn codes_and_magic_links_are_used_once(repository: &(impl UserRepository + AuthRepository)) {
+    let user_id = repository.create_user(register("alice@example.com")).await.unwrap().id;
+    repository.create_code("111111", user_id, 60).await.unwrap();
+    repository.create_code("222222", user_id, 60).await.unwrap();
+    assert!(repository.find_code_by_code("111111".to_string(), user_id).await.unwrap().is_none());
+    assert!(repository.find_code_by_code("222222".to_string(), user_id + 1).await.unwrap().is_none());
+    assert!(repository.find_code_by_code("222222".to_string(), user_id).await.unwrap().is_some());
+    repository.create_code("333333", user_id, -1).await.unwrap();
+    assert!(repository.find_code_by_code("333333".to_string(), user_id).await.unwrap().is_none());
+
+    repository.create_magic_link("jti", user_id, 60).await.unwrap();
+    repository.create_magic_link("expired", user_id, -1).await.unwrap();
+    assert_eq!(repository.consume_magic_link("jti").await.unwrap(), Some(user_id));
+    assert_eq!(repository.consume_magic_link("jti").await.unwrap(), None);
+    assert_eq!(repository.consume_magic_link("expired").await.unwrap(), None);
+}
+
+async fn deleting_a_user_returns_the_paths_of_its_files(repository: &(impl UserRepository + FileRepository)) {
+    let alice = repository.create_user(register("alice@example.com")).await.unwrap().id;
+    let bob = repository.create_user(register("bob@example.com")).await.unwrap().id;
+    repository.create_file(new_file(alice, 10)).await.unwrap();
+    repository.create_file(new_file(alice, 20)).await.unwrap();
+    repository.create_file(new_file(bob, 40)).await.unwrap();
+    assert_eq!(repository.used_bytes(alice, None).await.unwrap(), 30);
+
+    let mut paths = repository.delete_user(alice).await.unwrap();
+    paths.sort();
+    assert_eq!(paths, [format!("uploads/{}-10.txt", alice), format!("uploads/{}-20.txt", alice)]);
+    assert!(repository.find_user_by_id(alice).await.unwrap().is_none());
+    assert!(repository.find_personal_files(alice).await.unwrap().is_empty());
+    assert_eq!(repository.find_personal_files(bob).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn memory_users() {
+    users_are_found_by_email_regardless_of_case(&MemoryRepository::default()).await;
+}
+
+#[tokio::test]
+async fn memory_codes() {
+    codes_and_magic_links_are_used_once(&MemoryRepository::default()).await;
+}
+
+#[tokio::test]
+async fn memory_user_deletion() {
+    deleting_a_user_returns_the_paths_of_its_files(&MemoryRepository::default()).await;
+}
+
+#[cfg(feature = "postgres-tests")]
+mod postgres {
+    use server::models::repository::PgRepository;
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn postgres_users(pool: PgPool) {
+        users_are_found_by_email_regardless_of_case(&PgRepository { pool }).await;
+    }
+
+    #[sqlx::test]
+    async fn postgres_codes(pool: PgPool) {
+        codes_and_magic_links_are_used_once(&PgRepository { pool }).await;
+    }
+
+    #[sqlx::test]
+    async fn postgres_user_deletion(pool: PgPool) {
+        deleting_a_user_returns_the_paths_of_its_files(&PgRepository { pool }).await;
+    }
+}
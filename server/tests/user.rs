@@ -0,0 +1,134 @@
+mod common;
+
+use axum::http::Method;
+use common::{text_after, TestApp, PASSWORD};
+use serde_json::json;
+
+fn user_id(app: &TestApp, token: &str) -> i32 {
+    app.state.auth.verify_jwt(token).unwrap().sub
+}
+
+#[tokio::test]
+async fn a_new_email_takes_effect_once_confirmed() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+    app.verified_user("bob@example.com").await;
+    let profile = format!("/user/{}", user_id(&app, &alice));
+
+    let taken = app
+        .request(
+            Method::POST,
+            "/user/me/email",
+            Some(&alice),
+            Some(json!({ "email": "Bob@Example.com", "password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(taken.code(), 409);
+    assert_eq!(taken.json()["error"], "email_in_use");
+
+    let requested = app
+        .request(
+            Method::POST,
+            "/user/me/email",
+            Some(&alice),
+            Some(json!({ "email": "alice@example.org", "password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(requested.code(), 200, "{}", requested.json());
+    let current = app.request(Method::GET, &profile, Some(&alice), None).await;
+    assert_eq!(current.json()["data"]["email"], "alice@example.com");
+    let notices = app.emails_to("alice@example.com").await;
+    assert_eq!(notices.last().unwrap().subject, "Email change requested");
+
+    let emails = app.emails_to("alice@example.org").await;
+    let token = text_after(&emails.last().expect("no confirmation email").text_body, "token=");
+    let confirmed = app
+        .request(Method::GET, &format!("/auth/verify-email?token={}", token), None, None)
+        .await;
+    assert_eq!(confirmed.code(), 200, "{}", confirmed.json());
+    let current = app.request(Method::GET, &profile, Some(&alice), None).await;
+    assert_eq!(current.json()["data"]["email"], "alice@example.org");
+    assert_eq!(app.login("alice@example.com", PASSWORD).await.code(), 401);
+    assert_eq!(app.login("alice@example.org", PASSWORD).await.code(), 200);
+}
+
+#[tokio::test]
+async fn the_profile_is_updated_in_place() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+
+    let blank = app.request(Method::PATCH, "/user/me", Some(&alice), Some(json!({ "name": "  " }))).await;
+    assert_eq!(blank.json()["error"], "name_required");
+    let unknown = app.request(Method::PATCH, "/user/me", Some(&alice), Some(json!({ "locale": "xx" }))).await;
+    assert_eq!(unknown.code(), 400);
+
+    let updated = app
+        .request(Method::PATCH, "/user/me", Some(&alice), Some(json!({ "name": " Alice ", "locale": "ru" })))
+        .await;
+    assert_eq!(updated.code(), 200, "{}", updated.json());
+    assert_eq!(updated.json()["data"]["name"], "Alice");
+    assert_eq!(updated.json()["data"]["locale"], "ru");
+    // Fields left out keep their value.
+    let renamed = app.request(Method::PATCH, "/user/me", Some(&alice), Some(json!({ "name": "Al" }))).await;
+    assert_eq!(renamed.json()["data"]["locale"], "ru");
+}
+
+#[tokio::test]
+async fn the_password_changes_with_the_current_one() {
+    const NEW_PASSWORD: &str = "Nw5$kTq9@xLm";
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+
+    let weak = app
+        .request(Method::POST, "/user/me/password", Some(&alice), Some(json!({ "new_password": "short" })))
+        .await;
+    assert_eq!(weak.code(), 400);
+    let wrong = app
+        .request(
+            Method::POST,
+            "/user/me/password",
+            Some(&alice),
+            Some(json!({ "current_password": NEW_PASSWORD, "new_password": NEW_PASSWORD })),
+        )
+        .await;
+    assert_eq!(wrong.code(), 401);
+    assert_eq!(wrong.json()["error"], "invalid_password");
+
+    let changed = app
+        .request(
+            Method::POST,
+            "/user/me/password",
+            Some(&alice),
+            Some(json!({ "current_password": PASSWORD, "new_password": NEW_PASSWORD })),
+        )
+        .await;
+    assert_eq!(changed.code(), 200, "{}", changed.json());
+    assert_eq!(app.login("alice@example.com", PASSWORD).await.code(), 401);
+    assert_eq!(app.login("alice@example.com", NEW_PASSWORD).await.code(), 200);
+    let emails = app.emails_to("alice@example.com").await;
+    assert_eq!(emails.last().unwrap().subject, "Your password was changed");
+}
+
+#[tokio::test]
+async fn deleting_the_account_removes_its_files() {
+    let app = TestApp::new();
+    let alice = app.verified_user("alice@example.com").await;
+    assert_eq!(app.upload(&alice, "notes.txt", b"hello").await.code(), 200);
+
+    // A fresh session is not enough while the account has a password.
+    let without = app.request(Method::DELETE, "/user/me", Some(&alice), Some(json!({}))).await;
+    assert_eq!(without.code(), 401);
+    assert_eq!(without.json()["error"], "reauthentication_required");
+    let wrong = app
+        .request(Method::DELETE, "/user/me", Some(&alice), Some(json!({ "password": "not-it" })))
+        .await;
+    assert_eq!(wrong.json()["error"], "invalid_password");
+    let deleted = app
+        .request(Method::DELETE, "/user/me", Some(&alice), Some(json!({ "password": PASSWORD })))
+        .await;
+    assert_eq!(deleted.code(), 200, "{}", deleted.json());
+
+    assert_eq!(std::fs::read_dir(app.storage.path()).unwrap().count(), 0);
+    assert_eq!(app.login("alice@example.com", PASSWORD).await.code(), 401);
+    assert_eq!(app.request(Method::GET, "/files/quota", Some(&alice), None).await.code(), 401);
+}